@@ -0,0 +1,551 @@
+//! Export of symbols to disassemblers.
+//!
+//! [`AnalysisScript`] collects a name for every function that IL2CPP
+//! registers in the binary: the compiled code of methods and generic method
+//! instantiations, invokers, reverse P/Invoke wrappers and adjustor thunks.
+//! Methods also get a C prototype, which uses `void*` for every reference
+//! type and is left out if a parameter is a struct passed by value. The
+//! registration structures and the arrays they point to are labeled as
+//! `g_CodeRegistration`, `g_MetadataRegistration` and
+//! `g_CodeRegistration_invokerPointers` and so on.
+//!
+//! The symbols are written as a script for IDA Pro, Ghidra or Binary Ninja,
+//! or as JSON for other tools. Addresses are virtual addresses in the binary,
+//! which the scripts add to the image base.
+//!
+//! Ghidra scripts are only written in Python, which Ghidra runs with Jython.
+//! Ghidra's Java scripts are out of scope; a Java script can read the JSON
+//! output instead.
+
+use crate::codegen::unique_name;
+use crate::generics::{inflate, GenericContext, InflatedType, InflatedTypeData};
+use crate::global_metadata::Il2CppMethodDefinition;
+use crate::runtime_metadata::{Il2CppCodeGenModule, Il2CppMethodSpec, Il2CppTypeEnum};
+use crate::type_name::{TypeNameFormatter, TypeNameStyle};
+use crate::Metadata;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::io;
+
+/// The output format of an [`AnalysisScript`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScriptFormat {
+    /// A JSON object with `functions` and `labels` arrays.
+    Json,
+    IdaPython,
+    /// A Jython script for Ghidra's script manager. There is no Java
+    /// variant.
+    GhidraPython,
+    BinaryNinjaPython,
+}
+
+impl ScriptFormat {
+    /// The conventional file name of a script in this format.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            ScriptFormat::Json => "script.json",
+            ScriptFormat::IdaPython => "ida_script.py",
+            ScriptFormat::GhidraPython => "ghidra_script.py",
+            ScriptFormat::BinaryNinjaPython => "binaryninja_script.py",
+        }
+    }
+}
+
+/// A function to name in the binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptFunction {
+    pub address: u64,
+    pub name: String,
+    /// A C declaration of the function, including its name.
+    pub signature: Option<String>,
+}
+
+/// A data address to name in the binary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptLabel {
+    pub address: u64,
+    pub name: String,
+}
+
+/// The symbols of a binary, ready to be written as a script.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisScript {
+    pub functions: Vec<ScriptFunction>,
+    pub labels: Vec<ScriptLabel>,
+}
+
+const IDA_SCRIPT: &str = r#"
+import ida_funcs
+import idaapi
+import idc
+
+base = idaapi.get_imagebase()
+for address, name, signature in functions:
+    ea = base + address
+    ida_funcs.add_func(ea)
+    idc.set_name(ea, name, idc.SN_NOWARN | idc.SN_NOCHECK)
+    if signature is not None:
+        idc.SetType(ea, signature + ";")
+for address, name in labels:
+    idc.set_name(base + address, name, idc.SN_NOWARN | idc.SN_NOCHECK)
+"#;
+
+const GHIDRA_SCRIPT: &str = r#"
+from ghidra.app.cmd.function import ApplyFunctionSignatureCmd
+from ghidra.app.util.cparser.C import CParserUtils
+from ghidra.program.model.symbol import SourceType
+
+base = currentProgram.getImageBase()
+for address, name, signature in functions:
+    addr = base.add(address)
+    function = getFunctionAt(addr) or createFunction(addr, name)
+    if function is None:
+        continue
+    function.setName(name, SourceType.USER_DEFINED)
+    if signature is not None:
+        try:
+            parsed = CParserUtils.parseSignature(None, currentProgram, signature, False)
+        except Exception:
+            parsed = None
+        if parsed is not None:
+            ApplyFunctionSignatureCmd(addr, parsed, SourceType.USER_DEFINED).applyTo(currentProgram, monitor)
+for address, name in labels:
+    createLabel(base.add(address), name, True, SourceType.USER_DEFINED)
+"#;
+
+const BINARY_NINJA_SCRIPT: &str = r#"
+from binaryninja import Symbol, SymbolType
+
+base = bv.start
+for address, name, signature in functions:
+    addr = base + address
+    bv.add_function(addr)
+    bv.define_user_symbol(Symbol(SymbolType.FunctionSymbol, addr, name))
+    function = bv.get_function_at(addr)
+    if signature is not None and function is not None:
+        try:
+            function.type = bv.parse_type_string(signature)[0]
+        except SyntaxError:
+            pass
+for address, name in labels:
+    bv.define_user_symbol(Symbol(SymbolType.DataSymbol, base + address, name))
+"#;
+
+impl AnalysisScript {
+    /// Collects the symbols of the binary the metadata was read from.
+    pub fn new(metadata: &Metadata) -> Self {
+        let mut builder = Builder {
+            metadata,
+            names: TypeNameFormatter::new(metadata, TypeNameStyle::Cpp),
+            script: AnalysisScript::default(),
+            addresses: HashSet::new(),
+            used_names: HashSet::new(),
+        };
+        builder.push_methods();
+        builder.push_generic_methods();
+        builder.push_registration_functions();
+        builder.push_labels();
+        builder.script
+    }
+
+    /// Writes the symbols in `format`.
+    pub fn write<W: io::Write>(&self, format: ScriptFormat, out: &mut W) -> io::Result<()> {
+        let mut script = String::new();
+        match format {
+            ScriptFormat::Json => self.push_json(&mut script),
+            ScriptFormat::IdaPython => self.push_python(IDA_SCRIPT, "", &mut script),
+            // Jython is Python 2, where strings without a prefix are bytes.
+            ScriptFormat::GhidraPython => self.push_python(GHIDRA_SCRIPT, "u", &mut script),
+            ScriptFormat::BinaryNinjaPython => self.push_python(BINARY_NINJA_SCRIPT, "", &mut script),
+        }
+        out.write_all(script.as_bytes())
+    }
+
+    fn push_json(&self, out: &mut String) {
+        out.push_str("{\n  \"functions\": [");
+        for (i, function) in self.functions.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            let _ = write!(out, "    {{\"address\": {}, \"name\": {}, \"signature\": ", function.address, quote(&function.name));
+            match &function.signature {
+                Some(signature) => out.push_str(&quote(signature)),
+                None => out.push_str("null"),
+            }
+            out.push('}');
+        }
+        out.push_str("\n  ],\n  \"labels\": [");
+        for (i, label) in self.labels.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            let _ = write!(out, "    {{\"address\": {}, \"name\": {}}}", label.address, quote(&label.name));
+        }
+        out.push_str("\n  ]\n}\n");
+    }
+
+    /// Writes the symbols as Python lists followed by `script`, which applies
+    /// them. String literals start with `string_prefix`.
+    fn push_python(&self, script: &str, string_prefix: &str, out: &mut String) {
+        let quote = |s: &str| quote_python(s, string_prefix);
+        out.push_str("functions = [\n");
+        for function in &self.functions {
+            let signature = function.signature.as_deref().map_or("None".into(), quote);
+            let _ = writeln!(out, "    (0x{:X}, {}, {}),", function.address, quote(&function.name), signature);
+        }
+        out.push_str("]\n\nlabels = [\n");
+        for label in &self.labels {
+            let _ = writeln!(out, "    (0x{:X}, {}),", label.address, quote(&label.name));
+        }
+        out.push_str("]\n");
+        out.push_str(script);
+    }
+}
+
+struct Builder<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+    names: TypeNameFormatter<'md, 'gmd, 'rmd>,
+    script: AnalysisScript,
+    /// Functions that already have a name. Methods with identical code share
+    /// an address, and the first name is kept.
+    addresses: HashSet<u64>,
+    used_names: HashSet<String>,
+}
+
+impl Builder<'_, '_, '_> {
+    /// Names the function at `address`, with the prototype of `method` if it
+    /// is the compiled code of a method.
+    fn push_function(
+        &mut self,
+        address: u64,
+        name: String,
+        method: Option<(&Il2CppMethodDefinition, &GenericContext)>,
+    ) {
+        if address == 0 || !self.addresses.insert(address) {
+            return;
+        }
+        let name = unique_name(name, &mut self.used_names);
+        let signature = method.and_then(|(method, context)| self.prototype(&name, method, context));
+        self.script.functions.push(ScriptFunction { address, name, signature });
+    }
+
+    fn push_methods(&mut self) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let modules: HashMap<&str, &Il2CppCodeGenModule> = metadata
+            .runtime_metadata
+            .code_registration
+            .code_gen_modules
+            .iter()
+            .map(|module| (module.name, module))
+            .collect();
+        for image in gm.images.as_vec() {
+            let Some(module) = gm.string.get(image.name_index).ok().and_then(|name| modules.get(name)) else {
+                continue;
+            };
+            let mut tokens = HashMap::new();
+            for idx in image.type_start.indices(image.type_count) {
+                let Some(ty_def) = gm.type_definitions.get(idx) else {
+                    continue;
+                };
+                for idx in ty_def.method_start.indices(ty_def.method_count as u32) {
+                    let Some(method) = gm.methods.get(idx) else {
+                        continue;
+                    };
+                    tokens.insert(method.token.0, method);
+                    let rid = method.token.rid() as usize;
+                    let Some(&address) = rid.checked_sub(1).and_then(|i| module.method_pointers.get(i)) else {
+                        continue;
+                    };
+                    let name = self.method_name(method, None);
+                    self.push_function(address, name, Some((method, &GenericContext::default())));
+                }
+            }
+            // Adjustor thunks unbox `this` for the virtual methods of value
+            // types and take the same parameters.
+            for thunk in &module.adjustor_thunks {
+                let Some(&method) = tokens.get(&thunk.token.0) else {
+                    continue;
+                };
+                let name = format!("{}_AdjustorThunk", self.method_name(method, None));
+                self.push_function(thunk.adjustor_thunk, name, Some((method, &GenericContext::default())));
+            }
+        }
+    }
+
+    fn push_generic_methods(&mut self) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let cr = &metadata.runtime_metadata.code_registration;
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        for entry in &mr.generic_method_table {
+            let Some(spec) = mr.method_specs.get(entry.generic_method_index as usize) else {
+                continue;
+            };
+            let Some(method) = gm.methods.get(spec.method_definition_index) else {
+                continue;
+            };
+            let context = spec.generic_context(metadata);
+            let name = self.method_name(method, Some(spec));
+            if let Some(&address) = cr.generic_method_pointers.get(entry.indices.method_index as usize) {
+                self.push_function(address, name.clone(), Some((method, &context)));
+            }
+            if let Some(&address) = cr.generic_adjustor_thunks.get(entry.indices.adjustor_thunk_index as usize) {
+                self.push_function(address, format!("{}_AdjustorThunk", name), Some((method, &context)));
+            }
+        }
+    }
+
+    fn push_registration_functions(&mut self) {
+        let metadata = self.metadata;
+        let cr = &metadata.runtime_metadata.code_registration;
+        for (i, &address) in cr.invoker_pointers.iter().enumerate() {
+            self.push_function(address, format!("RuntimeInvoker_{}", i), None);
+        }
+        for (i, &address) in cr.reverse_pinvoke_wrappers.iter().enumerate() {
+            self.push_function(address, format!("ReversePInvokeWrapper_{}", i), None);
+        }
+    }
+
+    fn push_labels(&mut self) {
+        let metadata = self.metadata;
+        let addresses = &metadata.runtime_metadata.registration_addresses;
+        let registrations = [
+            ("g_CodeRegistration", addresses.code_registration, &addresses.code_registration_arrays),
+            ("g_MetadataRegistration", addresses.metadata_registration, &addresses.metadata_registration_arrays),
+        ];
+        for (registration, address, arrays) in registrations {
+            if address == 0 {
+                continue;
+            }
+            self.script.labels.push(ScriptLabel {
+                address,
+                name: registration.into(),
+            });
+            for &(field, address) in arrays {
+                self.script.labels.push(ScriptLabel {
+                    address,
+                    name: format!("{}_{}", registration, field),
+                });
+            }
+        }
+    }
+
+    /// The name of a method, such as `System_String__Concat`, with the type
+    /// arguments of `spec`.
+    fn method_name(&self, method: &Il2CppMethodDefinition, spec: Option<&Il2CppMethodSpec>) -> String {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let method_name = self.names.identifier(gm.string.get(method.name_index).unwrap_or_default());
+        let Some(spec) = spec else {
+            return format!("{}__{}", self.names.format_type_definition(method.declaring_type), method_name);
+        };
+        let context = spec.generic_context(metadata);
+        let type_name = match context.class_args.is_empty() {
+            true => self.names.format_type_definition(method.declaring_type),
+            false => self.names.format_inflated(&InflatedType {
+                ty: Il2CppTypeEnum::Genericinst,
+                byref: false,
+                data: InflatedTypeData::GenericInstance {
+                    definition: method.declaring_type,
+                    args: context.class_args.clone(),
+                },
+            }),
+        };
+        let mut name = format!("{}__{}", type_name, method_name);
+        for arg in &context.method_args {
+            name.push('_');
+            name.push_str(&self.names.format_inflated(arg));
+        }
+        name
+    }
+
+    /// The C declaration of the compiled code of a method, which takes `this`
+    /// first and the method's `MethodInfo` last.
+    fn prototype(&self, name: &str, method: &Il2CppMethodDefinition, context: &GenericContext) -> Option<String> {
+        let metadata = self.metadata;
+        let signature = method.signature(metadata)?;
+        let return_type = self.c_type(&inflate(metadata, &signature.return_type, context), 0)?;
+        let mut used = HashSet::from(["__this".to_string(), "method".to_string()]);
+        let mut parameters = Vec::new();
+        if !signature.is_static() {
+            parameters.push("void* __this".to_string());
+        }
+        for param in &signature.parameters {
+            let ty = self.c_type(&inflate(metadata, &param.ty, context), 0)?;
+            parameters.push(format!("{} {}", ty, unique_name(self.names.identifier(param.name), &mut used)));
+        }
+        parameters.push("const void* method".into());
+        Some(format!("{} {}({})", return_type, name, parameters.join(", ")))
+    }
+
+    /// The C type of a value of `ty`, or `None` for structs, which are not
+    /// declared.
+    fn c_type(&self, ty: &InflatedType, depth: usize) -> Option<&'static str> {
+        let gm = &self.metadata.global_metadata;
+        if ty.byref {
+            return Some("void*");
+        }
+        let name = match ty.ty {
+            Il2CppTypeEnum::Void => "void",
+            Il2CppTypeEnum::Boolean => "bool",
+            Il2CppTypeEnum::Char => "uint16_t",
+            Il2CppTypeEnum::I1 => "int8_t",
+            Il2CppTypeEnum::U1 => "uint8_t",
+            Il2CppTypeEnum::I2 => "int16_t",
+            Il2CppTypeEnum::U2 => "uint16_t",
+            Il2CppTypeEnum::I4 => "int32_t",
+            Il2CppTypeEnum::U4 => "uint32_t",
+            Il2CppTypeEnum::I8 => "int64_t",
+            Il2CppTypeEnum::U8 => "uint64_t",
+            Il2CppTypeEnum::R4 => "float",
+            Il2CppTypeEnum::R8 => "double",
+            Il2CppTypeEnum::I => "intptr_t",
+            Il2CppTypeEnum::U => "uintptr_t",
+            // Enums are passed as their underlying type.
+            Il2CppTypeEnum::Valuetype => {
+                let InflatedTypeData::Definition(idx) = &ty.data else {
+                    return None;
+                };
+                let ty_def = gm.type_definitions.get(*idx)?;
+                if !ty_def.layout_info().is_enum || depth > 0 {
+                    return None;
+                }
+                let underlying = InflatedType::from_index(self.metadata, ty_def.element_type_index as usize);
+                return self.c_type(&underlying, depth + 1);
+            }
+            Il2CppTypeEnum::Genericinst => {
+                let ty_def = gm.type_definitions.get(ty.type_definition_index()?)?;
+                match ty_def.layout_info().is_value_type {
+                    true => return None,
+                    false => "void*",
+                }
+            }
+            Il2CppTypeEnum::Var | Il2CppTypeEnum::Mvar | Il2CppTypeEnum::Typedbyref => return None,
+            _ => "void*",
+        };
+        Some(name)
+    }
+}
+
+/// Quotes a string as a JSON string literal.
+fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    push_escaped(s, false, &mut quoted);
+    quoted.push('"');
+    quoted
+}
+
+/// Quotes a string as a Python string literal that starts with `prefix`.
+fn quote_python(s: &str, prefix: &str) -> String {
+    let mut quoted = String::with_capacity(prefix.len() + s.len() + 2);
+    quoted.push_str(prefix);
+    quoted.push('"');
+    push_escaped(s, true, &mut quoted);
+    quoted.push('"');
+    quoted
+}
+
+/// Escapes quotes, backslashes, control characters and non-ASCII characters.
+/// Python doesn't combine UTF-16 surrogate pairs, so characters outside of
+/// the BMP are written as `\U` escapes for Python and as surrogate pairs for
+/// JSON.
+fn push_escaped(s: &str, python: bool, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if python && c as u32 > 0xFFFF => {
+                let _ = write!(out, "\\U{:08x}", c as u32);
+            }
+            c if (c as u32) < 0x20 || !c.is_ascii() => {
+                let mut buf = [0; 2];
+                for unit in c.encode_utf16(&mut buf) {
+                    let _ = write!(out, "\\u{:04x}", unit);
+                }
+            }
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script() -> AnalysisScript {
+        AnalysisScript {
+            functions: vec![
+                ScriptFunction {
+                    address: 0x1000,
+                    name: "Foo__Run".into(),
+                    signature: Some("void Foo__Run(void* __this, const void* method)".into()),
+                },
+                ScriptFunction {
+                    address: 0x2000,
+                    name: "RuntimeInvoker_0".into(),
+                    signature: None,
+                },
+            ],
+            labels: vec![ScriptLabel {
+                address: 0x3000,
+                name: "g_CodeRegistration".into(),
+            }],
+        }
+    }
+
+    fn write(script: &AnalysisScript, format: ScriptFormat) -> String {
+        let mut out = Vec::new();
+        script.write(format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            write(&script(), ScriptFormat::Json),
+            r#"{
+  "functions": [
+    {"address": 4096, "name": "Foo__Run", "signature": "void Foo__Run(void* __this, const void* method)"},
+    {"address": 8192, "name": "RuntimeInvoker_0", "signature": null}
+  ],
+  "labels": [
+    {"address": 12288, "name": "g_CodeRegistration"}
+  ]
+}
+"#
+        );
+        assert_eq!(
+            write(&AnalysisScript::default(), ScriptFormat::Json),
+            "{\n  \"functions\": [\n  ],\n  \"labels\": [\n  ]\n}\n"
+        );
+    }
+
+    #[test]
+    fn python() {
+        let symbols = r#"functions = [
+    (0x1000, "Foo__Run", "void Foo__Run(void* __this, const void* method)"),
+    (0x2000, "RuntimeInvoker_0", None),
+]
+
+labels = [
+    (0x3000, "g_CodeRegistration"),
+]
+"#;
+        assert_eq!(write(&script(), ScriptFormat::IdaPython), format!("{}{}", symbols, IDA_SCRIPT));
+        assert_eq!(
+            write(&script(), ScriptFormat::BinaryNinjaPython),
+            format!("{}{}", symbols, BINARY_NINJA_SCRIPT)
+        );
+        let ghidra = write(&script(), ScriptFormat::GhidraPython);
+        assert!(ghidra.starts_with("functions = [\n    (0x1000, u\"Foo__Run\", u\"void Foo__Run("));
+        assert!(ghidra.contains("    (0x2000, u\"RuntimeInvoker_0\", None),\n"));
+        assert!(ghidra.ends_with(GHIDRA_SCRIPT));
+    }
+
+    #[test]
+    fn escaping() {
+        let s = "a\"b\\c\nd\te\u{e9}\u{1F600}";
+        assert_eq!(quote(s), r#""a\"b\\c\nd\u0009e\u00e9\ud83d\ude00""#);
+        assert_eq!(quote_python(s, ""), r#""a\"b\\c\nd\u0009e\u00e9\U0001f600""#);
+        assert_eq!(quote_python(s, "u"), r#"u"a\"b\\c\nd\u0009e\u00e9\U0001f600""#);
+    }
+}
@@ -0,0 +1,249 @@
+//! Loading metadata directly from Android application archives.
+//!
+//! Android IL2CPP games are distributed as an `.apk`, which is a zip file
+//! containing the game binary at `lib/<abi>/libil2cpp.so` and the global
+//! metadata at `assets/bin/Data/Managed/Metadata/global-metadata.dat`. Games
+//! distributed through app bundles are instead split across several APKs
+//! (`base.apk`, `config.arm64_v8a.apk`, ...), which are sometimes packed into
+//! a single `.xapk` or `.apks` zip file.
+//!
+//! [`ArchiveContents`] handles all of these layouts. This module requires the
+//! `archive` feature.
+
+use crate::owned::OwnedMetadata;
+use crate::MetadataParseError;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek};
+use std::path::Path;
+use thiserror::Error;
+use zip::ZipArchive;
+
+/// Paths relative to the root of an APK where the global metadata may be
+/// found.
+const GLOBAL_METADATA_PATHS: &[&str] = &[
+    "assets/bin/Data/Managed/Metadata/global-metadata.dat",
+    "assets/bin/Data/Metadata/global-metadata.dat",
+];
+
+/// The name of the game binary inside of the `lib/<abi>` directory.
+const BINARY_NAME: &str = "libil2cpp.so";
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+    #[error("could not find {BINARY_NAME} for abi {0}")]
+    MissingBinary(&'static str),
+
+    #[error("could not find global-metadata.dat")]
+    MissingGlobalMetadata,
+
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+type Result<T> = std::result::Result<T, ArchiveError>;
+
+/// An Android application binary interface.
+///
+/// Only [`Abi::Arm64V8a`] binaries can currently be read by
+/// [`RuntimeMetadata`](crate::runtime_metadata::RuntimeMetadata).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Abi {
+    #[default]
+    Arm64V8a,
+    ArmeabiV7a,
+    X86,
+    X86_64,
+}
+
+impl Abi {
+    /// The name of the `lib/` subdirectory for this ABI, e.g. `arm64-v8a`.
+    pub fn dir_name(self) -> &'static str {
+        match self {
+            Abi::Arm64V8a => "arm64-v8a",
+            Abi::ArmeabiV7a => "armeabi-v7a",
+            Abi::X86 => "x86",
+            Abi::X86_64 => "x86_64",
+        }
+    }
+
+    /// The name of the split APK containing this ABI's native libraries,
+    /// e.g. `config.arm64_v8a.apk`.
+    pub fn split_apk_name(self) -> String {
+        format!("config.{}.apk", self.dir_name().replace('-', "_"))
+    }
+
+    fn binary_path(self) -> String {
+        format!("lib/{}/{BINARY_NAME}", self.dir_name())
+    }
+}
+
+/// The game binary and global metadata extracted from an application archive.
+///
+/// Both files are read into memory, so the contents own their data and can be
+/// parsed into an [`OwnedMetadata`] with [`ArchiveContents::parse()`].
+#[derive(Debug)]
+pub struct ArchiveContents {
+    /// The ABI that the binary was selected for.
+    pub abi: Abi,
+    /// The contents of `global-metadata.dat`.
+    pub global_metadata: Vec<u8>,
+    /// The contents of `libil2cpp.so`.
+    pub binary: Vec<u8>,
+}
+
+impl ArchiveContents {
+    /// Opens an APK, XAPK or APKS file and extracts the [`Abi::Arm64V8a`]
+    /// binary.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with_abi(path, Abi::default())
+    }
+
+    /// Opens an APK, XAPK or APKS file and extracts the binary for `abi`.
+    pub fn open_with_abi<P: AsRef<Path>>(path: P, abi: Abi) -> Result<Self> {
+        Self::open_split([path], abi)
+    }
+
+    /// Opens a set of split APKs, such as `base.apk` together with
+    /// `config.arm64_v8a.apk`, and extracts the binary for `abi`.
+    ///
+    /// Each path may also be an XAPK or APKS bundle.
+    pub fn open_split<I, P>(paths: I, abi: Abi) -> Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut files = FoundFiles::default();
+        for path in paths {
+            files.search(File::open(path)?, abi, true)?;
+        }
+        files.finish(abi)
+    }
+
+    /// Reads an APK, XAPK or APKS file from `reader` and extracts the binary
+    /// for `abi`.
+    pub fn from_reader<R: Read + Seek>(reader: R, abi: Abi) -> Result<Self> {
+        let mut files = FoundFiles::default();
+        files.search(reader, abi, true)?;
+        files.finish(abi)
+    }
+
+    /// Parses the extracted files into an [`OwnedMetadata`].
+    pub fn parse(self) -> std::result::Result<OwnedMetadata, MetadataParseError> {
+        OwnedMetadata::from_archive(self)
+    }
+}
+
+#[derive(Default)]
+struct FoundFiles {
+    global_metadata: Option<Vec<u8>>,
+    binary: Option<Vec<u8>>,
+}
+
+impl FoundFiles {
+    /// Searches a zip archive for the binary and global metadata. If `nested`
+    /// is true, APKs stored inside of the archive are searched as well.
+    fn search<R: Read + Seek>(&mut self, reader: R, abi: Abi, nested: bool) -> Result<()> {
+        let mut archive = ZipArchive::new(reader)?;
+        let binary_path = abi.binary_path();
+
+        let mut nested_apks = Vec::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i)?;
+            let name = file.name();
+            if self.binary.is_none() && name == binary_path {
+                self.binary = Some(read_file(&mut file)?);
+            } else if self.global_metadata.is_none() && GLOBAL_METADATA_PATHS.contains(&name) {
+                self.global_metadata = Some(read_file(&mut file)?);
+            } else if nested && name.ends_with(".apk") {
+                // Check the split APK for our ABI first so that we don't have
+                // to decompress every other split.
+                let priority = name.ends_with(&abi.split_apk_name());
+                nested_apks.push((!priority, i));
+            }
+        }
+
+        nested_apks.sort();
+        for (_, i) in nested_apks {
+            if self.binary.is_some() && self.global_metadata.is_some() {
+                break;
+            }
+            let data = read_file(&mut archive.by_index(i)?)?;
+            self.search(Cursor::new(data), abi, false)?;
+        }
+
+        Ok(())
+    }
+
+    fn finish(self, abi: Abi) -> Result<ArchiveContents> {
+        Ok(ArchiveContents {
+            abi,
+            global_metadata: self.global_metadata.ok_or(ArchiveError::MissingGlobalMetadata)?,
+            binary: self.binary.ok_or(ArchiveError::MissingBinary(abi.dir_name()))?,
+        })
+    }
+}
+
+/// The maximum number of bytes to preallocate when reading a file, since the
+/// size stored in the archive can't be trusted.
+const MAX_PREALLOC: usize = 0x10000;
+
+fn read_file(file: &mut zip::read::ZipFile) -> io::Result<Vec<u8>> {
+    let mut data = Vec::with_capacity((file.size() as usize).min(MAX_PREALLOC));
+    file.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::{FileOptions, ZipWriter};
+
+    const METADATA: &[u8] = b"global metadata";
+    const BINARY: &[u8] = b"binary";
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn apk() {
+        let apk = zip(&[
+            ("lib/armeabi-v7a/libil2cpp.so", b"other binary"),
+            ("lib/arm64-v8a/libil2cpp.so", BINARY),
+            ("assets/bin/Data/Managed/Metadata/global-metadata.dat", METADATA),
+        ]);
+        let contents = ArchiveContents::from_reader(Cursor::new(apk), Abi::Arm64V8a).unwrap();
+        assert_eq!(contents.abi, Abi::Arm64V8a);
+        assert_eq!(contents.global_metadata, METADATA);
+        assert_eq!(contents.binary, BINARY);
+    }
+
+    #[test]
+    fn split_apks() {
+        let base = zip(&[("assets/bin/Data/Metadata/global-metadata.dat", METADATA)]);
+        let config = zip(&[("lib/arm64-v8a/libil2cpp.so", BINARY)]);
+        let xapk = zip(&[("base.apk", &base), ("config.arm64_v8a.apk", &config)]);
+        let contents = ArchiveContents::from_reader(Cursor::new(xapk), Abi::Arm64V8a).unwrap();
+        assert_eq!(contents.global_metadata, METADATA);
+        assert_eq!(contents.binary, BINARY);
+    }
+
+    #[test]
+    fn missing_files() {
+        let apk = zip(&[("lib/x86/libil2cpp.so", BINARY)]);
+        let err = ArchiveContents::from_reader(Cursor::new(apk), Abi::X86).unwrap_err();
+        assert!(matches!(err, ArchiveError::MissingGlobalMetadata));
+        let apk = zip(&[("assets/bin/Data/Metadata/global-metadata.dat", METADATA)]);
+        let err = ArchiveContents::from_reader(Cursor::new(apk), Abi::X86).unwrap_err();
+        assert!(matches!(err, ArchiveError::MissingBinary("x86")));
+    }
+}
@@ -0,0 +1,130 @@
+//! Helpers shared by the writers that generate source code, such as
+//! [`crate::cpp_header`], [`crate::rust_bindings`] and
+//! [`crate::analysis_script`].
+
+use std::collections::HashSet;
+
+/// A data member of a struct.
+pub(crate) struct Member {
+    pub offset: u32,
+    /// `None` if the size is not known, in which case the member takes up
+    /// the space until the next member.
+    pub size: Option<u32>,
+    /// `None` if the type cannot be written in the target language, in which
+    /// case the member is written as a byte array.
+    pub ty: Option<String>,
+    pub name: String,
+}
+
+/// A part of a struct placed by [`place_members`].
+pub(crate) enum Slot<'a> {
+    /// Unnamed padding, numbered in the order it appears in the struct.
+    Padding { index: u32, size: u32 },
+    /// A member and the space it takes up. Members without a type or a known
+    /// size are written as byte arrays of that size.
+    Member(&'a Member, u32),
+    /// A member that overlaps another member. Members that overlap, as in
+    /// explicit layouts, cannot be placed without a union.
+    Overlap(&'a Member),
+}
+
+/// The members of a struct in the order they are written.
+pub(crate) struct Placement<'a> {
+    pub slots: Vec<Slot<'a>>,
+    /// The size of the struct, if it is known and the members end exactly at
+    /// it.
+    pub size: Option<u32>,
+}
+
+/// Sorts `members` by offset and places them with explicit padding between
+/// them and up to the struct's `size`.
+pub(crate) fn place_members(members: &mut [Member], size: Option<u32>) -> Placement<'_> {
+    members.sort_by_key(|member| member.offset);
+    let mut slots = Vec::new();
+    let mut end = 0;
+    let mut padding = 0;
+    for (i, member) in members.iter().enumerate() {
+        let next = members[i + 1..]
+            .iter()
+            .map(|next| next.offset)
+            .find(|&offset| offset > member.offset)
+            .or(size);
+        let member_size = match member.size {
+            Some(size) => Some(size),
+            None => next.and_then(|next| next.checked_sub(member.offset)),
+        };
+        let Some(member_size) = member_size.filter(|_| member.offset >= end) else {
+            slots.push(Slot::Overlap(member));
+            continue;
+        };
+        if member.offset > end {
+            slots.push(Slot::Padding { index: padding, size: member.offset - end });
+            padding += 1;
+        }
+        slots.push(Slot::Member(member, member_size));
+        end = member.offset + member_size;
+    }
+    if let Some(size) = size.filter(|&size| size > end) {
+        slots.push(Slot::Padding { index: padding, size: size - end });
+        end = size;
+    }
+    Placement {
+        slots,
+        size: size.filter(|&size| size == end),
+    }
+}
+
+/// Makes `name` unique among the names in `used` by adding a number.
+pub(crate) fn unique_name(name: String, used: &mut HashSet<String>) -> String {
+    let mut unique = name.clone();
+    let mut i = 1;
+    while !used.insert(unique.clone()) {
+        unique = format!("{}_{}", name, i);
+        i += 1;
+    }
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(offset: u32, size: Option<u32>, name: &str) -> Member {
+        Member {
+            offset,
+            size,
+            ty: Some("int".into()),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn padding_and_overlaps() {
+        let mut members = vec![
+            member(0x8, Some(4), "b"),
+            member(0x8, Some(4), "c"),
+            member(0x0, Some(2), "a"),
+            member(0xC, None, "d"),
+        ];
+        let placement = place_members(&mut members, Some(0x18));
+        let slots: Vec<_> = placement
+            .slots
+            .iter()
+            .map(|slot| match slot {
+                Slot::Padding { index, size } => format!("pad{} {}", index, size),
+                Slot::Member(member, size) => format!("{} {}", member.name, size),
+                Slot::Overlap(member) => format!("{} overlaps", member.name),
+            })
+            .collect();
+        assert_eq!(slots, ["a 2", "pad0 6", "b 4", "c overlaps", "d 12"]);
+        assert_eq!(placement.size, Some(0x18));
+    }
+
+    #[test]
+    fn unique_names() {
+        let mut used = HashSet::from(["a".to_string()]);
+        assert_eq!(unique_name("a".into(), &mut used), "a_1");
+        assert_eq!(unique_name("a".into(), &mut used), "a_2");
+        assert_eq!(unique_name("b".into(), &mut used), "b");
+    }
+}
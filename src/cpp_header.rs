@@ -0,0 +1,689 @@
+//! Generation of C++ headers for managed types.
+//!
+//! [`CppHeaderWriter`] writes one header per type definition that declares
+//! the memory layout of the type as a C++ struct, using the field offsets and
+//! type sizes that IL2CPP compiles into the binary. Every member is placed at
+//! its offset with explicit padding and checked with a `static_assert`, so a
+//! header that disagrees with the binary fails to compile instead of reading
+//! the wrong memory.
+//!
+//! Each header declares:
+//! * the instance layout, which starts with the object header for reference
+//!   types and is the unboxed value for value types,
+//! * a `_StaticFields` struct with the layout of the type's static fields,
+//! * an `enum class` for enums, with the values of their constants,
+//! * a function pointer type and accessor for every method with compiled
+//!   code, relative to `il2cpp_base`, which the including code must define.
+//!
+//! Only 64-bit binaries are supported. Generic type definitions have no
+//! layout of their own and are only forward declared, and fields whose type
+//! is an instance of a generic value type are written as byte arrays. Types
+//! with the same name, such as the `<Module>` type of every image, are
+//! numbered in the order of their type definitions, and so are their
+//! headers.
+
+use crate::codegen::{place_members, unique_name, Member, Slot};
+use crate::global_metadata::constant::ConstantValue;
+use crate::global_metadata::{Il2CppTypeDefinition, TypeDefinitionIndex, TypeKind};
+use crate::layout::{FieldLayout, Layout, POINTER_SIZE};
+use crate::lookup::MetadataIndex;
+use crate::runtime_metadata::{Il2CppType, Il2CppTypeEnum, TypeData};
+use crate::signature::MethodSignature;
+use crate::type_name::{TypeNameFormatter, TypeNameStyle};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fmt::Write as _;
+use std::path::Path;
+use std::{fs, io};
+
+/// The name of the header with the runtime types that every generated header
+/// includes.
+pub const PRELUDE_NAME: &str = "il2cpp-types.h";
+
+/// The runtime types used by the generated headers.
+pub const PRELUDE: &str = r#"#pragma once
+#include <cstddef>
+#include <cstdint>
+
+// The address the game binary is loaded at. Define this before calling any
+// method accessor.
+extern uintptr_t il2cpp_base;
+
+struct MethodInfo;
+
+struct Il2CppObject {
+    void* klass;
+    void* monitor;
+};
+
+struct Il2CppString {
+    Il2CppObject object;
+    int32_t length;
+    char16_t chars[32];
+};
+
+struct Il2CppArrayBounds {
+    uintptr_t length;
+    int32_t lower_bound;
+};
+
+template <typename T>
+struct Il2CppArray {
+    Il2CppObject object;
+    Il2CppArrayBounds* bounds;
+    uintptr_t max_length;
+    T vector[32];
+};
+"#;
+
+/// The names declared by [`PRELUDE`], which type names must not shadow.
+const PRELUDE_ITEMS: &[&str] = &["il2cpp_base", "MethodInfo", "Il2CppObject", "Il2CppString", "Il2CppArrayBounds", "Il2CppArray"];
+
+/// Writes C++ headers for the types in the metadata.
+pub struct CppHeaderWriter<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+    /// Finds the code of methods.
+    index: MetadataIndex<'md, 'gmd, 'rmd>,
+    /// Renders identifiers.
+    names: TypeNameFormatter<'md, 'gmd, 'rmd>,
+    /// Renders the full names of types in comments.
+    display_names: TypeNameFormatter<'md, 'gmd, 'rmd>,
+    /// The struct name of every type definition, which is unique among all
+    /// headers.
+    type_names: Vec<String>,
+    /// The header name of every type definition, which is unique even on
+    /// file systems that ignore case.
+    header_names: Vec<String>,
+}
+
+/// The types a header refers to.
+#[derive(Default)]
+struct Dependencies {
+    /// Headers of types that are used by value.
+    includes: BTreeSet<String>,
+    /// Declarations of types that are only used through pointers, by name.
+    forward: BTreeMap<String, String>,
+}
+
+impl<'md, 'gmd, 'rmd> CppHeaderWriter<'md, 'gmd, 'rmd> {
+    pub fn new(metadata: &'md Metadata<'gmd, 'rmd>) -> Self {
+        let names = TypeNameFormatter::new(metadata, TypeNameStyle::Cpp);
+        let type_names = names.unique_type_definition_names(PRELUDE_ITEMS, &["_StaticFields"]);
+        let mut used = HashSet::from([PRELUDE_NAME.to_lowercase()]);
+        let header_names = type_names
+            .iter()
+            .map(|name| {
+                let mut header = format!("{}.h", name);
+                let mut i = 1;
+                while !used.insert(header.to_lowercase()) {
+                    header = format!("{}_{}.h", name, i);
+                    i += 1;
+                }
+                header
+            })
+            .collect();
+        Self {
+            metadata,
+            index: MetadataIndex::new(metadata),
+            names,
+            display_names: TypeNameFormatter::new(metadata, TypeNameStyle::Reflection),
+            type_names,
+            header_names,
+        }
+    }
+
+    /// The name of the struct of a type definition.
+    pub fn type_name(&self, idx: TypeDefinitionIndex) -> String {
+        match self.type_names.get(idx.index() as usize) {
+            Some(name) => name.clone(),
+            None => self.names.format_type_definition(idx),
+        }
+    }
+
+    /// The name of the header for a type, such as
+    /// `UnityEngine_Vector3.h`.
+    pub fn header_name(&self, idx: TypeDefinitionIndex) -> String {
+        match self.header_names.get(idx.index() as usize) {
+            Some(name) => name.clone(),
+            None => format!("{}.h", self.type_name(idx)),
+        }
+    }
+
+    /// Writes [`PRELUDE`] and the header of every type definition into
+    /// `dir`.
+    pub fn write_all(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(PRELUDE_NAME), PRELUDE)?;
+        for i in 0..self.metadata.global_metadata.type_definitions.len() {
+            let idx = TypeDefinitionIndex::new(i as u32);
+            fs::write(dir.join(self.header_name(idx)), self.write_type(idx))?;
+        }
+        Ok(())
+    }
+
+    /// Renders the header of a single type.
+    pub fn write_type(&self, idx: TypeDefinitionIndex) -> String {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let mut out = String::new();
+        let Some(ty_def) = gm.type_definitions.get(idx) else {
+            return out;
+        };
+        let name = self.type_name(idx);
+        let mut deps = Dependencies::default();
+        let mut body = String::new();
+
+        let kind = ty_def.kind(metadata);
+        if ty_def.generic_container_index.is_valid() {
+            body.push_str("// Generic type definitions have no layout.\n");
+            let _ = writeln!(body, "struct {};", name);
+        } else if kind == TypeKind::Interface {
+            let _ = writeln!(body, "struct {};", name);
+        } else if let Some(layout) = metadata.type_layout(idx) {
+            if kind == TypeKind::Enum {
+                self.push_enum(ty_def, &name, &mut deps, &mut body);
+            } else {
+                self.push_instance_layout(&layout, &name, &mut deps, &mut body);
+            }
+            self.push_static_layout(&layout, &name, &mut deps, &mut body);
+        } else {
+            body.push_str("// The layout of this type is not known.\n");
+            let _ = writeln!(body, "struct {};", name);
+        }
+        self.push_methods(ty_def, &name, kind, &mut deps, &mut body);
+
+        let _ = writeln!(out, "// {}", self.display_names.format_type_definition(idx));
+        let _ = writeln!(out, "// TypeDefIndex: {}", idx.index());
+        out.push_str("#pragma once\n");
+        let _ = writeln!(out, "#include \"{}\"", PRELUDE_NAME);
+        let own_header = self.header_name(idx);
+        for include in deps.includes.iter().filter(|&include| *include != own_header) {
+            let _ = writeln!(out, "#include \"{}\"", include);
+        }
+        out.push('\n');
+        let mut forward = deps.forward.iter().filter(|&(ty, _)| *ty != name).peekable();
+        if forward.peek().is_some() {
+            for (_, declaration) in forward {
+                out.push_str(declaration);
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        out.push_str(&body);
+        out
+    }
+
+    fn push_enum(&self, ty_def: &Il2CppTypeDefinition, name: &str, deps: &mut Dependencies, out: &mut String) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let underlying = metadata
+            .runtime_metadata
+            .metadata_registration
+            .types
+            .get(ty_def.element_type_index as usize)
+            .and_then(|ty| self.cpp_type(ty, deps))
+            .unwrap_or_else(|| "int32_t".into());
+
+        let _ = writeln!(out, "enum class {} : {} {{", name, underlying);
+        let mut used = HashSet::new();
+        for field_idx in ty_def.field_start.indices(ty_def.field_count as u32) {
+            let Some(field) = gm.fields.get(field_idx) else {
+                continue;
+            };
+            if !field.is_literal(metadata) {
+                continue;
+            }
+            let Some(value) = metadata.global_metadata.field_default_values.get_by_field(field_idx).and_then(|value| value.value(metadata)) else {
+                continue;
+            };
+            let value = match value {
+                ConstantValue::I1(value) => value.to_string(),
+                ConstantValue::U1(value) => value.to_string(),
+                ConstantValue::I2(value) => value.to_string(),
+                ConstantValue::U2(value) | ConstantValue::Char(value) => value.to_string(),
+                ConstantValue::I4(i32::MIN) => "INT32_MIN".into(),
+                ConstantValue::I4(value) => value.to_string(),
+                ConstantValue::U4(value) => format!("{}u", value),
+                ConstantValue::I8(i64::MIN) => "INT64_MIN".into(),
+                ConstantValue::I8(value) => format!("{}ll", value),
+                ConstantValue::U8(value) => format!("{}ull", value),
+                ConstantValue::Boolean(value) => (value as u8).to_string(),
+                _ => continue,
+            };
+            let member = unique_name(self.names.identifier(gm.string.get(field.name_index).unwrap_or_default()), &mut used);
+            let _ = writeln!(out, "    {} = {},", member, value);
+        }
+        out.push_str("};\n");
+    }
+
+    fn push_instance_layout(&self, layout: &Layout, name: &str, deps: &mut Dependencies, out: &mut String) {
+        let mut used = HashSet::new();
+        let mut members = Vec::new();
+        if !layout.is_value_type {
+            for header in ["klass", "monitor"] {
+                members.push(Member {
+                    offset: members.len() as u32 * POINTER_SIZE,
+                    size: Some(POINTER_SIZE),
+                    ty: Some("void*".into()),
+                    name: unique_name(header.into(), &mut used),
+                });
+            }
+        }
+        // Fields inherited from generic instances keep their declared type,
+        // so those of type parameters are written as byte arrays.
+        members.extend(self.members(&layout.instance_fields, &mut used, deps));
+        push_struct(name, members, layout.instance_size, out);
+    }
+
+    fn push_static_layout(&self, layout: &Layout, name: &str, deps: &mut Dependencies, out: &mut String) {
+        if layout.static_fields.is_empty() {
+            return;
+        }
+        let members = self.members(&layout.static_fields, &mut HashSet::new(), deps);
+        out.push('\n');
+        push_struct(&format!("{}_StaticFields", name), members, layout.static_fields_size, out);
+    }
+
+    fn members(&self, fields: &[FieldLayout], used: &mut HashSet<String>, deps: &mut Dependencies) -> Vec<Member> {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let mut members = Vec::new();
+        for field in fields {
+            let Some(definition) = gm.fields.get(field.field) else {
+                continue;
+            };
+            let Some(ty) = metadata.runtime_metadata.metadata_registration.types.get(field.type_index as usize) else {
+                continue;
+            };
+            let name = self.names.identifier(gm.string.get(definition.name_index).unwrap_or_default());
+            members.push(Member {
+                offset: field.offset,
+                size: field.size,
+                ty: self.member_type(ty, deps),
+                name: unique_name(name, used),
+            });
+        }
+        members
+    }
+
+    fn push_methods(
+        &self,
+        ty_def: &Il2CppTypeDefinition,
+        name: &str,
+        kind: TypeKind,
+        deps: &mut Dependencies,
+        out: &mut String,
+    ) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let mut used = HashSet::new();
+        let mut first = true;
+        for method in ty_def.methods(metadata).unwrap_or_default() {
+            let Some(rva) = method.method_pointer(&self.index) else {
+                continue;
+            };
+            let method_name = self.names.identifier(gm.string.get(method.name_index).unwrap_or_default());
+            let function = unique_name(format!("{}__{}", name, method_name), &mut used);
+            if first {
+                first = false;
+                out.push('\n');
+            }
+            let signature = method.signature(metadata);
+            let Some(parameters) = signature.as_ref().and_then(|signature| self.parameters(signature, name, kind, deps))
+            else {
+                let _ = writeln!(out, "// {}: signature cannot be written in C++, RVA: 0x{:X}", function, rva);
+                continue;
+            };
+            let Some(return_type) = signature.and_then(|signature| self.cpp_type(&signature.return_type, deps)) else {
+                let _ = writeln!(out, "// {}: signature cannot be written in C++, RVA: 0x{:X}", function, rva);
+                continue;
+            };
+            let _ = writeln!(out, "// RVA: 0x{:X}", rva);
+            let _ = writeln!(out, "using {}_t = {} (*)({});", function, return_type, parameters.join(", "));
+            let _ = writeln!(
+                out,
+                "inline {0}_t {0}() {{ return reinterpret_cast<{0}_t>(il2cpp_base + 0x{1:X}); }}",
+                function, rva
+            );
+        }
+    }
+
+    /// The parameters of the compiled function of a method, which takes
+    /// `this` first and the method's `MethodInfo` last.
+    fn parameters(
+        &self,
+        signature: &MethodSignature,
+        name: &str,
+        kind: TypeKind,
+        deps: &mut Dependencies,
+    ) -> Option<Vec<String>> {
+        let mut used = HashSet::from(["__this".to_string(), "method".to_string()]);
+        let mut parameters = Vec::new();
+        if !signature.is_static() {
+            // The `this` of value types points to the unboxed value.
+            parameters.push(match kind {
+                TypeKind::Struct | TypeKind::Enum => format!("{}* __this", name),
+                _ => format!("{}* __this", self.pointee(name, deps)),
+            });
+        }
+        for param in &signature.parameters {
+            let ty = self.cpp_type(&param.ty, deps)?;
+            let name = unique_name(self.names.identifier(param.name), &mut used);
+            parameters.push(format!("{} {}", ty, name));
+        }
+        parameters.push("const MethodInfo* method".into());
+        Some(parameters)
+    }
+
+    /// The C++ type of a field, which is the type by value for value types.
+    fn member_type(&self, ty: &Il2CppType, deps: &mut Dependencies) -> Option<String> {
+        if let (false, TypeData::TypeDefinitionIndex(idx)) = (ty.byref, ty.data) {
+            if ty.ty == Il2CppTypeEnum::Valuetype {
+                deps.includes.insert(self.header_name(idx));
+            }
+        }
+        self.cpp_type(ty, deps)
+    }
+
+    /// The C++ type of a value of `ty`, or `None` if its layout is not known.
+    fn cpp_type(&self, ty: &Il2CppType, deps: &mut Dependencies) -> Option<String> {
+        self.cpp_type_inner(ty, deps, 0)
+    }
+
+    fn cpp_type_inner(&self, ty: &Il2CppType, deps: &mut Dependencies, depth: usize) -> Option<String> {
+        let metadata = self.metadata;
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        if depth > MAX_TYPE_DEPTH {
+            return None;
+        }
+        if ty.byref {
+            let element = Il2CppType { byref: false, ..*ty };
+            return Some(format!("{}*", self.cpp_type_inner(&element, deps, depth + 1)?));
+        }
+        let name = match (ty.ty, ty.data) {
+            (Il2CppTypeEnum::Void, _) => "void".into(),
+            (Il2CppTypeEnum::Boolean, _) => "bool".into(),
+            (Il2CppTypeEnum::Char, _) => "char16_t".into(),
+            (Il2CppTypeEnum::I1, _) => "int8_t".into(),
+            (Il2CppTypeEnum::U1, _) => "uint8_t".into(),
+            (Il2CppTypeEnum::I2, _) => "int16_t".into(),
+            (Il2CppTypeEnum::U2, _) => "uint16_t".into(),
+            (Il2CppTypeEnum::I4, _) => "int32_t".into(),
+            (Il2CppTypeEnum::U4, _) => "uint32_t".into(),
+            (Il2CppTypeEnum::I8, _) => "int64_t".into(),
+            (Il2CppTypeEnum::U8, _) => "uint64_t".into(),
+            (Il2CppTypeEnum::R4, _) => "float".into(),
+            (Il2CppTypeEnum::R8, _) => "double".into(),
+            (Il2CppTypeEnum::I, _) => "intptr_t".into(),
+            (Il2CppTypeEnum::U, _) => "uintptr_t".into(),
+            (Il2CppTypeEnum::Fnptr, _) => "void*".into(),
+            (Il2CppTypeEnum::String, _) => "Il2CppString*".into(),
+            (Il2CppTypeEnum::Object, _) => "Il2CppObject*".into(),
+            (Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(idx)) => {
+                let name = self.type_name(idx);
+                format!("{}*", self.pointee(&name, deps))
+            }
+            (Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(idx)) => {
+                let ty_def = metadata.global_metadata.type_definitions.get(idx)?;
+                if ty_def.generic_container_index.is_valid() {
+                    return None;
+                }
+                let name = self.type_name(idx);
+                let declaration = match ty_def.layout_info().is_enum {
+                    true => {
+                        let underlying = mr.types.get(ty_def.element_type_index as usize)?;
+                        format!("enum class {} : {};", name, self.cpp_type_inner(underlying, deps, depth + 1)?)
+                    }
+                    false => format!("struct {};", name),
+                };
+                deps.forward.insert(name.clone(), declaration);
+                name
+            }
+            (Il2CppTypeEnum::Ptr, TypeData::TypeIndex(element)) => {
+                let element = mr.types.get(element)?;
+                let element = self.cpp_type_inner(element, deps, depth + 1).unwrap_or_else(|| "void".into());
+                format!("{}*", element)
+            }
+            (Il2CppTypeEnum::Szarray, TypeData::TypeIndex(element)) => {
+                let element = mr.types.get(element)?;
+                let element = self.cpp_type_inner(element, deps, depth + 1).unwrap_or_else(|| "Il2CppObject*".into());
+                format!("Il2CppArray<{}>*", element)
+            }
+            (Il2CppTypeEnum::Array, TypeData::ArrayType(array)) => {
+                let element = mr.array_types.get(array).and_then(|array| mr.types.get(array.elem_ty))?;
+                let element = self.cpp_type_inner(element, deps, depth + 1).unwrap_or_else(|| "Il2CppObject*".into());
+                format!("Il2CppArray<{}>*", element)
+            }
+            (Il2CppTypeEnum::Genericinst, _) => match metadata.is_value_type(ty)? {
+                true => return None,
+                false => "Il2CppObject*".into(),
+            },
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// Forward declares a reference type.
+    fn pointee(&self, name: &str, deps: &mut Dependencies) -> String {
+        deps.forward.insert(name.to_string(), format!("struct {};", name));
+        name.to_string()
+    }
+}
+
+/// Writes a packed struct with explicit padding between `members`, followed
+/// by assertions of the offset of each member and of the struct's `size`.
+fn push_struct(name: &str, mut members: Vec<Member>, size: Option<u32>, out: &mut String) {
+    let placement = place_members(&mut members, size);
+    let _ = writeln!(out, "#pragma pack(push, 1)\nstruct {} {{", name);
+    let mut asserts = Vec::new();
+    for slot in &placement.slots {
+        match slot {
+            Slot::Padding { index, size } => {
+                let _ = writeln!(out, "    uint8_t _padding{}[0x{:X}];", index, size);
+            }
+            Slot::Member(member, size) => {
+                match &member.ty {
+                    Some(ty) if member.size.is_some() => {
+                        let _ = writeln!(out, "    {} {}; // 0x{:X}", ty, member.name, member.offset);
+                    }
+                    _ => {
+                        let _ = writeln!(out, "    uint8_t {}[0x{:X}]; // 0x{:X}", member.name, size, member.offset);
+                    }
+                }
+                asserts.push(format!(
+                    "static_assert(offsetof({0}, {1}) == 0x{2:X}, \"{0}::{1}\");",
+                    name, member.name, member.offset
+                ));
+            }
+            Slot::Overlap(member) => {
+                let _ = writeln!(out, "    // {} at 0x{:X} overlaps another member", member.name, member.offset);
+            }
+        }
+    }
+    out.push_str("};\n#pragma pack(pop)\n");
+    for assert in asserts {
+        out.push_str(&assert);
+        out.push('\n');
+    }
+    if let Some(size) = placement.size {
+        let _ = writeln!(out, "static_assert(sizeof({0}) == 0x{1:X}, \"sizeof({0})\");", name, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{
+        FieldAndParameterDefaultValueIndex, FieldIndex, Il2CppFieldDefaultValue, Il2CppFieldDefinition, StringIndex,
+    };
+    use crate::test_util::{self, write_compressed_i32, zeroed};
+
+    const STRINGS: &str = "\0Game\0Vec2\0x\0y\0Base\0id\0Node\0position\0next\0count\0Color\0value__\0Red\0Green\0";
+
+    fn string(name: &str) -> StringIndex {
+        StringIndex::new(STRINGS.find(&format!("\0{}\0", name)).unwrap() as u32 + 1)
+    }
+
+    /// The struct `Game.Vec2`, the class `Game.Node : Game.Base` and the enum
+    /// `Game.Color`.
+    fn metadata() -> Metadata<'static, 'static> {
+        let mut metadata = test_util::metadata(STRINGS);
+        let definition = |idx: u32| TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(idx));
+        // The attributes of fields: 0x6 is `public`, 0x16 `public static`
+        // and 0x56 `public const`.
+        let ty = |ty: Il2CppTypeEnum, data: TypeData, attrs: u16| Il2CppType {
+            attrs,
+            ..test_util::ty(ty, data)
+        };
+        metadata.runtime_metadata.metadata_registration.types = vec![
+            ty(Il2CppTypeEnum::R4, definition(u32::MAX), 0x6),
+            ty(Il2CppTypeEnum::I4, definition(u32::MAX), 0x6),
+            ty(Il2CppTypeEnum::Valuetype, definition(0), 0x6),
+            ty(Il2CppTypeEnum::Class, definition(2), 0x6),
+            ty(Il2CppTypeEnum::I4, definition(u32::MAX), 0x16),
+            ty(Il2CppTypeEnum::Valuetype, definition(3), 0x56),
+            ty(Il2CppTypeEnum::Class, definition(1), 0),
+            ty(Il2CppTypeEnum::I4, definition(u32::MAX), 0),
+        ];
+
+        let gm = &mut metadata.global_metadata;
+        let fields = [
+            ("x", 0),
+            ("y", 0),
+            ("id", 1),
+            ("position", 2),
+            ("next", 3),
+            ("count", 4),
+            ("value__", 1),
+            ("Red", 5),
+            ("Green", 5),
+        ];
+        gm.fields = fields
+            .iter()
+            .map(|&(name, type_index)| {
+                let mut field: Il2CppFieldDefinition = zeroed();
+                field.name_index = string(name);
+                field.type_index = type_index;
+                field
+            })
+            .collect::<Vec<_>>()
+            .into();
+        let mut data = Vec::new();
+        write_compressed_i32(&mut data, 0);
+        write_compressed_i32(&mut data, 1);
+        gm.field_and_parameter_default_value_data = data.into();
+        let default_value = |field: u32, data: u32| Il2CppFieldDefaultValue {
+            field_index: FieldIndex::new(field),
+            type_index: 7,
+            data_index: FieldAndParameterDefaultValueIndex::new(data),
+        };
+        gm.field_default_values = vec![default_value(7, 0), default_value(8, 1)].into();
+
+        let type_definition = |name: &str, fields: (u32, u16), parent: u32, bitfield: u32| {
+            let mut ty_def = test_util::type_definition();
+            ty_def.namespace_index = string("Game");
+            ty_def.name_index = string(name);
+            ty_def.field_start = FieldIndex::new(fields.0);
+            ty_def.field_count = fields.1;
+            ty_def.parent_index = parent;
+            ty_def.bitfield |= bitfield;
+            ty_def
+        };
+        let mut color = type_definition("Color", (6, 3), u32::MAX, 1 | 2);
+        color.element_type_index = 7;
+        gm.type_definitions = vec![
+            type_definition("Vec2", (0, 2), u32::MAX, 1),
+            type_definition("Base", (2, 1), u32::MAX, 0),
+            type_definition("Node", (3, 3), 6, 0),
+            color,
+        ]
+        .into();
+        metadata
+    }
+
+    #[test]
+    fn value_type() {
+        let metadata = metadata();
+        let header = CppHeaderWriter::new(&metadata).write_type(TypeDefinitionIndex::new(0));
+        assert_eq!(
+            header,
+            r#"// Game.Vec2
+// TypeDefIndex: 0
+#pragma once
+#include "il2cpp-types.h"
+
+#pragma pack(push, 1)
+struct Game_Vec2 {
+    float x; // 0x0
+    float y; // 0x4
+};
+#pragma pack(pop)
+static_assert(offsetof(Game_Vec2, x) == 0x0, "Game_Vec2::x");
+static_assert(offsetof(Game_Vec2, y) == 0x4, "Game_Vec2::y");
+static_assert(sizeof(Game_Vec2) == 0x8, "sizeof(Game_Vec2)");
+"#
+        );
+    }
+
+    #[test]
+    fn reference_type_with_base() {
+        let metadata = metadata();
+        let header = CppHeaderWriter::new(&metadata).write_type(TypeDefinitionIndex::new(2));
+        assert_eq!(
+            header,
+            r#"// Game.Node
+// TypeDefIndex: 2
+#pragma once
+#include "il2cpp-types.h"
+#include "Game_Vec2.h"
+
+struct Game_Vec2;
+
+#pragma pack(push, 1)
+struct Game_Node {
+    void* klass; // 0x0
+    void* monitor; // 0x8
+    int32_t id; // 0x10
+    Game_Vec2 position; // 0x14
+    uint8_t _padding0[0x4];
+    Game_Node* next; // 0x20
+};
+#pragma pack(pop)
+static_assert(offsetof(Game_Node, klass) == 0x0, "Game_Node::klass");
+static_assert(offsetof(Game_Node, monitor) == 0x8, "Game_Node::monitor");
+static_assert(offsetof(Game_Node, id) == 0x10, "Game_Node::id");
+static_assert(offsetof(Game_Node, position) == 0x14, "Game_Node::position");
+static_assert(offsetof(Game_Node, next) == 0x20, "Game_Node::next");
+static_assert(sizeof(Game_Node) == 0x28, "sizeof(Game_Node)");
+
+#pragma pack(push, 1)
+struct Game_Node_StaticFields {
+    int32_t count; // 0x0
+};
+#pragma pack(pop)
+static_assert(offsetof(Game_Node_StaticFields, count) == 0x0, "Game_Node_StaticFields::count");
+static_assert(sizeof(Game_Node_StaticFields) == 0x4, "sizeof(Game_Node_StaticFields)");
+"#
+        );
+    }
+
+    #[test]
+    fn enum_type() {
+        let metadata = metadata();
+        let header = CppHeaderWriter::new(&metadata).write_type(TypeDefinitionIndex::new(3));
+        assert_eq!(
+            header,
+            r#"// Game.Color
+// TypeDefIndex: 3
+#pragma once
+#include "il2cpp-types.h"
+
+enum class Game_Color : int32_t {
+    Red = 0,
+    Green = 1,
+};
+"#
+        );
+    }
+}
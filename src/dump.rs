@@ -0,0 +1,758 @@
+//! Generation of a C#-style dump of the metadata.
+//!
+//! [`CSharpDumper`] writes the declarations of every type in the metadata in
+//! C# syntax, in the style of the `dump.cs` file produced by tools like
+//! Il2CppDumper. Method bodies are not available, so methods are annotated
+//! with the address of their compiled code instead, and fields with their
+//! offsets.
+//!
+//! Custom attributes are decoded from the attribute data of metadata version
+//! 29 and later and written above the types and members they apply to, along
+//! with attributes that are stored as flags, such as `[Serializable]`.
+
+use crate::flags::{
+    FieldAttributes, GenericParameterAttributes, MemberAccess, MethodAttributes, MethodImplAttributes,
+    TypeAttributes, TypeLayout, TypeVisibility, Variance,
+};
+use crate::global_metadata::constant::ConstantValue;
+use crate::global_metadata::custom_attribute::{AttributeArgument, AttributeArgumentType, AttributeValue};
+use crate::global_metadata::{
+    Il2CppImageDefinition, Il2CppMethodDefinition, Il2CppTypeDefinition, ImageIndex, MethodIndex, Token,
+    TypeDefinitionIndex, TypeKind,
+};
+use crate::lookup::MetadataIndex;
+use crate::runtime_metadata::Il2CppType;
+use crate::type_name::{TypeNameFormatter, TypeNameStyle};
+use crate::Metadata;
+use std::fmt::Write as _;
+use std::io;
+
+/// Writes C# declarations for the types in the metadata.
+pub struct CSharpDumper<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+    /// Finds the code of methods.
+    index: MetadataIndex<'md, 'gmd, 'rmd>,
+    /// Renders the types of members.
+    names: TypeNameFormatter<'md, 'gmd, 'rmd>,
+    /// Renders the names of declared types.
+    declaration_names: TypeNameFormatter<'md, 'gmd, 'rmd>,
+    addresses: bool,
+}
+
+impl<'md, 'gmd, 'rmd> CSharpDumper<'md, 'gmd, 'rmd> {
+    /// Creates a dumper that writes type names without namespaces and
+    /// includes method addresses.
+    pub fn new(metadata: &'md Metadata<'gmd, 'rmd>) -> Self {
+        Self {
+            metadata,
+            index: MetadataIndex::new(metadata),
+            names: TypeNameFormatter::new(metadata, TypeNameStyle::CSharp).namespaces(false),
+            declaration_names: TypeNameFormatter::new(metadata, TypeNameStyle::CSharp).namespaces(false),
+            addresses: true,
+        }
+    }
+
+    /// Sets whether the types of members are qualified with their namespace.
+    pub fn namespaces(mut self, namespaces: bool) -> Self {
+        self.names = self.names.namespaces(namespaces);
+        self
+    }
+
+    /// Sets whether methods are annotated with the address of their code and
+    /// their vtable slot.
+    pub fn addresses(mut self, addresses: bool) -> Self {
+        self.addresses = addresses;
+        self
+    }
+
+    /// Writes every image, in the order they appear in the metadata.
+    pub fn write<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        let images = self.metadata.global_metadata.images.as_vec();
+        for i in 0..images.len() {
+            self.write_image(ImageIndex::new(i as u32), out)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the types defined by a single image, which can be used to
+    /// write one file per assembly.
+    pub fn write_image<W: io::Write>(&self, image: ImageIndex, out: &mut W) -> io::Result<()> {
+        let gm = &self.metadata.global_metadata;
+        let Some(image_def) = gm.images.get(image) else {
+            return Ok(());
+        };
+        let name = gm.string.get(image_def.name_index).unwrap_or("?");
+        writeln!(out, "// Image {}: {} - {}", image.index(), name, image_def.type_start.index())?;
+        for idx in image_def.type_start.indices(image_def.type_count) {
+            writeln!(out)?;
+            out.write_all(self.dump_type(idx).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Renders the declaration of a single type and its members.
+    ///
+    /// Nested types are not included. They are dumped on their own, like
+    /// every other type of their image.
+    pub fn dump_type(&self, idx: TypeDefinitionIndex) -> String {
+        let mut out = String::new();
+        let gm = &self.metadata.global_metadata;
+        let Some(ty_def) = gm.type_definitions.get(idx) else {
+            return out;
+        };
+        let image = gm.image_of(idx);
+        self.push_type_header(image, idx, ty_def, &mut out);
+        out.push_str("{\n");
+        self.push_fields(image, idx, ty_def, &mut out);
+        self.push_properties(image, ty_def, &mut out);
+        self.push_events(image, ty_def, &mut out);
+        self.push_methods(image, ty_def, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    fn push_type_header(
+        &self,
+        image: Option<&Il2CppImageDefinition>,
+        idx: TypeDefinitionIndex,
+        ty_def: &Il2CppTypeDefinition,
+        out: &mut String,
+    ) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let attributes = ty_def.attributes();
+        let kind = ty_def.kind(metadata);
+
+        let _ = writeln!(out, "// Namespace: {}", gm.string.get(ty_def.namespace_index).unwrap_or_default());
+        self.push_custom_attributes(image, ty_def.token, "", out);
+        if attributes.contains(TypeAttributes::SERIALIZABLE) {
+            out.push_str("[Serializable]\n");
+        }
+        match attributes.layout() {
+            TypeLayout::Explicit => out.push_str("[StructLayout(LayoutKind.Explicit)]\n"),
+            TypeLayout::Sequential if kind != TypeKind::Struct && kind != TypeKind::Enum => {
+                out.push_str("[StructLayout(LayoutKind.Sequential)]\n")
+            }
+            _ => {}
+        }
+
+        out.push_str(match ty_def.visibility() {
+            TypeVisibility::Public | TypeVisibility::NestedPublic => "public ",
+            TypeVisibility::NotPublic | TypeVisibility::NestedAssembly => "internal ",
+            TypeVisibility::NestedPrivate => "private ",
+            TypeVisibility::NestedFamily => "protected ",
+            TypeVisibility::NestedFamAndAssem => "private protected ",
+            TypeVisibility::NestedFamOrAssem => "protected internal ",
+        });
+        out.push_str(match kind {
+            TypeKind::Interface => "interface ",
+            TypeKind::Enum => "enum ",
+            TypeKind::Struct if ty_def.layout_info().is_byref_like => "ref struct ",
+            TypeKind::Struct => "struct ",
+            TypeKind::StaticClass => "static class ",
+            _ if ty_def.is_abstract() => "abstract class ",
+            _ if ty_def.is_sealed() => "sealed class ",
+            _ => "class ",
+        });
+        out.push_str(&self.declaration_names.format_type_definition(idx));
+
+        let generic_parameters: Vec<_> = ty_def
+            .generic_container(metadata)
+            .and_then(|container| container.generic_parameters(metadata))
+            .unwrap_or_default()
+            .iter()
+            .map(|param| {
+                let constraints = param.constraints(metadata).unwrap_or_default().iter().map(|&ty| self.names.format_index(ty as usize));
+                (gm.string.get(param.name_index).unwrap_or("?"), param.attributes(), constraints.collect())
+            })
+            .collect();
+        push_generic_parameters(&generic_parameters, out);
+
+        let mut bases = Vec::new();
+        match kind {
+            TypeKind::Enum => {
+                if let Some(underlying) = self.format_type_index(ty_def.element_type_index) {
+                    bases.push(underlying);
+                }
+            }
+            TypeKind::Struct | TypeKind::Interface => {}
+            _ => {
+                let is_object = ty_def
+                    .base_type_definition(metadata)
+                    .and_then(|base| gm.type_definitions.get(base))
+                    .is_some_and(|base| {
+                        gm.string.get(base.namespace_index) == Ok("System")
+                            && gm.string.get(base.name_index) == Ok("Object")
+                    });
+                if !is_object {
+                    bases.extend(ty_def.base_type(metadata).map(|base| self.names.format(base)));
+                }
+            }
+        }
+        if kind != TypeKind::Enum {
+            bases.extend(ty_def.interfaces(metadata).unwrap_or_default().iter().filter_map(|&ty| self.format_type_index(ty)));
+        }
+        if !bases.is_empty() {
+            out.push_str(" : ");
+            out.push_str(&bases.join(", "));
+        }
+        push_constraints(&generic_parameters, out);
+        let _ = writeln!(out, " // TypeDefIndex: {}", idx.index());
+    }
+
+    fn push_fields(
+        &self,
+        image: Option<&Il2CppImageDefinition>,
+        idx: TypeDefinitionIndex,
+        ty_def: &Il2CppTypeDefinition,
+        out: &mut String,
+    ) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        if ty_def.field_count == 0 {
+            return;
+        }
+        let offsets = metadata
+            .runtime_metadata
+            .metadata_registration
+            .field_offsets
+            .as_ref()
+            .and_then(|offsets| offsets.get(idx.index() as usize));
+
+        out.push_str("\t// Fields\n");
+        for (i, field_idx) in ty_def.field_start.indices(ty_def.field_count as u32).enumerate() {
+            let Some(field) = gm.fields.get(field_idx) else {
+                continue;
+            };
+            let attributes = field.attributes(metadata);
+            self.push_custom_attributes(image, field.token, "\t", out);
+            out.push('\t');
+            if attributes.contains(FieldAttributes::NOT_SERIALIZED) {
+                out.push_str("[NonSerialized]\n\t");
+            }
+            out.push_str(access_keyword(attributes.access()));
+            if attributes.contains(FieldAttributes::LITERAL) {
+                out.push_str("const ");
+            } else {
+                if attributes.contains(FieldAttributes::STATIC) {
+                    out.push_str("static ");
+                }
+                if attributes.contains(FieldAttributes::INIT_ONLY) {
+                    out.push_str("readonly ");
+                }
+            }
+            out.push_str(&self.format_type_index(field.type_index).unwrap_or_else(|| "?".into()));
+            out.push(' ');
+            out.push_str(gm.string.get(field.name_index).unwrap_or("?"));
+
+            let default_value = match attributes.contains(FieldAttributes::HAS_DEFAULT) {
+                true => metadata.global_metadata.field_default_values.get_by_field(field_idx).and_then(|value| value.value(metadata)),
+                false => None,
+            };
+            if let Some(value) = default_value {
+                out.push_str(" = ");
+                push_constant(value, out);
+            }
+            out.push(';');
+            let offset = offsets.and_then(|offsets| offsets.get(i));
+            if let (false, Some(offset)) = (attributes.contains(FieldAttributes::LITERAL), offset) {
+                let _ = write!(out, " // 0x{:X}", offset);
+            }
+            out.push('\n');
+        }
+    }
+
+    fn push_properties(&self, image: Option<&Il2CppImageDefinition>, ty_def: &Il2CppTypeDefinition, out: &mut String) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        if ty_def.property_count == 0 {
+            return;
+        }
+
+        out.push_str("\n\t// Properties\n");
+        for property in ty_def.properties(metadata).unwrap_or_default() {
+            let getter = property.get_method(ty_def, metadata);
+            let setter = property.set_method(ty_def, metadata);
+            let ty = match (getter, setter) {
+                (Some(getter), _) => self.format_type_index(getter.return_type),
+                (None, Some(setter)) => setter
+                    .parameters(metadata).unwrap_or_default()
+                    .last()
+                    .and_then(|param| self.format_type_index(param.type_index)),
+                (None, None) => None,
+            };
+
+            self.push_custom_attributes(image, property.token, "\t", out);
+            out.push('\t');
+            if let Some(method) = getter.or(setter) {
+                out.push_str(&method_modifiers(method));
+            }
+            out.push_str(&ty.unwrap_or_else(|| "?".into()));
+            out.push(' ');
+            out.push_str(gm.string.get(property.name_index).unwrap_or("?"));
+            out.push_str(" {");
+            if getter.is_some() {
+                out.push_str(" get;");
+            }
+            if setter.is_some() {
+                out.push_str(" set;");
+            }
+            out.push_str(" }\n");
+        }
+    }
+
+    fn push_events(&self, image: Option<&Il2CppImageDefinition>, ty_def: &Il2CppTypeDefinition, out: &mut String) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        if ty_def.event_count == 0 {
+            return;
+        }
+
+        out.push_str("\n\t// Events\n");
+        for event in ty_def.events(metadata).unwrap_or_default() {
+            self.push_custom_attributes(image, event.token, "\t", out);
+            out.push('\t');
+            if let Some(add) = event.add_method(ty_def, metadata) {
+                out.push_str(&method_modifiers(add));
+            }
+            out.push_str("event ");
+            out.push_str(&self.format_type_index(event.type_index).unwrap_or_else(|| "?".into()));
+            out.push(' ');
+            out.push_str(gm.string.get(event.name_index).unwrap_or("?"));
+            out.push_str(";\n");
+        }
+    }
+
+    fn push_methods(&self, image: Option<&Il2CppImageDefinition>, ty_def: &Il2CppTypeDefinition, out: &mut String) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        if ty_def.method_count == 0 {
+            return;
+        }
+
+        out.push_str("\n\t// Methods\n");
+        for (i, method) in ty_def.methods(metadata).unwrap_or_default().iter().enumerate() {
+            if i > 0 {
+                out.push('\n');
+            }
+            if self.addresses {
+                let pointer = method.method_pointer(&self.index);
+                let offset = pointer.and_then(|ptr| metadata.runtime_metadata.file_offset(ptr));
+                let _ = write!(out, "\t// VA: {} Offset: {}", hex_or_none(pointer), hex_or_none(offset));
+                if method.slot != crate::vtable::INVALID_SLOT {
+                    let _ = write!(out, " Slot: {}", method.slot);
+                }
+                out.push('\n');
+            }
+
+            let Some(signature) = method.signature(metadata) else {
+                let name = gm.string.get(method.name_index).unwrap_or("?");
+                let _ = writeln!(out, "\t// {}: signature could not be decoded", name);
+                continue;
+            };
+            self.push_custom_attributes(image, method.token, "\t", out);
+            out.push('\t');
+            out.push_str(&method_modifiers(method));
+            out.push_str(&self.names.format(&signature.return_type));
+            out.push(' ');
+            out.push_str(gm.string.get(method.name_index).unwrap_or("?"));
+
+            let generic_parameters: Vec<_> = signature
+                .generic_parameters
+                .iter()
+                .map(|param| {
+                    let constraints = param.constraints.iter().map(|ty| self.names.format(ty));
+                    (param.name, param.attributes, constraints.collect())
+                })
+                .collect();
+            push_generic_parameters(&generic_parameters, out);
+
+            out.push('(');
+            for (i, param) in signature.parameters.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                if param.is_byref() {
+                    out.push_str(match (param.is_in(), param.is_out()) {
+                        (_, true) => "out ",
+                        (true, false) => "in ",
+                        (false, false) => "ref ",
+                    });
+                }
+                out.push_str(&self.names.format(&Il2CppType { byref: false, ..param.ty }));
+                out.push(' ');
+                out.push_str(param.name);
+                if let Some(value) = param.default_value {
+                    out.push_str(" = ");
+                    push_constant(value, out);
+                }
+            }
+            out.push(')');
+            push_constraints(&generic_parameters, out);
+
+            let has_body = !method.is_abstract()
+                && !method.attributes().contains(MethodAttributes::PINVOKE_IMPL)
+                && !method.impl_attributes().contains(MethodImplAttributes::INTERNAL_CALL);
+            out.push_str(if has_body { " { }\n" } else { ";\n" });
+        }
+    }
+
+    fn format_type_index(&self, idx: u32) -> Option<String> {
+        let ty = self.metadata.runtime_metadata.metadata_registration.types.get(idx as usize)?;
+        Some(self.names.format(ty))
+    }
+
+    /// Pushes the decoded custom attributes of the item with `token`, one per
+    /// line.
+    fn push_custom_attributes(&self, image: Option<&Il2CppImageDefinition>, token: Token, indent: &str, out: &mut String) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let Some(image) = image else {
+            return;
+        };
+        let Some(attributes) = image.custom_attributes_of(metadata, token) else {
+            let _ = writeln!(out, "{}// Custom attributes could not be decoded", indent);
+            return;
+        };
+        for attribute in attributes {
+            let mut arguments: Vec<_> = attribute.arguments.iter().map(|arg| self.format_argument(arg)).collect();
+            for (idx, arg) in &attribute.fields {
+                let name = gm.fields.get(*idx).and_then(|field| gm.string.get(field.name_index).ok());
+                arguments.push(format!("{} = {}", name.unwrap_or("?"), self.format_argument(arg)));
+            }
+            for (idx, arg) in &attribute.properties {
+                let name = gm.properties.get(*idx).and_then(|property| gm.string.get(property.name_index).ok());
+                arguments.push(format!("{} = {}", name.unwrap_or("?"), self.format_argument(arg)));
+            }
+
+            out.push_str(indent);
+            out.push('[');
+            out.push_str(&self.attribute_name(attribute.constructor));
+            if !arguments.is_empty() {
+                let _ = write!(out, "({})", arguments.join(", "));
+            }
+            out.push_str("]\n");
+        }
+    }
+
+    /// The name of the attribute type whose constructor is `constructor`,
+    /// without the `Attribute` suffix.
+    fn attribute_name(&self, constructor: MethodIndex) -> String {
+        let Some(method) = self.metadata.global_metadata.methods.get(constructor) else {
+            return "?".into();
+        };
+        let name = self.names.format_type_definition(method.declaring_type);
+        match name.strip_suffix("Attribute") {
+            Some(short) if !short.is_empty() && !short.ends_with('.') => short.into(),
+            _ => name,
+        }
+    }
+
+    /// Renders an attribute argument as a C# expression.
+    fn format_argument(&self, arg: &AttributeArgument) -> String {
+        let mut out = String::new();
+        match &arg.value {
+            AttributeValue::Constant(value) => {
+                if let AttributeArgumentType::Enum(ty) = arg.ty {
+                    let _ = write!(out, "({})", self.names.format_index(ty));
+                }
+                push_constant(*value, &mut out);
+            }
+            AttributeValue::Type(Some(ty)) => {
+                let _ = write!(out, "typeof({})", self.names.format_index(*ty));
+            }
+            AttributeValue::Type(None) | AttributeValue::Array(None) => out.push_str("null"),
+            AttributeValue::Array(Some(elements)) => {
+                let elements: Vec<_> = elements.iter().map(|element| self.format_argument(element)).collect();
+                let _ = write!(out, "new {} {{ {} }}", self.argument_type_name(&arg.ty), elements.join(", "));
+            }
+        }
+        out
+    }
+
+    fn argument_type_name(&self, ty: &AttributeArgumentType) -> String {
+        match ty {
+            AttributeArgumentType::Primitive(ty) => self.names.builtin_name(*ty).unwrap_or("?").into(),
+            AttributeArgumentType::Enum(ty) => self.names.format_index(*ty),
+            AttributeArgumentType::Type => "Type".into(),
+            AttributeArgumentType::Array(element) => format!("{}[]", self.argument_type_name(element)),
+        }
+    }
+}
+
+/// Renders an address or offset in hex, or `-1` if it is not known.
+fn hex_or_none(value: Option<u64>) -> String {
+    match value {
+        Some(value) => format!("0x{:X}", value),
+        None => "-1".into(),
+    }
+}
+
+/// A generic parameter's name, attributes and rendered constraints.
+type GenericParameter<'a> = (&'a str, GenericParameterAttributes, Vec<String>);
+
+fn push_generic_parameters(params: &[GenericParameter], out: &mut String) {
+    if params.is_empty() {
+        return;
+    }
+    out.push('<');
+    for (i, (name, attributes, _)) in params.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        match attributes.variance() {
+            Variance::Covariant => out.push_str("out "),
+            Variance::Contravariant => out.push_str("in "),
+            Variance::Invariant => {}
+        }
+        out.push_str(name);
+    }
+    out.push('>');
+}
+
+fn push_constraints(params: &[GenericParameter], out: &mut String) {
+    for (name, attributes, constraints) in params {
+        let mut clauses = Vec::new();
+        if attributes.contains(GenericParameterAttributes::REFERENCE_TYPE_CONSTRAINT) {
+            clauses.push("class");
+        }
+        let is_struct = attributes.contains(GenericParameterAttributes::NOT_NULLABLE_VALUE_TYPE_CONSTRAINT);
+        if is_struct {
+            clauses.push("struct");
+        }
+        // Value type constraints are stored as a `System.ValueType` constraint
+        // as well.
+        clauses.extend(
+            constraints
+                .iter()
+                .map(String::as_str)
+                .filter(|&constraint| !(is_struct && matches!(constraint, "ValueType" | "System.ValueType"))),
+        );
+        // `struct` implies `new()`.
+        if attributes.contains(GenericParameterAttributes::DEFAULT_CONSTRUCTOR_CONSTRAINT) && !is_struct {
+            clauses.push("new()");
+        }
+        if !clauses.is_empty() {
+            let _ = write!(out, " where {} : {}", name, clauses.join(", "));
+        }
+    }
+}
+
+fn access_keyword(access: MemberAccess) -> &'static str {
+    match access {
+        MemberAccess::CompilerControlled | MemberAccess::Private => "private ",
+        MemberAccess::FamAndAssem => "private protected ",
+        MemberAccess::Assembly => "internal ",
+        MemberAccess::Family => "protected ",
+        MemberAccess::FamOrAssem => "protected internal ",
+        MemberAccess::Public => "public ",
+    }
+}
+
+fn method_modifiers(method: &Il2CppMethodDefinition) -> String {
+    let attributes = method.attributes();
+    let mut modifiers = String::from(access_keyword(attributes.access()));
+    if attributes.contains(MethodAttributes::STATIC) {
+        modifiers.push_str("static ");
+    }
+    if attributes.contains(MethodAttributes::ABSTRACT) {
+        modifiers.push_str("abstract ");
+    } else if attributes.contains(MethodAttributes::VIRTUAL) {
+        let final_ = attributes.contains(MethodAttributes::FINAL);
+        match attributes.contains(MethodAttributes::NEW_SLOT) {
+            // Non-virtual interface implementations are `virtual final`.
+            true if final_ => {}
+            true => modifiers.push_str("virtual "),
+            false if final_ => modifiers.push_str("sealed override "),
+            false => modifiers.push_str("override "),
+        }
+    }
+    if attributes.contains(MethodAttributes::PINVOKE_IMPL)
+        || method.impl_attributes().contains(MethodImplAttributes::INTERNAL_CALL)
+    {
+        modifiers.push_str("extern ");
+    }
+    modifiers
+}
+
+/// Pushes a constant as a C# literal.
+fn push_constant(value: ConstantValue, out: &mut String) {
+    let _ = match value {
+        ConstantValue::Boolean(value) => write!(out, "{}", value),
+        ConstantValue::Char(value) => {
+            out.push('\'');
+            match char::from_u32(value as u32) {
+                Some(c) => push_escaped(c, '\'', out),
+                None => {
+                    let _ = write!(out, "\\u{:04X}", value);
+                }
+            }
+            out.push('\'');
+            Ok(())
+        }
+        ConstantValue::I1(value) => write!(out, "{}", value),
+        ConstantValue::U1(value) => write!(out, "{}", value),
+        ConstantValue::I2(value) => write!(out, "{}", value),
+        ConstantValue::U2(value) => write!(out, "{}", value),
+        ConstantValue::I4(value) => write!(out, "{}", value),
+        ConstantValue::U4(value) => write!(out, "{}", value),
+        ConstantValue::I8(value) => write!(out, "{}", value),
+        ConstantValue::U8(value) => write!(out, "{}", value),
+        ConstantValue::R4(value) => match value {
+            _ if value.is_nan() => write!(out, "float.NaN"),
+            _ if value == f32::INFINITY => write!(out, "float.PositiveInfinity"),
+            _ if value == f32::NEG_INFINITY => write!(out, "float.NegativeInfinity"),
+            _ => write!(out, "{}f", value),
+        },
+        ConstantValue::R8(value) => match value {
+            _ if value.is_nan() => write!(out, "double.NaN"),
+            _ if value == f64::INFINITY => write!(out, "double.PositiveInfinity"),
+            _ if value == f64::NEG_INFINITY => write!(out, "double.NegativeInfinity"),
+            _ => write!(out, "{:?}", value),
+        },
+        ConstantValue::String(value) => {
+            out.push('"');
+            value.chars().for_each(|c| push_escaped(c, '"', out));
+            out.push('"');
+            Ok(())
+        }
+        ConstantValue::Null => write!(out, "null"),
+    };
+}
+
+fn push_escaped(c: char, quote: char, out: &mut String) {
+    match c {
+        '\\' => out.push_str("\\\\"),
+        '\0' => out.push_str("\\0"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        _ if c == quote => {
+            out.push('\\');
+            out.push(c);
+        }
+        _ if c.is_control() => {
+            let _ = write!(out, "\\u{:04X}", c as u32);
+        }
+        _ => out.push(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{
+        GenericContainerIndex, Il2CppCustomAttributeDataRange, Il2CppFieldDefinition, StringIndex,
+    };
+    use crate::runtime_metadata::{Il2CppCodeGenModule, Il2CppTypeEnum, Segment, TypeData};
+    use crate::test_util::{self, write_compressed_i32, write_compressed_u32, zeroed};
+    use crate::vtable::INVALID_SLOT;
+
+    #[test]
+    fn dump_type() {
+        // Offsets: 1 `Game`, 6 `Foo`, 10 `x`, 12 `Run`, 16 `MarkAttribute`,
+        // 30 `A.dll`
+        let mut metadata = test_util::metadata("\0Game\0Foo\0x\0Run\0MarkAttribute\0A.dll\0");
+        let definition = |idx: u32| TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(idx));
+        let public_field = Il2CppType {
+            // `public`
+            attrs: 0x6,
+            ..test_util::ty(Il2CppTypeEnum::I4, definition(u32::MAX))
+        };
+        metadata.runtime_metadata.metadata_registration.types = vec![
+            public_field,
+            test_util::ty(Il2CppTypeEnum::Class, definition(0)),
+            test_util::ty(Il2CppTypeEnum::Void, definition(u32::MAX)),
+        ];
+        metadata.runtime_metadata.metadata_registration.field_offsets = Some(vec![vec![0x10], Vec::new()]);
+        metadata.runtime_metadata.code_registration.code_gen_modules = vec![Il2CppCodeGenModule {
+            name: "A.dll",
+            method_pointers: vec![0x1010, 0],
+            adjustor_thunks: Vec::new(),
+            invoker_indices: Vec::new(),
+            rgctx_ranges: Vec::new(),
+            rgctxs: Vec::new(),
+        }];
+        metadata.runtime_metadata.segments = vec![Segment {
+            address: 0x1000,
+            file_offset: 0x400,
+            file_size: 0x100,
+        }];
+
+        let gm = &mut metadata.global_metadata;
+        let mut foo = test_util::type_definition();
+        foo.namespace_index = StringIndex::new(1);
+        foo.name_index = StringIndex::new(6);
+        foo.byval_type_index = 1;
+        foo.flags = 0x1 | TypeAttributes::SEALED.bits();
+        foo.token = Token(0x0200_0001);
+        foo.field_count = 1;
+        foo.method_count = 1;
+        let mut attribute = test_util::type_definition();
+        attribute.name_index = StringIndex::new(16);
+        attribute.token = Token(0x0200_0002);
+        attribute.method_start = MethodIndex::new(1);
+        attribute.method_count = 1;
+        gm.type_definitions = vec![foo, attribute].into();
+
+        let mut field: Il2CppFieldDefinition = zeroed();
+        field.name_index = StringIndex::new(10);
+        field.token = Token(0x0400_0001);
+        gm.fields = vec![field].into();
+        let method = |name: u32, declaring_type: u32, rid: u32| {
+            let mut method: Il2CppMethodDefinition = zeroed();
+            method.name_index = StringIndex::new(name);
+            method.declaring_type = TypeDefinitionIndex::new(declaring_type);
+            method.return_type = 2;
+            method.generic_container_index = GenericContainerIndex::new(u32::MAX);
+            method.token = Token(0x0600_0000 | rid);
+            method.flags = 0x6;
+            method.slot = INVALID_SLOT;
+            method
+        };
+        gm.methods = vec![method(12, 0, 1), method(0, 1, 2)].into();
+
+        let mut image: Il2CppImageDefinition = zeroed();
+        image.name_index = StringIndex::new(30);
+        image.type_count = 2;
+        image.custom_attribute_count = 2;
+        gm.images = vec![image].into();
+
+        // `[Mark]` on `Foo` and `[Mark(5)]` on `Run`
+        let mut data = Vec::new();
+        write_compressed_u32(&mut data, 1);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0]);
+        let second = data.len() as u32;
+        write_compressed_u32(&mut data, 1);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&[1, 0, 0]);
+        data.push(Il2CppTypeEnum::I4.to_ty());
+        write_compressed_i32(&mut data, 5);
+        let range = |token: u32, start_offset: u32| {
+            let mut range: Il2CppCustomAttributeDataRange = zeroed();
+            range.token = Token(token);
+            range.start_offset = start_offset;
+            range
+        };
+        gm.attribute_data = data.into();
+        gm.attribute_data_range = vec![range(0x0200_0001, 0), range(0x0600_0001, second)].into();
+
+        let dump = CSharpDumper::new(&metadata).dump_type(TypeDefinitionIndex::new(0));
+        assert_eq!(
+            dump,
+            "// Namespace: Game\n\
+             [Mark]\n\
+             public sealed class Foo // TypeDefIndex: 0\n\
+             {\n\
+             \t// Fields\n\
+             \tpublic int x; // 0x10\n\
+             \n\
+             \t// Methods\n\
+             \t// VA: 0x1010 Offset: 0x410\n\
+             \t[Mark(5)]\n\
+             \tpublic void Run() { }\n\
+             }\n"
+        );
+    }
+}
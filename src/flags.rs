@@ -0,0 +1,498 @@
+//! Typed attribute flags.
+//!
+//! The metadata stores attributes as the raw integers defined in ECMA-335
+//! II.23.1 and `il2cpp-tabledefs.h`. Fields that hold one of several values
+//! instead of independent bits, such as visibility, are exposed as enums.
+
+use crate::global_metadata::{
+    Il2CppFieldDefinition, Il2CppGenericParameter, Il2CppMethodDefinition, Il2CppParameterDefinition,
+    Il2CppPropertyDefinition, Il2CppTypeDefinition,
+};
+use crate::runtime_metadata::Il2CppType;
+use crate::Metadata;
+use bitflags::bitflags;
+
+bitflags! {
+    /// Attributes of a type definition. See ECMA-335 II.23.1.15.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct TypeAttributes: u32 {
+        const VISIBILITY_MASK = 0x0000_0007;
+        const LAYOUT_MASK = 0x0000_0018;
+        const SEQUENTIAL_LAYOUT = 0x0000_0008;
+        const EXPLICIT_LAYOUT = 0x0000_0010;
+        const INTERFACE = 0x0000_0020;
+        const ABSTRACT = 0x0000_0080;
+        const SEALED = 0x0000_0100;
+        const SPECIAL_NAME = 0x0000_0400;
+        const RT_SPECIAL_NAME = 0x0000_0800;
+        const IMPORT = 0x0000_1000;
+        const SERIALIZABLE = 0x0000_2000;
+        const WINDOWS_RUNTIME = 0x0000_4000;
+        const STRING_FORMAT_MASK = 0x0003_0000;
+        const UNICODE_CLASS = 0x0001_0000;
+        const AUTO_CLASS = 0x0002_0000;
+        const HAS_SECURITY = 0x0004_0000;
+        const BEFORE_FIELD_INIT = 0x0010_0000;
+        const CUSTOM_FORMAT_MASK = 0x00C0_0000;
+
+        const _ = !0;
+    }
+}
+
+bitflags! {
+    /// Attributes of a method definition. See ECMA-335 II.23.1.10.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MethodAttributes: u16 {
+        const MEMBER_ACCESS_MASK = 0x0007;
+        const UNMANAGED_EXPORT = 0x0008;
+        const STATIC = 0x0010;
+        const FINAL = 0x0020;
+        const VIRTUAL = 0x0040;
+        const HIDE_BY_SIG = 0x0080;
+        /// The method always gets a new vtable slot instead of reusing the
+        /// slot of the base type method with the same signature.
+        const NEW_SLOT = 0x0100;
+        const STRICT = 0x0200;
+        const ABSTRACT = 0x0400;
+        const SPECIAL_NAME = 0x0800;
+        const RT_SPECIAL_NAME = 0x1000;
+        const PINVOKE_IMPL = 0x2000;
+        const HAS_SECURITY = 0x4000;
+        const REQUIRE_SEC_OBJECT = 0x8000;
+
+        const _ = !0;
+    }
+}
+
+bitflags! {
+    /// Implementation attributes of a method definition. See ECMA-335
+    /// II.23.1.11.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct MethodImplAttributes: u16 {
+        const CODE_TYPE_MASK = 0x0003;
+        const NATIVE = 0x0001;
+        const OPTIL = 0x0002;
+        const RUNTIME = 0x0003;
+        const UNMANAGED = 0x0004;
+        const NO_INLINING = 0x0008;
+        const FORWARD_REF = 0x0010;
+        const SYNCHRONIZED = 0x0020;
+        const NO_OPTIMIZATION = 0x0040;
+        const PRESERVE_SIG = 0x0080;
+        const AGGRESSIVE_INLINING = 0x0100;
+        const INTERNAL_CALL = 0x1000;
+
+        const _ = !0;
+    }
+}
+
+bitflags! {
+    /// Attributes of a field. See ECMA-335 II.23.1.5.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct FieldAttributes: u16 {
+        const FIELD_ACCESS_MASK = 0x0007;
+        const STATIC = 0x0010;
+        const INIT_ONLY = 0x0020;
+        /// The field is a compile time constant and has no storage.
+        const LITERAL = 0x0040;
+        const NOT_SERIALIZED = 0x0080;
+        const HAS_FIELD_RVA = 0x0100;
+        const SPECIAL_NAME = 0x0200;
+        const RT_SPECIAL_NAME = 0x0400;
+        const HAS_FIELD_MARSHAL = 0x1000;
+        const PINVOKE_IMPL = 0x2000;
+        const HAS_DEFAULT = 0x8000;
+
+        const _ = !0;
+    }
+}
+
+bitflags! {
+    /// Attributes of a method parameter. See ECMA-335 II.23.1.13.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct ParamAttributes: u16 {
+        const IN = 0x0001;
+        const OUT = 0x0002;
+        const OPTIONAL = 0x0010;
+        const HAS_DEFAULT = 0x1000;
+        const HAS_FIELD_MARSHAL = 0x2000;
+
+        const _ = !0;
+    }
+}
+
+bitflags! {
+    /// Attributes of a property. See ECMA-335 II.23.1.14.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct PropertyAttributes: u32 {
+        const SPECIAL_NAME = 0x0200;
+        const RT_SPECIAL_NAME = 0x0400;
+        const HAS_DEFAULT = 0x1000;
+
+        const _ = !0;
+    }
+}
+
+bitflags! {
+    /// Attributes of a generic parameter. See ECMA-335 II.23.1.7.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct GenericParameterAttributes: u16 {
+        const VARIANCE_MASK = 0x0003;
+        const COVARIANT = 0x0001;
+        const CONTRAVARIANT = 0x0002;
+        const SPECIAL_CONSTRAINT_MASK = 0x001C;
+        /// The `class` constraint.
+        const REFERENCE_TYPE_CONSTRAINT = 0x0004;
+        /// The `struct` constraint.
+        const NOT_NULLABLE_VALUE_TYPE_CONSTRAINT = 0x0008;
+        /// The `new()` constraint.
+        const DEFAULT_CONSTRUCTOR_CONSTRAINT = 0x0010;
+
+        const _ = !0;
+    }
+}
+
+bitflags! {
+    /// The calling convention of a method signature. See ECMA-335 II.23.2.1.
+    ///
+    /// IL2CPP does not store method signatures, so this is derived from the
+    /// method's attributes and generic container.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct CallingConvention: u8 {
+        const GENERIC = 0x10;
+        const HAS_THIS = 0x20;
+        const EXPLICIT_THIS = 0x40;
+
+        const _ = !0;
+    }
+}
+
+/// The visibility of a type, stored in [`TypeAttributes::VISIBILITY_MASK`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeVisibility {
+    NotPublic,
+    Public,
+    NestedPublic,
+    NestedPrivate,
+    /// `protected`
+    NestedFamily,
+    /// `internal`
+    NestedAssembly,
+    /// `private protected`
+    NestedFamAndAssem,
+    /// `protected internal`
+    NestedFamOrAssem,
+}
+
+/// How the fields of a type are laid out, stored in
+/// [`TypeAttributes::LAYOUT_MASK`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeLayout {
+    Auto,
+    Sequential,
+    Explicit,
+}
+
+/// The accessibility of a method or field, stored in
+/// [`MethodAttributes::MEMBER_ACCESS_MASK`] and
+/// [`FieldAttributes::FIELD_ACCESS_MASK`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemberAccess {
+    CompilerControlled,
+    Private,
+    /// `private protected`
+    FamAndAssem,
+    /// `internal`
+    Assembly,
+    /// `protected`
+    Family,
+    /// `protected internal`
+    FamOrAssem,
+    Public,
+}
+
+impl MemberAccess {
+    fn from_bits(bits: u16) -> Self {
+        match bits & 0x7 {
+            0 => MemberAccess::CompilerControlled,
+            1 => MemberAccess::Private,
+            2 => MemberAccess::FamAndAssem,
+            3 => MemberAccess::Assembly,
+            4 => MemberAccess::Family,
+            5 => MemberAccess::FamOrAssem,
+            // 7 is not a valid access level
+            _ => MemberAccess::Public,
+        }
+    }
+}
+
+/// The variance of a generic parameter, stored in
+/// [`GenericParameterAttributes::VARIANCE_MASK`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variance {
+    Invariant,
+    /// `out`
+    Covariant,
+    /// `in`
+    Contravariant,
+}
+
+impl TypeAttributes {
+    pub fn visibility(self) -> TypeVisibility {
+        match self.bits() & Self::VISIBILITY_MASK.bits() {
+            0 => TypeVisibility::NotPublic,
+            1 => TypeVisibility::Public,
+            2 => TypeVisibility::NestedPublic,
+            3 => TypeVisibility::NestedPrivate,
+            4 => TypeVisibility::NestedFamily,
+            5 => TypeVisibility::NestedAssembly,
+            6 => TypeVisibility::NestedFamAndAssem,
+            _ => TypeVisibility::NestedFamOrAssem,
+        }
+    }
+
+    /// The layout of the type. The invalid combination of both layout bits is
+    /// treated as [`TypeLayout::Auto`].
+    pub fn layout(self) -> TypeLayout {
+        match self.intersection(Self::LAYOUT_MASK) {
+            Self::SEQUENTIAL_LAYOUT => TypeLayout::Sequential,
+            Self::EXPLICIT_LAYOUT => TypeLayout::Explicit,
+            _ => TypeLayout::Auto,
+        }
+    }
+}
+
+impl MethodAttributes {
+    pub fn access(self) -> MemberAccess {
+        MemberAccess::from_bits(self.bits())
+    }
+}
+
+impl FieldAttributes {
+    pub fn access(self) -> MemberAccess {
+        MemberAccess::from_bits(self.bits())
+    }
+}
+
+impl GenericParameterAttributes {
+    /// The variance of the parameter. The invalid combination of both variance
+    /// bits is treated as [`Variance::Invariant`].
+    pub fn variance(self) -> Variance {
+        match self.intersection(Self::VARIANCE_MASK) {
+            Self::COVARIANT => Variance::Covariant,
+            Self::CONTRAVARIANT => Variance::Contravariant,
+            _ => Variance::Invariant,
+        }
+    }
+}
+
+impl Il2CppTypeDefinition {
+    pub fn attributes(&self) -> TypeAttributes {
+        TypeAttributes::from_bits_retain(self.flags)
+    }
+
+    pub fn visibility(&self) -> TypeVisibility {
+        self.attributes().visibility()
+    }
+
+    pub fn is_interface(&self) -> bool {
+        self.attributes().contains(TypeAttributes::INTERFACE)
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.attributes().contains(TypeAttributes::ABSTRACT)
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.attributes().contains(TypeAttributes::SEALED)
+    }
+}
+
+impl Il2CppMethodDefinition {
+    pub fn attributes(&self) -> MethodAttributes {
+        MethodAttributes::from_bits_retain(self.flags)
+    }
+
+    pub fn impl_attributes(&self) -> MethodImplAttributes {
+        MethodImplAttributes::from_bits_retain(self.iflags)
+    }
+
+    pub fn access(&self) -> MemberAccess {
+        self.attributes().access()
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.attributes().contains(MethodAttributes::STATIC)
+    }
+
+    pub fn is_virtual(&self) -> bool {
+        self.attributes().contains(MethodAttributes::VIRTUAL)
+    }
+
+    pub fn is_abstract(&self) -> bool {
+        self.attributes().contains(MethodAttributes::ABSTRACT)
+    }
+}
+
+impl Il2CppType {
+    /// Interprets [`Il2CppType::attrs`] as the attributes of the field with
+    /// this type.
+    pub fn field_attributes(&self) -> FieldAttributes {
+        FieldAttributes::from_bits_retain(self.attrs)
+    }
+
+    /// Interprets [`Il2CppType::attrs`] as the attributes of the parameter
+    /// with this type.
+    pub fn param_attributes(&self) -> ParamAttributes {
+        ParamAttributes::from_bits_retain(self.attrs)
+    }
+}
+
+impl Il2CppFieldDefinition {
+    /// The field's attributes, which are stored in its type.
+    pub fn attributes(&self, metadata: &Metadata) -> FieldAttributes {
+        metadata
+            .runtime_metadata
+            .metadata_registration
+            .types
+            .get(self.type_index as usize)
+            .map_or(FieldAttributes::empty(), Il2CppType::field_attributes)
+    }
+
+    pub fn access(&self, metadata: &Metadata) -> MemberAccess {
+        self.attributes(metadata).access()
+    }
+
+    pub fn is_static(&self, metadata: &Metadata) -> bool {
+        self.attributes(metadata).contains(FieldAttributes::STATIC)
+    }
+
+    /// Whether the field is a constant.
+    pub fn is_literal(&self, metadata: &Metadata) -> bool {
+        self.attributes(metadata).contains(FieldAttributes::LITERAL)
+    }
+}
+
+impl Il2CppParameterDefinition {
+    /// The parameter's attributes, which are stored in its type.
+    pub fn attributes(&self, metadata: &Metadata) -> ParamAttributes {
+        metadata
+            .runtime_metadata
+            .metadata_registration
+            .types
+            .get(self.type_index as usize)
+            .map_or(ParamAttributes::empty(), Il2CppType::param_attributes)
+    }
+}
+
+impl Il2CppPropertyDefinition {
+    pub fn attributes(&self) -> PropertyAttributes {
+        PropertyAttributes::from_bits_retain(self.attrs)
+    }
+}
+
+impl Il2CppGenericParameter {
+    pub fn attributes(&self) -> GenericParameterAttributes {
+        GenericParameterAttributes::from_bits_retain(self.flags)
+    }
+
+    pub fn variance(&self) -> Variance {
+        self.attributes().variance()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime_metadata::{Il2CppTypeEnum, TypeData};
+    use crate::test_util::{self, zeroed};
+
+    #[test]
+    fn type_attributes() {
+        let visibilities = [
+            TypeVisibility::NotPublic,
+            TypeVisibility::Public,
+            TypeVisibility::NestedPublic,
+            TypeVisibility::NestedPrivate,
+            TypeVisibility::NestedFamily,
+            TypeVisibility::NestedAssembly,
+            TypeVisibility::NestedFamAndAssem,
+            TypeVisibility::NestedFamOrAssem,
+        ];
+        for (bits, visibility) in visibilities.into_iter().enumerate() {
+            assert_eq!(TypeAttributes::from_bits_retain(bits as u32 | 0x100).visibility(), visibility);
+        }
+
+        assert_eq!(TypeAttributes::empty().layout(), TypeLayout::Auto);
+        assert_eq!(TypeAttributes::SEQUENTIAL_LAYOUT.layout(), TypeLayout::Sequential);
+        assert_eq!(TypeAttributes::EXPLICIT_LAYOUT.layout(), TypeLayout::Explicit);
+        assert_eq!(TypeAttributes::LAYOUT_MASK.layout(), TypeLayout::Auto);
+
+        let mut ty_def = test_util::type_definition();
+        ty_def.flags = 0x0010_01A1;
+        assert_eq!(ty_def.visibility(), TypeVisibility::Public);
+        assert!(ty_def.is_interface() && ty_def.is_abstract() && ty_def.is_sealed());
+        assert!(ty_def.attributes().contains(TypeAttributes::BEFORE_FIELD_INIT));
+        // Unknown bits are kept
+        ty_def.flags = 0x8000_0000;
+        assert_eq!(ty_def.attributes().bits(), 0x8000_0000);
+    }
+
+    #[test]
+    fn member_access() {
+        let accesses = [
+            MemberAccess::CompilerControlled,
+            MemberAccess::Private,
+            MemberAccess::FamAndAssem,
+            MemberAccess::Assembly,
+            MemberAccess::Family,
+            MemberAccess::FamOrAssem,
+            MemberAccess::Public,
+            MemberAccess::Public,
+        ];
+        for (bits, access) in accesses.into_iter().enumerate() {
+            assert_eq!(MethodAttributes::from_bits_retain(bits as u16 | 0x10).access(), access);
+            assert_eq!(FieldAttributes::from_bits_retain(bits as u16 | 0x10).access(), access);
+        }
+
+        let mut method: Il2CppMethodDefinition = zeroed();
+        method.flags = 0x0456;
+        method.iflags = 0x1003;
+        assert_eq!(method.access(), MemberAccess::Public);
+        assert!(method.is_static() && method.is_virtual() && method.is_abstract());
+        assert_eq!(method.impl_attributes() & MethodImplAttributes::CODE_TYPE_MASK, MethodImplAttributes::RUNTIME);
+    }
+
+    #[test]
+    fn member_attributes_from_types() {
+        let mut metadata = test_util::metadata("");
+        let mut ty = test_util::ty(Il2CppTypeEnum::I4, TypeData::TypeIndex(0));
+        ty.attrs = 0x56;
+        metadata.runtime_metadata.metadata_registration.types = vec![ty];
+
+        let mut field: Il2CppFieldDefinition = zeroed();
+        assert_eq!(field.access(&metadata), MemberAccess::Public);
+        assert!(field.is_static(&metadata) && field.is_literal(&metadata));
+        let mut parameter: Il2CppParameterDefinition = zeroed();
+        assert_eq!(parameter.attributes(&metadata).bits(), 0x56);
+
+        // Types that cannot be read have no attributes
+        field.type_index = 1;
+        parameter.type_index = 1;
+        assert_eq!(field.attributes(&metadata), FieldAttributes::empty());
+        assert_eq!(parameter.attributes(&metadata), ParamAttributes::empty());
+    }
+
+    #[test]
+    fn variance() {
+        assert_eq!(GenericParameterAttributes::empty().variance(), Variance::Invariant);
+        assert_eq!(GenericParameterAttributes::COVARIANT.variance(), Variance::Covariant);
+        assert_eq!(GenericParameterAttributes::CONTRAVARIANT.variance(), Variance::Contravariant);
+        assert_eq!(GenericParameterAttributes::VARIANCE_MASK.variance(), Variance::Invariant);
+
+        let mut param: Il2CppGenericParameter = zeroed();
+        param.flags = 0x0009;
+        assert_eq!(param.variance(), Variance::Covariant);
+        assert!(param.attributes().contains(GenericParameterAttributes::NOT_NULLABLE_VALUE_TYPE_CONSTRAINT));
+    }
+}
@@ -0,0 +1,545 @@
+//! Generic type inflation.
+//!
+//! Types stored in the metadata refer to generic parameters with
+//! [`Il2CppTypeEnum::Var`] (a parameter of the declaring type) and
+//! [`Il2CppTypeEnum::Mvar`] (a parameter of the method). Inflating a type
+//! replaces these with the type arguments of a [`GenericContext`]. Since the
+//! resulting type does not necessarily exist in
+//! [`Il2CppMetadataRegistration::types`], inflated types are represented by
+//! the self-contained [`InflatedType`].
+//!
+//! IL2CPP compiles every generic instantiation ahead of time, so only the
+//! instantiations listed by [`Metadata::instantiations_of()`] and
+//! [`Metadata::method_instantiations_of()`] can be used at runtime.
+
+use crate::global_metadata::{FieldIndex, GenericParameterIndex, MethodIndex, TypeDefinitionIndex};
+use crate::runtime_metadata::{
+    GenericClassIndex, Il2CppGenericClass, Il2CppGenericContext, Il2CppMetadataRegistration, Il2CppMethodSpec,
+    Il2CppType, Il2CppTypeEnum, MethodSpecIndex, TypeData,
+};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+
+/// A type that does not depend on its position in the metadata tables.
+///
+/// Two inflated types are equal if they describe the same type, so they can be
+/// used as keys to deduplicate types.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InflatedType {
+    pub ty: Il2CppTypeEnum,
+    pub byref: bool,
+    pub data: InflatedTypeData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum InflatedTypeData {
+    /// A type definition, including the definitions of primitive types.
+    Definition(TypeDefinitionIndex),
+    /// A generic parameter that was not substituted.
+    GenericParameter(GenericParameterIndex),
+    /// For [`Il2CppTypeEnum::Ptr`] and [`Il2CppTypeEnum::Szarray`]
+    Element(Box<InflatedType>),
+    /// For [`Il2CppTypeEnum::Array`]
+    Array { element: Box<InflatedType>, rank: u8 },
+    /// For [`Il2CppTypeEnum::Genericinst`]
+    GenericInstance {
+        definition: TypeDefinitionIndex,
+        args: Vec<InflatedType>,
+    },
+    /// A type that could not be resolved because the metadata is malformed.
+    Invalid,
+}
+
+impl InflatedType {
+    /// Converts a type from the metadata tables without substituting any
+    /// generic parameters.
+    pub fn new(metadata: &Metadata, ty: &Il2CppType) -> Self {
+        Self::convert(metadata, ty, &GenericContext::default(), 0)
+    }
+
+    /// Converts the type at `index` in
+    /// [`Il2CppMetadataRegistration::types`].
+    pub fn from_index(metadata: &Metadata, index: usize) -> Self {
+        match metadata.runtime_metadata.metadata_registration.types.get(index) {
+            Some(ty) => Self::new(metadata, ty),
+            None => Self::invalid(),
+        }
+    }
+
+    fn invalid() -> Self {
+        InflatedType {
+            ty: Il2CppTypeEnum::Object,
+            byref: false,
+            data: InflatedTypeData::Invalid,
+        }
+    }
+
+    /// Whether the type still refers to generic parameters.
+    pub fn is_open(&self) -> bool {
+        match &self.data {
+            InflatedTypeData::GenericParameter(_) => true,
+            InflatedTypeData::Element(element) | InflatedTypeData::Array { element, .. } => element.is_open(),
+            InflatedTypeData::GenericInstance { args, .. } => args.iter().any(InflatedType::is_open),
+            InflatedTypeData::Definition(_) | InflatedTypeData::Invalid => false,
+        }
+    }
+
+    /// The type definition this type refers to. For generic instances, this
+    /// is the generic type definition.
+    pub fn type_definition_index(&self) -> Option<TypeDefinitionIndex> {
+        match self.data {
+            InflatedTypeData::Definition(idx) | InflatedTypeData::GenericInstance { definition: idx, .. } => Some(idx),
+            _ => None,
+        }
+    }
+
+    /// Substitutes the generic parameters in this type with the arguments of
+    /// `context`.
+    pub fn inflate(&self, metadata: &Metadata, context: &GenericContext) -> Self {
+        self.inflate_inner(metadata, context, 0)
+    }
+
+    fn inflate_inner(&self, metadata: &Metadata, context: &GenericContext, depth: usize) -> Self {
+        if depth > MAX_TYPE_DEPTH {
+            return Self::invalid();
+        }
+        let data = match &self.data {
+            InflatedTypeData::GenericParameter(idx) => {
+                if let Some(arg) = context.arg(metadata, self.ty, *idx) {
+                    return InflatedType {
+                        byref: self.byref || arg.byref,
+                        ..arg.clone()
+                    };
+                }
+                InflatedTypeData::GenericParameter(*idx)
+            }
+            InflatedTypeData::Element(element) => {
+                InflatedTypeData::Element(Box::new(element.inflate_inner(metadata, context, depth + 1)))
+            }
+            InflatedTypeData::Array { element, rank } => InflatedTypeData::Array {
+                element: Box::new(element.inflate_inner(metadata, context, depth + 1)),
+                rank: *rank,
+            },
+            InflatedTypeData::GenericInstance { definition, args } => InflatedTypeData::GenericInstance {
+                definition: *definition,
+                args: args
+                    .iter()
+                    .map(|arg| arg.inflate_inner(metadata, context, depth + 1))
+                    .collect(),
+            },
+            data => data.clone(),
+        };
+        InflatedType {
+            ty: self.ty,
+            byref: self.byref,
+            data,
+        }
+    }
+
+    fn convert(metadata: &Metadata, ty: &Il2CppType, context: &GenericContext, depth: usize) -> Self {
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        if depth > MAX_TYPE_DEPTH {
+            return Self::invalid();
+        }
+        let convert_index = |idx: usize| match mr.types.get(idx) {
+            Some(ty) => Self::convert(metadata, ty, context, depth + 1),
+            None => Self::invalid(),
+        };
+
+        let data = match ty.data {
+            TypeData::TypeDefinitionIndex(idx) => InflatedTypeData::Definition(idx),
+            TypeData::GenericParameterIndex(idx) => {
+                if let Some(arg) = context.arg(metadata, ty.ty, idx) {
+                    return InflatedType {
+                        byref: ty.byref || arg.byref,
+                        ..arg.clone()
+                    };
+                }
+                InflatedTypeData::GenericParameter(idx)
+            }
+            TypeData::TypeIndex(idx) => InflatedTypeData::Element(Box::new(convert_index(idx))),
+            TypeData::ArrayType(idx) => match mr.array_types.get(idx) {
+                Some(array) => InflatedTypeData::Array {
+                    element: Box::new(convert_index(array.elem_ty)),
+                    rank: array.rank,
+                },
+                None => InflatedTypeData::Invalid,
+            },
+            TypeData::GenericClassIndex(idx) => {
+                let definition = mr
+                    .generic_classes
+                    .get(idx)
+                    .and_then(|generic_class| generic_class.type_definition_index(metadata));
+                match definition {
+                    Some(definition) => InflatedTypeData::GenericInstance {
+                        definition,
+                        args: class_inst(mr, &mr.generic_classes[idx].context)
+                            .iter()
+                            .map(|&arg| convert_index(arg))
+                            .collect(),
+                    },
+                    None => InflatedTypeData::Invalid,
+                }
+            }
+        };
+        InflatedType {
+            ty: ty.ty,
+            byref: ty.byref,
+            data,
+        }
+    }
+}
+
+/// The type arguments used to inflate a type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct GenericContext {
+    /// The arguments for the generic parameters of the declaring type.
+    pub class_args: Vec<InflatedType>,
+    /// The arguments for the generic parameters of the method.
+    pub method_args: Vec<InflatedType>,
+}
+
+impl GenericContext {
+    pub fn new(class_args: Vec<InflatedType>, method_args: Vec<InflatedType>) -> Self {
+        Self {
+            class_args,
+            method_args,
+        }
+    }
+
+    /// Resolves the instantiations referenced by an [`Il2CppGenericContext`].
+    pub fn from_il2cpp(metadata: &Metadata, context: &Il2CppGenericContext) -> Self {
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        let args = |inst: Option<usize>| match inst.and_then(|inst| mr.generic_insts.get(inst)) {
+            Some(inst) => inst.types.iter().map(|&ty| InflatedType::from_index(metadata, ty)).collect(),
+            None => Vec::new(),
+        };
+        Self {
+            class_args: args(context.class_inst_idx),
+            method_args: args(context.method_inst_idx),
+        }
+    }
+
+    fn arg(&self, metadata: &Metadata, ty: Il2CppTypeEnum, param: GenericParameterIndex) -> Option<&InflatedType> {
+        let num = metadata.global_metadata.generic_parameters.get(param)?.num as usize;
+        match ty {
+            Il2CppTypeEnum::Var => self.class_args.get(num),
+            Il2CppTypeEnum::Mvar => self.method_args.get(num),
+            _ => None,
+        }
+    }
+}
+
+/// Substitutes the generic parameters in `ty` with the arguments of
+/// `context`.
+pub fn inflate(metadata: &Metadata, ty: &Il2CppType, context: &GenericContext) -> InflatedType {
+    InflatedType::convert(metadata, ty, context, 0)
+}
+
+/// A field of a generic type instantiation.
+#[derive(Debug, Clone)]
+pub struct InflatedField<'md> {
+    pub field: FieldIndex,
+    pub name: &'md str,
+    pub ty: InflatedType,
+}
+
+/// A method of a generic type instantiation.
+#[derive(Debug, Clone)]
+pub struct InflatedMethod<'md> {
+    pub method: MethodIndex,
+    pub name: &'md str,
+    pub return_type: InflatedType,
+    pub parameters: Vec<InflatedType>,
+}
+
+impl Il2CppGenericClass {
+    /// The generic type definition being instantiated.
+    pub fn type_definition_index(&self, metadata: &Metadata) -> Option<TypeDefinitionIndex> {
+        metadata
+            .runtime_metadata
+            .metadata_registration
+            .types
+            .get(self.type_index)?
+            .type_definition_index(metadata)
+    }
+
+    /// The type arguments of the instantiation.
+    pub fn generic_context(&self, metadata: &Metadata) -> GenericContext {
+        GenericContext::from_il2cpp(metadata, &self.context)
+    }
+
+    /// The fields of the generic type definition with the instantiation's
+    /// type arguments substituted into their types.
+    pub fn inflated_fields<'md>(&self, metadata: &'md Metadata) -> Vec<InflatedField<'md>> {
+        let gm = &metadata.global_metadata;
+        let Some(ty_def) = self.type_definition_index(metadata).and_then(|idx| gm.type_definitions.get(idx)) else {
+            return Vec::new();
+        };
+        let context = self.generic_context(metadata);
+        ty_def
+            .field_start
+            .indices(ty_def.field_count as u32)
+            .filter_map(|idx| {
+                let field = gm.fields.get(idx)?;
+                Some(InflatedField {
+                    field: idx,
+                    name: gm.string.get(field.name_index).unwrap_or_default(),
+                    ty: inflate_index(metadata, field.type_index as usize, &context),
+                })
+            })
+            .collect()
+    }
+
+    /// The methods of the generic type definition with the instantiation's
+    /// type arguments substituted into their signatures.
+    ///
+    /// Generic parameters of generic methods are left as they are.
+    pub fn inflated_methods<'md>(&self, metadata: &'md Metadata) -> Vec<InflatedMethod<'md>> {
+        let gm = &metadata.global_metadata;
+        let Some(ty_def) = self.type_definition_index(metadata).and_then(|idx| gm.type_definitions.get(idx)) else {
+            return Vec::new();
+        };
+        let context = self.generic_context(metadata);
+        ty_def
+            .method_start
+            .indices(ty_def.method_count as u32)
+            .filter_map(|idx| {
+                let method = gm.methods.get(idx)?;
+                let parameters = method
+                    .parameter_start
+                    .indices(method.parameter_count as u32)
+                    .filter_map(|idx| gm.parameters.get(idx))
+                    .map(|param| inflate_index(metadata, param.type_index as usize, &context))
+                    .collect();
+                Some(InflatedMethod {
+                    method: idx,
+                    name: gm.string.get(method.name_index).unwrap_or_default(),
+                    return_type: inflate_index(metadata, method.return_type as usize, &context),
+                    parameters,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Il2CppMethodSpec {
+    /// The type arguments of the declaring type and the method.
+    pub fn generic_context(&self, metadata: &Metadata) -> GenericContext {
+        let inst = |idx: u32| (idx != u32::MAX).then_some(idx as usize);
+        GenericContext::from_il2cpp(
+            metadata,
+            &Il2CppGenericContext {
+                class_inst_idx: inst(self.class_inst_index),
+                method_inst_idx: inst(self.method_inst_index),
+            },
+        )
+    }
+}
+
+impl Metadata<'_, '_> {
+    /// Lists the concrete instantiations of the generic type definition `ty`
+    /// that exist in the binary.
+    ///
+    /// Instantiations with type arguments that are themselves generic
+    /// parameters, such as the `List<T>` used inside of `List<T>`, are not
+    /// included.
+    pub fn instantiations_of<'a>(&'a self, ty: TypeDefinitionIndex) -> impl Iterator<Item = GenericClassIndex> + 'a {
+        let metadata: &'a Metadata<'a, 'a> = self;
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        mr.generic_classes
+            .iter()
+            .enumerate()
+            .filter(move |(_, generic_class)| {
+                generic_class.type_definition_index(metadata) == Some(ty)
+                    && !is_open_inst(metadata, generic_class.context.class_inst_idx)
+            })
+            .map(|(i, _)| i)
+    }
+
+    /// Lists the concrete instantiations of `method` that exist in the
+    /// binary. These are both instantiations of generic methods and methods
+    /// of generic types.
+    ///
+    /// The code of an instantiation can be found with
+    /// [`VTableMethod::method_pointer()`](crate::vtable::VTableMethod::method_pointer).
+    pub fn method_instantiations_of<'a>(&'a self, method: MethodIndex) -> impl Iterator<Item = MethodSpecIndex> + 'a {
+        let metadata: &'a Metadata<'a, 'a> = self;
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        let inst = |idx: u32| (idx != u32::MAX).then_some(idx as usize);
+        mr.method_specs
+            .iter()
+            .enumerate()
+            .filter(move |(_, spec)| {
+                spec.method_definition_index == method
+                    && !is_open_inst(metadata, inst(spec.class_inst_index))
+                    && !is_open_inst(metadata, inst(spec.method_inst_index))
+            })
+            .map(|(i, _)| i)
+    }
+}
+
+/// Whether any argument of a generic instantiation refers to a generic
+/// parameter.
+fn is_open_inst(metadata: &Metadata, inst: Option<usize>) -> bool {
+    let mr = &metadata.runtime_metadata.metadata_registration;
+    let Some(inst) = inst.and_then(|inst| mr.generic_insts.get(inst)) else {
+        return false;
+    };
+    inst.types
+        .iter()
+        .any(|&ty| InflatedType::from_index(metadata, ty).is_open())
+}
+
+fn inflate_index(metadata: &Metadata, index: usize, context: &GenericContext) -> InflatedType {
+    match metadata.runtime_metadata.metadata_registration.types.get(index) {
+        Some(ty) => inflate(metadata, ty, context),
+        None => InflatedType::invalid(),
+    }
+}
+
+fn class_inst<'a>(mr: &'a Il2CppMetadataRegistration, context: &Il2CppGenericContext) -> &'a [usize] {
+    context
+        .class_inst_idx
+        .and_then(|inst| mr.generic_insts.get(inst))
+        .map(|inst| inst.types.as_slice())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{
+        GenericContainerIndex, Il2CppFieldDefinition, Il2CppMethodDefinition, Il2CppParameterDefinition, StringIndex,
+    };
+    use crate::runtime_metadata::Il2CppGenericInst;
+    use crate::test_util;
+
+    const STRINGS: &str = "\0_item\0_items\0Add\0";
+    const LIST: u32 = 0;
+    const INT32: u32 = 1;
+    const VOID: u32 = 2;
+
+    fn string(name: &str) -> StringIndex {
+        StringIndex::new(STRINGS.find(name).unwrap() as u32)
+    }
+
+    fn definition(ty: Il2CppTypeEnum, idx: u32) -> InflatedType {
+        InflatedType {
+            ty,
+            byref: false,
+            data: InflatedTypeData::Definition(TypeDefinitionIndex::new(idx)),
+        }
+    }
+
+    /// `List<T>` with the fields `T _item` and `T[] _items` and the method
+    /// `void Add(T)`, instantiated as `List<int>`.
+    fn metadata() -> Metadata<'static, 'static> {
+        let mut metadata = test_util::metadata(STRINGS);
+        let mr = &mut metadata.runtime_metadata.metadata_registration;
+        mr.types = vec![
+            test_util::ty(Il2CppTypeEnum::I4, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(INT32))),
+            test_util::ty(Il2CppTypeEnum::Var, TypeData::GenericParameterIndex(GenericParameterIndex::new(0))),
+            test_util::ty(Il2CppTypeEnum::Szarray, TypeData::TypeIndex(1)),
+            test_util::ty(Il2CppTypeEnum::Void, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(VOID))),
+            test_util::ty(Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(LIST))),
+        ];
+        mr.generic_insts = vec![Il2CppGenericInst { types: vec![0] }];
+        mr.generic_classes = vec![Il2CppGenericClass {
+            type_index: 4,
+            context: Il2CppGenericContext { class_inst_idx: Some(0), method_inst_idx: None },
+        }];
+
+        let gm = &mut metadata.global_metadata;
+        let field = |name: &str, type_index: u32| {
+            let mut field: Il2CppFieldDefinition = test_util::zeroed();
+            field.name_index = string(name);
+            field.type_index = type_index;
+            field
+        };
+        gm.fields = vec![field("_item", 1), field("_items", 2)].into();
+        let mut parameter: Il2CppParameterDefinition = test_util::zeroed();
+        parameter.type_index = 1;
+        gm.parameters = vec![parameter].into();
+        let mut method: Il2CppMethodDefinition = test_util::zeroed();
+        method.name_index = string("Add");
+        method.return_type = 3;
+        method.parameter_count = 1;
+        method.generic_container_index = GenericContainerIndex::new(u32::MAX);
+        gm.methods = vec![method].into();
+        gm.generic_parameters = vec![test_util::zeroed()].into();
+
+        let mut list = test_util::type_definition();
+        list.generic_container_index = GenericContainerIndex::new(0);
+        list.field_count = 2;
+        list.method_count = 1;
+        gm.type_definitions = vec![list, test_util::type_definition(), test_util::type_definition()].into();
+        metadata
+    }
+
+    #[test]
+    fn inflated_fields() {
+        let metadata = metadata();
+        let generic_class = &metadata.runtime_metadata.metadata_registration.generic_classes[0];
+        let fields = generic_class.inflated_fields(&metadata);
+        let fields: Vec<_> = fields.iter().map(|field| (field.field.index(), field.name, &field.ty)).collect();
+        let int = definition(Il2CppTypeEnum::I4, INT32);
+        let int_array = InflatedType {
+            ty: Il2CppTypeEnum::Szarray,
+            byref: false,
+            data: InflatedTypeData::Element(Box::new(int.clone())),
+        };
+        assert_eq!(fields, [(0, "_item", &int), (1, "_items", &int_array)]);
+    }
+
+    #[test]
+    fn inflated_methods() {
+        let metadata = metadata();
+        let generic_class = &metadata.runtime_metadata.metadata_registration.generic_classes[0];
+        let methods = generic_class.inflated_methods(&metadata);
+        assert_eq!(methods.len(), 1);
+        assert_eq!(methods[0].method, MethodIndex::new(0));
+        assert_eq!(methods[0].name, "Add");
+        assert_eq!(methods[0].return_type, definition(Il2CppTypeEnum::Void, VOID));
+        assert_eq!(methods[0].parameters, [definition(Il2CppTypeEnum::I4, INT32)]);
+    }
+
+    #[test]
+    fn instantiations() {
+        let mut metadata = metadata();
+        let mr = &mut metadata.runtime_metadata.metadata_registration;
+        // `List<T>`, as used inside of `List<T>`
+        mr.generic_insts.push(Il2CppGenericInst { types: vec![1] });
+        mr.generic_classes.push(Il2CppGenericClass {
+            type_index: 4,
+            context: Il2CppGenericContext { class_inst_idx: Some(1), method_inst_idx: None },
+        });
+        let spec = |method, class_inst_index| Il2CppMethodSpec {
+            method_definition_index: MethodIndex::new(method),
+            class_inst_index,
+            method_inst_index: u32::MAX,
+        };
+        mr.method_specs = vec![spec(0, 0), spec(0, 1), spec(1, 0), spec(0, 0)];
+
+        let list = TypeDefinitionIndex::new(LIST);
+        assert_eq!(metadata.instantiations_of(list).collect::<Vec<_>>(), [0]);
+        assert_eq!(metadata.instantiations_of(TypeDefinitionIndex::new(INT32)).count(), 0);
+        assert_eq!(metadata.method_instantiations_of(MethodIndex::new(0)).collect::<Vec<_>>(), [0, 3]);
+        assert_eq!(metadata.method_instantiations_of(MethodIndex::new(2)).count(), 0);
+    }
+
+    #[test]
+    fn overflowing_ranges() {
+        let mut metadata = metadata();
+        let mut list = test_util::type_definition();
+        list.generic_container_index = GenericContainerIndex::new(0);
+        list.field_start = FieldIndex::new(u32::MAX - 1);
+        list.field_count = 2;
+        list.method_start = MethodIndex::new(u32::MAX - 1);
+        list.method_count = 1;
+        metadata.global_metadata.type_definitions = vec![list].into();
+        let generic_class = &metadata.runtime_metadata.metadata_registration.generic_classes[0];
+        assert!(generic_class.inflated_fields(&metadata).is_empty());
+        assert!(generic_class.inflated_methods(&metadata).is_empty());
+    }
+}
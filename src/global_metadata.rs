@@ -1,7 +1,15 @@
 //! Global metadata types.
 
+pub mod constant;
+pub mod custom_attribute;
+pub mod transform;
+
 use crate::Metadata;
-use crate::runtime_metadata::TypeData;
+use crate::lookup::MetadataIndex;
+use crate::flags::TypeLayout;
+use crate::runtime_metadata::{Il2CppType, TypeData};
+use crate::type_name::{TypeNameFormatter, TypeNameStyle};
+use std::collections::HashMap;
 use std::io::Cursor;
 use std::ops::Index;
 use std::{str, concat, stringify};
@@ -17,9 +25,10 @@ pub type TypeIndex = u32;
 
 macro_rules! range_helper {
     ($name:ident, $table:ident, $start:ident, $count:ident, $ty:ty) => {
-        pub fn $name<'md>(&self, metadata: &'md Metadata) -> &'md [$ty] {
-            let range = self.$start.make_range(self.$count as _);
-            &metadata.global_metadata.$table[range]
+        /// Returns `None` if the range is out of bounds.
+        pub fn $name<'md>(&self, metadata: &'md Metadata) -> Option<&'md [$ty]> {
+            let range = self.$start.make_range(self.$count as _)?;
+            metadata.global_metadata.$table.get_range(range)
         }
     };
     ($table:ident, $start:ident, $count:ident, $ty:ty) => {
@@ -29,19 +38,19 @@ macro_rules! range_helper {
 
 macro_rules! field_helper {
     ($name:ident, $table:ident, $field:ident, $ty:ty) => {
-        pub fn $name<'md>(&self, metadata: &'md Metadata) -> &'md $ty {
-            &metadata.global_metadata.$table[self.$field]
+        /// Returns `None` if the index is invalid or out of range.
+        pub fn $name<'md>(&self, metadata: &'md Metadata) -> Option<&'md $ty> {
+            metadata.global_metadata.$table.get(self.$field)
         }
     };
 }
 
-macro_rules! field_helper_optional {
-    ($name:ident, $table:ident, $field:ident, $ty:ty) => {
-        pub fn $name<'md>(&self, metadata: &'md Metadata) -> Option<&'md $ty> {
-            match self.$field.is_valid() {
-                false => None,
-                true => Some(&metadata.global_metadata.$table[self.$field])
-            }
+/// Like `field_helper!`, for string tables, which yield an empty string for
+/// indices that are out of range.
+macro_rules! string_helper {
+    ($name:ident, $table:ident, $field:ident) => {
+        pub fn $name<'md>(&self, metadata: &'md Metadata) -> &'md str {
+            &metadata.global_metadata.$table[self.$field]
         }
     };
 }
@@ -60,7 +69,9 @@ pub enum DecodedMethodIndex {
     MethodDef(MethodIndex),
     FieldInfo(FieldRefIndex),
     StringLiteral(StringLiteralIndex),
-    // TODO: Generic method index
+    /// Index into the
+    /// [`Il2CppMetadataRegistration::method_specs`](crate::runtime_metadata::Il2CppMetadataRegistration::method_specs)
+    /// field.
     MethodRef(u32),
     FieldRva(FieldRefIndex),
 }
@@ -74,11 +85,12 @@ impl EncodedMethodIndex {
         let idx = (self.0 & 0x1FFFFFFE) >> 1;
         let invalid = self.0 & 0x00000001;
 
+        // `ty` is only 3 bits wide and `invalid` is 1 bit wide, so every
+        // possible value is covered.
         match ty {
             0 => DecodedMethodIndex::Invalid(match invalid {
                 0 => InvalidMethodIndex::NoData,
-                1 => InvalidMethodIndex::AmbiguousMethod,
-                _ => panic!("Unknown invalid method index type: {}", invalid),
+                _ => InvalidMethodIndex::AmbiguousMethod,
             }),
             1 => DecodedMethodIndex::TypeInfo(idx),
             2 => DecodedMethodIndex::Il2CppType(idx),
@@ -86,8 +98,7 @@ impl EncodedMethodIndex {
             4 => DecodedMethodIndex::FieldInfo(FieldRefIndex::new(idx)),
             5 => DecodedMethodIndex::StringLiteral(StringLiteralIndex::new(idx)),
             6 => DecodedMethodIndex::MethodRef(idx),
-            7 => DecodedMethodIndex::FieldRva(FieldRefIndex::new(idx)),
-            _ => panic!("Unknown encoded method index type: {}", ty),
+            _ => DecodedMethodIndex::FieldRva(FieldRefIndex::new(idx)),
         }
 
     }
@@ -141,7 +152,7 @@ pub struct Il2CppStringLiteral {
 }
 
 impl Il2CppStringLiteral {
-    field_helper!(data, string_literal_data, data_index, str);
+    string_helper!(data, string_literal_data, data_index);
 }
 
 /// Defined at `vm/GlobalMetadataFileInternals.h:168`
@@ -149,17 +160,29 @@ impl Il2CppStringLiteral {
 pub struct Il2CppEventDefinition {
     pub name_index: StringIndex,
     pub type_index: TypeIndex,
+    /// Index into declaring type's method list
     pub add: MethodIndex,
+    /// Index into declaring type's method list
     pub remove: MethodIndex,
+    /// Index into declaring type's method list
     pub raise: MethodIndex,
     pub token: Token,
 }
 
 impl Il2CppEventDefinition {
-    field_helper!(name, string, name_index, str);
-    field_helper!(add_method, methods, add, Il2CppMethodDefinition);
-    field_helper!(remove_method, methods, remove, Il2CppMethodDefinition);
-    field_helper!(raise_method, methods, raise, Il2CppMethodDefinition);
+    string_helper!(name, string, name_index);
+
+    pub fn add_method<'md>(&self, decl_type: &Il2CppTypeDefinition, metadata: &'md Metadata) -> Option<&'md Il2CppMethodDefinition> {
+        metadata.global_metadata.methods.get(accessor_index(decl_type, self.add.index())?)
+    }
+
+    pub fn remove_method<'md>(&self, decl_type: &Il2CppTypeDefinition, metadata: &'md Metadata) -> Option<&'md Il2CppMethodDefinition> {
+        metadata.global_metadata.methods.get(accessor_index(decl_type, self.remove.index())?)
+    }
+
+    pub fn raise_method<'md>(&self, decl_type: &Il2CppTypeDefinition, metadata: &'md Metadata) -> Option<&'md Il2CppMethodDefinition> {
+        metadata.global_metadata.methods.get(accessor_index(decl_type, self.raise.index())?)
+    }
 }
 
 /// Defined at `vm/GlobalMetadataFileInternals.h:154`
@@ -174,44 +197,66 @@ pub struct Il2CppMethodDefinition {
     pub generic_container_index: GenericContainerIndex,
     pub token: Token,
 
-    /// Method attributes. See `il2cpp-tabledefs.h`.
+    /// Method attributes. See `il2cpp-tabledefs.h` and
+    /// [`Il2CppMethodDefinition::attributes()`].
     pub flags: u16,
 
-    /// Method implementation attributes. See `il2cpp-tabledefs.h`.
+    /// Method implementation attributes. See `il2cpp-tabledefs.h` and
+    /// [`Il2CppMethodDefinition::impl_attributes()`].
     pub iflags: u16,
     pub slot: u16,
     pub parameter_count: u16,
 }
 
 impl Il2CppMethodDefinition {
-    field_helper!(name, string, name_index, str);
+    string_helper!(name, string, name_index);
     field_helper!(declaring_type, type_definitions, declaring_type, Il2CppTypeDefinition);
     range_helper!(parameters, parameter_start, parameter_count, Il2CppParameterDefinition);
-    field_helper_optional!(generic_container, generic_containers, generic_container_index, Il2CppGenericContainer);
+    field_helper!(generic_container, generic_containers, generic_container_index, Il2CppGenericContainer);
 
     pub fn full_name(&self, metadata: &Metadata) -> String {
         let mr = &metadata.runtime_metadata.metadata_registration;
+        let type_name = |idx: TypeIndex| match mr.types.get(idx as usize) {
+            Some(ty) => ty.full_name(metadata),
+            None => "?".into(),
+        };
         let mut full_name = String::new();
-        full_name.push_str(&mr.types[self.return_type as usize].full_name(metadata));
+        full_name.push_str(&type_name(self.return_type));
         full_name.push(' ');
-        full_name.push_str(&self.declaring_type(metadata).full_name(metadata, true));
+        match self.declaring_type(metadata) {
+            Some(ty_def) => full_name.push_str(&ty_def.full_name(metadata, true)),
+            None => full_name.push('?'),
+        }
         full_name.push_str("::");
         full_name.push_str(self.name(metadata));
         if let Some(gc) = self.generic_container(metadata) {
             full_name.push_str(&gc.to_string(metadata));
         }
         full_name.push('(');
-        for (i, param) in self.parameters(metadata).iter().enumerate() {
+        for (i, param) in self.parameters(metadata).unwrap_or_default().iter().enumerate() {
             if i > 0 {
                 full_name.push_str(", ");
             }
-            full_name.push_str(&mr.types[param.type_index as usize].full_name(metadata));
+            full_name.push_str(&type_name(param.type_index));
             full_name.push(' ');
             full_name.push_str(param.name(metadata));
         }
         full_name.push(')');
         full_name
     }
+
+    /// The address of the method's compiled code in the binary.
+    ///
+    /// This is `None` for abstract methods, methods that were stripped, and
+    /// when the runtime metadata was not read from a binary. Methods of
+    /// generic types only have code for their instantiations, which can be
+    /// found through
+    /// [`Il2CppMetadataRegistration::generic_method_table`](crate::runtime_metadata::Il2CppMetadataRegistration::generic_method_table).
+    ///
+    /// The code gen module of every image is looked up once by `index`.
+    pub fn method_pointer(&self, index: &MetadataIndex) -> Option<u64> {
+        index.method_pointer(self)
+    }
 }
 
 /// Defined at `vm/GlobalMetadataFileInternals.h:140`
@@ -223,7 +268,7 @@ pub struct Il2CppParameterDefinition {
 }
 
 impl Il2CppParameterDefinition {
-    field_helper!(name, string, name_index, str);
+    string_helper!(name, string, name_index);
 }
 
 /// Defined at `vm/GlobalMetadataFileInternals.h:66`
@@ -261,7 +306,8 @@ pub struct Il2CppTypeDefinition {
     pub interfaces_count: u16,
     pub interface_offsets_count: u16,
 
-    /// bitfield to portably encode boolean values as single bits
+    /// bitfield to portably encode boolean values as single bits. Bits are
+    /// numbered from 1, the least significant bit.
     /// * 01 - valuetype;
     /// * 02 - enumtype;
     /// * 03 - has_finalize;
@@ -269,19 +315,64 @@ pub struct Il2CppTypeDefinition {
     /// * 05 - is_blittable;
     /// * 06 - is_import_or_windows_runtime;
     /// * 07-10 - One of nine possible PackingSize values (0, 1, 2, 4, 8, 16,
-    ///           32, 64, or 128)
+    ///   32, 64, or 128), stored as their position in this list
     /// * 11 - PackingSize is default
     /// * 12 - ClassSize is default
     /// * 13-16 - One of nine possible PackingSize values (0, 1, 2, 4, 8, 16,
-    ///           32, 64, or 128) - the specified packing size (even for
-    ///           explicit layouts)
+    ///   32, 64, or 128) - the specified packing size (even for explicit
+    ///   layouts)
+    /// * 17 - is_byref_like
+    ///
+    /// Use [`Il2CppTypeDefinition::layout_info()`] to decode it.
     pub bitfield: u32,
     pub token: Token,
 }
 
+/// Layout properties of a type definition, decoded from
+/// [`Il2CppTypeDefinition::bitfield`] and [`Il2CppTypeDefinition::flags`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeDefinitionLayoutInfo {
+    pub is_value_type: bool,
+    pub is_enum: bool,
+    pub has_finalizer: bool,
+    pub has_static_constructor: bool,
+    /// Whether instances have the same representation in managed and native
+    /// code.
+    pub is_blittable: bool,
+    pub is_import_or_windows_runtime: bool,
+    /// Whether the type is a `ref struct`.
+    pub is_byref_like: bool,
+    /// The packing size in bytes that fields are aligned to, or `None` if the
+    /// type uses the default packing.
+    pub packing_size: Option<u8>,
+    /// The packing size in bytes given in the type's `StructLayout`
+    /// attribute, even if it is ignored because the layout is explicit. `0`
+    /// if no packing size was given.
+    pub specified_packing_size: u8,
+    /// Whether the type uses the default size instead of a size given in its
+    /// `StructLayout` attribute.
+    pub class_size_is_default: bool,
+    pub layout: TypeLayout,
+}
+
+/// The kind of a type definition, as it would be declared in C#.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeKind {
+    Class,
+    /// A class that is both `abstract` and `sealed`.
+    StaticClass,
+    /// A class deriving from `System.Attribute`.
+    Attribute,
+    /// A class deriving from `System.MulticastDelegate`.
+    Delegate,
+    Struct,
+    Enum,
+    Interface,
+}
+
 impl Il2CppTypeDefinition {
-    field_helper!(name, string, name_index, str);
-    field_helper!(namespace, string, namespace_index, str);
+    string_helper!(name, string, name_index);
+    string_helper!(namespace, string, namespace_index);
     field_helper!(generic_container, generic_containers, generic_container_index, Il2CppGenericContainer);
     range_helper!(methods, method_start, method_count, Il2CppMethodDefinition);
     range_helper!(fields, field_start, field_count, Il2CppFieldDefinition);
@@ -292,28 +383,122 @@ impl Il2CppTypeDefinition {
     range_helper!(vtable_methods, vtable_start, vtable_count, EncodedMethodIndex);
     range_helper!(interface_offsets, interface_offsets_start, interface_offsets_count, Il2CppInterfaceOffsetPair);
 
-    pub fn full_name(&self, metadata: &Metadata, with_generics: bool) -> String {
-        let namespace = self.namespace(metadata);
-        let name = self.name(metadata);
+    /// Decodes the type's [`Il2CppTypeDefinition::bitfield`] and layout flags.
+    pub fn layout_info(&self) -> TypeDefinitionLayoutInfo {
+        let bit = |n: u32| (self.bitfield >> (n - 1)) & 1 != 0;
+        let packing = |n: u32| {
+            let packing = (self.bitfield >> (n - 1)) & 0xF;
+            [0, 1, 2, 4, 8, 16, 32, 64, 128].get(packing as usize).copied().unwrap_or(0)
+        };
+        TypeDefinitionLayoutInfo {
+            is_value_type: bit(1),
+            is_enum: bit(2),
+            has_finalizer: bit(3),
+            has_static_constructor: bit(4),
+            is_blittable: bit(5),
+            is_import_or_windows_runtime: bit(6),
+            is_byref_like: bit(17),
+            packing_size: (!bit(11)).then(|| packing(7)),
+            specified_packing_size: packing(13),
+            class_size_is_default: bit(12),
+            layout: self.attributes().layout(),
+        }
+    }
+
+    /// Classifies the type by its flags and base types.
+    pub fn kind(&self, metadata: &Metadata) -> TypeKind {
+        let layout = self.layout_info();
+        if self.is_interface() {
+            return TypeKind::Interface;
+        }
+        if layout.is_enum {
+            return TypeKind::Enum;
+        }
+        if layout.is_value_type {
+            return TypeKind::Struct;
+        }
+        if self.is_abstract() && self.is_sealed() {
+            return TypeKind::StaticClass;
+        }
 
+        // Unresolved base types are kept so that the direct base type is
+        // always the first entry.
+        let type_defs = &metadata.global_metadata.type_definitions;
+        let base_name = |base: TypeIndex| {
+            let ty = metadata.runtime_metadata.metadata_registration.types.get(base as usize)?;
+            let base = type_defs.get(ty.type_definition_index(metadata)?)?;
+            match base.namespace(metadata) {
+                "System" => metadata.global_metadata.string.get(base.name_index).ok(),
+                _ => None,
+            }
+        };
+        for (i, name) in self.base_chain(metadata).into_iter().map(base_name).enumerate() {
+            match name {
+                Some("MulticastDelegate") if i == 0 => return TypeKind::Delegate,
+                Some("Attribute") => return TypeKind::Attribute,
+                _ => {}
+            }
+        }
+        TypeKind::Class
+    }
 
-        let mut full_name = String::new();
-        if !namespace.is_empty() {
-            full_name.push_str(namespace);
-            full_name.push('.');
+    /// The `Invoke` method of a delegate type, whose signature is the
+    /// signature of the delegate.
+    ///
+    /// Returns `None` if the type is not a delegate.
+    pub fn delegate_invoke<'md>(&self, metadata: &'md Metadata) -> Option<&'md Il2CppMethodDefinition> {
+        if self.kind(metadata) != TypeKind::Delegate {
+            return None;
         }
+        self.methods(metadata)?
+            .iter()
+            .find(|method| method.name(metadata) == "Invoke")
+    }
 
-        if self.declaring_type_index != u32::MAX {
-            let s = metadata.runtime_metadata.metadata_registration.types
-                [self.declaring_type_index as usize]
-                .full_name(metadata)
-                + "::";
-            full_name.push_str(s.as_str());
+    /// The type this type directly inherits from, or `None` for
+    /// `System.Object`, interfaces and other types without a base type.
+    pub fn base_type<'md>(&self, metadata: &'md Metadata) -> Option<&'md Il2CppType> {
+        match self.parent_index {
+            u32::MAX => None,
+            idx => metadata.runtime_metadata.metadata_registration.types.get(idx as usize),
         }
+    }
 
-        full_name.push_str(name);
-        if self.generic_container_index.is_valid() && with_generics {
-            let gc = self.generic_container(metadata);
+    /// The definition of the type this type directly inherits from.
+    pub fn base_type_definition(&self, metadata: &Metadata) -> Option<TypeDefinitionIndex> {
+        self.base_type(metadata)?.type_definition_index(metadata)
+    }
+
+    /// All base types of this type, starting with the direct base type and
+    /// ending with `System.Object`.
+    ///
+    /// Each base type is listed as it is referenced by the type that inherits
+    /// from it, so generic base types are instantiated with the arguments of
+    /// the previous type in the chain.
+    pub fn base_chain(&self, metadata: &Metadata) -> Vec<TypeIndex> {
+        let type_defs = &metadata.global_metadata.type_definitions;
+        let mut chain = Vec::new();
+        let mut current = self;
+        while current.parent_index != u32::MAX && chain.len() <= type_defs.len() {
+            chain.push(current.parent_index);
+            match current.base_type_definition(metadata).and_then(|idx| type_defs.get(idx)) {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+        chain
+    }
+
+    /// The name of the type in C# syntax, including its namespace and
+    /// declaring types, such as ``System.Collections.Generic.Dictionary`2.Enumerator``.
+    ///
+    /// If `with_generics` is true, the names of the type's generic parameters
+    /// are appended.
+    pub fn full_name(&self, metadata: &Metadata, with_generics: bool) -> String {
+        let mut full_name = TypeNameFormatter::new(metadata, TypeNameStyle::CSharp)
+            .generic_arity(true)
+            .format_definition(self);
+        if let Some(gc) = self.generic_container(metadata).filter(|_| with_generics) {
             full_name.push_str(&gc.to_string(metadata));
         }
         full_name
@@ -340,7 +525,7 @@ pub struct Il2CppImageDefinition {
 }
 
 impl Il2CppImageDefinition {
-    field_helper!(name, string, name_index, str);
+    string_helper!(name, string, name_index);
     field_helper!(assembly, assemblies, assembly_index, Il2CppAssemblyDefinition);
     range_helper!(types, type_definitions, type_start, type_count, Il2CppTypeDefinition);
     range_helper!(exported_types, type_definitions, exported_type_start, exported_type_count, Il2CppTypeDefinition);
@@ -357,7 +542,7 @@ pub struct Il2CppFieldDefinition {
 }
 
 impl Il2CppFieldDefinition {
-    field_helper!(name, string, name_index, str);
+    string_helper!(name, string, name_index);
 }
 
 /// Defined at `vm/GlobalMetadataFileInternals.h:178`
@@ -368,30 +553,39 @@ pub struct Il2CppPropertyDefinition {
     pub get: u32,
     /// Index into declaring type's method list
     pub set: u32,
-    /// See `il2cpp-tabledef.h`
+    /// See `il2cpp-tabledef.h` and [`Il2CppPropertyDefinition::attributes()`]
     pub attrs: u32,
     pub token: Token,
 }
 
 impl Il2CppPropertyDefinition {
-    field_helper!(name, string, name_index, str);
+    string_helper!(name, string, name_index);
+
+    /// Returns `None` if the property has no getter.
+    pub fn get_method_index(&self, decl_type: &Il2CppTypeDefinition) -> Option<MethodIndex> {
+        accessor_index(decl_type, self.get)
+    }
 
-    pub fn get_method_index(&self, decl_type: &Il2CppTypeDefinition) -> MethodIndex {
-        MethodIndex::new(decl_type.method_start.index() + self.get)
+    pub fn get_method<'md>(&self, decl_type: &Il2CppTypeDefinition, metadata: &'md Metadata) -> Option<&'md Il2CppMethodDefinition> {
+        metadata.global_metadata.methods.get(self.get_method_index(decl_type)?)
     }
 
-    pub fn get_method<'md>(&self, decl_type: &Il2CppTypeDefinition, metadata: &'md Metadata) -> &'md Il2CppMethodDefinition {
-        let idx = self.get_method_index(decl_type);
-        &metadata.global_metadata.methods[idx]
+    /// Returns `None` if the property has no setter.
+    pub fn set_method_index(&self, decl_type: &Il2CppTypeDefinition) -> Option<MethodIndex> {
+        accessor_index(decl_type, self.set)
     }
 
-    pub fn set_method_index(&self, decl_type: &Il2CppTypeDefinition) -> MethodIndex {
-        MethodIndex::new(decl_type.method_start.index() + self.set)
+    pub fn set_method<'md>(&self, decl_type: &Il2CppTypeDefinition, metadata: &'md Metadata) -> Option<&'md Il2CppMethodDefinition> {
+        metadata.global_metadata.methods.get(self.set_method_index(decl_type)?)
     }
+}
 
-    pub fn set_method<'md>(&self, decl_type: &Il2CppTypeDefinition, metadata: &'md Metadata) -> &'md Il2CppMethodDefinition {
-        let idx = self.set_method_index(decl_type);
-        &metadata.global_metadata.methods[idx]
+/// Resolves the index of a property or event accessor, which is stored relative
+/// to the first method of the declaring type.
+fn accessor_index(decl_type: &Il2CppTypeDefinition, index: u32) -> Option<MethodIndex> {
+    match index {
+        u32::MAX => None,
+        index => decl_type.method_start.index().checked_add(index).map(MethodIndex::new),
     }
 }
 
@@ -405,7 +599,7 @@ pub struct Il2CppParameterDefaultValue {
 
 impl Il2CppParameterDefaultValue {
     field_helper!(parameter, parameters, parameter_index, Il2CppParameterDefinition);
-    // TODO: data type
+    // Use `value()` to decode the data.
     field_helper!(data, field_and_parameter_default_value_data, data_index, u8);
 }
 
@@ -419,7 +613,7 @@ pub struct Il2CppFieldDefaultValue {
 
 impl Il2CppFieldDefaultValue {
     field_helper!(field, fields, field_index, Il2CppFieldDefinition);
-    // TODO: data type
+    // Use `value()` to decode the data.
     field_helper!(data, field_and_parameter_default_value_data, data_index, u8);
 }
 
@@ -452,7 +646,7 @@ pub struct Il2CppGenericParameter {
 
 impl Il2CppGenericParameter {
     field_helper!(owner, generic_containers, owner_index, Il2CppGenericContainer);
-    field_helper!(name, string, name_index, str);
+    string_helper!(name, string, name_index);
     range_helper!(constraints, generic_parameter_constraints, constraints_start, constraints_count, TypeIndex);
 }
 
@@ -477,7 +671,7 @@ impl Il2CppGenericContainer {
     pub fn to_string(&self, metadata: &Metadata) -> String {
         let mut full_name = String::new();
         full_name.push('<');
-        for (i, param) in self.generic_parameters(metadata).iter().enumerate() {
+        for (i, param) in self.generic_parameters(metadata).unwrap_or_default().iter().enumerate() {
             if i > 0 {
                 full_name.push_str(", ");
             }
@@ -516,7 +710,7 @@ pub struct Il2CppAssemblyNameDefinition {
 
 
 impl Il2CppAssemblyNameDefinition {
-    field_helper!(name, string, name_index, str);
+    string_helper!(name, string, name_index);
     // TODO: are culture and public_key valid utf-8?
 }
 /// Defined at `vm/GlobalMetadataFileInternals.h:226`
@@ -564,13 +758,32 @@ pub struct Il2CppFieldRef {
 }
 
 impl Il2CppFieldRef {
-    pub fn resolve_field(&self, metadata: &Metadata) -> FieldIndex {
-        let ty_data = &metadata.runtime_metadata.metadata_registration.types[self.type_index as usize].data;
-        let TypeData::TypeDefinitionIndex(ty_def_idx) = ty_data else {
-            panic!("Bad Il2CppFieldRef type data type: {:?}", ty_data);
+    pub fn resolve_field(&self, metadata: &Metadata) -> Result<FieldIndex, MetadataIndexError> {
+        let ty = metadata
+            .runtime_metadata
+            .metadata_registration
+            .types
+            .get(self.type_index as usize)
+            .ok_or(MetadataIndexError::OutOfRange {
+                table: "types",
+                index: self.type_index as usize,
+            })?;
+        let TypeData::TypeDefinitionIndex(ty_def_idx) = ty.data else {
+            return Err(MetadataIndexError::NotTypeDefinition(self.type_index));
         };
-        let ty_def = &metadata.global_metadata.type_definitions[*ty_def_idx];
-        FieldIndex::new(ty_def.field_start.0 + self.field_index)
+        let ty_def = metadata.global_metadata.type_definitions.get(ty_def_idx).ok_or(
+            MetadataIndexError::OutOfRange {
+                table: "type_definitions",
+                index: ty_def_idx.0 as usize,
+            },
+        )?;
+        if self.field_index >= ty_def.field_count as u32 {
+            return Err(MetadataIndexError::OutOfRange {
+                table: "fields",
+                index: self.field_index as usize,
+            });
+        }
+        Ok(FieldIndex::new(ty_def.field_start.0 + self.field_index))
     }
 }
 
@@ -587,6 +800,11 @@ where
     fn read(cursor: &mut Cursor<&'a [u8]>, size: usize) -> std::io::Result<Self>;
 }
 
+/// Returns an error for a table that does not fit inside of the data.
+fn table_out_of_bounds() -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "table extends past end of data")
+}
+
 macro_rules! metadata {
     ($($(#[$($attrss:tt)*])* $name:ident: $ty:ty,)*) => {
         #[derive(Debug, BinaryDeserialize)]
@@ -598,6 +816,15 @@ macro_rules! metadata {
             )*
         }
 
+        /// Names of the tables in the order they appear in the header.
+        const HEADER_TABLES: &[&str] = &[$(stringify!($name),)*];
+
+        impl Il2CppGlobalMetadataHeader {
+            fn table_ranges(&self) -> impl Iterator<Item = &OffsetLen> {
+                [$(&self.$name,)*].into_iter()
+            }
+        }
+
         #[derive(Debug)]
         pub struct GlobalMetadata<'a> {
             $(
@@ -618,7 +845,13 @@ macro_rules! metadata {
                             let size = header.$name.len as usize;
                             if size > 0 {
                                 cursor.set_position(header.$name.offset as u64);
-                                ReadMetadataTable::read(&mut cursor, size)?
+                                ReadMetadataTable::read(&mut cursor, size).map_err(|source| {
+                                    MetadataDeserializeError::Table {
+                                        table: stringify!($name),
+                                        offset: header.$name.offset,
+                                        source,
+                                    }
+                                })?
                             } else {
                                 Default::default()
                             }
@@ -650,14 +883,22 @@ macro_rules! index_type {
                 Self(index)
             }
 
-            pub fn make_range(self, count: $ty) -> std::ops::Range<$name> {
+            /// Returns the range of `count` indices starting at this one, or
+            /// `None` if its end overflows.
+            pub fn make_range(self, count: $ty) -> Option<std::ops::Range<$name>> {
                 if count > 0 {
-                    self..Self::new(self.0 + count)
+                    Some(self..Self::new(self.0.checked_add(count)?))
                 } else {
-                    Self(0)..Self(0)
+                    Some(Self(0)..Self(0))
                 }
             }
 
+            /// Iterates over the `count` indices starting at this one, stopping
+            /// early if an index overflows.
+            pub fn indices(self, count: $ty) -> impl Iterator<Item = $name> {
+                (0..count).map_while(move |i| self.0.checked_add(i).map(Self))
+            }
+
             pub fn is_valid(self) -> bool {
                 self.0 != <$ty>::MAX
             }
@@ -691,20 +932,98 @@ macro_rules! basic_table {
             table: Vec<$ty>,
         }
 
+        impl $name {
+            fn from_vec(table: Vec<$ty>) -> Self {
+                Self { table }
+            }
+        }
+
+        basic_table!(@impl $name: $ty, $idx_name: $idx_ty);
+    };
+    ($name:ident: $ty:ty, $idx_name:ident, $lookup:ident($key:ident: $key_ty:ty)) => {
+        #[doc =
+            concat!(
+                "A metadata table of [`",
+                stringify!($ty),
+                "`]s.\n\nUse a [`",
+                stringify!($idx_name),
+                "`] to index into it, or look items up by their `",
+                stringify!($key),
+                "` with [`",
+                stringify!($name),
+                "::",
+                stringify!($lookup),
+                "`]."
+            )
+        ]
+        #[derive(Debug, Default)]
+        pub struct $name {
+            table: Vec<$ty>,
+            /// The position of the first item with each key.
+            positions: HashMap<$key_ty, usize>,
+        }
+
+        impl $name {
+            fn from_vec(table: Vec<$ty>) -> Self {
+                let mut positions = HashMap::with_capacity(table.len());
+                for (i, item) in table.iter().enumerate() {
+                    positions.entry(item.$key).or_insert(i);
+                }
+                Self { table, positions }
+            }
+
+            #[doc = concat!("Returns the first item whose `", stringify!($key), "` is `key`.")]
+            pub fn $lookup(&self, key: $key_ty) -> Option<&$ty> {
+                self.table.get(*self.positions.get(&key)?)
+            }
+        }
+
+        basic_table!(@impl $name: $ty, $idx_name: u32);
+    };
+    (@impl $name:ident: $ty:ty, $idx_name:ident: $idx_ty:ty) => {
         impl $name {
             pub fn as_vec(&self) -> &Vec<$ty> {
                 &self.table
             }
+
+            /// Returns the item at `index`, or `None` if it is out of range.
+            pub fn get(&self, index: $idx_name) -> Option<&$ty> {
+                self.table.get(index.0 as usize)
+            }
+
+            /// Returns the items in `range`, or `None` if it is out of range.
+            pub fn get_range(&self, range: std::ops::Range<$idx_name>) -> Option<&[$ty]> {
+                self.table.get(range.start.0 as usize..range.end.0 as usize)
+            }
+
+            pub fn len(&self) -> usize {
+                self.table.len()
+            }
+
+            pub fn is_empty(&self) -> bool {
+                self.table.is_empty()
+            }
+        }
+
+        #[cfg(test)]
+        impl From<Vec<$ty>> for $name {
+            fn from(table: Vec<$ty>) -> Self {
+                Self::from_vec(table)
+            }
         }
 
         impl ReadMetadataTable<'_> for $name {
             fn read(cursor: &mut Cursor<&[u8]>, size: usize) -> std::io::Result<Self> {
                 let count = size / <$ty>::SIZE;
-                let mut vec = Vec::new();
+                let remaining = cursor.get_ref().len().saturating_sub(cursor.position() as usize);
+                if count * <$ty>::SIZE > remaining {
+                    return Err(table_out_of_bounds());
+                }
+                let mut vec = Vec::with_capacity(count);
                 for _ in 0..count {
                     vec.push(<$ty>::deserialize::<LittleEndian, _>(&mut *cursor)?);
                 }
-                Ok($name { table: vec })
+                Ok($name::from_vec(vec))
             }
         }
 
@@ -762,31 +1081,57 @@ macro_rules! string_data_table {
             pub fn data(&self) -> &'data [u8] {
                 self.data
             }
+
+            /// Returns the raw bytes of the null-terminated string at
+            /// `index`, or `None` if `index` is out of range.
+            pub fn get_bytes(&self, index: $idx_name) -> Option<&'data [u8]> {
+                let data = self.data.get(index.0 as usize..)?;
+                let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                Some(&data[..len])
+            }
+
+            /// Returns the string at `index`.
+            ///
+            /// Unlike indexing, this returns an error if `index` is out of
+            /// range or the string is not valid UTF-8.
+            pub fn get(&self, index: $idx_name) -> Result<&'data str, MetadataIndexError> {
+                let bytes = self.get_bytes(index).ok_or(MetadataIndexError::OutOfRange {
+                    table: stringify!($name),
+                    index: index.0 as usize,
+                })?;
+                str::from_utf8(bytes).map_err(|_| MetadataIndexError::InvalidString {
+                    table: stringify!($name),
+                    index: index.0 as usize,
+                })
+            }
         }
 
         index_type!($idx_name, u32, $name);
 
+        /// Indexing never panics. An out of range index yields an empty string
+        /// and invalid UTF-8 is cut off at the first invalid byte. Use
+        #[doc = concat!("[`", stringify!($name), "::get()`]")]
+        /// to detect these cases.
         impl<'data> Index<$idx_name> for $name<'data> {
             type Output = str;
 
             fn index(&self, index: $idx_name) -> &Self::Output {
-                let idx = index.0 as usize;
-                let mut len = 0;
-                while self.data[idx + len] != 0 {
-                    len += 1;
+                let bytes = self.get_bytes(index).unwrap_or_default();
+                match str::from_utf8(bytes) {
+                    Ok(str) => str,
+                    Err(err) => str::from_utf8(&bytes[..err.valid_up_to()]).unwrap_or_default(),
                 }
-
-                // FIXME: maybe not panic here?
-                str::from_utf8(&self.data[idx..idx + len]).unwrap()
             }
         }
 
         impl<'data> ReadMetadataTable<'data> for $name<'data> {
                 fn read(cursor: &mut Cursor<&'data [u8]>, size: usize) -> std::io::Result<Self> {
                     let start = cursor.position() as usize;
-                    Ok($name {
-                        data: &cursor.get_ref()[start..start + size],
-                    })
+                    let data = cursor
+                        .get_ref()
+                        .get(start..start.saturating_add(size))
+                        .ok_or_else(table_out_of_bounds)?;
+                    Ok($name { data })
                 }
             }
         };
@@ -798,8 +1143,12 @@ string_data_table!(StringData, StringIndex);
 basic_table!(EventTable: Il2CppEventDefinition, EventIndex);
 basic_table!(PropertyTable: Il2CppPropertyDefinition, PropertyIndex);
 basic_table!(MethodTable: Il2CppMethodDefinition, MethodIndex);
-basic_table!(ParameterDefaultValueTable: Il2CppParameterDefaultValue, ParameterDefaultValueIndex);
-basic_table!(FieldDefaultValueTable: Il2CppFieldDefaultValue, FieldDefaultValueIndex);
+basic_table!(
+    ParameterDefaultValueTable: Il2CppParameterDefaultValue,
+    ParameterDefaultValueIndex,
+    get_by_parameter(parameter_index: ParameterIndex)
+);
+basic_table!(FieldDefaultValueTable: Il2CppFieldDefaultValue, FieldDefaultValueIndex, get_by_field(field_index: FieldIndex));
 // TODO: Read default value data
 basic_table!(FieldAndParameterDefaultValueTable: u8, FieldAndParameterDefaultValueIndex);
 basic_table!(FieldMarshaledSizeTable: Il2CppFieldMarshaledSize, FieldMarshaledSizeIndex);
@@ -819,7 +1168,7 @@ basic_table!(FieldRefTable: Il2CppFieldRef, FieldRefIndex);
 // TODO: reference assemblies?
 basic_table!(ReferencedAssemblyTable: u32, ReferencedAssemblyIndex);
 basic_table!(AttributeDataRangeTable: Il2CppCustomAttributeDataRange, AttributeDataRangeIndex);
-// TODO: Read custom attribute data
+// Decoded by `custom_attribute`
 basic_table!(AttributeDataTable: u8, AttributeDataIndex);
 basic_table!(UnresolvedIndirectCallParameterTypeTable: TypeIndex, UnresolvedIndirectCallParameterTypeIndex);
 basic_table!(UnresolvedIndirectCallParameterRangeTable: Il2CppMetadataRange, UnresolvedIndirectCallParameterRangeIndex);
@@ -877,18 +1226,169 @@ pub enum MetadataDeserializeError {
 
     #[error("il2cpp metadata header version check failed, found {0}")]
     VersionCheck(u32),
+
+    #[error("il2cpp metadata is too small to contain a header ({0} bytes)")]
+    Truncated(usize),
+
+    #[error("il2cpp metadata looks encrypted")]
+    LooksEncrypted,
+
+    #[error("il2cpp metadata header table ranges are out of bounds, the header may be scrambled")]
+    ScrambledHeader,
+
+    #[error("could not read metadata table {table} at offset {offset:#x}")]
+    Table {
+        table: &'static str,
+        offset: u32,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl GlobalMetadata<'_> {
+    /// Finds the image that defines the type definition `ty`.
+    pub fn image_of(&self, ty: TypeDefinitionIndex) -> Option<&Il2CppImageDefinition> {
+        let ty = ty.index();
+        self.images
+            .as_vec()
+            .iter()
+            .find(|image| (image.type_start.index()..image.type_start.index().saturating_add(image.type_count)).contains(&ty))
+    }
+}
+
+/// An error from looking up an item in the metadata by index.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataIndexError {
+    #[error("{table} index {index} is out of range")]
+    OutOfRange { table: &'static str, index: usize },
+
+    #[error("{table} string at {index} is not valid utf-8")]
+    InvalidString { table: &'static str, index: usize },
+
+    #[error("type {0} is not a type definition")]
+    NotTypeDefinition(TypeIndex),
 }
 
 pub fn deserialize(data: &[u8]) -> Result<GlobalMetadata, MetadataDeserializeError> {
+    match transform::diagnose(data) {
+        transform::Diagnosis::Valid => {}
+        transform::Diagnosis::Truncated => return Err(MetadataDeserializeError::Truncated(data.len())),
+        transform::Diagnosis::WrongVersion(version) => return Err(MetadataDeserializeError::VersionCheck(version)),
+        transform::Diagnosis::ScrambledHeader => return Err(MetadataDeserializeError::ScrambledHeader),
+        transform::Diagnosis::LooksEncrypted => return Err(MetadataDeserializeError::LooksEncrypted),
+        transform::Diagnosis::NotMetadata => return Err(MetadataDeserializeError::SanityCheck),
+    }
+
     let header = Il2CppGlobalMetadataHeader::deserialize::<LittleEndian, _>(Cursor::new(data))?;
+    GlobalMetadata::deserialize(data, header)
+}
 
-    if header.sanity != SANITY {
-        return Err(MetadataDeserializeError::SanityCheck);
-    }
+/// Applies `transform` to `data` in-place and then deserializes it.
+///
+/// See [`transform`] for more information.
+pub fn deserialize_with_transform<'a, T: transform::MetadataTransform>(
+    data: &'a mut [u8],
+    transform: &T,
+) -> Result<GlobalMetadata<'a>, MetadataDeserializeError> {
+    transform::apply(data, transform)?;
+    deserialize(data)
+}
 
-    if header.version != VERSION {
-        return Err(MetadataDeserializeError::VersionCheck(header.version));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::TypeAttributes;
+    use crate::runtime_metadata::Il2CppTypeEnum;
+    use crate::test_util::{self, type_definition, zeroed};
+
+    #[test]
+    fn out_of_range_accessors() {
+        let mut metadata = test_util::metadata("");
+        let methods: Vec<Il2CppMethodDefinition> = (0..2).map(|_| zeroed()).collect();
+        metadata.global_metadata.methods = methods.into();
+
+        let mut ty_def = type_definition();
+        ty_def.method_start = MethodIndex::new(1);
+        ty_def.method_count = 1;
+        assert_eq!(ty_def.methods(&metadata).map(<[_]>::len), Some(1));
+        ty_def.method_count = 2;
+        assert!(ty_def.methods(&metadata).is_none());
+        ty_def.method_start = MethodIndex::new(u32::MAX - 1);
+        assert!(ty_def.methods(&metadata).is_none());
+        assert!(ty_def.generic_container(&metadata).is_none());
+
+        let mut property: Il2CppPropertyDefinition = zeroed();
+        property.set = u32::MAX;
+        assert_eq!(property.get_method_index(&ty_def), Some(MethodIndex::new(u32::MAX - 1)));
+        assert!(property.get_method(&ty_def, &metadata).is_none());
+        assert!(property.set_method_index(&ty_def).is_none());
+        property.get = 2;
+        assert!(property.get_method_index(&ty_def).is_none());
     }
 
-    GlobalMetadata::deserialize(data, header)
+    #[test]
+    fn type_kinds() {
+        const STRINGS: &str = "\0System\0Object\0Delegate\0MulticastDelegate\0Attribute\0Invoke\0";
+        let string = |name: &str| StringIndex::new(STRINGS.find(&format!("\0{name}\0")).unwrap() as u32 + 1);
+        let mut metadata = test_util::metadata(STRINGS);
+        let mut invoke: Il2CppMethodDefinition = zeroed();
+        invoke.name_index = string("Invoke");
+        metadata.global_metadata.methods = vec![invoke].into();
+
+        let system = |name: StringIndex, parent: u32| {
+            let mut ty_def = type_definition();
+            ty_def.namespace_index = string("System");
+            ty_def.name_index = name;
+            ty_def.parent_index = parent;
+            ty_def
+        };
+        let class = |parent: u32, flags: TypeAttributes, bitfield: u32| {
+            let mut ty_def = type_definition();
+            ty_def.parent_index = parent;
+            ty_def.flags = flags.bits();
+            ty_def.bitfield |= bitfield;
+            ty_def
+        };
+        let mut delegate = class(2, TypeAttributes::SEALED, 0);
+        delegate.method_count = 1;
+        let type_defs = vec![
+            system(string("Object"), u32::MAX),
+            system(string("Delegate"), 0),
+            system(string("MulticastDelegate"), 1),
+            system(string("Attribute"), 0),
+            // A base type in `System` whose name can't be read.
+            system(StringIndex::new(u32::MAX), 2),
+            delegate,
+            class(3, TypeAttributes::empty(), 0),
+            class(0, TypeAttributes::empty(), 0),
+            class(0, TypeAttributes::ABSTRACT | TypeAttributes::SEALED, 0),
+            class(0, TypeAttributes::SEALED, 1),
+            class(0, TypeAttributes::SEALED, 1 | 2),
+            class(u32::MAX, TypeAttributes::INTERFACE | TypeAttributes::ABSTRACT, 0),
+            class(4, TypeAttributes::empty(), 0),
+        ];
+        metadata.runtime_metadata.metadata_registration.types = (0..type_defs.len() as u32)
+            .map(|i| test_util::ty(Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(i))))
+            .collect();
+        metadata.global_metadata.type_definitions = type_defs.into();
+
+        let gm = &metadata.global_metadata;
+        let kinds: Vec<_> = gm.type_definitions.as_vec()[5..].iter().map(|ty_def| ty_def.kind(&metadata)).collect();
+        assert_eq!(
+            kinds,
+            [
+                TypeKind::Delegate,
+                TypeKind::Attribute,
+                TypeKind::Class,
+                TypeKind::StaticClass,
+                TypeKind::Struct,
+                TypeKind::Enum,
+                TypeKind::Interface,
+                TypeKind::Class,
+            ]
+        );
+        let invoke = gm.type_definitions.as_vec()[5].delegate_invoke(&metadata);
+        assert!(invoke.is_some_and(|method| method.name(&metadata) == "Invoke"));
+        assert!(gm.type_definitions.as_vec()[7].delegate_invoke(&metadata).is_none());
+    }
 }
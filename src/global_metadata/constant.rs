@@ -0,0 +1,221 @@
+//! Decoding of field and parameter default values.
+//!
+//! Default values are stored in the `field_and_parameter_default_value_data`
+//! table in the encoding of `vm/BlobReader.cpp`. `I4` and `U4` values and the
+//! length of strings use the variable length encoding of ECMA-335 II.23.2,
+//! everything else is stored as little endian bytes.
+
+use super::{FieldAndParameterDefaultValueIndex, Il2CppFieldDefaultValue, Il2CppParameterDefaultValue, TypeIndex};
+use crate::runtime_metadata::{Il2CppTypeEnum, TypeData};
+use crate::Metadata;
+
+/// A constant value of a field or parameter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConstantValue<'md> {
+    Boolean(bool),
+    Char(u16),
+    I1(i8),
+    U1(u8),
+    I2(i16),
+    U2(u16),
+    I4(i32),
+    U4(u32),
+    I8(i64),
+    U8(u64),
+    R4(f32),
+    R8(f64),
+    String(&'md str),
+    /// A `null` reference or default struct value.
+    Null,
+}
+
+impl Il2CppFieldDefaultValue {
+    /// Decodes the default value. Returns `None` if the data is malformed or
+    /// the type is not supported.
+    pub fn value<'md>(&self, metadata: &'md Metadata) -> Option<ConstantValue<'md>> {
+        read_constant(metadata, self.type_index, self.data_index)
+    }
+}
+
+impl Il2CppParameterDefaultValue {
+    /// Decodes the default value. Returns `None` if the data is malformed or
+    /// the type is not supported.
+    pub fn value<'md>(&self, metadata: &'md Metadata) -> Option<ConstantValue<'md>> {
+        read_constant(metadata, self.type_index, self.data_index)
+    }
+}
+
+fn read_constant<'md>(
+    metadata: &'md Metadata,
+    type_index: TypeIndex,
+    data_index: FieldAndParameterDefaultValueIndex,
+) -> Option<ConstantValue<'md>> {
+    let types = &metadata.runtime_metadata.metadata_registration.types;
+    let mut ty = types.get(type_index as usize)?;
+
+    // Enums are stored as their underlying type.
+    if let (Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(idx)) = (ty.ty, ty.data) {
+        let ty_def = metadata.global_metadata.type_definitions.get(idx)?;
+        if ty_def.layout_info().is_enum {
+            ty = types.get(ty_def.element_type_index as usize)?;
+        }
+    }
+
+    if !data_index.is_valid() {
+        return Some(ConstantValue::Null);
+    }
+    let data = metadata
+        .global_metadata
+        .field_and_parameter_default_value_data
+        .as_vec()
+        .get(data_index.index() as usize..)?;
+    read_value(&mut Reader(data), ty.ty)
+}
+
+/// Reads a value of the primitive type `ty`.
+pub(super) fn read_value<'md>(reader: &mut Reader<'md>, ty: Il2CppTypeEnum) -> Option<ConstantValue<'md>> {
+    Some(match ty {
+        Il2CppTypeEnum::Boolean => ConstantValue::Boolean(reader.bytes::<1>()?[0] != 0),
+        Il2CppTypeEnum::I1 => ConstantValue::I1(i8::from_le_bytes(reader.bytes()?)),
+        Il2CppTypeEnum::U1 => ConstantValue::U1(u8::from_le_bytes(reader.bytes()?)),
+        Il2CppTypeEnum::Char => ConstantValue::Char(u16::from_le_bytes(reader.bytes()?)),
+        Il2CppTypeEnum::I2 => ConstantValue::I2(i16::from_le_bytes(reader.bytes()?)),
+        Il2CppTypeEnum::U2 => ConstantValue::U2(u16::from_le_bytes(reader.bytes()?)),
+        Il2CppTypeEnum::I4 => ConstantValue::I4(reader.compressed_i32()?),
+        Il2CppTypeEnum::U4 => ConstantValue::U4(reader.compressed_u32()?),
+        Il2CppTypeEnum::I8 => ConstantValue::I8(i64::from_le_bytes(reader.bytes()?)),
+        Il2CppTypeEnum::U8 => ConstantValue::U8(u64::from_le_bytes(reader.bytes()?)),
+        Il2CppTypeEnum::R4 => ConstantValue::R4(f32::from_le_bytes(reader.bytes()?)),
+        Il2CppTypeEnum::R8 => ConstantValue::R8(f64::from_le_bytes(reader.bytes()?)),
+        Il2CppTypeEnum::String => match reader.compressed_i32()? {
+            -1 => ConstantValue::Null,
+            len => ConstantValue::String(std::str::from_utf8(reader.slice(usize::try_from(len).ok()?)?).ok()?),
+        },
+        Il2CppTypeEnum::Class | Il2CppTypeEnum::Object | Il2CppTypeEnum::Genericinst => ConstantValue::Null,
+        _ => return None,
+    })
+}
+
+pub(super) struct Reader<'a>(pub(super) &'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(super) fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.0.get(..N)?.try_into().ok()?;
+        self.0 = &self.0[N..];
+        Some(bytes)
+    }
+
+    pub(super) fn slice(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.0.get(..len)?;
+        self.0 = &self.0[len..];
+        Some(slice)
+    }
+
+    pub(super) fn u8(&mut self) -> Option<u32> {
+        Some(self.bytes::<1>()?[0] as u32)
+    }
+
+    pub(super) fn compressed_u32(&mut self) -> Option<u32> {
+        let first = self.u8()?;
+        Some(match first {
+            _ if first & 0x80 == 0 => first,
+            _ if first & 0xC0 == 0x80 => ((first & !0x80) << 8) | self.u8()?,
+            _ if first & 0xE0 == 0xC0 => {
+                ((first & !0xC0) << 24) | (self.u8()? << 16) | (self.u8()? << 8) | self.u8()?
+            }
+            0xF0 => u32::from_le_bytes(self.bytes()?),
+            0xFE => u32::MAX - 1,
+            0xFF => u32::MAX,
+            _ => return None,
+        })
+    }
+
+    pub(super) fn compressed_i32(&mut self) -> Option<i32> {
+        let encoded = self.compressed_u32()?;
+        if encoded == u32::MAX {
+            return Some(i32::MIN);
+        }
+        let value = (encoded >> 1) as i32;
+        Some(match encoded & 1 {
+            0 => value,
+            _ => -(value + 1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{FieldIndex, ParameterIndex, TypeDefinitionIndex};
+    use crate::runtime_metadata::TypeData;
+    use crate::test_util::{self, write_compressed_i32, write_compressed_u32};
+
+    #[test]
+    fn compressed_u32_round_trip() {
+        for value in [0, 0x7F, 0x80, 0x3FFF, 0x4000, 0x1FFF_FFFF, 0x2000_0000, u32::MAX - 2] {
+            let mut data = Vec::new();
+            write_compressed_u32(&mut data, value);
+            let mut reader = Reader(&data);
+            assert_eq!(reader.compressed_u32(), Some(value), "{value:#x}");
+            assert!(reader.0.is_empty());
+        }
+        assert_eq!(Reader(&[0xFE]).compressed_u32(), Some(u32::MAX - 1));
+        assert_eq!(Reader(&[0xFF]).compressed_u32(), Some(u32::MAX));
+        assert_eq!(Reader(&[0xE0]).compressed_u32(), None);
+        assert_eq!(Reader(&[0xC0, 0]).compressed_u32(), None);
+    }
+
+    #[test]
+    fn compressed_i32_round_trip() {
+        for value in [0, 1, -1, 63, -64, 64, -65, 0x0FFF_FFFF, -0x1000_0000, i32::MAX, i32::MIN] {
+            let mut data = Vec::new();
+            write_compressed_i32(&mut data, value);
+            assert_eq!(Reader(&data).compressed_i32(), Some(value), "{value}");
+        }
+    }
+
+    #[test]
+    fn strings() {
+        let mut data = Vec::new();
+        write_compressed_i32(&mut data, 5);
+        data.extend_from_slice(b"Hello");
+        write_compressed_i32(&mut data, -1);
+        let mut reader = Reader(&data);
+        assert_eq!(read_value(&mut reader, Il2CppTypeEnum::String), Some(ConstantValue::String("Hello")));
+        assert_eq!(read_value(&mut reader, Il2CppTypeEnum::String), Some(ConstantValue::Null));
+        assert_eq!(read_value(&mut reader, Il2CppTypeEnum::String), None);
+    }
+
+    #[test]
+    fn default_values_by_member() {
+        let mut metadata = test_util::metadata("");
+        metadata.runtime_metadata.metadata_registration.types =
+            vec![test_util::ty(Il2CppTypeEnum::I4, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0)))];
+        let mut data = Vec::new();
+        write_compressed_i32(&mut data, 7);
+        write_compressed_i32(&mut data, -9);
+        let gm = &mut metadata.global_metadata;
+        gm.field_and_parameter_default_value_data = data.into();
+        let field = |field: u32, data: u32| Il2CppFieldDefaultValue {
+            field_index: FieldIndex::new(field),
+            type_index: 0,
+            data_index: FieldAndParameterDefaultValueIndex::new(data),
+        };
+        gm.field_default_values = vec![field(3, 0), field(3, 1), field(4, 1)].into();
+        gm.parameter_default_values = vec![Il2CppParameterDefaultValue {
+            parameter_index: ParameterIndex::new(2),
+            type_index: 0,
+            data_index: FieldAndParameterDefaultValueIndex::new(1),
+        }]
+        .into();
+
+        let gm = &metadata.global_metadata;
+        let field_value = |idx: u32| gm.field_default_values.get_by_field(FieldIndex::new(idx)).and_then(|value| value.value(&metadata));
+        assert_eq!(field_value(3), Some(ConstantValue::I4(7)));
+        assert_eq!(field_value(4), Some(ConstantValue::I4(-9)));
+        assert_eq!(field_value(5), None);
+        let param = gm.parameter_default_values.get_by_parameter(ParameterIndex::new(2));
+        assert_eq!(param.and_then(|value| value.value(&metadata)), Some(ConstantValue::I4(-9)));
+        assert!(gm.parameter_default_values.get_by_parameter(ParameterIndex::new(3)).is_none());
+    }
+}
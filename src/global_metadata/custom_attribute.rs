@@ -0,0 +1,369 @@
+//! Decoding of custom attribute data.
+//!
+//! Since metadata version 29, the custom attributes of every item with
+//! attributes are stored in the `attribute_data` table, in the encoding of
+//! `vm/CustomAttributeDataReader.cpp`. Each
+//! [`Il2CppCustomAttributeDataRange`] points at the data of one item: a count,
+//! the constructor of each attribute, and then the arguments of each
+//! attribute in turn.
+
+use super::constant::{read_value, ConstantValue, Reader};
+use super::{
+    AttributeDataRangeIndex, FieldIndex, Il2CppCustomAttributeDataRange, Il2CppImageDefinition, MethodIndex,
+    PropertyIndex, Token, TypeDefinitionIndex,
+};
+use crate::runtime_metadata::Il2CppTypeEnum;
+use crate::Metadata;
+
+/// The type of a value of `System.Type` in attribute data.
+const IL2CPP_TYPE_INDEX: u8 = 0xFF;
+const IL2CPP_TYPE_ENUM: u8 = 0x55;
+
+/// Arrays nested deeper than this are treated as malformed.
+const MAX_ARRAY_DEPTH: usize = 8;
+
+/// The type of an attribute argument, as encoded in the attribute data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttributeArgumentType {
+    /// A primitive type, `string` or `object`.
+    Primitive(Il2CppTypeEnum),
+    /// An enum. Index into the
+    /// [`Il2CppMetadataRegistration::types`](crate::runtime_metadata::Il2CppMetadataRegistration::types)
+    /// field.
+    Enum(usize),
+    /// `System.Type`
+    Type,
+    /// A single-dimensional array.
+    Array(Box<AttributeArgumentType>),
+}
+
+/// The value of an attribute argument.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttributeValue<'md> {
+    /// A primitive or string value. The values of enums are stored as their
+    /// underlying type.
+    Constant(ConstantValue<'md>),
+    /// A `System.Type`, or `None` for `null`. Index into the
+    /// [`Il2CppMetadataRegistration::types`](crate::runtime_metadata::Il2CppMetadataRegistration::types)
+    /// field.
+    Type(Option<usize>),
+    /// An array, or `None` for `null`.
+    Array(Option<Vec<AttributeArgument<'md>>>),
+}
+
+/// A typed argument of a custom attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttributeArgument<'md> {
+    pub ty: AttributeArgumentType,
+    pub value: AttributeValue<'md>,
+}
+
+/// A custom attribute applied to an item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomAttribute<'md> {
+    /// The constructor of the attribute type.
+    pub constructor: MethodIndex,
+    /// The arguments passed to the constructor.
+    pub arguments: Vec<AttributeArgument<'md>>,
+    /// Fields set by the attribute.
+    pub fields: Vec<(FieldIndex, AttributeArgument<'md>)>,
+    /// Properties set by the attribute.
+    pub properties: Vec<(PropertyIndex, AttributeArgument<'md>)>,
+}
+
+impl Il2CppImageDefinition {
+    /// Finds the index of the attribute data of the item with `token` in this
+    /// image.
+    pub fn custom_attribute_range(&self, metadata: &Metadata, token: Token) -> Option<AttributeDataRangeIndex> {
+        let start = self.custom_attribute_start.index();
+        let ranges = metadata.global_metadata.attribute_data_range.as_vec();
+        let image_ranges = ranges.get(start as usize..)?.get(..self.custom_attribute_count as usize)?;
+        // Ranges are sorted by token.
+        let i = image_ranges.binary_search_by_key(&token.0, |range| range.token.0).ok()?;
+        Some(AttributeDataRangeIndex::new(start + i as u32))
+    }
+
+    /// Decodes the custom attributes of the item with `token` in this image.
+    /// Returns an empty list if the item has no attributes.
+    pub fn custom_attributes_of<'md>(&self, metadata: &'md Metadata, token: Token) -> Option<Vec<CustomAttribute<'md>>> {
+        match self.custom_attribute_range(metadata, token) {
+            Some(range) => Il2CppCustomAttributeDataRange::decode(metadata, range),
+            None => Some(Vec::new()),
+        }
+    }
+}
+
+impl Il2CppCustomAttributeDataRange {
+    /// Decodes the custom attributes in the range at `index`. Returns `None`
+    /// if the data is malformed.
+    pub fn decode<'md>(metadata: &'md Metadata, index: AttributeDataRangeIndex) -> Option<Vec<CustomAttribute<'md>>> {
+        let gm = &metadata.global_metadata;
+        let start = gm.attribute_data_range.get(index)?.start_offset as usize;
+        let next = AttributeDataRangeIndex::new(index.index() + 1);
+        let data = gm.attribute_data.as_vec();
+        let end = gm.attribute_data_range.get(next).map_or(data.len(), |next| next.start_offset as usize);
+        let mut reader = Reader(data.get(start..end)?);
+
+        let count = reader.compressed_u32()? as usize;
+        let mut constructors = Vec::with_capacity(count.min(reader.0.len() / 4));
+        for _ in 0..count {
+            constructors.push(MethodIndex::new(u32::from_le_bytes(reader.bytes()?)));
+        }
+
+        let mut attributes = Vec::with_capacity(count);
+        for constructor in constructors {
+            let declaring_type = gm.methods.get(constructor)?.declaring_type;
+            let argument_count = reader.compressed_u32()?;
+            let field_count = reader.compressed_u32()?;
+            let property_count = reader.compressed_u32()?;
+
+            let arguments = (0..argument_count)
+                .map(|_| read_argument(metadata, &mut reader, 0))
+                .collect::<Option<_>>()?;
+            let mut fields = Vec::new();
+            for _ in 0..field_count {
+                let value = read_argument(metadata, &mut reader, 0)?;
+                let (ty, i) = read_member(&mut reader, declaring_type)?;
+                let ty = gm.type_definitions.get(ty)?;
+                fields.push((FieldIndex::new(ty.field_start.index().checked_add(i)?), value));
+            }
+            let mut properties = Vec::new();
+            for _ in 0..property_count {
+                let value = read_argument(metadata, &mut reader, 0)?;
+                let (ty, i) = read_member(&mut reader, declaring_type)?;
+                let ty = gm.type_definitions.get(ty)?;
+                properties.push((PropertyIndex::new(ty.property_start.index().checked_add(i)?), value));
+            }
+
+            attributes.push(CustomAttribute {
+                constructor,
+                arguments,
+                fields,
+                properties,
+            });
+        }
+        Some(attributes)
+    }
+}
+
+/// Reads the type of a value. Returns the type and the primitive type its
+/// value is stored as.
+fn read_type(metadata: &Metadata, reader: &mut Reader) -> Option<(AttributeArgumentType, u8)> {
+    let ty = reader.bytes::<1>()?[0];
+    if ty != IL2CPP_TYPE_ENUM {
+        return Some((AttributeArgumentType::Primitive(Il2CppTypeEnum::from_ty(ty)?), ty));
+    }
+
+    let types = &metadata.runtime_metadata.metadata_registration.types;
+    let enum_type = usize::try_from(reader.compressed_i32()?).ok()?;
+    let ty_def = types.get(enum_type)?.type_definition_index(metadata)?;
+    let ty_def = metadata.global_metadata.type_definitions.get(ty_def)?;
+    let underlying = types.get(ty_def.element_type_index as usize)?;
+    Some((AttributeArgumentType::Enum(enum_type), underlying.ty.to_ty()))
+}
+
+fn read_argument<'md>(metadata: &'md Metadata, reader: &mut Reader<'md>, depth: usize) -> Option<AttributeArgument<'md>> {
+    if reader.0.first() == Some(&IL2CPP_TYPE_INDEX) {
+        reader.bytes::<1>()?;
+        let value = match reader.compressed_i32()? {
+            -1 => None,
+            idx => Some(usize::try_from(idx).ok()?),
+        };
+        return Some(AttributeArgument {
+            ty: AttributeArgumentType::Type,
+            value: AttributeValue::Type(value),
+        });
+    }
+
+    let (ty, stored_as) = read_type(metadata, reader)?;
+    if stored_as != Il2CppTypeEnum::Szarray.to_ty() {
+        let value = read_value(reader, Il2CppTypeEnum::from_ty(stored_as)?)?;
+        return Some(AttributeArgument {
+            ty,
+            value: AttributeValue::Constant(value),
+        });
+    }
+
+    if depth > MAX_ARRAY_DEPTH {
+        return None;
+    }
+    let len = reader.compressed_i32()?;
+    if len == -1 {
+        return Some(AttributeArgument {
+            ty: AttributeArgumentType::Array(Box::new(AttributeArgumentType::Primitive(Il2CppTypeEnum::Object))),
+            value: AttributeValue::Array(None),
+        });
+    }
+    let len = usize::try_from(len).ok()?;
+
+    // The element type is followed by a flag telling whether every element
+    // has its own type, which is the case for arrays of `object`.
+    let element_type = match reader.0.first() {
+        Some(&IL2CPP_TYPE_INDEX) => {
+            reader.bytes::<1>()?;
+            AttributeArgumentType::Type
+        }
+        _ => read_type(metadata, reader)?.0,
+    };
+    let elements_are_different = reader.bytes::<1>()?[0] == 1;
+    let mut elements = Vec::with_capacity(len.min(reader.0.len()));
+    for _ in 0..len {
+        let element = match elements_are_different {
+            true => read_argument(metadata, reader, depth + 1)?,
+            false => read_element(metadata, reader, &element_type)?,
+        };
+        elements.push(element);
+    }
+    Some(AttributeArgument {
+        ty: AttributeArgumentType::Array(Box::new(element_type)),
+        value: AttributeValue::Array(Some(elements)),
+    })
+}
+
+/// Reads an array element whose type was given by the array.
+fn read_element<'md>(
+    metadata: &'md Metadata,
+    reader: &mut Reader<'md>,
+    ty: &AttributeArgumentType,
+) -> Option<AttributeArgument<'md>> {
+    let value = match ty {
+        AttributeArgumentType::Type => AttributeValue::Type(match reader.compressed_i32()? {
+            -1 => None,
+            idx => Some(usize::try_from(idx).ok()?),
+        }),
+        AttributeArgumentType::Primitive(Il2CppTypeEnum::Szarray) | AttributeArgumentType::Array(_) => {
+            // Nested arrays are not valid attribute arguments.
+            return None;
+        }
+        AttributeArgumentType::Primitive(primitive) => AttributeValue::Constant(read_value(reader, *primitive)?),
+        AttributeArgumentType::Enum(enum_type) => {
+            let types = &metadata.runtime_metadata.metadata_registration.types;
+            let ty_def = types.get(*enum_type)?.type_definition_index(metadata)?;
+            let ty_def = metadata.global_metadata.type_definitions.get(ty_def)?;
+            let underlying = types.get(ty_def.element_type_index as usize)?;
+            AttributeValue::Constant(read_value(reader, underlying.ty)?)
+        }
+    };
+    Some(AttributeArgument { ty: ty.clone(), value })
+}
+
+/// Reads the member a named argument sets, which is either a member of the
+/// attribute type or of one of its base types.
+fn read_member(reader: &mut Reader, attribute_type: TypeDefinitionIndex) -> Option<(TypeDefinitionIndex, u32)> {
+    let index = reader.compressed_i32()?;
+    if index >= 0 {
+        return Some((attribute_type, index as u32));
+    }
+    let declaring_type = TypeDefinitionIndex::new(reader.compressed_u32()?);
+    Some((declaring_type, (-(index + 1)) as u32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::Il2CppMethodDefinition;
+    use crate::runtime_metadata::TypeData;
+    use crate::test_util::{self, write_compressed_i32, write_compressed_u32};
+
+    fn constant(ty: Il2CppTypeEnum, value: ConstantValue) -> AttributeArgument {
+        AttributeArgument {
+            ty: AttributeArgumentType::Primitive(ty),
+            value: AttributeValue::Constant(value),
+        }
+    }
+
+    #[test]
+    fn decode_arguments() {
+        let mut metadata = test_util::metadata("");
+        // An attribute class and an enum with `int` as its underlying type
+        let mut enum_def = test_util::type_definition();
+        enum_def.element_type_index = 0;
+        enum_def.bitfield |= 0b11;
+        let mut attribute_def = test_util::type_definition();
+        attribute_def.field_start = FieldIndex::new(10);
+        attribute_def.property_start = PropertyIndex::new(20);
+        let mut constructor: Il2CppMethodDefinition = test_util::zeroed();
+        constructor.declaring_type = TypeDefinitionIndex::new(1);
+        metadata.global_metadata.type_definitions = vec![enum_def, attribute_def].into();
+        metadata.global_metadata.methods = vec![test_util::zeroed(), constructor].into();
+        metadata.runtime_metadata.metadata_registration.types = vec![
+            test_util::ty(Il2CppTypeEnum::I4, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(u32::MAX))),
+            test_util::ty(Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0))),
+        ];
+
+        let mut data = Vec::new();
+        write_compressed_u32(&mut data, 1);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        // Three arguments, a field and a property
+        write_compressed_u32(&mut data, 3);
+        write_compressed_u32(&mut data, 1);
+        write_compressed_u32(&mut data, 1);
+        // An enum value
+        data.push(IL2CPP_TYPE_ENUM);
+        write_compressed_i32(&mut data, 1);
+        write_compressed_i32(&mut data, -2);
+        // typeof(int)
+        data.push(IL2CPP_TYPE_INDEX);
+        write_compressed_i32(&mut data, 0);
+        // new int[] { 5, -1 }
+        data.push(Il2CppTypeEnum::Szarray.to_ty());
+        write_compressed_i32(&mut data, 2);
+        data.push(Il2CppTypeEnum::I4.to_ty());
+        data.push(0);
+        write_compressed_i32(&mut data, 5);
+        write_compressed_i32(&mut data, -1);
+        // A field of the attribute class set to `true`
+        data.push(Il2CppTypeEnum::Boolean.to_ty());
+        data.push(1);
+        write_compressed_i32(&mut data, 2);
+        // A property of a base class set to a string
+        data.push(Il2CppTypeEnum::String.to_ty());
+        write_compressed_i32(&mut data, 2);
+        data.extend_from_slice(b"hi");
+        write_compressed_i32(&mut data, -1);
+        write_compressed_u32(&mut data, 0);
+
+        let mut range: Il2CppCustomAttributeDataRange = test_util::zeroed();
+        range.token = Token(0x0200_0001);
+        metadata.global_metadata.attribute_data = data.into();
+        metadata.global_metadata.attribute_data_range = vec![range].into();
+
+        let attributes = Il2CppCustomAttributeDataRange::decode(&metadata, AttributeDataRangeIndex::new(0));
+        let expected = CustomAttribute {
+            constructor: MethodIndex::new(1),
+            arguments: vec![
+                AttributeArgument {
+                    ty: AttributeArgumentType::Enum(1),
+                    value: AttributeValue::Constant(ConstantValue::I4(-2)),
+                },
+                AttributeArgument {
+                    ty: AttributeArgumentType::Type,
+                    value: AttributeValue::Type(Some(0)),
+                },
+                AttributeArgument {
+                    ty: AttributeArgumentType::Array(Box::new(AttributeArgumentType::Primitive(Il2CppTypeEnum::I4))),
+                    value: AttributeValue::Array(Some(vec![
+                        constant(Il2CppTypeEnum::I4, ConstantValue::I4(5)),
+                        constant(Il2CppTypeEnum::I4, ConstantValue::I4(-1)),
+                    ])),
+                },
+            ],
+            fields: vec![(FieldIndex::new(12), constant(Il2CppTypeEnum::Boolean, ConstantValue::Boolean(true)))],
+            properties: vec![(PropertyIndex::new(0), constant(Il2CppTypeEnum::String, ConstantValue::String("hi")))],
+        };
+        assert_eq!(attributes, Some(vec![expected]));
+    }
+
+    #[test]
+    fn truncated_data() {
+        let mut metadata = test_util::metadata("");
+        let mut constructor: Il2CppMethodDefinition = test_util::zeroed();
+        constructor.declaring_type = TypeDefinitionIndex::new(0);
+        metadata.global_metadata.type_definitions = vec![test_util::type_definition()].into();
+        metadata.global_metadata.methods = vec![constructor].into();
+        let data = vec![1, 0, 0, 0, 0, 1, 0, 0, Il2CppTypeEnum::I8.to_ty(), 1, 2];
+        metadata.global_metadata.attribute_data = data.into();
+        metadata.global_metadata.attribute_data_range = vec![test_util::zeroed()].into();
+        assert_eq!(Il2CppCustomAttributeDataRange::decode(&metadata, AttributeDataRangeIndex::new(0)), None);
+    }
+}
@@ -0,0 +1,349 @@
+//! Decryption and deobfuscation hooks for `global-metadata.dat`.
+//!
+//! Many commercial games ship an obfuscated global metadata file, either by
+//! encrypting its contents or by scrambling the order of the fields in the
+//! header. A [`MetadataTransform`] undoes this in-place before the metadata is
+//! parsed. See [`Metadata::parse_with_transform()`] and
+//! [`deserialize_with_transform()`].
+//!
+//! When a file fails to parse, [`diagnose()`] can be used to find out whether
+//! it looks encrypted, has a scrambled header, or is simply for a different
+//! IL2CPP version.
+//!
+//! [`Metadata::parse_with_transform()`]: crate::Metadata::parse_with_transform
+//! [`deserialize_with_transform()`]: super::deserialize_with_transform
+
+use super::{Il2CppGlobalMetadataHeader, MetadataDeserializeError, HEADER_TABLES, SANITY, VERSION};
+use binde::{BinaryDeserialize, LittleEndian};
+use std::io::Cursor;
+
+/// The size in bytes of a single table entry in the header.
+const HEADER_ENTRY_SIZE: usize = 8;
+
+/// The size in bytes of the sanity and version fields of the header.
+const HEADER_PREFIX_SIZE: usize = 8;
+
+/// Shannon entropy (in bits per byte) above which data is considered to be
+/// encrypted or compressed.
+const ENCRYPTED_ENTROPY: f64 = 7.5;
+
+/// The amount of data sampled when estimating entropy.
+const ENTROPY_SAMPLE_SIZE: usize = 0x10000;
+
+/// The size of the global metadata header in bytes.
+pub const HEADER_SIZE: usize = HEADER_PREFIX_SIZE + HEADER_TABLES.len() * HEADER_ENTRY_SIZE;
+
+/// Returns the names of the tables described by the header, in the order that
+/// IL2CPP stores them.
+///
+/// These are the names passed to [`MetadataTransform::transform_table()`] and
+/// match the field names of [`GlobalMetadata`](super::GlobalMetadata).
+pub fn table_names() -> &'static [&'static str] {
+    HEADER_TABLES
+}
+
+/// A transformation that is applied to the raw bytes of a global metadata file
+/// before it is parsed.
+///
+/// Both methods operate in-place and default to doing nothing, so an
+/// implementation only needs to override the parts of the file it cares
+/// about.
+pub trait MetadataTransform {
+    /// Transforms the raw header bytes, including the sanity and version
+    /// fields.
+    ///
+    /// `header` is always exactly [`HEADER_SIZE`] bytes long.
+    fn transform_header(&self, header: &mut [u8]) {
+        let _ = header;
+    }
+
+    /// Transforms the contents of a single table.
+    ///
+    /// This is called once for every non-empty table after the header has
+    /// been transformed. `offset` is the position of `data` within the file.
+    fn transform_table(&self, table: &str, offset: u32, data: &mut [u8]) {
+        let _ = (table, offset, data);
+    }
+}
+
+impl<T: MetadataTransform + ?Sized> MetadataTransform for &T {
+    fn transform_header(&self, header: &mut [u8]) {
+        (**self).transform_header(header)
+    }
+
+    fn transform_table(&self, table: &str, offset: u32, data: &mut [u8]) {
+        (**self).transform_table(table, offset, data)
+    }
+}
+
+/// Applies the first transform followed by the second.
+impl<A: MetadataTransform, B: MetadataTransform> MetadataTransform for (A, B) {
+    fn transform_header(&self, header: &mut [u8]) {
+        self.0.transform_header(header);
+        self.1.transform_header(header);
+    }
+
+    fn transform_table(&self, table: &str, offset: u32, data: &mut [u8]) {
+        self.0.transform_table(table, offset, data);
+        self.1.transform_table(table, offset, data);
+    }
+}
+
+/// A repeating-key XOR cipher keyed on the absolute file offset.
+///
+/// The byte at file offset `n` is XORed with `key[n % key.len()]`. An empty
+/// key leaves the data unchanged.
+#[derive(Debug, Clone)]
+pub struct XorTransform {
+    pub key: Vec<u8>,
+}
+
+impl XorTransform {
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self { key: key.into() }
+    }
+
+    fn apply(&self, offset: usize, data: &mut [u8]) {
+        if self.key.is_empty() {
+            return;
+        }
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte ^= self.key[(offset + i) % self.key.len()];
+        }
+    }
+}
+
+impl MetadataTransform for XorTransform {
+    fn transform_header(&self, header: &mut [u8]) {
+        self.apply(0, header);
+    }
+
+    fn transform_table(&self, _table: &str, offset: u32, data: &mut [u8]) {
+        self.apply(offset as usize, data);
+    }
+}
+
+/// Undoes a reordering of the table entries in the header.
+///
+/// `order[i]` is the position at which the `i`th table (as listed by
+/// [`table_names()`]) is stored in the scrambled header. The sanity and
+/// version fields are left in place.
+#[derive(Debug, Clone)]
+pub struct HeaderFieldOrder {
+    pub order: Vec<usize>,
+}
+
+impl HeaderFieldOrder {
+    /// Creates a new remapping, returning `None` if `order` is not a
+    /// permutation of the header's table positions.
+    pub fn new(order: Vec<usize>) -> Option<Self> {
+        let mut seen = vec![false; HEADER_TABLES.len()];
+        if order.len() != seen.len() {
+            return None;
+        }
+        for &pos in &order {
+            if pos >= seen.len() || seen[pos] {
+                return None;
+            }
+            seen[pos] = true;
+        }
+        Some(Self { order })
+    }
+}
+
+impl MetadataTransform for HeaderFieldOrder {
+    fn transform_header(&self, header: &mut [u8]) {
+        let scrambled = header[HEADER_PREFIX_SIZE..].to_vec();
+        for (i, &pos) in self.order.iter().enumerate() {
+            let dst = HEADER_PREFIX_SIZE + i * HEADER_ENTRY_SIZE;
+            let src = pos * HEADER_ENTRY_SIZE;
+            header[dst..dst + HEADER_ENTRY_SIZE]
+                .copy_from_slice(&scrambled[src..src + HEADER_ENTRY_SIZE]);
+        }
+    }
+}
+
+/// Applies `transform` to the metadata file in-place.
+///
+/// The header is transformed first and then parsed to find the table ranges
+/// that are passed to [`MetadataTransform::transform_table()`].
+pub fn apply<T: MetadataTransform>(data: &mut [u8], transform: &T) -> Result<(), MetadataDeserializeError> {
+    if data.len() < HEADER_SIZE {
+        return Err(MetadataDeserializeError::Truncated(data.len()));
+    }
+    transform.transform_header(&mut data[..HEADER_SIZE]);
+
+    let header = Il2CppGlobalMetadataHeader::deserialize::<LittleEndian, _>(Cursor::new(&*data))?;
+    for (name, range) in HEADER_TABLES.iter().zip(header.table_ranges()) {
+        if range.len == 0 {
+            continue;
+        }
+        let start = range.offset as usize;
+        let end = start.checked_add(range.len as usize);
+        let Some(table) = end.and_then(|end| data.get_mut(start..end)) else {
+            return Err(MetadataDeserializeError::ScrambledHeader);
+        };
+        transform.transform_table(name, range.offset, table);
+    }
+    Ok(())
+}
+
+/// The result of inspecting a global metadata file with [`diagnose()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagnosis {
+    /// The file has a valid header for the supported IL2CPP version.
+    Valid,
+    /// The file is too small to contain a header.
+    Truncated,
+    /// The file is a plain metadata file for a different IL2CPP version.
+    WrongVersion(u32),
+    /// The header has the right sanity and version values, but the table
+    /// ranges do not fit in the file. This usually means the header fields
+    /// have been reordered.
+    ScrambledHeader,
+    /// The sanity value is missing and the contents have the byte
+    /// distribution of encrypted or compressed data.
+    LooksEncrypted,
+    /// The sanity value is missing, but the contents do not look encrypted.
+    NotMetadata,
+}
+
+/// Inspects raw global metadata without parsing any tables.
+pub fn diagnose(data: &[u8]) -> Diagnosis {
+    if data.len() < HEADER_SIZE {
+        return Diagnosis::Truncated;
+    }
+    let Ok(header) = Il2CppGlobalMetadataHeader::deserialize::<LittleEndian, _>(Cursor::new(data)) else {
+        return Diagnosis::Truncated;
+    };
+
+    if header.sanity != SANITY {
+        let sample = &data[..data.len().min(ENTROPY_SAMPLE_SIZE)];
+        return match entropy(sample) > ENCRYPTED_ENTROPY {
+            true => Diagnosis::LooksEncrypted,
+            false => Diagnosis::NotMetadata,
+        };
+    }
+
+    if header.version != VERSION {
+        return Diagnosis::WrongVersion(header.version);
+    }
+
+    let in_bounds = header
+        .table_ranges()
+        .all(|range| range.offset as u64 + range.len as u64 <= data.len() as u64);
+    match in_bounds {
+        true => Diagnosis::Valid,
+        false => Diagnosis::ScrambledHeader,
+    }
+}
+
+/// Shannon entropy of `data` in bits per byte.
+fn entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &byte in data {
+        counts[byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A plain metadata file whose `string` table holds `strings`.
+    fn file(strings: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; HEADER_SIZE];
+        data[0..4].copy_from_slice(&SANITY.to_le_bytes());
+        data[4..8].copy_from_slice(&VERSION.to_le_bytes());
+        set_table(&mut data, "string", HEADER_SIZE as u32, strings.len() as u32);
+        data.extend_from_slice(strings);
+        data
+    }
+
+    fn set_table(data: &mut [u8], name: &str, offset: u32, len: u32) {
+        let pos = HEADER_TABLES.iter().position(|&table| table == name).unwrap();
+        let entry = HEADER_PREFIX_SIZE + pos * HEADER_ENTRY_SIZE;
+        data[entry..entry + 4].copy_from_slice(&offset.to_le_bytes());
+        data[entry + 4..entry + 8].copy_from_slice(&len.to_le_bytes());
+    }
+
+    #[test]
+    fn header_field_order() {
+        let count = HEADER_TABLES.len();
+        assert!(HeaderFieldOrder::new((0..count - 1).collect()).is_none());
+        assert!(HeaderFieldOrder::new((1..=count).collect()).is_none());
+        assert!(HeaderFieldOrder::new(vec![0; count]).is_none());
+
+        let order: Vec<_> = (0..count).rev().collect();
+        let mut header = vec![0; HEADER_SIZE];
+        header[0..8].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        for (i, &pos) in order.iter().enumerate() {
+            let entry = HEADER_PREFIX_SIZE + pos * HEADER_ENTRY_SIZE;
+            header[entry..entry + 4].copy_from_slice(&(i as u32).to_le_bytes());
+        }
+        HeaderFieldOrder::new(order).unwrap().transform_header(&mut header);
+        assert_eq!(header[0..8], [1, 2, 3, 4, 5, 6, 7, 8]);
+        for i in 0..count {
+            let entry = HEADER_PREFIX_SIZE + i * HEADER_ENTRY_SIZE;
+            assert_eq!(header[entry..entry + 4], (i as u32).to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn xor() {
+        let plain = file(b"\0Hello\0");
+        let transform = XorTransform::new([0x12, 0x34, 0x56]);
+        let mut data = plain.clone();
+        transform.apply(0, &mut data);
+        assert_eq!(diagnose(&data), Diagnosis::NotMetadata);
+        apply(&mut data, &transform).unwrap();
+        assert_eq!(data, plain);
+    }
+
+    #[test]
+    fn apply_out_of_bounds() {
+        let mut data = file(b"\0Hello\0");
+        set_table(&mut data, "string", u32::MAX, u32::MAX);
+        assert!(matches!(apply(&mut data, &XorTransform::new([])), Err(MetadataDeserializeError::ScrambledHeader)));
+        assert!(matches!(apply(&mut [0; 4], &XorTransform::new([])), Err(MetadataDeserializeError::Truncated(4))));
+    }
+
+    #[test]
+    fn diagnoses() {
+        assert_eq!(diagnose(&file(b"\0Hello\0")), Diagnosis::Valid);
+        assert_eq!(diagnose(&[0; 16]), Diagnosis::Truncated);
+
+        let mut data = file(b"\0Hello\0");
+        data[4..8].copy_from_slice(&24u32.to_le_bytes());
+        assert_eq!(diagnose(&data), Diagnosis::WrongVersion(24));
+
+        let mut data = file(b"\0Hello\0");
+        set_table(&mut data, "string", HEADER_SIZE as u32, 0x100);
+        assert_eq!(diagnose(&data), Diagnosis::ScrambledHeader);
+
+        assert_eq!(diagnose(&vec![0; HEADER_SIZE]), Diagnosis::NotMetadata);
+        let random: Vec<_> = (0..HEADER_SIZE * 4).map(|i| (i * 167 + 13) as u8).collect();
+        assert_eq!(diagnose(&random), Diagnosis::LooksEncrypted);
+    }
+
+    #[test]
+    fn entropies() {
+        assert_eq!(entropy(&[]), 0.0);
+        assert_eq!(entropy(&[7; 100]), 0.0);
+        assert_eq!(entropy(&[0, 1, 0, 1]), 1.0);
+        let all: Vec<_> = (0..=255).collect();
+        assert_eq!(entropy(&all), 8.0);
+    }
+}
@@ -0,0 +1,364 @@
+//! Navigation of the type hierarchy.
+//!
+//! Type definitions only store their direct base type and the interfaces they
+//! declare themselves. [`TypeHierarchy`] builds the reverse direction, so that
+//! questions like "which types derive from `UnityEngine.MonoBehaviour`" can be
+//! answered without scanning every type, and resolves the full set of
+//! interfaces a type implements.
+
+use crate::global_metadata::{Il2CppTypeDefinition, TypeDefinitionIndex, TypeIndex};
+use crate::runtime_metadata::{Il2CppTypeEnum, TypeData};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// A reverse index of base types and implemented interfaces.
+pub struct TypeHierarchy<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+    derived: HashMap<TypeDefinitionIndex, Vec<TypeDefinitionIndex>>,
+    implementors: HashMap<TypeDefinitionIndex, Vec<TypeDefinitionIndex>>,
+    /// Maps a generic type definition's type index and its type arguments to
+    /// the type index of the instantiation.
+    generic_instances: HashMap<(usize, Vec<usize>), usize>,
+}
+
+impl<'md, 'gmd, 'rmd> TypeHierarchy<'md, 'gmd, 'rmd> {
+    pub fn new(metadata: &'md Metadata<'gmd, 'rmd>) -> Self {
+        let type_defs = metadata.global_metadata.type_definitions.as_vec();
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+        let resolve = |idx: TypeIndex| {
+            types
+                .get(idx as usize)
+                .and_then(|ty| ty.type_definition_index(metadata))
+        };
+
+        let mut derived: HashMap<_, Vec<_>> = HashMap::new();
+        let mut implementors: HashMap<_, Vec<_>> = HashMap::new();
+        for (i, ty_def) in type_defs.iter().enumerate() {
+            let idx = TypeDefinitionIndex::new(i as u32);
+            if let Some(parent) = ty_def.base_type_definition(metadata) {
+                derived.entry(parent).or_default().push(idx);
+            }
+            for &interface in ty_def.interfaces(metadata).unwrap_or_default() {
+                if let Some(interface) = resolve(interface) {
+                    let entry = implementors.entry(interface).or_default();
+                    // A type may implement several instantiations of the same
+                    // generic interface.
+                    if entry.last() != Some(&idx) {
+                        entry.push(idx);
+                    }
+                }
+            }
+        }
+
+        let mut generic_instances = HashMap::new();
+        for i in 0..types.len() {
+            if let Some((definition, args)) = generic_args(metadata, i) {
+                generic_instances.entry((definition, args.to_vec())).or_insert(i);
+            }
+        }
+
+        Self {
+            metadata,
+            derived,
+            implementors,
+            generic_instances,
+        }
+    }
+
+    pub fn metadata(&self) -> &'md Metadata<'gmd, 'rmd> {
+        self.metadata
+    }
+
+    /// The types that directly inherit from `ty`.
+    pub fn derived_types(&self, ty: TypeDefinitionIndex) -> &[TypeDefinitionIndex] {
+        self.derived.get(&ty).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// All types that directly or indirectly inherit from `ty`.
+    pub fn all_derived_types(&self, ty: TypeDefinitionIndex) -> Vec<TypeDefinitionIndex> {
+        self.collect(ty, |idx| self.derived_types(idx).iter())
+    }
+
+    /// The types that directly declare that they implement `interface`, in
+    /// any instantiation if the interface is generic.
+    ///
+    /// This includes interfaces that extend `interface`.
+    pub fn implementors(&self, interface: TypeDefinitionIndex) -> &[TypeDefinitionIndex] {
+        self.implementors
+            .get(&interface)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// All types that implement `interface`, either directly, through an
+    /// interface that extends it, or by inheriting from a type that implements
+    /// it.
+    pub fn all_implementors(&self, interface: TypeDefinitionIndex) -> Vec<TypeDefinitionIndex> {
+        self.collect(interface, |idx| {
+            self.implementors(idx).iter().chain(self.derived_types(idx))
+        })
+    }
+
+    /// All interfaces implemented by `ty`, including interfaces declared by its
+    /// base types and interfaces extended by other interfaces.
+    ///
+    /// Interfaces inherited from generic types are instantiated with the type
+    /// arguments used by `ty`. For example, a type deriving from
+    /// `List<int>` implements `IEnumerable<int>`. If an instantiation does not
+    /// exist in the binary, the interface is listed as declared instead.
+    pub fn all_interfaces(&self, ty: TypeDefinitionIndex) -> Vec<TypeIndex> {
+        let type_defs = &self.metadata.global_metadata.type_definitions;
+        let mut interfaces = Vec::new();
+        let mut seen = HashSet::new();
+
+        let mut current = type_defs.get(ty).map(|ty_def| (ty_def, None));
+        let mut depth = 0;
+        while let Some((ty_def, args)) = current.take() {
+            for &interface in ty_def.interfaces(self.metadata).unwrap_or_default() {
+                let interface = self.substitute(interface as usize, args.as_deref(), 0);
+                self.collect_interface(interface, &mut interfaces, &mut seen);
+            }
+
+            depth += 1;
+            if ty_def.parent_index == u32::MAX || depth > type_defs.len() {
+                break;
+            }
+            let parent = self.substitute(ty_def.parent_index as usize, args.as_deref(), 0);
+            current = self.definition_of(parent);
+        }
+
+        interfaces
+    }
+
+    fn collect_interface(&self, interface: usize, interfaces: &mut Vec<TypeIndex>, seen: &mut HashSet<usize>) {
+        if !seen.insert(interface) {
+            return;
+        }
+        interfaces.push(interface as TypeIndex);
+        if let Some((ty_def, args)) = self.definition_of(interface) {
+            for &base in ty_def.interfaces(self.metadata).unwrap_or_default() {
+                let base = self.substitute(base as usize, args.as_deref(), 0);
+                self.collect_interface(base, interfaces, seen);
+            }
+        }
+    }
+
+    /// Resolves a type to its definition and, for generic instances, its type
+    /// arguments.
+    fn definition_of(&self, ty: usize) -> Option<(&'md Il2CppTypeDefinition, Option<Vec<usize>>)> {
+        let metadata = self.metadata;
+        let idx = metadata
+            .runtime_metadata
+            .metadata_registration
+            .types
+            .get(ty)?
+            .type_definition_index(metadata)?;
+        let ty_def = metadata.global_metadata.type_definitions.get(idx)?;
+        let args = generic_args(metadata, ty).map(|(_, args)| args.to_vec());
+        Some((ty_def, args))
+    }
+
+    /// Replaces the generic type parameters in `ty` with `args`, returning the
+    /// type index of the resulting instantiation if it exists.
+    fn substitute(&self, ty: usize, args: Option<&[usize]>, depth: usize) -> usize {
+        let Some(args) = args else {
+            return ty;
+        };
+        let metadata = self.metadata;
+        let Some(il2cpp_type) = metadata.runtime_metadata.metadata_registration.types.get(ty) else {
+            return ty;
+        };
+        if depth > MAX_TYPE_DEPTH {
+            return ty;
+        }
+
+        match il2cpp_type.data {
+            TypeData::GenericParameterIndex(idx) if il2cpp_type.ty == Il2CppTypeEnum::Var => metadata
+                .global_metadata
+                .generic_parameters
+                .get(idx)
+                .and_then(|param| args.get(param.num as usize))
+                .copied()
+                .unwrap_or(ty),
+            TypeData::GenericClassIndex(_) => {
+                let Some((definition, inst)) = generic_args(metadata, ty) else {
+                    return ty;
+                };
+                let inst: Vec<usize> = inst
+                    .iter()
+                    .map(|&arg| self.substitute(arg, Some(args), depth + 1))
+                    .collect();
+                self.generic_instances
+                    .get(&(definition, inst))
+                    .copied()
+                    .unwrap_or(ty)
+            }
+            _ => ty,
+        }
+    }
+
+    /// Breadth-first search from `start`, returning every reachable type
+    /// except `start` itself.
+    fn collect<'a, F, I>(&'a self, start: TypeDefinitionIndex, next: F) -> Vec<TypeDefinitionIndex>
+    where
+        F: Fn(TypeDefinitionIndex) -> I,
+        I: Iterator<Item = &'a TypeDefinitionIndex>,
+    {
+        let mut found = Vec::new();
+        let mut seen = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(idx) = queue.pop_front() {
+            for &next in next(idx) {
+                if seen.insert(next) {
+                    found.push(next);
+                    queue.push_back(next);
+                }
+            }
+        }
+        found
+    }
+}
+
+/// The generic type definition and type arguments of a generic instance type.
+fn generic_args<'a>(metadata: &'a Metadata, ty: usize) -> Option<(usize, &'a [usize])> {
+    let mr = &metadata.runtime_metadata.metadata_registration;
+    let TypeData::GenericClassIndex(idx) = mr.types.get(ty)?.data else {
+        return None;
+    };
+    let generic_class = mr.generic_classes.get(idx)?;
+    let inst = generic_class.context.class_inst_idx?;
+    Some((generic_class.type_index, mr.generic_insts.get(inst)?.types.as_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::TypeAttributes;
+    use crate::global_metadata::{GenericContainerIndex, GenericParameterIndex, InterfaceIndex};
+    use crate::runtime_metadata::{Il2CppGenericClass, Il2CppGenericContext, Il2CppGenericInst};
+    use crate::test_util;
+
+    const OBJECT: u32 = 0;
+    const IENUMERABLE: u32 = 1;
+    const ICOLLECTION: u32 = 2;
+    const BASE: u32 = 3;
+    const DERIVED: u32 = 4;
+    const MORE_DERIVED: u32 = 5;
+    const OTHER: u32 = 6;
+    const IGENERIC: u32 = 7;
+    const GENERIC: u32 = 8;
+    const INT_GENERIC: u32 = 9;
+
+    /// The type index of `IGeneric<int>`.
+    const IGENERIC_INT: TypeIndex = 13;
+
+    fn indices(indices: &[u32]) -> Vec<TypeDefinitionIndex> {
+        indices.iter().copied().map(TypeDefinitionIndex::new).collect()
+    }
+
+    /// `ICollection : IEnumerable`, `MoreDerived : Derived : Base : ICollection`
+    /// and `Other : IEnumerable`, as well as `IntGeneric : Generic<int>` with
+    /// `Generic<T> : IGeneric<T>`.
+    fn metadata() -> Metadata<'static, 'static> {
+        let mut metadata = test_util::metadata("");
+        let mr = &mut metadata.runtime_metadata.metadata_registration;
+        let class = |idx: u32| {
+            test_util::ty(Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(idx)))
+        };
+        mr.types = (0..=OTHER).map(class).collect();
+        mr.types.extend([
+            test_util::ty(Il2CppTypeEnum::I4, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(OBJECT))),
+            // The `T` of `Generic<T>`.
+            test_util::ty(Il2CppTypeEnum::Var, TypeData::GenericParameterIndex(GenericParameterIndex::new(1))),
+            // `IGeneric<T>`
+            test_util::ty(Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(0)),
+            // `Generic<int>`
+            test_util::ty(Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(1)),
+            class(IGENERIC),
+            class(GENERIC),
+            // `IGeneric<int>`
+            test_util::ty(Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(2)),
+        ]);
+        mr.generic_insts = vec![Il2CppGenericInst { types: vec![8] }, Il2CppGenericInst { types: vec![7] }];
+        let generic_class = |type_index: usize, inst: usize| Il2CppGenericClass {
+            type_index,
+            context: Il2CppGenericContext {
+                class_inst_idx: Some(inst),
+                method_inst_idx: None,
+            },
+        };
+        mr.generic_classes = vec![generic_class(11, 0), generic_class(12, 1), generic_class(11, 1)];
+
+        let gm = &mut metadata.global_metadata;
+        gm.generic_parameters = vec![test_util::zeroed(), test_util::zeroed()].into();
+        gm.interfaces = vec![IENUMERABLE, ICOLLECTION, IENUMERABLE, 9].into();
+        // (parent, interfaces, is_interface)
+        let types = [
+            (u32::MAX, (0, 0), false),
+            (u32::MAX, (0, 0), true),
+            (u32::MAX, (0, 1), true),
+            (OBJECT, (1, 1), false),
+            (BASE, (0, 0), false),
+            (DERIVED, (0, 0), false),
+            (OBJECT, (2, 1), false),
+            (u32::MAX, (0, 0), true),
+            (OBJECT, (3, 1), false),
+            (10, (0, 0), false),
+        ];
+        gm.type_definitions = types
+            .iter()
+            .enumerate()
+            .map(|(i, &(parent, interfaces, is_interface))| {
+                let mut ty_def = test_util::type_definition();
+                ty_def.parent_index = parent;
+                ty_def.interfaces_start = InterfaceIndex::new(interfaces.0);
+                ty_def.interfaces_count = interfaces.1;
+                if is_interface {
+                    ty_def.flags = (TypeAttributes::INTERFACE | TypeAttributes::ABSTRACT).bits();
+                }
+                if i as u32 == IGENERIC || i as u32 == GENERIC {
+                    ty_def.generic_container_index = GenericContainerIndex::new(i as u32 - IGENERIC);
+                }
+                ty_def
+            })
+            .collect::<Vec<_>>()
+            .into();
+        metadata
+    }
+
+    #[test]
+    fn derived_types() {
+        let metadata = metadata();
+        let hierarchy = TypeHierarchy::new(&metadata);
+        assert_eq!(hierarchy.derived_types(TypeDefinitionIndex::new(OBJECT)), indices(&[BASE, OTHER, GENERIC]));
+        assert_eq!(hierarchy.derived_types(TypeDefinitionIndex::new(GENERIC)), indices(&[INT_GENERIC]));
+        assert_eq!(hierarchy.all_derived_types(TypeDefinitionIndex::new(BASE)), indices(&[DERIVED, MORE_DERIVED]));
+        assert!(hierarchy.derived_types(TypeDefinitionIndex::new(MORE_DERIVED)).is_empty());
+    }
+
+    #[test]
+    fn implementors() {
+        let metadata = metadata();
+        let hierarchy = TypeHierarchy::new(&metadata);
+        let ienumerable = TypeDefinitionIndex::new(IENUMERABLE);
+        assert_eq!(hierarchy.implementors(ienumerable), indices(&[ICOLLECTION, OTHER]));
+        assert_eq!(
+            hierarchy.all_implementors(ienumerable),
+            indices(&[ICOLLECTION, OTHER, BASE, DERIVED, MORE_DERIVED])
+        );
+        assert_eq!(hierarchy.implementors(TypeDefinitionIndex::new(IGENERIC)), indices(&[GENERIC]));
+    }
+
+    #[test]
+    fn all_interfaces() {
+        let metadata = metadata();
+        let hierarchy = TypeHierarchy::new(&metadata);
+        assert_eq!(
+            hierarchy.all_interfaces(TypeDefinitionIndex::new(MORE_DERIVED)),
+            [ICOLLECTION, IENUMERABLE]
+        );
+        assert_eq!(hierarchy.all_interfaces(TypeDefinitionIndex::new(INT_GENERIC)), [IGENERIC_INT]);
+        assert!(hierarchy.all_interfaces(TypeDefinitionIndex::new(OBJECT)).is_empty());
+    }
+}
@@ -0,0 +1,822 @@
+//! Memory layout of managed types.
+//!
+//! IL2CPP compiles the offset of every field and the sizes of every type into
+//! the binary, as [`Il2CppMetadataRegistration::field_offsets`] and
+//! [`Il2CppMetadataRegistration::type_definition_sizes`]. Neither table says
+//! which offsets belong to instance, static or thread static fields, and the
+//! offsets of value type fields include an object header that unboxed values
+//! don't have. [`Metadata::type_layout`] combines them with the field
+//! attributes into a [`Layout`].
+//!
+//! Generic instances have no recorded offsets, and metadata read from C++
+//! sources has none at all. For these, layouts are computed the way the
+//! IL2CPP runtime computes them: fields are placed in declaration order at
+//! the next offset aligned to their type, or to the type's packing size if
+//! that is smaller, and the fields of reference types follow the fields of
+//! their base type. [`Metadata::layout_mismatches`] compares computed layouts
+//! with the recorded ones.
+//!
+//! Only 64-bit binaries are supported.
+//!
+//! [`Il2CppMetadataRegistration::field_offsets`]: crate::runtime_metadata::Il2CppMetadataRegistration::field_offsets
+//! [`Il2CppMetadataRegistration::type_definition_sizes`]: crate::runtime_metadata::Il2CppMetadataRegistration::type_definition_sizes
+
+use crate::flags::{FieldAttributes, TypeLayout};
+use crate::generics::{inflate, GenericContext, InflatedType, InflatedTypeData};
+use crate::global_metadata::{
+    FieldIndex, Il2CppTypeDefinition, TypeDefinitionIndex, TypeDefinitionLayoutInfo, TypeIndex,
+};
+use crate::runtime_metadata::{GenericClassIndex, Il2CppType, Il2CppTypeEnum, TypeData};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+use std::fmt;
+
+/// The size of the header at the start of every object, which holds the
+/// object's class and monitor.
+pub const OBJECT_HEADER_SIZE: u32 = 0x10;
+pub const POINTER_SIZE: u32 = 8;
+
+/// The offset stored for thread static fields before IL2CPP recorded their
+/// offsets in the thread static fields block.
+pub const THREAD_STATIC_FIELD_OFFSET: u32 = u32::MAX;
+
+/// The bit set in the stored offsets of thread static fields. The remaining
+/// bits are the offset in the thread static fields block.
+const THREAD_LOCAL_STATIC_MASK: u32 = 0x8000_0000;
+
+/// The memory layout of a type definition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    /// The type definition, which is the generic type definition for generic
+    /// instances.
+    pub type_definition: TypeDefinitionIndex,
+    pub generic_class: Option<GenericClassIndex>,
+    pub is_value_type: bool,
+    /// The size of the object header at the start of instances.
+    /// [`OBJECT_HEADER_SIZE`] for reference types, and `0` for value types,
+    /// whose layout is that of the unboxed value.
+    pub header_size: u32,
+    /// The size of an instance, including the object header of reference
+    /// types. `None` if the binary doesn't record sizes for the type.
+    pub instance_size: Option<u32>,
+    /// The alignment of the fields of an instance. `None` if the alignment of
+    /// a field is not known.
+    pub alignment: Option<u32>,
+    /// The packing size given in the type's `StructLayout` attribute.
+    pub packing_size: Option<u8>,
+    /// The instance fields, including those of base types, ordered by
+    /// offset. Offsets are relative to the start of the instance.
+    pub instance_fields: Vec<FieldLayout>,
+    /// The static fields, with offsets relative to the type's static fields
+    /// block.
+    pub static_fields: Vec<FieldLayout>,
+    pub static_fields_size: Option<u32>,
+    /// The thread static fields, with offsets relative to the type's thread
+    /// static fields block. Fields are left out if the binary doesn't record
+    /// their offsets, and thread static fields are treated as static fields
+    /// if the binary doesn't record any offsets.
+    pub thread_static_fields: Vec<FieldLayout>,
+    pub thread_static_fields_size: Option<u32>,
+    /// The parts of an instance that no known instance field covers,
+    /// excluding the object header.
+    ///
+    /// Base types that are generic instances have no recorded offsets, so
+    /// their fields are placed by the computed layout of the base. If that
+    /// can't be computed, the space of their fields is included here.
+    pub padding: Vec<Padding>,
+}
+
+/// The position of a field in memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldLayout {
+    pub field: FieldIndex,
+    /// The type that defines the field, which is a base type for inherited
+    /// fields.
+    pub declaring_type: TypeDefinitionIndex,
+    /// The type of the field as it is declared, before the arguments of a
+    /// generic instance are substituted.
+    pub type_index: TypeIndex,
+    pub offset: u32,
+    /// `None` if the field's type has no known layout.
+    pub size: Option<u32>,
+    pub alignment: Option<u32>,
+}
+
+/// A range of an instance that is not covered by a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Padding {
+    pub offset: u32,
+    pub size: u32,
+}
+
+/// A difference between a computed layout and the layout recorded in the
+/// binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutMismatch {
+    FieldOffset {
+        type_definition: TypeDefinitionIndex,
+        field: FieldIndex,
+        stored: u32,
+        computed: u32,
+    },
+    InstanceSize {
+        type_definition: TypeDefinitionIndex,
+        stored: u32,
+        computed: u32,
+    },
+}
+
+impl fmt::Display for LayoutMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutMismatch::FieldOffset { type_definition, field, stored, computed } => write!(
+                f,
+                "field {} of type {} is at 0x{stored:X}, computed 0x{computed:X}",
+                field.index(),
+                type_definition.index()
+            ),
+            LayoutMismatch::InstanceSize { type_definition, stored, computed } => write!(
+                f,
+                "type {} has size 0x{stored:X}, computed 0x{computed:X}",
+                type_definition.index()
+            ),
+        }
+    }
+}
+
+/// A layout computed by IL2CPP's rules. Instance field offsets include the
+/// object header, also for value types.
+struct ComputedLayout {
+    instance_fields: Vec<FieldLayout>,
+    static_fields: Vec<FieldLayout>,
+    thread_static_fields: Vec<FieldLayout>,
+    /// The end of the last instance field, where the fields of derived types
+    /// start.
+    actual_size: u32,
+    instance_size: u32,
+    alignment: u32,
+    static_fields_size: u32,
+    thread_static_fields_size: u32,
+}
+
+/// Places fields one after another, like the runtime's
+/// `FieldLayout::LayoutFields`.
+struct FieldPlacer {
+    end: u32,
+    alignment: u32,
+    packing: Option<u32>,
+}
+
+impl FieldPlacer {
+    fn new(end: u32, alignment: u32, packing: Option<u32>) -> Self {
+        Self { end, alignment, packing }
+    }
+
+    /// Places a field after the previous one and returns its offset, or
+    /// `None` if the field would end past `u32::MAX`.
+    fn place(&mut self, size: u32, alignment: u32) -> Option<u32> {
+        let alignment = self.effective_alignment(alignment);
+        let offset = self.end.checked_next_multiple_of(alignment)?;
+        self.place_at(offset, size, alignment)?;
+        Some(offset)
+    }
+
+    /// Places a field at an offset given by an explicit layout.
+    fn place_at(&mut self, offset: u32, size: u32, alignment: u32) -> Option<()> {
+        self.end = self.end.max(offset.checked_add(size.max(1))?);
+        self.alignment = self.alignment.max(self.effective_alignment(alignment));
+        Some(())
+    }
+
+    fn effective_alignment(&self, alignment: u32) -> u32 {
+        self.packing.map_or(alignment, |packing| alignment.min(packing)).max(1)
+    }
+
+    /// The size of the placed fields, padded to their alignment.
+    fn size(&self) -> Option<u32> {
+        self.end.checked_next_multiple_of(self.alignment)
+    }
+}
+
+impl ComputedLayout {
+    fn into_layout(
+        self,
+        type_definition: TypeDefinitionIndex,
+        generic_class: Option<GenericClassIndex>,
+        info: TypeDefinitionLayoutInfo,
+    ) -> Layout {
+        let header_size = match info.is_value_type {
+            true => 0,
+            false => OBJECT_HEADER_SIZE,
+        };
+        let mut instance_fields = self.instance_fields;
+        for field in &mut instance_fields {
+            field.offset = field.offset.wrapping_sub(OBJECT_HEADER_SIZE - header_size);
+        }
+        instance_fields.sort_by_key(|field| field.offset);
+        let instance_size = self.instance_size - (OBJECT_HEADER_SIZE - header_size);
+        Layout {
+            type_definition,
+            generic_class,
+            is_value_type: info.is_value_type,
+            header_size,
+            instance_size: Some(instance_size),
+            alignment: Some(self.alignment),
+            packing_size: info.packing_size,
+            padding: padding(&instance_fields, header_size, Some(instance_size)),
+            instance_fields,
+            static_fields: self.static_fields,
+            static_fields_size: Some(self.static_fields_size),
+            thread_static_fields: self.thread_static_fields,
+            thread_static_fields_size: Some(self.thread_static_fields_size),
+        }
+    }
+}
+
+impl Metadata<'_, '_> {
+    /// The memory layout of a type definition.
+    ///
+    /// Uses the field offsets recorded in the binary, and computes the layout
+    /// if there are none. Returns `None` for generic type definitions, which
+    /// only have layouts once instantiated.
+    pub fn type_layout(&self, idx: TypeDefinitionIndex) -> Option<Layout> {
+        match self.runtime_metadata.metadata_registration.field_offsets {
+            Some(_) => self.stored_type_layout(idx),
+            None => self.computed_type_layout(idx),
+        }
+    }
+
+    /// Computes the memory layout of a type definition, ignoring the field
+    /// offsets recorded in the binary.
+    ///
+    /// Returns `None` for generic type definitions, and if the layout of a
+    /// field's type is not known. Types with an explicit layout can only be
+    /// computed from a binary, which records their offsets.
+    pub fn computed_type_layout(&self, idx: TypeDefinitionIndex) -> Option<Layout> {
+        let ty_def = self.global_metadata.type_definitions.get(idx)?;
+        if ty_def.generic_container_index.is_valid() {
+            return None;
+        }
+        let computed = self.compute_layout(idx, &GenericContext::default(), true, 0)?;
+        Some(computed.into_layout(idx, None, ty_def.layout_info()))
+    }
+
+    /// Computes the memory layout of an instance of a generic type, such as
+    /// `List<int>`.
+    ///
+    /// Index into [`Il2CppMetadataRegistration::generic_classes`].
+    ///
+    /// [`Il2CppMetadataRegistration::generic_classes`]: crate::runtime_metadata::Il2CppMetadataRegistration::generic_classes
+    pub fn generic_class_layout(&self, class: GenericClassIndex) -> Option<Layout> {
+        let generic_class = self.runtime_metadata.metadata_registration.generic_classes.get(class)?;
+        let idx = generic_class.type_definition_index(self)?;
+        let ty_def = self.global_metadata.type_definitions.get(idx)?;
+        let computed = self.compute_layout(idx, &generic_class.generic_context(self), true, 0)?;
+        Some(computed.into_layout(idx, Some(class), ty_def.layout_info()))
+    }
+
+    /// Compares the computed layout of every type definition with the field
+    /// offsets and sizes recorded in the binary.
+    ///
+    /// Returns nothing if the binary doesn't record field offsets.
+    pub fn layout_mismatches(&self) -> Vec<LayoutMismatch> {
+        let mut mismatches = Vec::new();
+        if self.runtime_metadata.metadata_registration.field_offsets.is_none() {
+            return mismatches;
+        }
+        for i in 0..self.global_metadata.type_definitions.len() {
+            let idx = TypeDefinitionIndex::new(i as u32);
+            let (Some(stored), Some(computed)) = (self.stored_type_layout(idx), self.computed_type_layout(idx)) else {
+                continue;
+            };
+            if let (Some(stored), Some(computed)) = (stored.instance_size, computed.instance_size) {
+                if stored != computed {
+                    mismatches.push(LayoutMismatch::InstanceSize { type_definition: idx, stored, computed });
+                }
+            }
+            // Inherited fields are compared with the layouts of base types.
+            let fields = |layout: &Layout| {
+                [&layout.instance_fields, &layout.static_fields, &layout.thread_static_fields]
+                    .into_iter()
+                    .flatten()
+                    .filter(|field| field.declaring_type == idx)
+                    .map(|field| (field.field, field.offset))
+                    .collect::<Vec<_>>()
+            };
+            let computed = fields(&computed);
+            for (field, stored) in fields(&stored) {
+                let Some(&(_, computed)) = computed.iter().find(|&&(other, _)| other == field) else {
+                    continue;
+                };
+                if stored != computed {
+                    mismatches.push(LayoutMismatch::FieldOffset { type_definition: idx, field, stored, computed });
+                }
+            }
+        }
+        mismatches
+    }
+
+    fn stored_type_layout(&self, idx: TypeDefinitionIndex) -> Option<Layout> {
+        let gm = &self.global_metadata;
+        let mr = &self.runtime_metadata.metadata_registration;
+        mr.field_offsets.as_ref()?;
+        let ty_def = gm.type_definitions.get(idx)?;
+        if ty_def.generic_container_index.is_valid() {
+            return None;
+        }
+        let info = ty_def.layout_info();
+        let header_size = match info.is_value_type {
+            true => 0,
+            false => OBJECT_HEADER_SIZE,
+        };
+        let sizes = mr.type_definition_sizes.as_ref().and_then(|sizes| sizes.get(idx.index() as usize));
+        let instance_size = sizes.and_then(|sizes| sizes.instance_size.checked_sub(OBJECT_HEADER_SIZE - header_size));
+
+        // Reference types start with the fields of their base types. A base
+        // that is a generic instance has no recorded offsets, so its fields
+        // and those of its own bases come from its computed layout.
+        let mut chain = vec![(idx, ty_def)];
+        let mut generic_base_fields = Vec::new();
+        let mut current = ty_def;
+        while !info.is_value_type && chain.len() <= gm.type_definitions.len() {
+            let Some(base) = current.base_type(self) else {
+                break;
+            };
+            let base_idx = match (base.ty, base.data) {
+                (Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(base_idx)) => base_idx,
+                (Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(class)) => {
+                    if let Some(base) = self.generic_base_layout(class) {
+                        generic_base_fields = base.instance_fields;
+                    }
+                    break;
+                }
+                _ => break,
+            };
+            let Some(base_def) = gm.type_definitions.get(base_idx) else {
+                break;
+            };
+            chain.push((base_idx, base_def));
+            current = base_def;
+        }
+
+        let mut layout = Layout {
+            type_definition: idx,
+            generic_class: None,
+            is_value_type: info.is_value_type,
+            header_size,
+            instance_size,
+            alignment: None,
+            packing_size: info.packing_size,
+            instance_fields: generic_base_fields,
+            static_fields: Vec::new(),
+            static_fields_size: sizes.map(|sizes| sizes.static_fields_size),
+            thread_static_fields: Vec::new(),
+            thread_static_fields_size: sizes.map(|sizes| sizes.thread_static_fields_size),
+            padding: Vec::new(),
+        };
+        for &(declaring_type, ty_def) in chain.iter().rev() {
+            for (field, type_index, ty, offset) in self.stored_field_offsets(declaring_type, ty_def) {
+                let attributes = ty.field_attributes();
+                if attributes.contains(FieldAttributes::LITERAL) {
+                    continue;
+                }
+                let mut field = FieldLayout {
+                    field,
+                    declaring_type,
+                    type_index,
+                    offset,
+                    size: self.type_size(ty),
+                    alignment: self.type_alignment(ty),
+                };
+                if !attributes.contains(FieldAttributes::STATIC) {
+                    field.offset = offset.wrapping_sub(OBJECT_HEADER_SIZE - header_size);
+                    layout.instance_fields.push(field);
+                } else if declaring_type != idx {
+                    // Static fields of base types are not part of this type.
+                } else if offset == THREAD_STATIC_FIELD_OFFSET {
+                    // The offset of the field is not known.
+                } else if offset & THREAD_LOCAL_STATIC_MASK != 0 {
+                    field.offset = offset & !THREAD_LOCAL_STATIC_MASK;
+                    layout.thread_static_fields.push(field);
+                } else {
+                    layout.static_fields.push(field);
+                }
+            }
+        }
+        layout.instance_fields.sort_by_key(|field| field.offset);
+        layout.static_fields.sort_by_key(|field| field.offset);
+        layout.thread_static_fields.sort_by_key(|field| field.offset);
+
+        let header_alignment = match info.is_value_type {
+            true => 1,
+            false => POINTER_SIZE,
+        };
+        layout.alignment = fields_alignment(&layout.instance_fields, header_alignment, info.packing_size);
+        layout.padding = padding(&layout.instance_fields, header_size, instance_size);
+        Some(layout)
+    }
+
+    /// Computes the instance fields of a generic instance that is the base of
+    /// a reference type, without its static fields.
+    fn generic_base_layout(&self, class: GenericClassIndex) -> Option<ComputedLayout> {
+        let generic_class = self.runtime_metadata.metadata_registration.generic_classes.get(class)?;
+        let idx = generic_class.type_definition_index(self)?;
+        self.compute_layout(idx, &generic_class.generic_context(self), false, 0)
+    }
+
+    /// The size of a value of a type, as stored in a field. Reference types
+    /// are stored as pointers.
+    ///
+    /// Uses the sizes recorded in the binary where possible. Returns `None`
+    /// for types without a known layout, such as generic parameters.
+    pub fn type_size(&self, ty: &Il2CppType) -> Option<u32> {
+        if let (false, Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(idx)) = (ty.byref, ty.ty, ty.data) {
+            let sizes = self.runtime_metadata.metadata_registration.type_definition_sizes.as_ref();
+            if let Some(sizes) = sizes.and_then(|sizes| sizes.get(idx.index() as usize)) {
+                return sizes.instance_size.checked_sub(OBJECT_HEADER_SIZE);
+            }
+        }
+        self.size_and_alignment(&InflatedType::new(self, ty), 0).map(|(size, _)| size)
+    }
+
+    /// The alignment of a value of a type, as stored in a field.
+    pub fn type_alignment(&self, ty: &Il2CppType) -> Option<u32> {
+        self.size_and_alignment(&InflatedType::new(self, ty), 0).map(|(_, alignment)| alignment)
+    }
+
+    /// Whether a type is a value type, looking through generic instances.
+    /// Returns `None` for types without a definition.
+    pub fn is_value_type(&self, ty: &Il2CppType) -> Option<bool> {
+        let definition = ty.type_definition_index(self)?;
+        Some(self.global_metadata.type_definitions.get(definition)?.layout_info().is_value_type)
+    }
+
+    /// The size and alignment of a value of a type, as stored in a field.
+    fn size_and_alignment(&self, ty: &InflatedType, depth: usize) -> Option<(u32, u32)> {
+        if ty.byref {
+            return Some((POINTER_SIZE, POINTER_SIZE));
+        }
+        let size = match ty.ty {
+            Il2CppTypeEnum::Boolean | Il2CppTypeEnum::I1 | Il2CppTypeEnum::U1 => 1,
+            Il2CppTypeEnum::Char | Il2CppTypeEnum::I2 | Il2CppTypeEnum::U2 => 2,
+            Il2CppTypeEnum::I4 | Il2CppTypeEnum::U4 | Il2CppTypeEnum::R4 => 4,
+            Il2CppTypeEnum::I8 | Il2CppTypeEnum::U8 | Il2CppTypeEnum::R8 => 8,
+            Il2CppTypeEnum::Valuetype | Il2CppTypeEnum::Genericinst => {
+                let (idx, context) = match &ty.data {
+                    InflatedTypeData::Definition(idx) => (*idx, GenericContext::default()),
+                    InflatedTypeData::GenericInstance { definition, args } => {
+                        (*definition, GenericContext::new(args.clone(), Vec::new()))
+                    }
+                    _ => return None,
+                };
+                if !self.global_metadata.type_definitions.get(idx)?.layout_info().is_value_type {
+                    return Some((POINTER_SIZE, POINTER_SIZE));
+                }
+                let layout = self.compute_layout(idx, &context, false, depth + 1)?;
+                return Some((layout.instance_size - OBJECT_HEADER_SIZE, layout.alignment));
+            }
+            Il2CppTypeEnum::Void
+            | Il2CppTypeEnum::Var
+            | Il2CppTypeEnum::Mvar
+            | Il2CppTypeEnum::Typedbyref => return None,
+            _ => POINTER_SIZE,
+        };
+        // Primitives and pointers are aligned to their size.
+        Some((size, size))
+    }
+
+    /// Lays out the fields of a type definition with the type arguments of
+    /// `context`.
+    ///
+    /// Static fields are only laid out if `with_statics` is set, since value
+    /// types often have static fields of their own type.
+    fn compute_layout(
+        &self,
+        idx: TypeDefinitionIndex,
+        context: &GenericContext,
+        with_statics: bool,
+        depth: usize,
+    ) -> Option<ComputedLayout> {
+        let gm = &self.global_metadata;
+        let mr = &self.runtime_metadata.metadata_registration;
+        if depth > MAX_TYPE_DEPTH {
+            return None;
+        }
+        let ty_def = gm.type_definitions.get(idx)?;
+        let info = ty_def.layout_info();
+        let packing = info.packing_size.filter(|&packing| packing != 0).map(u32::from);
+
+        // Value types are laid out as if they were boxed.
+        let mut instance_fields = Vec::new();
+        let mut instance = FieldPlacer::new(OBJECT_HEADER_SIZE, 1, packing);
+        if !info.is_value_type {
+            instance.alignment = POINTER_SIZE;
+            if let Some(base) = ty_def.base_type(self) {
+                let base = inflate(self, base, context);
+                let base_context = match &base.data {
+                    InflatedTypeData::GenericInstance { args, .. } => GenericContext::new(args.clone(), Vec::new()),
+                    _ => GenericContext::default(),
+                };
+                let base = self.compute_layout(base.type_definition_index()?, &base_context, false, depth + 1)?;
+                instance_fields = base.instance_fields;
+                instance.end = base.actual_size;
+                instance.alignment = base.alignment;
+            }
+        }
+        let mut statics = FieldPlacer::new(0, 1, None);
+        let mut thread_statics = FieldPlacer::new(0, 1, None);
+        let mut static_fields = Vec::new();
+        let mut thread_static_fields = Vec::new();
+
+        // Explicit offsets and thread statics are only known from the offsets
+        // recorded in the binary.
+        let stored = self.stored_offsets(idx, ty_def);
+        let explicit = info.layout == TypeLayout::Explicit;
+        for (i, field) in ty_def.field_start.indices(ty_def.field_count as u32).enumerate() {
+            let type_index = gm.fields.get(field)?.type_index;
+            let ty = mr.types.get(type_index as usize)?;
+            let attributes = ty.field_attributes();
+            let is_static = attributes.contains(FieldAttributes::STATIC);
+            if attributes.contains(FieldAttributes::LITERAL) || (is_static && !with_statics) {
+                continue;
+            }
+            let (size, alignment) = self.size_and_alignment(&inflate(self, ty, context), depth + 1)?;
+            let stored_offset = stored.and_then(|offsets| offsets.get(i)).copied();
+            let mut layout = FieldLayout {
+                field,
+                declaring_type: idx,
+                type_index,
+                offset: 0,
+                size: Some(size),
+                alignment: Some(alignment),
+            };
+            if !is_static {
+                layout.offset = match explicit {
+                    true => {
+                        let offset = stored_offset?;
+                        instance.place_at(offset, size, alignment)?;
+                        offset
+                    }
+                    false => instance.place(size, alignment)?,
+                };
+                instance_fields.push(layout);
+            } else if stored_offset.is_some_and(|offset| offset & THREAD_LOCAL_STATIC_MASK != 0) {
+                layout.offset = thread_statics.place(size, alignment)?;
+                thread_static_fields.push(layout);
+            } else {
+                layout.offset = statics.place(size, alignment)?;
+                static_fields.push(layout);
+            }
+        }
+
+        // Empty value types take up a byte.
+        if info.is_value_type && instance.end == OBJECT_HEADER_SIZE {
+            instance.end += 1;
+        }
+        let mut instance_size = instance.size()?;
+        // Sizes given in a `StructLayout` attribute are only known from the
+        // sizes recorded in the binary.
+        if !info.class_size_is_default && !ty_def.generic_container_index.is_valid() {
+            let sizes = mr.type_definition_sizes.as_ref().and_then(|sizes| sizes.get(idx.index() as usize));
+            if let Some(sizes) = sizes {
+                instance_size = instance_size.max(sizes.instance_size);
+            }
+        }
+        Some(ComputedLayout {
+            instance_fields,
+            static_fields,
+            thread_static_fields,
+            actual_size: instance.end,
+            instance_size,
+            alignment: instance.alignment,
+            static_fields_size: statics.size()?,
+            thread_static_fields_size: thread_statics.size()?,
+        })
+    }
+
+    /// The field offsets of a type definition recorded in the binary.
+    fn stored_offsets(&self, idx: TypeDefinitionIndex, ty_def: &Il2CppTypeDefinition) -> Option<&[u32]> {
+        let offsets = self.runtime_metadata.metadata_registration.field_offsets.as_ref()?;
+        let offsets = offsets.get(idx.index() as usize)?;
+        // Types without instance fields don't have an offset table.
+        (offsets.len() == ty_def.field_count as usize).then_some(offsets.as_slice())
+    }
+
+    /// The fields of a type definition with their types and the offsets
+    /// recorded in the binary.
+    fn stored_field_offsets(
+        &self,
+        idx: TypeDefinitionIndex,
+        ty_def: &Il2CppTypeDefinition,
+    ) -> Vec<(FieldIndex, TypeIndex, &Il2CppType, u32)> {
+        let gm = &self.global_metadata;
+        let mr = &self.runtime_metadata.metadata_registration;
+        let Some(offsets) = self.stored_offsets(idx, ty_def) else {
+            return Vec::new();
+        };
+        let mut fields = Vec::with_capacity(offsets.len());
+        let indices = ty_def.field_start.indices(ty_def.field_count as u32);
+        for (idx, &offset) in indices.zip(offsets) {
+            let Some(field) = gm.fields.get(idx) else {
+                continue;
+            };
+            let Some(ty) = mr.types.get(field.type_index as usize) else {
+                continue;
+            };
+            fields.push((idx, field.type_index, ty, offset));
+        }
+        fields
+    }
+}
+
+/// The alignment of a type with the given instance fields.
+fn fields_alignment(fields: &[FieldLayout], header_alignment: u32, packing_size: Option<u8>) -> Option<u32> {
+    let packing = packing_size.filter(|&packing| packing != 0).map(u32::from);
+    let mut alignment = header_alignment;
+    for field in fields {
+        let field_alignment = field.alignment?;
+        alignment = alignment.max(packing.map_or(field_alignment, |packing| field_alignment.min(packing)));
+    }
+    Some(alignment)
+}
+
+/// The ranges between `header_size` and `instance_size` that are not covered
+/// by `fields`, which must be ordered by offset.
+fn padding(fields: &[FieldLayout], header_size: u32, instance_size: Option<u32>) -> Vec<Padding> {
+    let mut padding = Vec::new();
+    let mut end = header_size;
+    for (i, field) in fields.iter().enumerate() {
+        if field.offset > end {
+            padding.push(Padding { offset: end, size: field.offset - end });
+        }
+        // A field of unknown size takes up the space until the next field.
+        let field_end = match field.size {
+            Some(size) => field.offset.saturating_add(size),
+            None => fields[i + 1..]
+                .iter()
+                .map(|next| next.offset)
+                .find(|&offset| offset > field.offset)
+                .or(instance_size)
+                .unwrap_or(field.offset),
+        };
+        end = end.max(field_end);
+    }
+    if let Some(size) = instance_size.filter(|&size| size > end) {
+        padding.push(Padding { offset: end, size: size - end });
+    }
+    padding
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{GenericContainerIndex, GenericParameterIndex, Il2CppFieldDefinition};
+    use crate::runtime_metadata::{Il2CppGenericClass, Il2CppGenericContext, Il2CppGenericInst};
+    use crate::test_util;
+
+    const INNER: u32 = 0;
+    const PACKED: u32 = 1;
+    const EMPTY: u32 = 2;
+    const BASE: u32 = 3;
+    const DERIVED: u32 = 4;
+    const OUTER: u32 = 5;
+    const GENERIC_BASE: u32 = 6;
+    const GENERIC_DERIVED: u32 = 7;
+
+    /// Type definitions for every kind of layout, with field offsets recorded
+    /// only for `GENERIC_DERIVED`.
+    fn metadata() -> Metadata<'static, 'static> {
+        let mut metadata = test_util::metadata("");
+        let mr = &mut metadata.runtime_metadata.metadata_registration;
+        mr.types = vec![
+            test_util::ty(Il2CppTypeEnum::I4, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0))),
+            test_util::ty(Il2CppTypeEnum::U1, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0))),
+            test_util::ty(Il2CppTypeEnum::I8, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0))),
+            test_util::ty(Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(INNER))),
+            test_util::ty(Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(BASE))),
+            test_util::ty(Il2CppTypeEnum::Var, TypeData::GenericParameterIndex(GenericParameterIndex::new(0))),
+            test_util::ty(Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(0)),
+            test_util::ty(Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(GENERIC_BASE))),
+        ];
+        // `GenericBase<long>`
+        mr.generic_insts = vec![Il2CppGenericInst { types: vec![2] }];
+        mr.generic_classes = vec![Il2CppGenericClass {
+            type_index: 7,
+            context: Il2CppGenericContext { class_inst_idx: Some(0), method_inst_idx: None },
+        }];
+        let mut field_offsets = vec![Vec::new(); 8];
+        field_offsets[GENERIC_DERIVED as usize] = vec![0x18];
+        mr.field_offsets = Some(field_offsets);
+
+        let gm = &mut metadata.global_metadata;
+        let field_types = [1, 0, 1, 0, 1, 1, 1, 3, 2, 5, 1];
+        gm.fields = field_types
+            .iter()
+            .map(|&type_index| {
+                let mut field: Il2CppFieldDefinition = test_util::zeroed();
+                field.type_index = type_index;
+                field
+            })
+            .collect::<Vec<_>>()
+            .into();
+        gm.generic_parameters = vec![test_util::zeroed()].into();
+        let type_definition = |field_start: u32, field_count: u16, value_type: bool, parent: u32| {
+            let mut ty_def = test_util::type_definition();
+            ty_def.field_start = FieldIndex::new(field_start);
+            ty_def.field_count = field_count;
+            ty_def.bitfield |= value_type as u32;
+            ty_def.parent_index = parent;
+            ty_def
+        };
+        let mut packed = type_definition(2, 2, true, u32::MAX);
+        // A packing size of 1, which is not the default.
+        packed.bitfield = (packed.bitfield & !(1 << 10)) | 1 << 6;
+        let mut generic_base = type_definition(9, 1, false, u32::MAX);
+        generic_base.generic_container_index = GenericContainerIndex::new(0);
+        gm.type_definitions = vec![
+            type_definition(0, 2, true, u32::MAX),
+            packed,
+            type_definition(4, 0, true, u32::MAX),
+            type_definition(4, 1, false, u32::MAX),
+            type_definition(5, 1, false, 4),
+            type_definition(6, 3, true, u32::MAX),
+            generic_base,
+            type_definition(10, 1, false, 6),
+        ]
+        .into();
+        metadata
+    }
+
+    fn offsets(layout: &Layout) -> Vec<(u32, u32)> {
+        layout.instance_fields.iter().map(|field| (field.field.index(), field.offset)).collect()
+    }
+
+    #[test]
+    fn fields_are_aligned() {
+        let metadata = metadata();
+        let layout = metadata.computed_type_layout(TypeDefinitionIndex::new(INNER)).unwrap();
+        assert_eq!(offsets(&layout), [(0, 0), (1, 4)]);
+        assert_eq!(layout.instance_size, Some(8));
+        assert_eq!(layout.alignment, Some(4));
+        assert_eq!(layout.padding, [Padding { offset: 1, size: 3 }]);
+    }
+
+    #[test]
+    fn packing() {
+        let metadata = metadata();
+        let layout = metadata.computed_type_layout(TypeDefinitionIndex::new(PACKED)).unwrap();
+        assert_eq!(layout.packing_size, Some(1));
+        assert_eq!(offsets(&layout), [(2, 0), (3, 1)]);
+        assert_eq!(layout.instance_size, Some(5));
+        assert_eq!(layout.alignment, Some(1));
+    }
+
+    #[test]
+    fn empty_struct() {
+        let metadata = metadata();
+        let layout = metadata.computed_type_layout(TypeDefinitionIndex::new(EMPTY)).unwrap();
+        assert_eq!(layout.instance_size, Some(1));
+    }
+
+    #[test]
+    fn fields_follow_base_fields() {
+        let metadata = metadata();
+        let base = metadata.computed_type_layout(TypeDefinitionIndex::new(BASE)).unwrap();
+        assert_eq!(base.instance_size, Some(0x18));
+        // Derived fields start at the end of the base fields, not at the
+        // padded size of the base.
+        let layout = metadata.computed_type_layout(TypeDefinitionIndex::new(DERIVED)).unwrap();
+        assert_eq!(offsets(&layout), [(4, 0x10), (5, 0x11)]);
+        assert_eq!(layout.instance_fields[0].declaring_type, TypeDefinitionIndex::new(BASE));
+        assert_eq!(layout.instance_size, Some(0x18));
+    }
+
+    #[test]
+    fn nested_value_types() {
+        let metadata = metadata();
+        let layout = metadata.computed_type_layout(TypeDefinitionIndex::new(OUTER)).unwrap();
+        assert_eq!(offsets(&layout), [(6, 0), (7, 4), (8, 0x10)]);
+        assert_eq!(layout.instance_fields[1].size, Some(8));
+        assert_eq!(layout.instance_size, Some(0x18));
+        assert_eq!(layout.alignment, Some(8));
+    }
+
+    #[test]
+    fn generic_base_fields() {
+        let metadata = metadata();
+        let layout = metadata.type_layout(TypeDefinitionIndex::new(GENERIC_DERIVED)).unwrap();
+        assert_eq!(offsets(&layout), [(9, 0x10), (10, 0x18)]);
+        assert_eq!(layout.instance_fields[0].size, Some(8));
+        assert_eq!(layout.instance_fields[0].declaring_type, TypeDefinitionIndex::new(GENERIC_BASE));
+        assert!(layout.padding.is_empty());
+    }
+
+    #[test]
+    fn overflow() {
+        let mut placer = FieldPlacer::new(u32::MAX - 2, 1, None);
+        assert_eq!(placer.place(1, 4), None);
+        assert_eq!(placer.place_at(u32::MAX - 1, 4, 1), None);
+        assert_eq!(placer.place(1, 1), Some(u32::MAX - 2));
+        assert_eq!(placer.size(), Some(u32::MAX - 1));
+    }
+}
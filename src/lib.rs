@@ -7,12 +7,39 @@
 
 pub mod global_metadata;
 pub mod runtime_metadata;
+pub mod owned;
+pub mod lookup;
+pub mod hierarchy;
+pub mod vtable;
+pub mod flags;
+pub mod signature;
+pub mod generics;
+pub mod type_map;
+pub mod type_name;
+pub mod layout;
+pub mod dump;
+pub mod cpp_header;
+pub mod rust_bindings;
+pub mod stub_assembly;
+pub mod analysis_script;
+pub mod validation;
+pub(crate) mod codegen;
+#[cfg(feature = "archive")]
+pub mod archive;
+#[cfg(test)]
+mod test_util;
 
 use runtime_metadata::elf::Il2CppBinaryError;
 use runtime_metadata::RuntimeMetadata;
+use global_metadata::transform::MetadataTransform;
 use global_metadata::{GlobalMetadata, MetadataDeserializeError};
 use thiserror::Error;
 
+/// Types nested deeper than this, through generic arguments, element types,
+/// base types or value type fields, are treated as malformed. Cyclic metadata
+/// would otherwise recurse forever.
+pub(crate) const MAX_TYPE_DEPTH: usize = 32;
+
 /// A container for all of the applications metadata structures.
 /// 
 /// A Unity IL2CPP application stores metadata in two different ways, the
@@ -24,6 +51,9 @@ use thiserror::Error;
 /// The runtime metadata is stored inside the game binary itself. This is
 /// generally the `libil2cpp.so` file in the application. See
 /// [`RuntimeMetadata`] for more information.
+///
+/// This borrows the data it was parsed from. See [`owned::OwnedMetadata`] for
+/// a version that owns its data.
 pub struct Metadata<'gmd, 'rmd> {
     /// The application's global metadata.
     ///
@@ -43,6 +73,9 @@ pub enum MetadataParseError {
 
     #[error("could not parse runtime metadata")]
     Binary(#[from] Il2CppBinaryError),
+
+    #[error("could not read metadata")]
+    Io(#[from] std::io::Error),
 }
 
 impl<'gmd, 'rmd> Metadata<'gmd, 'rmd> {
@@ -54,4 +87,22 @@ impl<'gmd, 'rmd> Metadata<'gmd, 'rmd> {
             runtime_metadata,
         })
     }
+
+    /// Parses metadata after undoing the obfuscation of the global metadata
+    /// file with `transform`.
+    ///
+    /// The global metadata buffer is transformed in-place. See
+    /// [`global_metadata::transform`] for more information.
+    pub fn parse_with_transform<T: MetadataTransform>(
+        global_metadata: &'gmd mut [u8],
+        elf: &'rmd [u8],
+        transform: &T,
+    ) -> Result<Self, MetadataParseError> {
+        let global_metadata = global_metadata::deserialize_with_transform(global_metadata, transform)?;
+        let runtime_metadata = RuntimeMetadata::read_elf(elf, &global_metadata)?;
+        Ok(Metadata {
+            global_metadata,
+            runtime_metadata,
+        })
+    }
 }
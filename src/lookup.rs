@@ -0,0 +1,373 @@
+//! Name-based lookup of types, methods and fields.
+//!
+//! Type names use the same syntax as IL2CPP's own type names: the namespace
+//! and type name are separated by `.`, nested types are separated from their
+//! declaring type by `/` (`+` is accepted as well), and generic types carry
+//! their arity as a suffix, e.g.
+//! ``System.Collections.Generic.Dictionary`2/Enumerator``.
+//!
+//! [`MetadataIndex`] also finds the compiled code of methods, which is
+//! recorded per image and per method instantiation.
+
+use crate::global_metadata::{FieldIndex, Il2CppMethodDefinition, MethodIndex, TypeDefinitionIndex};
+use crate::runtime_metadata::{Il2CppCodeGenModule, Il2CppGenericClass, Il2CppType, TypeData};
+use crate::type_name::{TypeNameFormatter, TypeNameStyle};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// A lazily built index for finding metadata items by name.
+///
+/// The type name maps are built the first time they are needed. Member
+/// lookups scan only the members of the given type and do not allocate.
+pub struct MetadataIndex<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+    types_by_full_name: OnceLock<HashMap<String, Vec<TypeDefinitionIndex>>>,
+    types_by_name: OnceLock<HashMap<&'md str, Vec<TypeDefinitionIndex>>>,
+    /// The code gen module of the image of every type definition.
+    type_modules: OnceLock<Vec<Option<&'md Il2CppCodeGenModule<'rmd>>>>,
+    /// The entry of the generic method pointer table for every method spec.
+    method_spec_pointers: OnceLock<HashMap<usize, u64>>,
+}
+
+impl<'md, 'gmd, 'rmd> MetadataIndex<'md, 'gmd, 'rmd> {
+    pub fn new(metadata: &'md Metadata<'gmd, 'rmd>) -> Self {
+        Self {
+            metadata,
+            types_by_full_name: OnceLock::new(),
+            types_by_name: OnceLock::new(),
+            type_modules: OnceLock::new(),
+            method_spec_pointers: OnceLock::new(),
+        }
+    }
+
+    pub fn metadata(&self) -> &'md Metadata<'gmd, 'rmd> {
+        self.metadata
+    }
+
+    fn types_by_full_name(&self) -> &HashMap<String, Vec<TypeDefinitionIndex>> {
+        self.types_by_full_name.get_or_init(|| {
+            let type_defs = self.metadata.global_metadata.type_definitions.as_vec();
+            let mut map: HashMap<String, Vec<TypeDefinitionIndex>> = HashMap::with_capacity(type_defs.len());
+            // Keys use the reflection syntax, with `+` between nested types.
+            let formatter = TypeNameFormatter::new(self.metadata, TypeNameStyle::Reflection);
+            for i in 0..type_defs.len() {
+                let idx = TypeDefinitionIndex::new(i as u32);
+                map.entry(formatter.format_type_definition(idx)).or_default().push(idx);
+            }
+            map
+        })
+    }
+
+    fn types_by_name(&self) -> &HashMap<&'md str, Vec<TypeDefinitionIndex>> {
+        self.types_by_name.get_or_init(|| {
+            let gm = &self.metadata.global_metadata;
+            let mut map: HashMap<&'md str, Vec<TypeDefinitionIndex>> = HashMap::new();
+            for (i, ty) in gm.type_definitions.as_vec().iter().enumerate() {
+                let name = &gm.string[ty.name_index];
+                map.entry(name).or_default().push(TypeDefinitionIndex::new(i as u32));
+            }
+            map
+        })
+    }
+
+    fn type_modules(&self) -> &[Option<&'md Il2CppCodeGenModule<'rmd>>] {
+        self.type_modules.get_or_init(|| {
+            let gm = &self.metadata.global_metadata;
+            let modules: HashMap<&str, &'md Il2CppCodeGenModule<'rmd>> = self
+                .metadata
+                .runtime_metadata
+                .code_registration
+                .code_gen_modules
+                .iter()
+                .map(|module| (module.name, module))
+                .collect();
+            let mut type_modules = vec![None; gm.type_definitions.len()];
+            // Images are visited in reverse so that the first image wins if
+            // the type ranges of malformed images overlap.
+            for image in gm.images.as_vec().iter().rev() {
+                let module = gm.string.get(image.name_index).ok().and_then(|name| modules.get(name)).copied();
+                let start = (image.type_start.index() as usize).min(type_modules.len());
+                let end = start.saturating_add(image.type_count as usize).min(type_modules.len());
+                type_modules[start..end].fill(module);
+            }
+            type_modules
+        })
+    }
+
+    fn method_spec_pointers(&self) -> &HashMap<usize, u64> {
+        self.method_spec_pointers.get_or_init(|| {
+            let runtime_metadata = &self.metadata.runtime_metadata;
+            let pointers = &runtime_metadata.code_registration.generic_method_pointers;
+            let mut map = HashMap::new();
+            for entry in &runtime_metadata.metadata_registration.generic_method_table {
+                let pointer = pointers.get(entry.indices.method_index as usize).copied().unwrap_or(0);
+                map.entry(entry.generic_method_index as usize).or_insert(pointer);
+            }
+            map
+        })
+    }
+
+    /// The code gen module of the image that defines the type definition
+    /// `ty`, which holds the code of the type's methods.
+    pub fn code_gen_module(&self, ty: TypeDefinitionIndex) -> Option<&'md Il2CppCodeGenModule<'rmd>> {
+        self.type_modules().get(ty.index() as usize).copied().flatten()
+    }
+
+    /// The address of the compiled code of `method`. See
+    /// [`Il2CppMethodDefinition::method_pointer()`].
+    pub fn method_pointer(&self, method: &Il2CppMethodDefinition) -> Option<u64> {
+        let module = self.code_gen_module(method.declaring_type)?;
+        let rid = method.token.rid() as usize;
+        match *module.method_pointers.get(rid.checked_sub(1)?)? {
+            0 => None,
+            ptr => Some(ptr),
+        }
+    }
+
+    /// The address of the compiled code of a method instantiation.
+    ///
+    /// Index into the
+    /// [`Il2CppMetadataRegistration::method_specs`](crate::runtime_metadata::Il2CppMetadataRegistration::method_specs)
+    /// field.
+    pub fn method_spec_pointer(&self, method_spec: usize) -> Option<u64> {
+        match *self.method_spec_pointers().get(&method_spec)? {
+            0 => None,
+            ptr => Some(ptr),
+        }
+    }
+
+    /// Finds a type by its fully qualified name, such as
+    /// `UnityEngine.Transform` or ``System.Collections.Generic.List`1``.
+    ///
+    /// If types with the same name exist in multiple assemblies, the first one
+    /// is returned. Use [`MetadataIndex::find_types()`] to get all of them.
+    pub fn find_type(&self, full_name: &str) -> Option<TypeDefinitionIndex> {
+        self.find_types(full_name).first().copied()
+    }
+
+    /// Finds all types with the fully qualified name `full_name`.
+    pub fn find_types(&self, full_name: &str) -> &[TypeDefinitionIndex] {
+        let map = self.types_by_full_name();
+        let found = match full_name.contains('/') {
+            true => map.get(&full_name.replace('/', "+")),
+            false => map.get(full_name),
+        };
+        found.map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Finds all types with the unqualified name `name`, regardless of
+    /// namespace or declaring type.
+    pub fn find_types_by_name(&self, name: &str) -> &[TypeDefinitionIndex] {
+        self.types_by_name()
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Finds all methods named `name` declared by `ty`.
+    pub fn find_methods<'a>(&'a self, ty: TypeDefinitionIndex, name: &'a str) -> impl Iterator<Item = MethodIndex> + 'a {
+        let gm = &self.metadata.global_metadata;
+        let ty_def = gm.type_definitions.get(ty);
+        let start = ty_def.map_or(0, |ty_def| ty_def.method_start.index());
+        let count = ty_def.map_or(0, |ty_def| ty_def.method_count as u32);
+        (start..start.checked_add(count).unwrap_or(start))
+            .map(MethodIndex::new)
+            .filter(move |&idx| gm.methods.get(idx).is_some_and(|method| &gm.string[method.name_index] == name))
+    }
+
+    /// Finds a method named `name` declared by `ty`.
+    ///
+    /// If `param_count` is given, only overloads with that many parameters are
+    /// considered.
+    pub fn find_method(&self, ty: TypeDefinitionIndex, name: &str, param_count: Option<usize>) -> Option<MethodIndex> {
+        let methods = &self.metadata.global_metadata.methods;
+        self.find_methods(ty, name).find(|&idx| match param_count {
+            Some(count) => methods[idx].parameter_count as usize == count,
+            None => true,
+        })
+    }
+
+    /// Finds the overload of the method named `name` declared by `ty` whose
+    /// parameters have the types `param_types`.
+    ///
+    /// Types are compared by what they refer to rather than by their index,
+    /// and parameter attributes such as `out` are ignored. The type of a type
+    /// definition is found with
+    /// [`Il2CppTypeDefinition::byval_type_index`](crate::global_metadata::Il2CppTypeDefinition::byval_type_index).
+    pub fn find_method_by_params(&self, ty: TypeDefinitionIndex, name: &str, param_types: &[&Il2CppType]) -> Option<MethodIndex> {
+        let metadata = self.metadata;
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+        self.find_methods(ty, name).find(|&idx| {
+            let Some(params) = metadata.global_metadata.methods.get(idx).and_then(|method| method.parameters(metadata)) else {
+                return false;
+            };
+            params.len() == param_types.len()
+                && params.iter().zip(param_types).all(|(param, &expected)| {
+                    types
+                        .get(param.type_index as usize)
+                        .is_some_and(|ty| same_type(metadata, ty, expected, 0))
+                })
+        })
+    }
+
+    /// Finds a field named `name` declared by `ty`.
+    pub fn find_field(&self, ty: TypeDefinitionIndex, name: &str) -> Option<FieldIndex> {
+        let gm = &self.metadata.global_metadata;
+        let ty_def = gm.type_definitions.get(ty)?;
+        let start = ty_def.field_start.index();
+        (start..start.checked_add(ty_def.field_count as u32)?)
+            .map(FieldIndex::new)
+            .find(|&idx| gm.fields.get(idx).is_some_and(|field| &gm.string[field.name_index] == name))
+    }
+}
+
+/// Whether `a` and `b` are the same type, following the indices of element
+/// types and generic arguments. Attributes are not compared.
+fn same_type(metadata: &Metadata, a: &Il2CppType, b: &Il2CppType, depth: usize) -> bool {
+    let mr = &metadata.runtime_metadata.metadata_registration;
+    if a.ty != b.ty || a.byref != b.byref || depth > MAX_TYPE_DEPTH {
+        return false;
+    }
+    let same_index = |a: usize, b: usize| {
+        a == b
+            || match (mr.types.get(a), mr.types.get(b)) {
+                (Some(a), Some(b)) => same_type(metadata, a, b, depth + 1),
+                _ => false,
+            }
+    };
+    match (a.data, b.data) {
+        (TypeData::TypeIndex(a), TypeData::TypeIndex(b)) => same_index(a, b),
+        (TypeData::ArrayType(a), TypeData::ArrayType(b)) => match (mr.array_types.get(a), mr.array_types.get(b)) {
+            (Some(a), Some(b)) => a.rank == b.rank && same_index(a.elem_ty, b.elem_ty),
+            _ => false,
+        },
+        (TypeData::GenericClassIndex(a), TypeData::GenericClassIndex(b)) => {
+            let (Some(a), Some(b)) = (mr.generic_classes.get(a), mr.generic_classes.get(b)) else {
+                return false;
+            };
+            let args = |class: &Il2CppGenericClass| {
+                class.context.class_inst_idx.and_then(|idx| mr.generic_insts.get(idx)).map_or(&[][..], |inst| &inst.types)
+            };
+            let (a_args, b_args) = (args(a), args(b));
+            same_index(a.type_index, b.type_index)
+                && a_args.len() == b_args.len()
+                && a_args.iter().zip(b_args).all(|(&a, &b)| same_index(a, b))
+        }
+        (a, b) => a == b,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{Il2CppImageDefinition, Il2CppParameterDefinition, ParameterIndex, StringIndex, Token};
+    use crate::runtime_metadata::{GenericMethodIndices, Il2CppGenericMethodFunctionsDefinitions, Il2CppTypeEnum};
+    use crate::test_util;
+
+    fn module(name: &'static str, method_pointers: Vec<u64>) -> Il2CppCodeGenModule<'static> {
+        Il2CppCodeGenModule {
+            name,
+            method_pointers,
+            adjustor_thunks: Vec::new(),
+            invoker_indices: Vec::new(),
+            rgctx_ranges: Vec::new(),
+            rgctxs: Vec::new(),
+        }
+    }
+
+    fn generic_method(method_spec: u32, pointer: u32) -> Il2CppGenericMethodFunctionsDefinitions {
+        Il2CppGenericMethodFunctionsDefinitions {
+            generic_method_index: method_spec,
+            indices: GenericMethodIndices {
+                method_index: pointer,
+                invoker_index: 0,
+                adjustor_thunk_index: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn method_pointers() {
+        // Offsets: 1 `A.dll`, 7 `B.dll`
+        let mut metadata = test_util::metadata("\0A.dll\0B.dll\0");
+        let image = |name: u32, type_start: u32| {
+            let mut image: Il2CppImageDefinition = test_util::zeroed();
+            image.name_index = StringIndex::new(name);
+            image.type_start = TypeDefinitionIndex::new(type_start);
+            image.type_count = 1;
+            image
+        };
+        let method = |declaring_type: u32, rid: u32| {
+            let mut method: Il2CppMethodDefinition = test_util::zeroed();
+            method.declaring_type = TypeDefinitionIndex::new(declaring_type);
+            method.token = Token(0x0600_0000 | rid);
+            method
+        };
+        let gm = &mut metadata.global_metadata;
+        gm.images = vec![image(1, 0), image(7, 1)].into();
+        gm.type_definitions = vec![test_util::type_definition(), test_util::type_definition()].into();
+        let runtime_metadata = &mut metadata.runtime_metadata;
+        runtime_metadata.code_registration.code_gen_modules =
+            vec![module("B.dll", vec![0xB1, 0]), module("A.dll", vec![0xA1])];
+        runtime_metadata.code_registration.generic_method_pointers = vec![0, 0x51, 0x52];
+        runtime_metadata.metadata_registration.generic_method_table =
+            vec![generic_method(3, 1), generic_method(3, 2), generic_method(4, 0)];
+
+        let index = MetadataIndex::new(&metadata);
+        assert_eq!(index.method_pointer(&method(0, 1)), Some(0xA1));
+        assert_eq!(index.method_pointer(&method(1, 1)), Some(0xB1));
+        assert_eq!(index.method_pointer(&method(1, 2)), None);
+        assert_eq!(index.method_pointer(&method(1, 3)), None);
+        assert_eq!(index.method_pointer(&method(2, 1)), None);
+        // The first entry of a method spec is used.
+        assert_eq!(index.method_spec_pointer(3), Some(0x51));
+        assert_eq!(index.method_spec_pointer(4), None);
+        assert_eq!(index.method_spec_pointer(5), None);
+    }
+
+    #[test]
+    fn methods_by_param_types() {
+        let mut metadata = test_util::metadata("\0M\0");
+        let int = test_util::ty(Il2CppTypeEnum::I4, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0)));
+        let by_ref = |attrs: u16| Il2CppType { byref: true, attrs, ..int };
+        metadata.runtime_metadata.metadata_registration.types = vec![
+            int,
+            test_util::ty(Il2CppTypeEnum::Szarray, TypeData::TypeIndex(0)),
+            test_util::ty(Il2CppTypeEnum::Szarray, TypeData::TypeIndex(0)),
+            // `out int`
+            by_ref(0x2),
+            by_ref(0),
+        ];
+        let gm = &mut metadata.global_metadata;
+        let method = |param: u32| {
+            let mut method: Il2CppMethodDefinition = test_util::zeroed();
+            method.name_index = StringIndex::new(1);
+            method.parameter_start = ParameterIndex::new(param);
+            method.parameter_count = 1;
+            method
+        };
+        gm.methods = vec![method(0), method(1)].into();
+        gm.parameters = [1, 3]
+            .into_iter()
+            .map(|type_index| {
+                let mut param: Il2CppParameterDefinition = test_util::zeroed();
+                param.type_index = type_index;
+                param
+            })
+            .collect::<Vec<_>>()
+            .into();
+        let mut ty_def = test_util::type_definition();
+        ty_def.method_count = 2;
+        gm.type_definitions = vec![ty_def].into();
+
+        let index = MetadataIndex::new(&metadata);
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+        let ty = TypeDefinitionIndex::new(0);
+        assert_eq!(index.find_method_by_params(ty, "M", &[&types[2]]), Some(MethodIndex::new(0)));
+        assert_eq!(index.find_method_by_params(ty, "M", &[&types[4]]), Some(MethodIndex::new(1)));
+        assert_eq!(index.find_method_by_params(ty, "M", &[&types[0]]), None);
+        assert_eq!(index.find_method_by_params(ty, "M", &[]), None);
+    }
+}
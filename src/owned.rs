@@ -0,0 +1,107 @@
+//! Metadata that owns its underlying data.
+//!
+//! [`Metadata`] borrows the buffers it was parsed from, which makes it hard to
+//! store in long-lived application state. [`OwnedMetadata`] keeps the buffers
+//! and the parsed metadata together, and can be shared between threads in an
+//! [`Arc`](std::sync::Arc).
+
+use crate::global_metadata::transform::{self, MetadataTransform};
+use crate::{Metadata, MetadataParseError};
+use self_cell::self_cell;
+use std::fs;
+use std::path::Path;
+
+struct Buffers {
+    global_metadata: Vec<u8>,
+    binary: Vec<u8>,
+}
+
+type BorrowedMetadata<'a> = Metadata<'a, 'a>;
+
+self_cell!(
+    struct MetadataCell {
+        owner: Buffers,
+
+        #[covariant]
+        dependent: BorrowedMetadata,
+    }
+);
+
+/// A [`Metadata`] that owns the global metadata and binary it was parsed from.
+///
+/// Use [`OwnedMetadata::metadata()`] to access the parsed metadata.
+pub struct OwnedMetadata {
+    cell: MetadataCell,
+}
+
+impl OwnedMetadata {
+    /// Parses metadata from owned global metadata and binary data.
+    pub fn new(global_metadata: Vec<u8>, binary: Vec<u8>) -> Result<Self, MetadataParseError> {
+        let buffers = Buffers {
+            global_metadata,
+            binary,
+        };
+        let cell = MetadataCell::try_new(buffers, |buffers| {
+            Metadata::parse(&buffers.global_metadata, &buffers.binary)
+        })?;
+        Ok(Self { cell })
+    }
+
+    /// Like [`OwnedMetadata::new()`], but undoes the obfuscation of the global
+    /// metadata with `transform` first.
+    ///
+    /// See [`Metadata::parse_with_transform()`] for more information.
+    pub fn with_transform<T: MetadataTransform>(
+        mut global_metadata: Vec<u8>,
+        binary: Vec<u8>,
+        transform: &T,
+    ) -> Result<Self, MetadataParseError> {
+        transform::apply(&mut global_metadata, transform)?;
+        Self::new(global_metadata, binary)
+    }
+
+    /// Reads and parses the global metadata and binary at the given paths.
+    pub fn open<M, B>(global_metadata_path: M, binary_path: B) -> Result<Self, MetadataParseError>
+    where
+        M: AsRef<Path>,
+        B: AsRef<Path>,
+    {
+        let global_metadata = fs::read(global_metadata_path)?;
+        let binary = fs::read(binary_path)?;
+        Self::new(global_metadata, binary)
+    }
+
+    /// Parses the files extracted from an application archive.
+    #[cfg(feature = "archive")]
+    pub fn from_archive(contents: crate::archive::ArchiveContents) -> Result<Self, MetadataParseError> {
+        Self::new(contents.global_metadata, contents.binary)
+    }
+
+    /// The parsed metadata.
+    pub fn metadata(&self) -> &Metadata<'_, '_> {
+        self.cell.borrow_dependent()
+    }
+
+    /// The raw global metadata data.
+    ///
+    /// If a transform was used, this is the data after it was applied.
+    pub fn global_metadata_data(&self) -> &[u8] {
+        &self.cell.borrow_owner().global_metadata
+    }
+
+    /// The raw binary data.
+    pub fn binary_data(&self) -> &[u8] {
+        &self.cell.borrow_owner().binary
+    }
+}
+
+impl std::fmt::Debug for OwnedMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OwnedMetadata").finish_non_exhaustive()
+    }
+}
+
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<OwnedMetadata>();
+};
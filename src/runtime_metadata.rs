@@ -3,7 +3,9 @@ pub mod elf;
 
 use binread::BinRead;
 use crate::global_metadata::{Token, TypeDefinitionIndex, GenericParameterIndex, MethodIndex};
+use crate::type_name::{TypeNameFormatter, TypeNameStyle};
 use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
 
 /// Defined at `il2cpp-class-internals:570`
 #[derive(BinRead, Debug)]
@@ -170,7 +172,8 @@ pub enum Il2CppTypeEnum {
 }
 
 impl Il2CppTypeEnum {
-    fn from_ty(ty: u8) -> Option<Self> {
+    /// Converts an element type from ECMA-335 II.23.1.16.
+    pub fn from_ty(ty: u8) -> Option<Self> {
         Some(match ty {
             0x00 => Il2CppTypeEnum::End,
             0x01 => Il2CppTypeEnum::Void,
@@ -211,8 +214,56 @@ impl Il2CppTypeEnum {
             _ => return None,
         })
     }
+
+    /// The element type from ECMA-335 II.23.1.16.
+    pub fn to_ty(self) -> u8 {
+        match self {
+            Il2CppTypeEnum::End => 0x00,
+            Il2CppTypeEnum::Void => 0x01,
+            Il2CppTypeEnum::Boolean => 0x02,
+            Il2CppTypeEnum::Char => 0x03,
+            Il2CppTypeEnum::I1 => 0x04,
+            Il2CppTypeEnum::U1 => 0x05,
+            Il2CppTypeEnum::I2 => 0x06,
+            Il2CppTypeEnum::U2 => 0x07,
+            Il2CppTypeEnum::I4 => 0x08,
+            Il2CppTypeEnum::U4 => 0x09,
+            Il2CppTypeEnum::I8 => 0x0a,
+            Il2CppTypeEnum::U8 => 0x0b,
+            Il2CppTypeEnum::R4 => 0x0c,
+            Il2CppTypeEnum::R8 => 0x0d,
+            Il2CppTypeEnum::String => 0x0e,
+            Il2CppTypeEnum::Ptr => 0x0f,
+            Il2CppTypeEnum::Byref => 0x10,
+            Il2CppTypeEnum::Valuetype => 0x11,
+            Il2CppTypeEnum::Class => 0x12,
+            Il2CppTypeEnum::Var => 0x13,
+            Il2CppTypeEnum::Array => 0x14,
+            Il2CppTypeEnum::Genericinst => 0x15,
+            Il2CppTypeEnum::Typedbyref => 0x16,
+            Il2CppTypeEnum::I => 0x18,
+            Il2CppTypeEnum::U => 0x19,
+            Il2CppTypeEnum::Fnptr => 0x1b,
+            Il2CppTypeEnum::Object => 0x1c,
+            Il2CppTypeEnum::Szarray => 0x1d,
+            Il2CppTypeEnum::Mvar => 0x1e,
+            Il2CppTypeEnum::CmodReqd => 0x1f,
+            Il2CppTypeEnum::CmodOpt => 0x20,
+            Il2CppTypeEnum::Internal => 0x21,
+            Il2CppTypeEnum::Modifier => 0x40,
+            Il2CppTypeEnum::Sentinel => 0x41,
+            Il2CppTypeEnum::Pinned => 0x45,
+            Il2CppTypeEnum::Enum => 0x55,
+        }
+    }
 }
 
+/// Index into the [`Il2CppMetadataRegistration::generic_classes`] field.
+pub type GenericClassIndex = usize;
+
+/// Index into the [`Il2CppMetadataRegistration::method_specs`] field.
+pub type MethodSpecIndex = usize;
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum TypeData {
     TypeDefinitionIndex(TypeDefinitionIndex),
@@ -230,7 +281,8 @@ pub enum TypeData {
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct Il2CppType {
     pub data: TypeData,
-    /// Param attributes or field flags. See `il2cpp-tabledef.h`
+    /// Param attributes or field flags. See `il2cpp-tabledef.h`,
+    /// [`Il2CppType::field_attributes()`] and [`Il2CppType::param_attributes()`]
     pub attrs: u16,
     pub ty: Il2CppTypeEnum,
     pub byref: bool,
@@ -240,10 +292,57 @@ pub struct Il2CppType {
 }
 
 impl Il2CppType {
+    /// Returns the type definition this type refers to.
+    ///
+    /// For generic instances, this is the generic type definition. Returns
+    /// `None` for types without a definition, such as pointers, arrays and
+    /// generic parameters.
+    pub fn type_definition_index(&self, metadata: &Metadata) -> Option<TypeDefinitionIndex> {
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        let idx = match self.data {
+            TypeData::GenericClassIndex(idx) => {
+                let generic_class = mr.generic_classes.get(idx)?;
+                match mr.types.get(generic_class.type_index)?.data {
+                    TypeData::TypeDefinitionIndex(idx) => idx,
+                    _ => return None,
+                }
+            }
+            TypeData::TypeDefinitionIndex(idx) => idx,
+            _ => return None,
+        };
+        metadata.global_metadata.type_definitions.get(idx).map(|_| idx)
+    }
+
+    /// A name for this type, such as ``System.Collections.Generic.List`1<System.Int32>``.
+    /// Nested types are separated from their declaring type by `.`, like in
+    /// [`Il2CppTypeDefinition::full_name()`](crate::global_metadata::Il2CppTypeDefinition::full_name).
+    ///
+    /// Use a [`TypeNameFormatter`](crate::type_name::TypeNameFormatter) to
+    /// control how the name is rendered.
     pub fn full_name(&self, metadata: &Metadata) -> String {
+        self.full_name_at(metadata, 0)
+    }
+
+    /// Like [`Il2CppType::full_name`], but writes `?` for indices that are
+    /// out of range and for types nested more than [`MAX_TYPE_DEPTH`] deep.
+    fn full_name_at(&self, metadata: &Metadata, depth: usize) -> String {
+        let mut name = self.full_name_inner(metadata, depth);
+        if self.byref {
+            name.push('&');
+        }
+        name
+    }
+
+    fn full_name_inner(&self, metadata: &Metadata, depth: usize) -> String {
         let mr = &metadata.runtime_metadata.metadata_registration;
         let types = &mr.types;
-        let type_defs = &metadata.global_metadata.type_definitions;
+        if depth > MAX_TYPE_DEPTH {
+            return "?".into();
+        }
+        let type_name = |ty_idx: usize| match types.get(ty_idx) {
+            Some(ty) => ty.full_name_at(metadata, depth + 1),
+            None => "?".into(),
+        };
 
         String::from(match self.ty {
             Il2CppTypeEnum::Void => "System.Void",
@@ -255,36 +354,51 @@ impl Il2CppType {
             Il2CppTypeEnum::U2 => "System.UInt16",
             Il2CppTypeEnum::I4 => "System.Int32",
             Il2CppTypeEnum::U4 => "System.UInt32",
-            Il2CppTypeEnum::U8 => "System.Int64",
-            Il2CppTypeEnum::I8 => "System.UInt64",
-            Il2CppTypeEnum::R4 => "System.Float",
+            Il2CppTypeEnum::I8 => "System.Int64",
+            Il2CppTypeEnum::U8 => "System.UInt64",
+            Il2CppTypeEnum::R4 => "System.Single",
             Il2CppTypeEnum::R8 => "System.Double",
             Il2CppTypeEnum::String => "System.String",
             Il2CppTypeEnum::Typedbyref => "System.TypedReference",
-            Il2CppTypeEnum::I => "System.IntPtr",
+            Il2CppTypeEnum::I | Il2CppTypeEnum::Fnptr => "System.IntPtr",
             Il2CppTypeEnum::U => "System.UIntPtr",
             Il2CppTypeEnum::Object => "System.Object",
             Il2CppTypeEnum::Sentinel => "<<SENTINEL>>",
             _ => return match (self.ty, self.data) {
-                (Il2CppTypeEnum::Var | Il2CppTypeEnum::Mvar, TypeData::GenericParameterIndex(idx)) => metadata.global_metadata.generic_parameters[idx].name(metadata).to_string(),
-                (Il2CppTypeEnum::Ptr, TypeData::TypeIndex(ty_idx)) => format!("{}*", types[ty_idx].full_name(metadata)),
-                (Il2CppTypeEnum::Szarray, TypeData::TypeIndex(ty_idx)) => format!("{}[]", types[ty_idx].full_name(metadata)),
+                (Il2CppTypeEnum::Var | Il2CppTypeEnum::Mvar, TypeData::GenericParameterIndex(idx)) => {
+                    let gm = &metadata.global_metadata;
+                    let name = gm.generic_parameters.get(idx).and_then(|param| gm.string.get(param.name_index).ok());
+                    name.unwrap_or("?").to_string()
+                }
+                (Il2CppTypeEnum::Ptr, TypeData::TypeIndex(ty_idx)) => format!("{}*", type_name(ty_idx)),
+                (Il2CppTypeEnum::Szarray, TypeData::TypeIndex(ty_idx)) => format!("{}[]", type_name(ty_idx)),
                 (Il2CppTypeEnum::Array, TypeData::ArrayType(arr_ty_idx)) => {
-                    let arr_type = &mr.array_types[arr_ty_idx];
-                    let mut str = types[arr_type.elem_ty].full_name(metadata);
+                    let Some(arr_type) = mr.array_types.get(arr_ty_idx) else {
+                        return "?[]".into();
+                    };
+                    let mut str = type_name(arr_type.elem_ty);
                     str.push('[');
-                    for _ in 0..arr_type.rank - 1 {
+                    for _ in 1..arr_type.rank {
                         str.push(',');
                     }
                     str.push(']');
                     str
                 },
-                (Il2CppTypeEnum::Class | Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(ty_idx)) => type_defs[ty_idx].full_name(metadata, false),
+                (Il2CppTypeEnum::Class | Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(ty_idx)) => {
+                    TypeNameFormatter::new(metadata, TypeNameStyle::CSharp)
+                        .generic_arity(true)
+                        .format_type_definition(ty_idx)
+                }
                 (Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(gc)) => {
-                    let gc = &mr.generic_classes[gc];
-                    let inst = &mr.generic_insts[gc.context.class_inst_idx.unwrap()];
-                    let generic_args = inst.types.iter().map(|ty| types[*ty].full_name(metadata)).collect::<Vec<_>>().join(", ");
-                    format!("{}<{}>", types[gc.type_index].full_name(metadata), generic_args)
+                    let Some(gc) = mr.generic_classes.get(gc) else {
+                        return "?".into();
+                    };
+                    let inst_types = match gc.context.class_inst_idx.and_then(|idx| mr.generic_insts.get(idx)) {
+                        Some(inst) => inst.types.as_slice(),
+                        None => &[],
+                    };
+                    let generic_args = inst_types.iter().map(|&ty| type_name(ty)).collect::<Vec<_>>().join(", ");
+                    format!("{}<{}>", type_name(gc.type_index), generic_args)
                 }
                 _ => format!("({:?}?)", self.ty)
             }
@@ -401,8 +515,69 @@ pub struct Il2CppMetadataRegistration {
     // pub metadata_usages: ??
 }
 
+/// The virtual addresses of the registration structures and the arrays they
+/// point to.
+#[derive(Debug, Clone, Default)]
+pub struct RegistrationAddresses {
+    /// The address of `g_CodeRegistration`.
+    pub code_registration: u64,
+    /// The address of `g_MetadataRegistration`.
+    pub metadata_registration: u64,
+    /// The addresses of the arrays, with the name of the registration field
+    /// that points to them, such as `invokerPointers`. Null pointers are left
+    /// out.
+    pub code_registration_arrays: Vec<(&'static str, u64)>,
+    pub metadata_registration_arrays: Vec<(&'static str, u64)>,
+}
+
+/// A loadable segment of the binary, from its program header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub address: u64,
+    pub file_offset: u64,
+    /// The number of bytes of the segment stored in the file. The rest of the
+    /// segment, such as `.bss`, is zero-initialized when loaded.
+    pub file_size: u64,
+}
+
 #[derive(Debug)]
 pub struct RuntimeMetadata<'data> {
     pub code_registration: Il2CppCodeRegistration<'data>,
     pub metadata_registration: Il2CppMetadataRegistration,
+    pub registration_addresses: RegistrationAddresses,
+    /// The segments of the binary the metadata was read from.
+    pub segments: Vec<Segment>,
+}
+
+impl RuntimeMetadata<'_> {
+    /// Maps a virtual address to its offset in the binary, or `None` if the
+    /// address is not backed by the file.
+    pub fn file_offset(&self, vaddr: u64) -> Option<u64> {
+        self.segments.iter().find_map(|segment| {
+            let offset = vaddr.checked_sub(segment.address)?;
+            (offset < segment.file_size).then(|| segment.file_offset + offset)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn full_names_of_malformed_types() {
+        let mut metadata = test_util::metadata("");
+        metadata.runtime_metadata.metadata_registration.types = vec![
+            test_util::ty(Il2CppTypeEnum::Ptr, TypeData::TypeIndex(0)),
+            test_util::ty(Il2CppTypeEnum::Szarray, TypeData::TypeIndex(9)),
+            test_util::ty(Il2CppTypeEnum::Array, TypeData::ArrayType(0)),
+            test_util::ty(Il2CppTypeEnum::Var, TypeData::GenericParameterIndex(GenericParameterIndex::new(0))),
+            test_util::ty(Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(0)),
+        ];
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+        let names: Vec<_> = types.iter().map(|ty| ty.full_name(&metadata)).collect();
+        assert_eq!(names[0], format!("?{}", "*".repeat(MAX_TYPE_DEPTH + 1)));
+        assert_eq!(names[1..], ["?[]", "?[]", "?", "?"]);
+    }
 }
@@ -40,12 +40,45 @@ pub enum Il2CppBinaryError {
     #[error("could not find indirect branch in Runtime::Init")]
     MissingBlr,
 
+    #[error("could not find call number {n} starting at {vaddr:#016x}")]
+    MissingBl { vaddr: u64, n: usize },
+
     #[error("could not find registration function")]
     MissingRegistration,
 
     #[error("invalid Il2CppType with type {0}")]
     InvalidType(u8),
 
+    #[error("file offset {offset:#x} with length {len:#x} is out of bounds")]
+    OutOfBounds { offset: u64, len: usize },
+
+    #[error("unterminated string at file offset {0:#x}")]
+    UnterminatedString(u64),
+
+    #[error("{table}[{index}] points to unknown {kind} at {vaddr:#016x}")]
+    UnknownPointer {
+        table: &'static str,
+        index: usize,
+        kind: &'static str,
+        vaddr: u64,
+    },
+
+    #[error("{table}[{index}] references type definition {type_definition} which does not exist")]
+    UnknownTypeDefinition {
+        table: &'static str,
+        index: usize,
+        type_definition: u32,
+    },
+
+    #[error("could not read {table}[{index}] at {vaddr:#016x}")]
+    Table {
+        table: &'static str,
+        index: usize,
+        vaddr: u64,
+        #[source]
+        source: Box<Il2CppBinaryError>,
+    },
+
     #[error(transparent)]
     Io(#[from] io::Error),
 
@@ -61,20 +94,65 @@ pub enum Il2CppBinaryError {
 
 type Result<T> = std::result::Result<T, Il2CppBinaryError>;
 
+/// Upper bound on the number of elements preallocated for an array whose
+/// length was read from the binary.
+const MAX_PREALLOC: usize = 0x10000;
+
+/// The maximum number of instructions to scan when looking for a call.
+const MAX_BL_SEARCH: usize = 200;
+
+/// Returns the length of the null-terminated string at `offset`. If there is
+/// no null terminator, this is the length of the rest of the data.
 pub fn strlen(data: &[u8], offset: usize) -> usize {
-    let mut len = 0;
-    while data[offset + len] != 0 {
-        len += 1;
-    }
-    len
+    let data = data.get(offset..).unwrap_or_default();
+    data.iter().position(|&b| b == 0).unwrap_or(data.len())
 }
 
 pub fn get_str(data: &[u8], offset: usize) -> Result<&str> {
     let len = strlen(data, offset);
+    if data.get(offset + len) != Some(&0) {
+        return Err(Il2CppBinaryError::UnterminatedString(offset as u64));
+    }
     let str = str::from_utf8(&data[offset..offset + len])?;
     Ok(str)
 }
 
+/// Returns `len` bytes of `data` starting at the file offset `offset`.
+fn get_bytes(data: &[u8], offset: u64, len: usize) -> Result<&[u8]> {
+    usize::try_from(offset)
+        .ok()
+        .and_then(|start| data.get(start..start.checked_add(len)?))
+        .ok_or(Il2CppBinaryError::OutOfBounds { offset, len })
+}
+
+/// Attaches the location of a table entry to an error.
+fn table_context<T>(table: &'static str, index: usize, vaddr: u64, res: Result<T>) -> Result<T> {
+    res.map_err(|source| Il2CppBinaryError::Table {
+        table,
+        index,
+        vaddr,
+        source: Box::new(source),
+    })
+}
+
+/// Looks up the index of the table entry at `vaddr`.
+fn lookup_ptr(
+    map: &HashMap<u64, usize>,
+    vaddr: u64,
+    kind: &'static str,
+    table: &'static str,
+    index: usize,
+) -> Result<usize> {
+    map.get(&vaddr)
+        .copied()
+        .ok_or(Il2CppBinaryError::UnknownPointer {
+            table,
+            index,
+            kind,
+            vaddr,
+        })
+}
+
 pub fn addr_in_bss(elf: &Elf, vaddr: u64) -> bool {
     match elf.section_by_name(".bss") {
         Some(bss) => bss.address() <= vaddr && vaddr - bss.address() < bss.size(),
@@ -96,7 +174,7 @@ pub fn vaddr_conv(elf: &Elf, vaddr: u64) -> Result<u64> {
     Err(Il2CppBinaryError::VAddrConv(vaddr))
 }
 
-fn analyze_reg_rel(elf: &Elf, elf_rel: &[u8], instructions: &[Instruction]) -> HashMap<Reg, u64> {
+fn analyze_reg_rel(elf: &Elf, elf_rel: &[u8], instructions: &[Instruction]) -> Result<HashMap<Reg, u64>> {
     let mut map = HashMap::new();
     for ins in instructions {
         match (ins.op(), ins.operands()) {
@@ -126,18 +204,15 @@ fn analyze_reg_rel(elf: &Elf, elf_rel: &[u8], instructions: &[Instruction]) -> H
                 if a != b {
                     continue;
                 }
-                map.entry(*a).and_modify(|v| {
-                    // TODO: propogate error
-                    let offset = vaddr_conv(elf, (*v as i64 + imm) as u64).unwrap();
-                    *v = (&elf_rel[offset as usize..offset as usize + 8])
-                        .read_u64::<LittleEndian>()
-                        .unwrap();
-                });
+                if let Some(v) = map.get_mut(a) {
+                    let offset = vaddr_conv(elf, v.wrapping_add_signed(*imm))?;
+                    *v = get_bytes(elf_rel, offset, 8)?.read_u64::<LittleEndian>()?;
+                }
             }
             _ => {}
         }
     }
-    map
+    Ok(map)
 }
 
 fn try_disassemble(code: &[u8], addr: u64) -> Result<Vec<Instruction>> {
@@ -146,14 +221,22 @@ fn try_disassemble(code: &[u8], addr: u64) -> Result<Vec<Instruction>> {
         .collect()
 }
 
-fn nth_bl(elf: &Elf, addr: u64, n: usize) -> Result<u64> {
+/// Disassembles the single instruction at the file offset `offset`.
+fn disassemble_one(elf: &Elf, offset: u64, addr: u64) -> Result<Instruction> {
+    let code = get_bytes(elf.data(), offset, 4)?;
+    try_disassemble(code, addr)?
+        .pop()
+        .ok_or(Il2CppBinaryError::OutOfBounds { offset, len: 4 })
+}
+
+/// Finds the target of the `n`th `bl` instruction starting from `addr`,
+/// scanning at most `limit` instructions.
+fn nth_bl(elf: &Elf, addr: u64, n: usize, limit: usize) -> Result<u64> {
     let offset = vaddr_conv(elf, addr)?;
     let mut count = 0;
 
-    for i in 0.. {
-        let offset = offset + i * 4;
-        let code = &elf.data()[offset as usize..offset as usize + 4];
-        let ins = &try_disassemble(code, addr + i * 4)?[0];
+    for i in 0..limit as u64 {
+        let ins = disassemble_one(elf, offset + i * 4, addr + i * 4)?;
         if let (Op::BL, [Operand::Label(Imm::Unsigned(target))]) = (ins.op(), ins.operands()) {
             count += 1;
             if count == n {
@@ -162,7 +245,7 @@ fn nth_bl(elf: &Elf, addr: u64, n: usize) -> Result<u64> {
         }
     }
 
-    unreachable!()
+    Err(Il2CppBinaryError::MissingBl { vaddr: addr, n })
 }
 
 /// Finds and returns the address of the first `blr` instruction it comes across starting from `addr`.
@@ -170,8 +253,7 @@ fn find_blr(elf: &Elf, addr: u64, limit: usize) -> Result<Option<(u64, Reg)>> {
     let offset = vaddr_conv(elf, addr)?;
     for i in 0..limit {
         let offset = offset + i as u64 * 4;
-        let code = &elf.data()[offset as usize..offset as usize + 4];
-        let ins = &try_disassemble(code, addr + i as u64 * 4)?[0];
+        let ins = disassemble_one(elf, offset, addr + i as u64 * 4)?;
         if let (Op::BLR, [Operand::Reg { reg, .. }]) = (ins.op(), ins.operands()) {
             return Ok(Some((offset, *reg)));
         }
@@ -207,24 +289,30 @@ fn find_registration(elf: &Elf, elf_rel: &[u8]) -> Result<(u64, u64)> {
         .find(|s| s.name() == Ok("il2cpp_init"))
         .ok_or(Il2CppBinaryError::MissingIl2CppInit)?
         .address();
-    let runtime_init = nth_bl(elf, il2cpp_init, 2)?;
+    let runtime_init = nth_bl(elf, il2cpp_init, 2, MAX_BL_SEARCH)?;
     let runtime_init_offset = vaddr_conv(elf, runtime_init)?;
 
     let (blr_offset, blr_reg) =
         find_blr(elf, runtime_init, 200)?.ok_or(Il2CppBinaryError::MissingBlr)?;
 
-    let instructions = try_disassemble(
-        &elf.data()[runtime_init_offset as usize..blr_offset as usize],
-        runtime_init,
+    let code = get_bytes(
+        elf.data(),
+        runtime_init_offset,
+        blr_offset.saturating_sub(runtime_init_offset) as usize,
     )?;
-    let regs = analyze_reg_rel(elf, &elf_rel, &instructions);
-
-    let fn_addr = vaddr_conv(elf, regs[&blr_reg])?;
-    let code = &elf.data()[fn_addr as usize..fn_addr as usize + 7 * 4];
-    let instructions = try_disassemble(code, regs[&blr_reg])?;
-    let regs = analyze_reg_rel(elf, &elf_rel, instructions.as_slice());
-
-    Ok((regs[&Reg::X0], regs[&Reg::X1]))
+    let instructions = try_disassemble(code, runtime_init)?;
+    let regs = analyze_reg_rel(elf, elf_rel, &instructions)?;
+
+    let reg_fn = *regs.get(&blr_reg).ok_or(Il2CppBinaryError::MissingRegistration)?;
+    let fn_addr = vaddr_conv(elf, reg_fn)?;
+    let code = get_bytes(elf.data(), fn_addr, 7 * 4)?;
+    let instructions = try_disassemble(code, reg_fn)?;
+    let regs = analyze_reg_rel(elf, elf_rel, instructions.as_slice())?;
+
+    match (regs.get(&Reg::X0), regs.get(&Reg::X1)) {
+        (Some(&code_registration), Some(&metadata_registration)) => Ok((code_registration, metadata_registration)),
+        _ => Err(Il2CppBinaryError::MissingRegistration),
+    }
 }
 
 struct ElfReader<'elf, 'data, 'elf_rel> {
@@ -255,7 +343,7 @@ where
     T: BinRead,
 {
     let mut cur = reader.make_cur(vaddr)?;
-    let mut vec = Vec::with_capacity(len);
+    let mut vec = Vec::with_capacity(len.min(MAX_PREALLOC));
     for _ in 0..len {
         vec.push(cur.read_le()?);
     }
@@ -272,6 +360,36 @@ where
     read_arr(reader, addr, count)
 }
 
+/// The addresses of the arrays a registration structure points to, by the
+/// name of the field that holds them.
+type ArrayAddresses = Vec<(&'static str, u64)>;
+
+/// Reads the pointer field `name` and records it in `arrays`, unless it is
+/// null.
+fn read_array_ptr(cur: &mut Cursor<&[u8]>, name: &'static str, arrays: &mut ArrayAddresses) -> Result<u64> {
+    let addr = cur.read_u64::<LittleEndian>()?;
+    if addr != 0 {
+        arrays.push((name, addr));
+    }
+    Ok(addr)
+}
+
+/// Like [`read_len_arr`], but records the address of the array in `arrays`.
+fn read_named_len_arr<T>(
+    reader: &ElfReader,
+    cur: &mut Cursor<&[u8]>,
+    name: &'static str,
+    arrays: &mut ArrayAddresses,
+) -> Result<Vec<T>>
+where
+    T: BinRead,
+{
+    let count = cur.read_u32::<LittleEndian>()? as usize;
+    let _padding = cur.read_u32::<LittleEndian>()?;
+    let addr = read_array_ptr(cur, name, arrays)?;
+    read_arr(reader, addr, count)
+}
+
 fn read_len_arr_nullable<T>(reader: &ElfReader, cur: &mut Cursor<&[u8]>) -> Result<Vec<T>>
 where
     T: BinRead + Default + Clone,
@@ -315,35 +433,40 @@ impl<'data> Il2CppCodeGenModule<'data> {
 }
 
 impl<'data> Il2CppCodeRegistration<'data> {
-    fn read(elf: &Elf<'data>, elf_rel: &[u8], addr: u64) -> Result<Self> {
+    /// Reads the structure at `addr`, recording the addresses of the arrays
+    /// it points to in `arrays`.
+    fn read(elf: &Elf<'data>, elf_rel: &[u8], addr: u64, arrays: &mut ArrayAddresses) -> Result<Self> {
         let reader = ElfReader::new(elf, elf_rel);
         let mut cur = reader.make_cur(addr)?;
 
-        let reverse_pinvoke_wrappers = read_len_arr(&reader, &mut cur)?;
+        let reverse_pinvoke_wrappers = read_named_len_arr(&reader, &mut cur, "reversePInvokeWrappers", arrays)?;
 
-        let generic_method_pointers = read_len_arr(&reader, &mut cur)?;
-        let addr = cur.read_u64::<LittleEndian>()?;
+        let generic_method_pointers = read_named_len_arr(&reader, &mut cur, "genericMethodPointers", arrays)?;
+        let addr = read_array_ptr(&mut cur, "genericAdjustorThunks", arrays)?;
         let generic_adjustor_thunks = read_arr(&reader, addr, generic_method_pointers.len())?;
 
-        let invoker_pointers = read_len_arr(&reader, &mut cur)?;
+        let invoker_pointers = read_named_len_arr(&reader, &mut cur, "invokerPointers", arrays)?;
         // unresolvedIndirectCallCount
         // unresolvedVirtualCallPointers
-        let unresolved_virtual_call_pointers: Vec<u64> = read_len_arr(&reader, &mut cur)?;
-        let _unresolved_instance_call_pointers = cur.read_u64::<LittleEndian>()?;
-        let _unresolved_static_call_pointers = cur.read_u64::<LittleEndian>()?;
+        let unresolved_virtual_call_pointers: Vec<u64> =
+            read_named_len_arr(&reader, &mut cur, "unresolvedVirtualCallPointers", arrays)?;
+        let _unresolved_instance_call_pointers = read_array_ptr(&mut cur, "unresolvedInstanceCallPointers", arrays)?;
+        let _unresolved_static_call_pointers = read_array_ptr(&mut cur, "unresolvedStaticCallPointers", arrays)?;
 
         // interopDataCount
         // interopData
-        let _interop_data: Vec<u64> = read_len_arr(&reader, &mut cur)?;
+        let _interop_data: Vec<u64> = read_named_len_arr(&reader, &mut cur, "interopData", arrays)?;
 
         // windowsRuntimeFactoryCount
         // windowsRuntimeFactoryTable
-        let _windows_runtime_factory_table: Vec<u64> = read_len_arr(&reader, &mut cur)?;
+        let _windows_runtime_factory_table: Vec<u64> =
+            read_named_len_arr(&reader, &mut cur, "windowsRuntimeFactoryTable", arrays)?;
 
-        let module_addrs = read_len_arr(&reader, &mut cur)?;
+        let module_addrs: Vec<u64> = read_named_len_arr(&reader, &mut cur, "codeGenModules", arrays)?;
         let mut code_gen_modules = Vec::with_capacity(module_addrs.len());
-        for addr in module_addrs {
-            code_gen_modules.push(Il2CppCodeGenModule::read(&reader, addr)?);
+        for (i, addr) in module_addrs.into_iter().enumerate() {
+            let module = Il2CppCodeGenModule::read(&reader, addr);
+            code_gen_modules.push(table_context("code_gen_modules", i, addr, module)?);
         }
 
         Ok(Self {
@@ -361,6 +484,7 @@ impl Il2CppType {
     fn read(
         reader: &ElfReader,
         vaddr: u64,
+        index: usize,
         type_map: &HashMap<u64, usize>,
         generic_class_map: &HashMap<u64, usize>,
         array_types: &mut Vec<Il2CppArrayType>,
@@ -376,19 +500,20 @@ impl Il2CppType {
 
         let data = match ty {
             Il2CppTypeEnum::Var | Il2CppTypeEnum::Mvar => TypeData::GenericParameterIndex(GenericParameterIndex::new(raw_data as u32)),
-            Il2CppTypeEnum::Ptr | Il2CppTypeEnum::Szarray => TypeData::TypeIndex(type_map[&raw_data]),
+            Il2CppTypeEnum::Ptr | Il2CppTypeEnum::Szarray => TypeData::TypeIndex(lookup_ptr(type_map, raw_data, "type", "types", index)?),
             Il2CppTypeEnum::Array => TypeData::ArrayType({
                 match array_type_map.get(&raw_data) {
                     Some(idx) => *idx,
                     None => {
                         let idx = array_types.len();
-                        array_types.push(Il2CppArrayType::read(reader, raw_data, type_map)?);
+                        let array_type = Il2CppArrayType::read(reader, raw_data, idx, type_map);
+                        array_types.push(table_context("array_types", idx, raw_data, array_type)?);
                         array_type_map.insert(raw_data, idx);
                         idx
                     }
                 }
             }),
-            Il2CppTypeEnum::Genericinst => TypeData::GenericClassIndex(generic_class_map[&raw_data]),
+            Il2CppTypeEnum::Genericinst => TypeData::GenericClassIndex(lookup_ptr(generic_class_map, raw_data, "generic class", "types", index)?),
             _ => TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(raw_data as u32)),
         };
         let byref = (bitfield >> 5) != 0;
@@ -410,13 +535,14 @@ impl Il2CppGenericClass {
     fn read(
         reader: &ElfReader,
         vaddr: u64,
+        index: usize,
         generic_inst_map: &HashMap<u64, usize>,
         type_map: &HashMap<u64, usize>,
     ) -> Result<Self> {
         let mut cur = reader.make_cur(vaddr)?;
 
         let type_ptr = cur.read_u64::<LittleEndian>()?;
-        let type_index = type_map[&type_ptr];
+        let type_index = lookup_ptr(type_map, type_ptr, "type", "generic_classes", index)?;
 
         let context = Il2CppGenericContext::read(&mut cur, generic_inst_map)?;
         Ok(Self {
@@ -440,24 +566,24 @@ impl Il2CppGenericContext {
 }
 
 impl Il2CppGenericInst {
-    fn read(reader: &ElfReader, vaddr: u64, types_map: &HashMap<u64, usize>) -> Result<Self> {
+    fn read(reader: &ElfReader, vaddr: u64, index: usize, types_map: &HashMap<u64, usize>) -> Result<Self> {
         let mut cur = reader.make_cur(vaddr)?;
 
         let type_ptrs = read_len_arr(reader, &mut cur)?;
         let mut types = Vec::with_capacity(type_ptrs.len());
         for addr in type_ptrs {
-            types.push(types_map[&addr]);
+            types.push(lookup_ptr(types_map, addr, "type", "generic_insts", index)?);
         }
         Ok(Self { types })
     }
 }
 
 impl Il2CppArrayType {
-    fn read(reader: &ElfReader, vaddr: u64, types_map: &HashMap<u64, usize>) -> Result<Self> {
+    fn read(reader: &ElfReader, vaddr: u64, index: usize, types_map: &HashMap<u64, usize>) -> Result<Self> {
         let mut cur = reader.make_cur(vaddr)?;
 
         let elem_ty_ptr = cur.read_u64::<LittleEndian>()?;
-        let elem_ty = types_map[&elem_ty_ptr];
+        let elem_ty = lookup_ptr(types_map, elem_ty_ptr, "type", "array_types", index)?;
 
         let rank = cur.read_u8()?;
         let num_sizes = cur.read_u8()?;
@@ -477,17 +603,25 @@ impl Il2CppArrayType {
 }
 
 impl Il2CppMetadataRegistration {
-    fn read(elf: &Elf, elf_rel: &[u8], addr: u64, metadata: &GlobalMetadata) -> Result<Self> {
+    /// Reads the structure at `addr`, recording the addresses of the arrays
+    /// it points to in `arrays`.
+    fn read(
+        elf: &Elf,
+        elf_rel: &[u8],
+        addr: u64,
+        metadata: &GlobalMetadata,
+        arrays: &mut ArrayAddresses,
+    ) -> Result<Self> {
         let reader = ElfReader::new(elf, elf_rel);
         let mut cur = reader.make_cur(addr)?;
 
-        let generic_class_addrs = read_len_arr(&reader, &mut cur)?;
-        let generic_inst_addrs = read_len_arr(&reader, &mut cur)?;
-        let generic_method_table = read_len_arr(&reader, &mut cur)?;
-        let type_addrs = read_len_arr(&reader, &mut cur)?;
-        let method_specs = read_len_arr(&reader, &mut cur)?;
-        let field_offset_ptrs = read_len_arr(&reader, &mut cur)?;
-        let type_definition_sizes_ptrs = read_len_arr(&reader, &mut cur)?;
+        let generic_class_addrs = read_named_len_arr(&reader, &mut cur, "genericClasses", arrays)?;
+        let generic_inst_addrs = read_named_len_arr(&reader, &mut cur, "genericInsts", arrays)?;
+        let generic_method_table = read_named_len_arr(&reader, &mut cur, "genericMethodTable", arrays)?;
+        let type_addrs = read_named_len_arr(&reader, &mut cur, "types", arrays)?;
+        let method_specs = read_named_len_arr(&reader, &mut cur, "methodSpecs", arrays)?;
+        let field_offset_ptrs = read_named_len_arr(&reader, &mut cur, "fieldOffsets", arrays)?;
+        let type_definition_sizes_ptrs = read_named_len_arr(&reader, &mut cur, "typeDefinitionsSizes", arrays)?;
 
         let mut generic_inst_map = HashMap::new();
         for (i, &addr) in generic_inst_addrs.iter().enumerate() {
@@ -502,26 +636,29 @@ impl Il2CppMetadataRegistration {
         let mut generic_classes = Vec::with_capacity(type_addrs.len());
         let mut generic_class_map = HashMap::new();
         for (i, addr) in generic_class_addrs.into_iter().enumerate() {
-            generic_classes.push(Il2CppGenericClass::read(&reader, addr, &generic_inst_map, &type_map)?);
+            let generic_class = Il2CppGenericClass::read(&reader, addr, i, &generic_inst_map, &type_map);
+            generic_classes.push(table_context("generic_classes", i, addr, generic_class)?);
             generic_class_map.insert(addr, i);
         }
 
         let mut types = Vec::with_capacity(type_addrs.len());
         let mut array_types = Vec::new();
         let mut array_type_map = HashMap::new();
-        for addr in type_addrs {
-            types.push(Il2CppType::read(&reader, addr, &type_map, &generic_class_map, &mut array_types, &mut array_type_map)?);
+        for (i, addr) in type_addrs.into_iter().enumerate() {
+            let ty = Il2CppType::read(&reader, addr, i, &type_map, &generic_class_map, &mut array_types, &mut array_type_map);
+            types.push(table_context("types", i, addr, ty)?);
         }
 
         let mut generic_insts = Vec::with_capacity(generic_inst_addrs.len());
-        for addr in generic_inst_addrs {
-            generic_insts.push(Il2CppGenericInst::read(&reader, addr, &type_map)?);
+        for (i, addr) in generic_inst_addrs.into_iter().enumerate() {
+            let generic_inst = Il2CppGenericInst::read(&reader, addr, i, &type_map);
+            generic_insts.push(table_context("generic_insts", i, addr, generic_inst)?);
         }
 
         let mut type_definition_sizes = Vec::with_capacity(type_definition_sizes_ptrs.len());
-        for addr in type_definition_sizes_ptrs {
-            let mut cur = reader.make_cur(addr)?;
-            type_definition_sizes.push(cur.read_le()?);
+        for (i, addr) in type_definition_sizes_ptrs.into_iter().enumerate() {
+            let sizes = reader.make_cur(addr).and_then(|mut cur| Ok(cur.read_le()?));
+            type_definition_sizes.push(table_context("type_definition_sizes", i, addr, sizes)?);
         }
 
         let mut field_offsets = Vec::with_capacity(field_offset_ptrs.len());
@@ -530,15 +667,24 @@ impl Il2CppMetadataRegistration {
                 field_offsets.push(Vec::new());
                 continue;
             }
-            let mut cur = reader.make_cur(addr)?;
 
             let type_def_idx = TypeDefinitionIndex::new(i as u32);
-            let arr_len = metadata.type_definitions[type_def_idx].field_count as usize;
-            let mut arr = Vec::with_capacity(arr_len);
-            for _ in 0..arr_len {
-                arr.push(cur.read_u32::<LittleEndian>()?);
-            }
-            field_offsets.push(arr);
+            let type_def = metadata.type_definitions.get(type_def_idx).ok_or(
+                Il2CppBinaryError::UnknownTypeDefinition {
+                    table: "field_offsets",
+                    index: i,
+                    type_definition: i as u32,
+                },
+            )?;
+            let arr_len = type_def.field_count as usize;
+            let arr = reader.make_cur(addr).and_then(|mut cur| {
+                let mut arr = Vec::with_capacity(arr_len);
+                for _ in 0..arr_len {
+                    arr.push(cur.read_u32::<LittleEndian>()?);
+                }
+                Ok(arr)
+            });
+            field_offsets.push(table_context("field_offsets", i, addr, arr)?);
         }
 
         Ok(Il2CppMetadataRegistration {
@@ -560,11 +706,36 @@ impl<'data> RuntimeMetadata<'data> {
         let elf_rel = process_relocations(elf)?;
 
         let (cr_addr, mr_addr) = find_registration(elf, &elf_rel)?;
-        let code_registration = Il2CppCodeRegistration::read(elf, &elf_rel, cr_addr)?;
-        let metadata_registration = Il2CppMetadataRegistration::read(elf, &elf_rel, mr_addr, global_metadata)?;
+        let mut code_registration_arrays = Vec::new();
+        let code_registration = Il2CppCodeRegistration::read(elf, &elf_rel, cr_addr, &mut code_registration_arrays)?;
+        let mut metadata_registration_arrays = Vec::new();
+        let metadata_registration = Il2CppMetadataRegistration::read(
+            elf,
+            &elf_rel,
+            mr_addr,
+            global_metadata,
+            &mut metadata_registration_arrays,
+        )?;
         Ok(RuntimeMetadata {
             code_registration,
             metadata_registration,
+            registration_addresses: RegistrationAddresses {
+                code_registration: cr_addr,
+                metadata_registration: mr_addr,
+                code_registration_arrays,
+                metadata_registration_arrays,
+            },
+            segments: elf
+                .segments()
+                .map(|segment| {
+                    let (file_offset, file_size) = segment.file_range();
+                    Segment {
+                        address: segment.address(),
+                        file_offset,
+                        file_size,
+                    }
+                })
+                .collect(),
         })
     }
 
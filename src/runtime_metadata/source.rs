@@ -12,12 +12,39 @@ pub enum SourceParseError {
     #[error("could not find {0}")]
     TableNotFound(String),
 
+    #[error("could not find source file {0}")]
+    FileNotFound(String),
+
+    #[error("could not find definition of {0}")]
+    UnknownName(String),
+
+    #[error("malformed line: {0}")]
+    Malformed(String),
+
+    #[error("reading {0} from source is not supported")]
+    Unsupported(&'static str),
+
     #[error(transparent)]
     ParseInt(#[from] std::num::ParseIntError),
 }
 
 type Result<T> = std::result::Result<T, SourceParseError>;
 
+/// Returns the `i`th word of `line`.
+fn word<'s>(words: &[&'s str], i: usize, line: &str) -> Result<&'s str> {
+    words
+        .get(i)
+        .copied()
+        .ok_or_else(|| SourceParseError::Malformed(line.to_string()))
+}
+
+/// Looks up the index of the item defined with the name `name`.
+fn lookup<T: Copy>(map: &HashMap<&str, T>, name: &str) -> Result<T> {
+    map.get(name)
+        .copied()
+        .ok_or_else(|| SourceParseError::UnknownName(name.to_string()))
+}
+
 pub struct SourceArrIterator<'src> {
     lines: std::str::Lines<'src>,
     // TODO: size hint
@@ -34,6 +61,13 @@ impl<'src> Iterator for SourceArrIterator<'src> {
     }
 }
 
+/// Paths relative to the source directory where the global metadata may be
+/// found.
+const GLOBAL_METADATA_PATHS: &[&str] = &[
+    "Data/Metadata/global-metadata.dat",
+    "Data/Managed/Metadata/global-metadata.dat",
+];
+
 pub struct SourceDir {
     pub(crate) global_metadata_data: Vec<u8>,
     source_files: HashMap<String, String>
@@ -45,7 +79,22 @@ impl SourceDir {
         P: AsRef<Path>
     {
         let path = path.as_ref();
-        let global_metadata_path = path.join("Data/Metadata/global-metadata.dat");
+        let global_metadata_path = GLOBAL_METADATA_PATHS
+            .iter()
+            .map(|p| path.join(p))
+            .find(|p| p.is_file())
+            .unwrap_or_else(|| path.join(GLOBAL_METADATA_PATHS[0]));
+        Self::with_global_metadata(path, global_metadata_path)
+    }
+
+    /// Like [`SourceDir::new`], but reads the global metadata from
+    /// `global_metadata_path` instead of searching for it in `path`.
+    pub fn with_global_metadata<P, G>(path: P, global_metadata_path: G) -> std::io::Result<Self>
+    where
+        P: AsRef<Path>,
+        G: AsRef<Path>,
+    {
+        let path = path.as_ref();
         let global_metadata_data = fs::read(global_metadata_path)?;
 
         let mut source_files = HashMap::new();
@@ -56,7 +105,9 @@ impl SourceDir {
             }
 
             let name = entry.file_name();
-            let name = name.to_str().unwrap();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
             let data = fs::read_to_string(entry.path())?;
             source_files.insert(name.to_string(), data);
 
@@ -68,8 +119,15 @@ impl SourceDir {
         })
     }
 
+    fn source_file(&self, file: &str) -> Result<&str> {
+        self.source_files
+            .get(file)
+            .map(String::as_str)
+            .ok_or_else(|| SourceParseError::FileNotFound(file.to_string()))
+    }
+
     fn parse_array(&self, ty: &str, name: &str, file: &str) -> Result<SourceArrIterator> {
-        let src = &self.source_files[file];
+        let src = self.source_file(file)?;
         let mut lines = src.lines();
         let header = format!("{ty} {name}");
         loop {
@@ -140,21 +198,21 @@ impl Il2CppType {
             0
         };
         let words: Vec<&str> = line.split_whitespace().collect();
-        let name = words[1 + offset];
-        let data_str = words[4 + offset].trim_end_matches(',').trim_start_matches("(void*)").trim_start_matches('&');
-        let attrs: u16 = words[5 + offset].trim_end_matches(',').parse()?;
-        let ty = Il2CppTypeEnum::read_src(words[6 + offset].trim_end_matches(','))?;
+        let name = word(&words, 1 + offset, line)?;
+        let data_str = word(&words, 4 + offset, line)?.trim_end_matches(',').trim_start_matches("(void*)").trim_start_matches('&');
+        let attrs: u16 = word(&words, 5 + offset, line)?.trim_end_matches(',').parse()?;
+        let ty = Il2CppTypeEnum::read_src(word(&words, 6 + offset, line)?.trim_end_matches(','))?;
         // This isn't actually used
         // let num_mods = words[7].trim_end_matches(',').parse::<u8>()?;
-        let byref = words[8 + offset].trim_end_matches(',').parse::<u8>()? != 0;
-        let pinned = words[9 + offset].trim_end_matches(',').parse::<u8>()? != 0;
-        let valuetype = words[10 + offset].parse::<u8>()? != 0;
+        let byref = word(&words, 8 + offset, line)?.trim_end_matches(',').parse::<u8>()? != 0;
+        let pinned = word(&words, 9 + offset, line)?.trim_end_matches(',').parse::<u8>()? != 0;
+        let valuetype = word(&words, 10 + offset, line)?.parse::<u8>()? != 0;
 
         let data = match ty {
             Il2CppTypeEnum::Var | Il2CppTypeEnum::Mvar => TypeData::GenericParameterIndex(GenericParameterIndex::new(data_str.parse()?)),
-            Il2CppTypeEnum::Ptr | Il2CppTypeEnum::Szarray => TypeData::TypeIndex(name_mappings.types[data_str]),
-            Il2CppTypeEnum::Array => TypeData::ArrayType(todo!()),
-            Il2CppTypeEnum::Genericinst => TypeData::GenericClassIndex(name_mappings.generic_classes[data_str]),
+            Il2CppTypeEnum::Ptr | Il2CppTypeEnum::Szarray => TypeData::TypeIndex(lookup(&name_mappings.types, data_str)?),
+            Il2CppTypeEnum::Array => return Err(SourceParseError::Unsupported("array types")),
+            Il2CppTypeEnum::Genericinst => TypeData::GenericClassIndex(lookup(&name_mappings.generic_classes, data_str)?),
             _ => TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(data_str.parse()?)),
         };
 
@@ -169,7 +227,7 @@ impl Il2CppType {
     }
 
     pub fn read_src_all(src_dir: &SourceDir, name_mappings: &NameMappings) -> Result<Vec<Self>> {
-        let src = &src_dir.source_files["Il2CppTypeDefinitions.c"];
+        let src = src_dir.source_file("Il2CppTypeDefinitions.c")?;
 
         let mut map = HashMap::new();
         for line in src.lines() {
@@ -182,7 +240,7 @@ impl Il2CppType {
 
         let mut vec = Vec::with_capacity(map.len());
         for name in &name_mappings.types_list {
-            vec.push(map[name]);
+            vec.push(lookup(&map, name)?);
         }
 
         Ok(vec)
@@ -192,18 +250,18 @@ impl Il2CppType {
 impl Il2CppGenericClass {
     fn read_src<'s>(line: &'s str, name_mappings: &NameMappings) -> Result<(&'s str, Self)> {
         let words: Vec<&str> = line.split_whitespace().collect();
-        let name = words[1];
-        let type_name = words[4].trim_start_matches('&').trim_end_matches(',');
-        let type_index = name_mappings.types[type_name];
-        let class_inst = words[6].trim_start_matches('&').trim_end_matches(',');
-        let class_inst_idx = Some(name_mappings.generic_insts[class_inst]);
-        let method_inst_idx = match words[7].trim_start_matches('&') {
+        let name = word(&words, 1, line)?;
+        let type_name = word(&words, 4, line)?.trim_start_matches('&').trim_end_matches(',');
+        let type_index = lookup(&name_mappings.types, type_name)?;
+        let class_inst = word(&words, 6, line)?.trim_start_matches('&').trim_end_matches(',');
+        let class_inst_idx = Some(lookup(&name_mappings.generic_insts, class_inst)?);
+        let method_inst_idx = match word(&words, 7, line)?.trim_start_matches('&') {
             "NULL" => None,
             gi => {
                 println!("Woah, there's something interesting happening here:");
                 println!("There's a generic class with its method inst field filled in.");
                 println!("Please open an issue on https://github.com/StackDoubleFlow/brocolib");
-                Some(name_mappings.generic_insts[gi])
+                Some(lookup(&name_mappings.generic_insts, gi)?)
             }
         };
         Ok((name, Self {
@@ -213,7 +271,7 @@ impl Il2CppGenericClass {
     }
 
     pub fn read_src_all(src_dir: &SourceDir, name_mappings: &NameMappings) -> Result<Vec<Self>> {
-        let src = &src_dir.source_files["Il2CppTypeDefinitions.c"];
+        let src = src_dir.source_file("Il2CppTypeDefinitions.c")?;
 
         let mut map = HashMap::new();
         for line in src.lines() {
@@ -230,7 +288,10 @@ impl Il2CppGenericClass {
 
         let mut vec = Vec::with_capacity(map.len());
         for name in &name_mappings.generic_classes_list {
-            vec.push(map[name].clone());
+            let item = map
+                .get(name)
+                .ok_or_else(|| SourceParseError::UnknownName(name.to_string()))?;
+            vec.push(item.clone());
         }
 
         Ok(vec)
@@ -241,24 +302,28 @@ impl Il2CppGenericInst {
     /// Only the line with the types (Il2CppType*)
     fn read_src<'s>(line: &'s str, name_mappings: &NameMappings) -> Result<(&'s str, Self)> {
         let words = line.split_whitespace().collect::<Vec<_>>();
-        let name = words[3].strip_suffix("_Types[]").unwrap();
-            // .context("generic inst def has wrong name suffix")?;
-        let types = words[6..words.len() - 1]
+        let name = word(&words, 3, line)?
+            .strip_suffix("_Types[]")
+            .ok_or_else(|| SourceParseError::Malformed(line.to_string()))?;
+        let type_words = words
+            .get(6..words.len().saturating_sub(1))
+            .ok_or_else(|| SourceParseError::Malformed(line.to_string()))?;
+        let types = type_words
             .iter()
             .map(|item| {
                 item.trim_end_matches(',')
                     .trim_end_matches(')')
                     .trim_start_matches("(&")
             })
-            .map(|item| name_mappings.types[item])
-            .collect();
+            .map(|item| lookup(&name_mappings.types, item))
+            .collect::<Result<_>>()?;
         Ok((name, Self {
             types,
         }))
     }
 
     pub fn read_src_all(src_dir: &SourceDir, name_mappings: &NameMappings) -> Result<Vec<Self>> {
-        let src = &src_dir.source_files["Il2CppGenericInstDefinitions.c"];
+        let src = src_dir.source_file("Il2CppGenericInstDefinitions.c")?;
         let start_loc = src.find("static const Il2CppType* ").ok_or(SourceParseError::TableNotFound("generic inst definitions".to_string()))?;
 
         let mut map = HashMap::new();
@@ -272,7 +337,10 @@ impl Il2CppGenericInst {
 
         let mut vec = Vec::with_capacity(map.len());
         for name in &name_mappings.generic_insts_list {
-            vec.push(map.remove(name).unwrap());
+            let item = map
+                .remove(name)
+                .ok_or_else(|| SourceParseError::UnknownName(name.to_string()))?;
+            vec.push(item);
         }
 
         Ok(vec)
@@ -282,10 +350,10 @@ impl Il2CppGenericInst {
 impl Il2CppGenericMethodFunctionsDefinitions {
     fn read_src(line: &str) -> Result<Self> {
         let words = line.split_whitespace().collect::<Vec<_>>();
-        let generic_method_index = words[1].trim_end_matches(',').parse()?;
-        let method_index = words[2].trim_end_matches(',').parse()?;
-        let invoker_index = words[3].trim_end_matches(',').parse()?;
-        let adjustor_thunk_index = words[4].trim_end_matches('}').parse::<i32>()? as u32;
+        let generic_method_index = word(&words, 1, line)?.trim_end_matches(',').parse()?;
+        let method_index = word(&words, 2, line)?.trim_end_matches(',').parse()?;
+        let invoker_index = word(&words, 3, line)?.trim_end_matches(',').parse()?;
+        let adjustor_thunk_index = word(&words, 4, line)?.trim_end_matches('}').parse::<i32>()? as u32;
         Ok(Self {
             generic_method_index,
             indices: GenericMethodIndices {
@@ -306,9 +374,9 @@ impl Il2CppGenericMethodFunctionsDefinitions {
 impl Il2CppMethodSpec {
     fn read_src(line: &str) -> Result<Self> {
         let words = line.split_whitespace().collect::<Vec<_>>();
-        let method_definition_index = words[1].trim_end_matches(',').parse()?;
-        let class_inst_index = words[2].trim_end_matches(',').parse::<i32>()? as u32;
-        let method_inst_index = words[3].parse::<i32>()? as u32;
+        let method_definition_index = word(&words, 1, line)?.trim_end_matches(',').parse()?;
+        let class_inst_index = word(&words, 2, line)?.trim_end_matches(',').parse::<i32>()? as u32;
+        let method_inst_index = word(&words, 3, line)?.parse::<i32>()? as u32;
         Ok(Self {
             method_definition_index: MethodIndex::new(method_definition_index),
             class_inst_index,
@@ -338,7 +406,7 @@ impl<'s> NameMappings<'s> {
     pub fn from_src(src_dir: &'s SourceDir) -> Result<Self> {
         let mut types = HashMap::new();
         let mut types_list = Vec::new();
-        let src = &src_dir.source_files["Il2CppTypeDefinitions.c"];
+        let src = src_dir.source_file("Il2CppTypeDefinitions.c")?;
         let arr_start = src
             .find("const Il2CppType* const  g_Il2CppTypeTable")
             .ok_or(SourceParseError::TableNotFound("g_Il2CppTypeTable".to_string()))?;
@@ -353,7 +421,7 @@ impl<'s> NameMappings<'s> {
 
         let mut generic_classes = HashMap::new();
         let mut generic_classes_list = Vec::new();
-        let src = &src_dir.source_files["Il2CppGenericClassTable.c"];
+        let src = src_dir.source_file("Il2CppGenericClassTable.c")?;
         let arr_start = src
             .find("Il2CppGenericClass* const g_Il2CppGenericTypes")
             .ok_or(SourceParseError::TableNotFound("g_Il2CppGenericTypes".to_string()))?;
@@ -411,10 +479,8 @@ impl Il2CppMetadataRegistration {
 impl<'data> RuntimeMetadata<'data> {
     pub fn read_src(src_dir: &SourceDir) -> Result<Self> {
         let name_mappings = NameMappings::from_src(src_dir)?;
-        Ok(RuntimeMetadata {
-            code_registration: todo!(),
-            metadata_registration: Il2CppMetadataRegistration::read_src(src_dir, &name_mappings)?
-        })
-
+        let _metadata_registration = Il2CppMetadataRegistration::read_src(src_dir, &name_mappings)?;
+        // TODO: code registration
+        Err(SourceParseError::Unsupported("code registration"))
     }
 }
\ No newline at end of file
@@ -0,0 +1,590 @@
+//! Generation of Rust bindings for managed types.
+//!
+//! [`RustBindingWriter`] writes a Rust module that declares the memory layout
+//! of every type definition as a `#[repr(C)]` struct, using the field offsets
+//! and type sizes that IL2CPP compiles into the binary. It is the Rust
+//! counterpart of [`CppHeaderWriter`](crate::cpp_header::CppHeaderWriter).
+//! Members are placed at their offsets with explicit padding, and every
+//! offset and size is checked with a `const` assertion.
+//!
+//! Enums become `#[repr(transparent)]` wrappers around their underlying type
+//! with an associated constant for each value, since the game can store
+//! values that are not declared. Methods with compiled code become `unsafe`
+//! functions that call the code at its address relative to `IL2CPP_BASE`,
+//! which must be set before they are called. They pass a null `MethodInfo`,
+//! which the compiled code of non-generic methods does not use.
+//!
+//! Only 64-bit binaries are supported. Interfaces and generic type
+//! definitions have no layout of their own and are declared as opaque
+//! structs. Types with the same name, such as the `<Module>` type of every
+//! image, are numbered in the order of their type definitions.
+
+use crate::codegen::{place_members, unique_name, Member, Slot};
+use crate::global_metadata::constant::ConstantValue;
+use crate::global_metadata::{Il2CppTypeDefinition, TypeDefinitionIndex, TypeKind};
+use crate::layout::{FieldLayout, Layout, POINTER_SIZE};
+use crate::lookup::MetadataIndex;
+use crate::runtime_metadata::{Il2CppType, Il2CppTypeEnum, TypeData};
+use crate::signature::MethodSignature;
+use crate::type_name::{TypeNameFormatter, TypeNameStyle};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::io;
+
+/// The runtime types used by the generated bindings, written at the start of
+/// the module.
+pub const PRELUDE: &str = r#"#![allow(non_camel_case_types, non_snake_case, non_upper_case_globals, dead_code)]
+
+use core::ffi::c_void;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// The address the game binary is loaded at. Set this before calling any
+/// method.
+pub static IL2CPP_BASE: AtomicUsize = AtomicUsize::new(0);
+
+fn il2cpp_base() -> usize {
+    IL2CPP_BASE.load(Ordering::Relaxed)
+}
+
+#[repr(C)]
+pub struct MethodInfo {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+pub struct Il2CppObject {
+    pub klass: *mut c_void,
+    pub monitor: *mut c_void,
+}
+
+#[repr(C)]
+pub struct Il2CppString {
+    pub object: Il2CppObject,
+    pub length: i32,
+    pub chars: [u16; 0],
+}
+
+#[repr(C)]
+pub struct Il2CppArrayBounds {
+    pub length: usize,
+    pub lower_bound: i32,
+}
+
+#[repr(C)]
+pub struct Il2CppArray<T> {
+    pub object: Il2CppObject,
+    pub bounds: *mut Il2CppArrayBounds,
+    pub max_length: usize,
+    pub vector: [T; 0],
+}
+"#;
+
+/// The items declared and imported by [`PRELUDE`], which type names must not
+/// shadow.
+const PRELUDE_ITEMS: &[&str] = &[
+    "c_void", "AtomicUsize", "Ordering", "IL2CPP_BASE", "il2cpp_base", "MethodInfo", "Il2CppObject", "Il2CppString",
+    "Il2CppArrayBounds", "Il2CppArray",
+];
+
+/// Writes Rust bindings for the types in the metadata.
+pub struct RustBindingWriter<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+    /// Finds the code of methods.
+    index: MetadataIndex<'md, 'gmd, 'rmd>,
+    /// Renders identifiers.
+    names: TypeNameFormatter<'md, 'gmd, 'rmd>,
+    /// Renders the full names of types in doc comments.
+    display_names: TypeNameFormatter<'md, 'gmd, 'rmd>,
+    /// The struct name of every type definition, which is unique in the
+    /// module.
+    type_names: Vec<String>,
+}
+
+impl<'md, 'gmd, 'rmd> RustBindingWriter<'md, 'gmd, 'rmd> {
+    pub fn new(metadata: &'md Metadata<'gmd, 'rmd>) -> Self {
+        let names = TypeNameFormatter::new(metadata, TypeNameStyle::Rust);
+        Self {
+            metadata,
+            index: MetadataIndex::new(metadata),
+            type_names: names.unique_type_definition_names(PRELUDE_ITEMS, &["_StaticFields"]),
+            names,
+            display_names: TypeNameFormatter::new(metadata, TypeNameStyle::Reflection),
+        }
+    }
+
+    /// The name of the struct of a type definition.
+    pub fn type_name(&self, idx: TypeDefinitionIndex) -> String {
+        match self.type_names.get(idx.index() as usize) {
+            Some(name) => name.clone(),
+            None => self.names.format_type_definition(idx),
+        }
+    }
+
+    /// Writes [`PRELUDE`] and the bindings of every type definition as a
+    /// single module.
+    pub fn write<W: io::Write>(&self, out: &mut W) -> io::Result<()> {
+        out.write_all(PRELUDE.as_bytes())?;
+        for i in 0..self.metadata.global_metadata.type_definitions.len() {
+            writeln!(out)?;
+            out.write_all(self.write_type(TypeDefinitionIndex::new(i as u32)).as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Renders the bindings of a single type.
+    pub fn write_type(&self, idx: TypeDefinitionIndex) -> String {
+        let metadata = self.metadata;
+        let mut out = String::new();
+        let Some(ty_def) = metadata.global_metadata.type_definitions.get(idx) else {
+            return out;
+        };
+        let name = self.type_name(idx);
+        let _ = writeln!(out, "/// `{}`, TypeDefIndex: {}", self.display_names.format_type_definition(idx), idx.index());
+
+        // Enum values and methods are both associated items of the struct.
+        let mut associated = HashSet::new();
+        let kind = ty_def.kind(metadata);
+        if ty_def.generic_container_index.is_valid() || kind == TypeKind::Interface {
+            let _ = writeln!(out, "#[repr(C)]\npub struct {} {{\n    _private: [u8; 0],\n}}", name);
+        } else if kind == TypeKind::Enum {
+            self.push_enum(ty_def, &name, &mut associated, &mut out);
+        } else if let Some(layout) = metadata.type_layout(idx) {
+            self.push_instance_layout(&layout, &name, &mut out);
+            self.push_static_layout(&layout, &name, &mut out);
+        } else {
+            out.push_str("// The layout of this type is not known.\n");
+            let _ = writeln!(out, "#[repr(C)]\npub struct {} {{\n    _private: [u8; 0],\n}}", name);
+        }
+        self.push_methods(ty_def, &name, &mut associated, &mut out);
+        out
+    }
+
+    fn push_enum(&self, ty_def: &Il2CppTypeDefinition, name: &str, used: &mut HashSet<String>, out: &mut String) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let underlying = metadata
+            .runtime_metadata
+            .metadata_registration
+            .types
+            .get(ty_def.element_type_index as usize)
+            .and_then(|ty| self.rust_type(ty))
+            .unwrap_or_else(|| "i32".into());
+
+        out.push_str("#[repr(transparent)]\n#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]\n");
+        let _ = writeln!(out, "pub struct {}(pub {});", name, underlying);
+        let _ = writeln!(out, "\nimpl {} {{", name);
+        for field_idx in ty_def.field_start.indices(ty_def.field_count as u32) {
+            let Some(field) = gm.fields.get(field_idx) else {
+                continue;
+            };
+            if !field.is_literal(metadata) {
+                continue;
+            }
+            let Some(value) = metadata.global_metadata.field_default_values.get_by_field(field_idx).and_then(|value| value.value(metadata)) else {
+                continue;
+            };
+            let value = match value {
+                ConstantValue::Boolean(value) => value.to_string(),
+                ConstantValue::I1(value) => value.to_string(),
+                ConstantValue::U1(value) => value.to_string(),
+                ConstantValue::I2(value) => value.to_string(),
+                ConstantValue::U2(value) | ConstantValue::Char(value) => value.to_string(),
+                ConstantValue::I4(value) => value.to_string(),
+                ConstantValue::U4(value) => value.to_string(),
+                ConstantValue::I8(value) => value.to_string(),
+                ConstantValue::U8(value) => value.to_string(),
+                _ => continue,
+            };
+            let member = unique_name(self.names.identifier(gm.string.get(field.name_index).unwrap_or_default()), used);
+            let _ = writeln!(out, "    pub const {}: {} = {}({});", member, name, name, value);
+        }
+        out.push_str("}\n");
+    }
+
+    fn push_instance_layout(&self, layout: &Layout, name: &str, out: &mut String) {
+        let mut used = HashSet::new();
+        let mut members = Vec::new();
+        if !layout.is_value_type {
+            for header in ["klass", "monitor"] {
+                members.push(Member {
+                    offset: members.len() as u32 * POINTER_SIZE,
+                    size: Some(POINTER_SIZE),
+                    ty: Some("*mut c_void".into()),
+                    name: unique_name(header.into(), &mut used),
+                });
+            }
+        }
+        // Fields inherited from generic instances keep their declared type,
+        // so those of type parameters are written as byte arrays.
+        members.extend(self.members(&layout.instance_fields, &mut used));
+        // Types with a packing size can have members that are not aligned.
+        let packed = layout.packing_size.is_some();
+        push_struct(name, members, layout.instance_size, layout.is_value_type, packed, out);
+    }
+
+    fn push_static_layout(&self, layout: &Layout, name: &str, out: &mut String) {
+        if layout.static_fields.is_empty() {
+            return;
+        }
+        let members = self.members(&layout.static_fields, &mut HashSet::new());
+        out.push('\n');
+        push_struct(&format!("{}_StaticFields", name), members, layout.static_fields_size, false, false, out);
+    }
+
+    fn members(&self, fields: &[FieldLayout], used: &mut HashSet<String>) -> Vec<Member> {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let mut members = Vec::new();
+        for field in fields {
+            let Some(definition) = gm.fields.get(field.field) else {
+                continue;
+            };
+            let Some(ty) = metadata.runtime_metadata.metadata_registration.types.get(field.type_index as usize) else {
+                continue;
+            };
+            let name = self.names.identifier(gm.string.get(definition.name_index).unwrap_or_default());
+            members.push(Member {
+                offset: field.offset,
+                size: field.size,
+                ty: self.rust_type(ty),
+                name: unique_name(name, used),
+            });
+        }
+        members
+    }
+
+    fn push_methods(&self, ty_def: &Il2CppTypeDefinition, name: &str, used: &mut HashSet<String>, out: &mut String) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let mut methods = String::new();
+        for method in ty_def.methods(metadata).unwrap_or_default() {
+            let Some(rva) = method.method_pointer(&self.index) else {
+                continue;
+            };
+            let method_name = unique_name(self.names.identifier(gm.string.get(method.name_index).unwrap_or_default()), used);
+            let signature = method.signature(metadata);
+            let parameters = signature.as_ref().and_then(|signature| self.parameters(signature, name));
+            let return_type = signature.as_ref().and_then(|signature| match signature.return_type.ty {
+                Il2CppTypeEnum::Void if !signature.return_type.byref => Some(String::new()),
+                _ => self.rust_type(&signature.return_type).map(|ty| format!(" -> {}", ty)),
+            });
+            let (Some(parameters), Some(return_type)) = (parameters, return_type) else {
+                let _ = writeln!(methods, "    // {}: signature cannot be written in Rust, RVA: 0x{:X}", method_name, rva);
+                continue;
+            };
+
+            let declared = parameters.iter().map(|(name, ty)| format!("{}: {}", name, ty)).collect::<Vec<_>>();
+            let types = parameters.iter().map(|(_, ty)| ty.as_str()).chain(["*const MethodInfo"]).collect::<Vec<_>>();
+            let arguments = parameters.iter().map(|(name, _)| name.as_str()).chain(["core::ptr::null()"]).collect::<Vec<_>>();
+            let _ = writeln!(methods, "    /// RVA: 0x{:X}", rva);
+            let _ = writeln!(methods, "    pub unsafe fn {}({}){} {{", method_name, declared.join(", "), return_type);
+            let _ = writeln!(
+                methods,
+                "        let f: unsafe extern \"C\" fn({}){} = unsafe {{ core::mem::transmute(il2cpp_base() + 0x{:X}) }};",
+                types.join(", "),
+                return_type,
+                rva
+            );
+            let _ = writeln!(methods, "        unsafe {{ f({}) }}", arguments.join(", "));
+            methods.push_str("    }\n");
+        }
+        if !methods.is_empty() {
+            let _ = writeln!(out, "\nimpl {} {{", name);
+            out.push_str(&methods);
+            out.push_str("}\n");
+        }
+    }
+
+    /// The names and types of the parameters of the compiled function of a
+    /// method, without the `MethodInfo` it takes last.
+    fn parameters(&self, signature: &MethodSignature, name: &str) -> Option<Vec<(String, String)>> {
+        // `f` is the local that holds the function pointer.
+        let mut used = HashSet::from(["this".to_string(), "f".to_string()]);
+        let mut parameters = Vec::new();
+        if !signature.is_static() {
+            // The `this` of value types points to the unboxed value.
+            parameters.push(("this".to_string(), format!("*mut {}", name)));
+        }
+        for param in &signature.parameters {
+            let ty = self.rust_type(&param.ty)?;
+            let name = unique_name(self.names.identifier(param.name), &mut used);
+            parameters.push((name, ty));
+        }
+        Some(parameters)
+    }
+
+    /// The Rust type of a value of `ty`, or `None` if its layout is not known.
+    fn rust_type(&self, ty: &Il2CppType) -> Option<String> {
+        self.rust_type_inner(ty, 0)
+    }
+
+    fn rust_type_inner(&self, ty: &Il2CppType, depth: usize) -> Option<String> {
+        let metadata = self.metadata;
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        if depth > MAX_TYPE_DEPTH {
+            return None;
+        }
+        if ty.byref {
+            let element = Il2CppType { byref: false, ..*ty };
+            return Some(format!("*mut {}", self.pointee(&element, depth)));
+        }
+        let name = match (ty.ty, ty.data) {
+            (Il2CppTypeEnum::Boolean, _) => "bool".into(),
+            (Il2CppTypeEnum::Char, _) => "u16".into(),
+            (Il2CppTypeEnum::I1, _) => "i8".into(),
+            (Il2CppTypeEnum::U1, _) => "u8".into(),
+            (Il2CppTypeEnum::I2, _) => "i16".into(),
+            (Il2CppTypeEnum::U2, _) => "u16".into(),
+            (Il2CppTypeEnum::I4, _) => "i32".into(),
+            (Il2CppTypeEnum::U4, _) => "u32".into(),
+            (Il2CppTypeEnum::I8, _) => "i64".into(),
+            (Il2CppTypeEnum::U8, _) => "u64".into(),
+            (Il2CppTypeEnum::R4, _) => "f32".into(),
+            (Il2CppTypeEnum::R8, _) => "f64".into(),
+            (Il2CppTypeEnum::I, _) => "isize".into(),
+            (Il2CppTypeEnum::U, _) => "usize".into(),
+            (Il2CppTypeEnum::Fnptr, _) => "*mut c_void".into(),
+            (Il2CppTypeEnum::String, _) => "*mut Il2CppString".into(),
+            (Il2CppTypeEnum::Object, _) => "*mut Il2CppObject".into(),
+            (Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(idx)) => {
+                format!("*mut {}", self.type_name(idx))
+            }
+            (Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(idx)) => {
+                let ty_def = metadata.global_metadata.type_definitions.get(idx)?;
+                if ty_def.generic_container_index.is_valid() {
+                    return None;
+                }
+                self.type_name(idx)
+            }
+            (Il2CppTypeEnum::Ptr, TypeData::TypeIndex(element)) => {
+                format!("*mut {}", self.pointee(mr.types.get(element)?, depth))
+            }
+            (Il2CppTypeEnum::Szarray, TypeData::TypeIndex(element)) => {
+                let element = mr.types.get(element)?;
+                let element = self.rust_type_inner(element, depth + 1).unwrap_or_else(|| "*mut Il2CppObject".into());
+                format!("*mut Il2CppArray<{}>", element)
+            }
+            (Il2CppTypeEnum::Array, TypeData::ArrayType(array)) => {
+                let element = mr.array_types.get(array).and_then(|array| mr.types.get(array.elem_ty))?;
+                let element = self.rust_type_inner(element, depth + 1).unwrap_or_else(|| "*mut Il2CppObject".into());
+                format!("*mut Il2CppArray<{}>", element)
+            }
+            (Il2CppTypeEnum::Genericinst, _) => match metadata.is_value_type(ty)? {
+                true => return None,
+                false => "*mut Il2CppObject".into(),
+            },
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// The type a pointer to `ty` points to, which is `c_void` for `void` and
+    /// types without a known layout.
+    fn pointee(&self, ty: &Il2CppType, depth: usize) -> String {
+        match ty.ty {
+            Il2CppTypeEnum::Void => "c_void".into(),
+            _ => self.rust_type_inner(ty, depth + 1).unwrap_or_else(|| "c_void".into()),
+        }
+    }
+}
+
+/// Writes a struct with explicit padding between `members`, followed by
+/// assertions of the offset of each member and of the struct's `size`.
+fn push_struct(name: &str, mut members: Vec<Member>, size: Option<u32>, copy: bool, packed: bool, out: &mut String) {
+    let placement = place_members(&mut members, size);
+    out.push_str(match packed {
+        true => "#[repr(C, packed)]\n",
+        false => "#[repr(C)]\n",
+    });
+    if copy {
+        out.push_str("#[derive(Clone, Copy)]\n");
+    }
+    let _ = writeln!(out, "pub struct {} {{", name);
+    let mut asserts = Vec::new();
+    for slot in &placement.slots {
+        match slot {
+            Slot::Padding { index, size } => {
+                let _ = writeln!(out, "    _padding{}: [u8; 0x{:X}],", index, size);
+            }
+            Slot::Member(member, size) => {
+                match &member.ty {
+                    Some(ty) if member.size.is_some() => {
+                        let _ = writeln!(out, "    pub {}: {}, // 0x{:X}", member.name, ty, member.offset);
+                    }
+                    _ => {
+                        let _ = writeln!(out, "    pub {}: [u8; 0x{:X}], // 0x{:X}", member.name, size, member.offset);
+                    }
+                }
+                asserts.push(format!(
+                    "const _: () = assert!(core::mem::offset_of!({}, {}) == 0x{:X});",
+                    name, member.name, member.offset
+                ));
+            }
+            Slot::Overlap(member) => {
+                let _ = writeln!(out, "    // {} at 0x{:X} overlaps another member", member.name, member.offset);
+            }
+        }
+    }
+    out.push_str("}\n");
+    for assert in asserts {
+        out.push_str(&assert);
+        out.push('\n');
+    }
+    if let Some(size) = placement.size {
+        let _ = writeln!(out, "const _: () = assert!(core::mem::size_of::<{}>() == 0x{:X});", name, size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{
+        FieldAndParameterDefaultValueIndex, FieldIndex, GenericContainerIndex, Il2CppFieldDefaultValue,
+        Il2CppFieldDefinition, Il2CppImageDefinition, Il2CppMethodDefinition, Il2CppParameterDefinition,
+        StringIndex, Token,
+    };
+    use crate::runtime_metadata::Il2CppCodeGenModule;
+    use crate::test_util::{self, write_compressed_i32, zeroed};
+    use crate::vtable::INVALID_SLOT;
+
+    const STRINGS: &str = "\0Game\0Vec2\0x\0y\0Scale\0factor\0Color\0value__\0Red\0Green\0A.dll\0";
+
+    fn string(name: &str) -> StringIndex {
+        StringIndex::new(STRINGS.find(&format!("\0{}\0", name)).unwrap() as u32 + 1)
+    }
+
+    /// The struct `Game.Vec2` with the method `void Scale(float factor)` and
+    /// the enum `Game.Color`.
+    fn metadata() -> Metadata<'static, 'static> {
+        let mut metadata = test_util::metadata(STRINGS);
+        let definition = |idx: u32| TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(idx));
+        // The attributes of fields: 0x6 is `public` and 0x56 `public const`.
+        let ty = |ty: Il2CppTypeEnum, data: TypeData, attrs: u16| Il2CppType {
+            attrs,
+            ..test_util::ty(ty, data)
+        };
+        metadata.runtime_metadata.metadata_registration.types = vec![
+            ty(Il2CppTypeEnum::R4, definition(u32::MAX), 0x6),
+            ty(Il2CppTypeEnum::I4, definition(u32::MAX), 0x6),
+            ty(Il2CppTypeEnum::Valuetype, definition(1), 0x56),
+            ty(Il2CppTypeEnum::I4, definition(u32::MAX), 0),
+            ty(Il2CppTypeEnum::Void, definition(u32::MAX), 0),
+            ty(Il2CppTypeEnum::R4, definition(u32::MAX), 0),
+        ];
+        metadata.runtime_metadata.code_registration.code_gen_modules = vec![Il2CppCodeGenModule {
+            name: "A.dll",
+            method_pointers: vec![0x1234],
+            adjustor_thunks: Vec::new(),
+            invoker_indices: Vec::new(),
+            rgctx_ranges: Vec::new(),
+            rgctxs: Vec::new(),
+        }];
+
+        let gm = &mut metadata.global_metadata;
+        let fields = [("x", 0), ("y", 0), ("value__", 1), ("Red", 2), ("Green", 2)];
+        gm.fields = fields
+            .iter()
+            .map(|&(name, type_index)| {
+                let mut field: Il2CppFieldDefinition = zeroed();
+                field.name_index = string(name);
+                field.type_index = type_index;
+                field
+            })
+            .collect::<Vec<_>>()
+            .into();
+        let mut data = Vec::new();
+        write_compressed_i32(&mut data, 0);
+        write_compressed_i32(&mut data, 1);
+        gm.field_and_parameter_default_value_data = data.into();
+        let default_value = |field: u32, data: u32| Il2CppFieldDefaultValue {
+            field_index: FieldIndex::new(field),
+            type_index: 3,
+            data_index: FieldAndParameterDefaultValueIndex::new(data),
+        };
+        gm.field_default_values = vec![default_value(3, 0), default_value(4, 1)].into();
+
+        let mut parameter: Il2CppParameterDefinition = zeroed();
+        parameter.name_index = string("factor");
+        parameter.type_index = 5;
+        gm.parameters = vec![parameter].into();
+        let mut method: Il2CppMethodDefinition = zeroed();
+        method.name_index = string("Scale");
+        method.return_type = 4;
+        method.parameter_count = 1;
+        method.generic_container_index = GenericContainerIndex::new(u32::MAX);
+        method.token = Token(0x0600_0001);
+        method.flags = 0x6;
+        method.slot = INVALID_SLOT;
+        gm.methods = vec![method].into();
+
+        let mut vec2 = test_util::type_definition();
+        vec2.namespace_index = string("Game");
+        vec2.name_index = string("Vec2");
+        vec2.field_count = 2;
+        vec2.method_count = 1;
+        vec2.bitfield |= 1;
+        let mut color = test_util::type_definition();
+        color.namespace_index = string("Game");
+        color.name_index = string("Color");
+        color.field_start = FieldIndex::new(2);
+        color.field_count = 3;
+        color.element_type_index = 3;
+        color.bitfield |= 1 | 2;
+        gm.type_definitions = vec![vec2, color].into();
+
+        let mut image: Il2CppImageDefinition = zeroed();
+        image.name_index = string("A.dll");
+        image.type_count = 2;
+        gm.images = vec![image].into();
+        metadata
+    }
+
+    #[test]
+    fn struct_with_method() {
+        let metadata = metadata();
+        let bindings = RustBindingWriter::new(&metadata).write_type(TypeDefinitionIndex::new(0));
+        assert_eq!(
+            bindings,
+            r#"/// `Game.Vec2`, TypeDefIndex: 0
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Game_Vec2 {
+    pub x: f32, // 0x0
+    pub y: f32, // 0x4
+}
+const _: () = assert!(core::mem::offset_of!(Game_Vec2, x) == 0x0);
+const _: () = assert!(core::mem::offset_of!(Game_Vec2, y) == 0x4);
+const _: () = assert!(core::mem::size_of::<Game_Vec2>() == 0x8);
+
+impl Game_Vec2 {
+    /// RVA: 0x1234
+    pub unsafe fn Scale(this: *mut Game_Vec2, factor: f32) {
+        let f: unsafe extern "C" fn(*mut Game_Vec2, f32, *const MethodInfo) = unsafe { core::mem::transmute(il2cpp_base() + 0x1234) };
+        unsafe { f(this, factor, core::ptr::null()) }
+    }
+}
+"#
+        );
+    }
+
+    #[test]
+    fn enum_type() {
+        let metadata = metadata();
+        let bindings = RustBindingWriter::new(&metadata).write_type(TypeDefinitionIndex::new(1));
+        assert_eq!(
+            bindings,
+            r#"/// `Game.Color`, TypeDefIndex: 1
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Game_Color(pub i32);
+
+impl Game_Color {
+    pub const Red: Game_Color = Game_Color(0);
+    pub const Green: Game_Color = Game_Color(1);
+}
+"#
+        );
+    }
+}
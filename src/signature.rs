@@ -0,0 +1,301 @@
+//! Structured method signatures.
+//!
+//! A [`MethodSignature`] describes everything about a method that callers
+//! depend on: its return type, parameters, generic parameters and whether it
+//! has a `this` argument. Signatures compare equal when they would be
+//! considered the same signature by the CLI (ECMA-335 I.8.6.1.5), so they can
+//! be used to match methods across types, for example to find the method that
+//! hides or implements another one.
+
+use crate::flags::{CallingConvention, GenericParameterAttributes, ParamAttributes};
+use crate::global_metadata::constant::ConstantValue;
+use crate::global_metadata::Il2CppMethodDefinition;
+use crate::runtime_metadata::{Il2CppType, TypeData};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+use std::hash::{Hash, Hasher};
+
+/// A method parameter.
+#[derive(Debug, Clone)]
+pub struct ParameterSignature<'md> {
+    pub name: &'md str,
+    pub ty: Il2CppType,
+    pub attributes: ParamAttributes,
+    /// The value of an optional parameter.
+    pub default_value: Option<ConstantValue<'md>>,
+}
+
+impl ParameterSignature<'_> {
+    /// Whether the parameter is passed by reference (`ref`, `in` or `out`).
+    pub fn is_byref(&self) -> bool {
+        self.ty.byref
+    }
+
+    pub fn is_in(&self) -> bool {
+        self.attributes.contains(ParamAttributes::IN)
+    }
+
+    pub fn is_out(&self) -> bool {
+        self.attributes.contains(ParamAttributes::OUT)
+    }
+
+    pub fn is_optional(&self) -> bool {
+        self.attributes.contains(ParamAttributes::OPTIONAL)
+    }
+}
+
+/// A generic parameter of a generic method.
+#[derive(Debug, Clone)]
+pub struct GenericParameterSignature<'md> {
+    pub name: &'md str,
+    pub attributes: GenericParameterAttributes,
+    /// The types the parameter is constrained to.
+    pub constraints: Vec<Il2CppType>,
+}
+
+/// The signature of a method.
+///
+/// Two signatures are equal if they have the same calling convention, number
+/// of generic parameters, return type and parameter types. Parameter names,
+/// attributes, default values and generic constraints are not compared.
+#[derive(Debug, Clone)]
+pub struct MethodSignature<'md> {
+    pub return_type: Il2CppType,
+    pub parameters: Vec<ParameterSignature<'md>>,
+    pub generic_parameters: Vec<GenericParameterSignature<'md>>,
+    pub calling_convention: CallingConvention,
+    /// The types of the signature in a form that does not depend on where
+    /// they are stored in the metadata.
+    key: Vec<u64>,
+}
+
+impl MethodSignature<'_> {
+    /// Whether the method has no `this` argument.
+    pub fn is_static(&self) -> bool {
+        !self.calling_convention.contains(CallingConvention::HAS_THIS)
+    }
+
+    pub fn is_generic(&self) -> bool {
+        self.calling_convention.contains(CallingConvention::GENERIC)
+    }
+}
+
+impl PartialEq for MethodSignature<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.calling_convention == other.calling_convention
+            && self.generic_parameters.len() == other.generic_parameters.len()
+            && self.key == other.key
+    }
+}
+
+impl Eq for MethodSignature<'_> {}
+
+impl Hash for MethodSignature<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.calling_convention.hash(state);
+        self.generic_parameters.len().hash(state);
+        self.key.hash(state);
+    }
+}
+
+impl Il2CppMethodDefinition {
+    /// Builds the structured signature of the method.
+    ///
+    /// Returns `None` if the return type or one of the parameters cannot be
+    /// resolved.
+    pub fn signature<'md>(&self, metadata: &'md Metadata) -> Option<MethodSignature<'md>> {
+        let gm = &metadata.global_metadata;
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+
+        let return_type = *types.get(self.return_type as usize)?;
+        let mut key = Vec::new();
+        push_type_key(metadata, &return_type, &mut key, 0);
+
+        let mut parameters = Vec::with_capacity(self.parameter_count as usize);
+        for idx in self.parameter_start.indices(self.parameter_count as u32) {
+            let param = gm.parameters.get(idx)?;
+            let ty = *types.get(param.type_index as usize)?;
+            push_type_key(metadata, &ty, &mut key, 0);
+
+            let attributes = ty.param_attributes();
+            let default_value = match attributes.contains(ParamAttributes::HAS_DEFAULT) {
+                true => gm.parameter_default_values.get_by_parameter(idx).and_then(|value| value.value(metadata)),
+                false => None,
+            };
+            parameters.push(ParameterSignature {
+                name: gm.string.get(param.name_index).unwrap_or_default(),
+                ty,
+                attributes,
+                default_value,
+            });
+        }
+        // The indices stop early if they overflow.
+        if parameters.len() != self.parameter_count as usize {
+            return None;
+        }
+
+        let generic_parameters: Vec<_> = self
+            .generic_container(metadata)
+            .and_then(|container| container.generic_parameters(metadata))
+            .unwrap_or_default()
+            .iter()
+            .map(|param| GenericParameterSignature {
+                name: gm.string.get(param.name_index).unwrap_or_default(),
+                attributes: param.attributes(),
+                constraints: param
+                    .constraints(metadata).unwrap_or_default()
+                    .iter()
+                    .filter_map(|&idx| types.get(idx as usize).copied())
+                    .collect(),
+            })
+            .collect();
+
+        let mut calling_convention = CallingConvention::empty();
+        if !self.is_static() {
+            calling_convention |= CallingConvention::HAS_THIS;
+        }
+        if !generic_parameters.is_empty() {
+            calling_convention |= CallingConvention::GENERIC;
+        }
+
+        Some(MethodSignature {
+            return_type,
+            parameters,
+            generic_parameters,
+            calling_convention,
+            key,
+        })
+    }
+}
+
+/// Appends a description of `ty` to `key` that identifies generic parameters
+/// by their position instead of their index.
+fn push_type_key(metadata: &Metadata, ty: &Il2CppType, key: &mut Vec<u64>, depth: usize) {
+    let mr = &metadata.runtime_metadata.metadata_registration;
+    key.push(ty.ty as u64 | (ty.byref as u64) << 8);
+    if depth > MAX_TYPE_DEPTH {
+        return;
+    }
+
+    match ty.data {
+        TypeData::TypeDefinitionIndex(idx) => key.push(idx.index() as u64),
+        TypeData::GenericParameterIndex(idx) => {
+            let num = metadata.global_metadata.generic_parameters.get(idx).map_or(u64::MAX, |param| param.num as u64);
+            key.push(num);
+        }
+        TypeData::TypeIndex(idx) => match mr.types.get(idx) {
+            Some(elem) => push_type_key(metadata, elem, key, depth + 1),
+            None => key.push(u64::MAX),
+        },
+        TypeData::GenericClassIndex(idx) => {
+            let Some(generic_class) = mr.generic_classes.get(idx) else {
+                key.push(u64::MAX);
+                return;
+            };
+            match mr.types.get(generic_class.type_index) {
+                Some(definition) => push_type_key(metadata, definition, key, depth + 1),
+                None => key.push(u64::MAX),
+            }
+            let args = generic_class
+                .context
+                .class_inst_idx
+                .and_then(|inst| mr.generic_insts.get(inst))
+                .map(|inst| inst.types.as_slice())
+                .unwrap_or_default();
+            key.push(args.len() as u64);
+            for &arg in args {
+                match mr.types.get(arg) {
+                    Some(arg) => push_type_key(metadata, arg, key, depth + 1),
+                    None => key.push(u64::MAX),
+                }
+            }
+        }
+        TypeData::ArrayType(idx) => {
+            let Some(array) = mr.array_types.get(idx) else {
+                key.push(u64::MAX);
+                return;
+            };
+            key.push(array.rank as u64);
+            match mr.types.get(array.elem_ty) {
+                Some(elem) => push_type_key(metadata, elem, key, depth + 1),
+                None => key.push(u64::MAX),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{
+        GenericContainerIndex, Il2CppParameterDefinition, ParameterIndex, StringIndex, TypeDefinitionIndex,
+    };
+    use crate::runtime_metadata::Il2CppTypeEnum;
+    use crate::test_util::{self, zeroed};
+
+    const STRINGS: &str = "\0count\0name\0value\0";
+
+    fn string(name: &str) -> StringIndex {
+        let pos = STRINGS.find(&format!("\0{}\0", name)).unwrap();
+        StringIndex::new(pos as u32 + 1)
+    }
+
+    fn metadata() -> Metadata<'static, 'static> {
+        let mut metadata = test_util::metadata(STRINGS);
+        let definition = |ty| test_util::ty(ty, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0)));
+        metadata.runtime_metadata.metadata_registration.types =
+            vec![definition(Il2CppTypeEnum::Void), definition(Il2CppTypeEnum::I4), definition(Il2CppTypeEnum::String)];
+        let parameter = |name, type_index| {
+            let mut parameter: Il2CppParameterDefinition = zeroed();
+            parameter.name_index = string(name);
+            parameter.type_index = type_index;
+            parameter
+        };
+        metadata.global_metadata.parameters =
+            vec![parameter("count", 1), parameter("name", 2), parameter("value", 1), parameter("name", 2)].into();
+        metadata
+    }
+
+    fn method(return_type: u32, parameter_start: u32, parameter_count: u16) -> Il2CppMethodDefinition {
+        let mut method: Il2CppMethodDefinition = zeroed();
+        method.return_type = return_type;
+        method.parameter_start = ParameterIndex::new(parameter_start);
+        method.parameter_count = parameter_count;
+        method.generic_container_index = GenericContainerIndex::new(u32::MAX);
+        method
+    }
+
+    #[test]
+    fn signature() {
+        let metadata = metadata();
+        let signature = method(1, 0, 2).signature(&metadata).unwrap();
+        assert_eq!(signature.return_type.ty, Il2CppTypeEnum::I4);
+        let parameters: Vec<_> = signature.parameters.iter().map(|param| (param.name, param.ty.ty)).collect();
+        assert_eq!(parameters, [("count", Il2CppTypeEnum::I4), ("name", Il2CppTypeEnum::String)]);
+        assert!(!signature.is_static());
+        assert!(!signature.is_generic());
+
+        // Parameter names do not matter, but their types and the return type do.
+        assert_eq!(method(1, 2, 2).signature(&metadata), Some(signature.clone()));
+        assert_ne!(method(0, 2, 2).signature(&metadata), Some(signature.clone()));
+        assert_ne!(method(1, 1, 2).signature(&metadata), Some(signature));
+    }
+
+    #[test]
+    fn unresolved_types() {
+        let metadata = metadata();
+        // Return type out of range
+        assert!(method(3, 0, 2).signature(&metadata).is_none());
+        // Parameter past the end of the table
+        assert!(method(1, 3, 2).signature(&metadata).is_none());
+        // Parameter indices that overflow
+        assert!(method(1, u32::MAX, 2).signature(&metadata).is_none());
+
+        let mut metadata = metadata;
+        let mut parameter: Il2CppParameterDefinition = zeroed();
+        parameter.type_index = 3;
+        metadata.global_metadata.parameters = vec![parameter].into();
+        // Parameter type out of range
+        assert!(method(0, 0, 1).signature(&metadata).is_none());
+    }
+}
@@ -0,0 +1,1254 @@
+//! Generation of stub assemblies.
+//!
+//! [`StubAssemblyWriter`] turns each image in the metadata back into an
+//! ECMA-335 assembly that declares the same types, fields, methods,
+//! properties, events, generic parameters and custom attributes, similar to
+//! the `DummyDll` output of Il2CppDumper. These assemblies can be referenced
+//! to compile code against the game's types.
+//!
+//! IL2CPP does not keep any IL, so every method body is replaced by
+//! `throw null`. Rows are written in the order of their original
+//! [`Token`](crate::global_metadata::Token)s, so tokens of the stub
+//! assemblies match the original assemblies as long as nothing was stripped.
+
+mod heaps;
+mod pe;
+mod tables;
+
+use crate::flags::{
+    FieldAttributes, MethodAttributes, MethodImplAttributes, ParamAttributes, PropertyAttributes, TypeAttributes,
+    TypeLayout,
+};
+use crate::global_metadata::constant::ConstantValue;
+use crate::global_metadata::custom_attribute::{
+    AttributeArgument, AttributeArgumentType, AttributeValue, CustomAttribute,
+};
+use crate::global_metadata::{
+    EventIndex, FieldIndex, Il2CppAssemblyDefinition, Il2CppCustomAttributeDataRange,
+    Il2CppGenericParameter, Il2CppImageDefinition, Il2CppMethodDefinition, Il2CppTypeDefinition, ImageIndex,
+    MethodIndex, ParameterIndex, PropertyIndex, TypeDefinitionIndex,
+};
+use crate::generics::InflatedType;
+use crate::layout::OBJECT_HEADER_SIZE;
+use crate::runtime_metadata::{Il2CppType, Il2CppTypeEnum, TypeData};
+use crate::type_name::{TypeNameFormatter, TypeNameStyle};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+use heaps::{write_compressed_u32, BlobHeap, GuidHeap, StringHeap};
+use std::collections::HashMap;
+use std::path::Path;
+use std::{fs, io};
+use tables::{token, CodedIndex, Column, HeapSizes, Tables};
+
+/// Writes stub assemblies for the images in the metadata.
+pub struct StubAssemblyWriter<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+}
+
+impl<'md, 'gmd, 'rmd> StubAssemblyWriter<'md, 'gmd, 'rmd> {
+    pub fn new(metadata: &'md Metadata<'gmd, 'rmd>) -> Self {
+        Self { metadata }
+    }
+
+    /// The file name of the assembly for `image`, such as
+    /// `Assembly-CSharp.dll`.
+    pub fn file_name(&self, image: ImageIndex) -> Option<&'md str> {
+        let gm = &self.metadata.global_metadata;
+        gm.string.get(gm.images.get(image)?.name_index).ok()
+    }
+
+    /// Builds the assembly for `image`. Returns `None` if the image does not
+    /// exist.
+    pub fn write_image(&self, image: ImageIndex) -> Option<Vec<u8>> {
+        let image_def = self.metadata.global_metadata.images.get(image)?;
+        Some(Builder::new(self.metadata, image_def).build())
+    }
+
+    /// Writes the assembly of every image into `dir`, named by
+    /// [`StubAssemblyWriter::file_name()`].
+    pub fn write_all(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for i in 0..self.metadata.global_metadata.images.len() {
+            let image = ImageIndex::new(i as u32);
+            if let (Some(name), Some(data)) = (self.file_name(image), self.write_image(image)) {
+                // Image names come from the metadata and should not be able
+                // to escape `dir`.
+                let name = Path::new(name).file_name().unwrap_or_default();
+                fs::write(dir.join(name), data)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A generic parameter row, before the table is sorted.
+struct GenericParamRow {
+    owner: u32,
+    number: u16,
+    flags: u16,
+    name: u32,
+    constraints: Vec<u32>,
+}
+
+struct Builder<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+    image: &'md Il2CppImageDefinition,
+    /// Renders type names in custom attribute values.
+    names: TypeNameFormatter<'md, 'gmd, 'rmd>,
+
+    tables: Tables,
+    strings: StringHeap,
+    blobs: BlobHeap,
+    guids: GuidHeap,
+
+    type_defs: HashMap<TypeDefinitionIndex, u32>,
+    fields: HashMap<FieldIndex, u32>,
+    methods: HashMap<MethodIndex, u32>,
+    params: HashMap<ParameterIndex, u32>,
+    properties: HashMap<PropertyIndex, u32>,
+    events: HashMap<EventIndex, u32>,
+    /// Maps tokens from the metadata to tokens in the new assembly.
+    tokens: HashMap<u32, u32>,
+
+    type_refs: HashMap<TypeDefinitionIndex, u32>,
+    type_specs: HashMap<Vec<u8>, u32>,
+    assembly_refs: HashMap<&'md str, u32>,
+    member_refs: HashMap<MethodIndex, u32>,
+}
+
+impl<'md, 'gmd, 'rmd> Builder<'md, 'gmd, 'rmd> {
+    fn new(metadata: &'md Metadata<'gmd, 'rmd>, image: &'md Il2CppImageDefinition) -> Self {
+        Self {
+            metadata,
+            image,
+            names: TypeNameFormatter::new(metadata, TypeNameStyle::Reflection),
+            tables: Tables::new(),
+            strings: StringHeap::new(),
+            blobs: BlobHeap::new(),
+            guids: GuidHeap::new(),
+            type_defs: HashMap::new(),
+            fields: HashMap::new(),
+            methods: HashMap::new(),
+            params: HashMap::new(),
+            properties: HashMap::new(),
+            events: HashMap::new(),
+            tokens: HashMap::new(),
+            type_refs: HashMap::new(),
+            type_specs: HashMap::new(),
+            assembly_refs: HashMap::new(),
+            member_refs: HashMap::new(),
+        }
+    }
+
+    fn build(mut self) -> Vec<u8> {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+
+        let mut types: Vec<_> = self
+            .image
+            .type_start
+            .indices(self.image.type_count)
+            .filter_map(|idx| Some((idx, gm.type_definitions.get(idx)?)))
+            .collect();
+        types.sort_by_key(|(_, ty_def)| ty_def.token.rid());
+
+        // The first type must be the `<Module>` type, which holds global
+        // members.
+        let has_module_type = types
+            .first()
+            .is_some_and(|(_, ty_def)| gm.string.get(ty_def.name_index) == Ok("<Module>"));
+        if !has_module_type {
+            let name = self.strings.add("<Module>");
+            self.tables.push(
+                tables::TYPE_DEF,
+                vec![
+                    Column::U32(0),
+                    Column::String(name),
+                    Column::String(0),
+                    Column::Coded(CodedIndex::TypeDefOrRef, 0),
+                    Column::Index(tables::FIELD, 1),
+                    Column::Index(tables::METHOD_DEF, 1),
+                ],
+            );
+        }
+        self.assign_rows(&types, !has_module_type as u32);
+
+        let image_name = gm.string.get(self.image.name_index).unwrap_or_default();
+        let module_name = self.strings.add(image_name);
+        let mvid = self.guids.add(mvid(image_name));
+        self.tables.push(
+            tables::MODULE,
+            vec![
+                Column::U16(0),
+                Column::String(module_name),
+                Column::Guid(mvid),
+                Column::Guid(0),
+                Column::Guid(0),
+            ],
+        );
+        self.tokens.insert(self.image.token.0, token(tables::MODULE, 1));
+
+        let mut generic_params = Vec::new();
+        for &(idx, ty_def) in &types {
+            self.push_type(idx, ty_def, &mut generic_params);
+        }
+        self.push_generic_params(generic_params);
+        self.push_assembly();
+        self.push_custom_attributes();
+
+        self.tables.sort_by_column(tables::INTERFACE_IMPL, 0);
+        self.tables.sort_by_column(tables::CONSTANT, 1);
+        self.tables.sort_by_column(tables::CUSTOM_ATTRIBUTE, 0);
+        self.tables.sort_by_column(tables::CLASS_LAYOUT, 2);
+        self.tables.sort_by_column(tables::FIELD_LAYOUT, 1);
+        self.tables.sort_by_column(tables::METHOD_SEMANTICS, 2);
+        self.tables.sort_by_column(tables::NESTED_CLASS, 0);
+
+        let mut strings = self.strings.data().to_vec();
+        let mut blobs = self.blobs.data().to_vec();
+        pad(&mut strings);
+        pad(&mut blobs);
+        let table_stream = self.tables.write(&HeapSizes {
+            strings: strings.len(),
+            guids: self.guids.data().len() / 16,
+            blobs: blobs.len(),
+        });
+        let metadata_root = pe::metadata_root(&[
+            ("#~", &table_stream),
+            ("#Strings", &strings),
+            ("#US", &[0u8; 4]),
+            ("#GUID", self.guids.data()),
+            ("#Blob", &blobs),
+        ]);
+        pe::write_image(&metadata_root)
+    }
+
+    /// Assigns row indices to every member, so that the type rows can refer
+    /// to the first row of their members.
+    fn assign_rows(&mut self, types: &[(TypeDefinitionIndex, &Il2CppTypeDefinition)], first_type_rid: u32) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let (mut field_rid, mut method_rid, mut param_rid, mut property_rid, mut event_rid) = (1, 1, 1, 1, 1);
+        for (i, &(idx, ty_def)) in types.iter().enumerate() {
+            let rid = first_type_rid + i as u32 + 1;
+            self.type_defs.insert(idx, rid);
+            self.tokens.insert(ty_def.token.0, token(tables::TYPE_DEF, rid));
+
+            for idx in ty_def.field_start.indices(ty_def.field_count as u32) {
+                if let Some(field) = gm.fields.get(idx) {
+                    self.fields.insert(idx, field_rid);
+                    self.tokens.insert(field.token.0, token(tables::FIELD, field_rid));
+                    field_rid += 1;
+                }
+            }
+            for idx in ty_def.method_start.indices(ty_def.method_count as u32) {
+                let Some(method) = gm.methods.get(idx) else {
+                    continue;
+                };
+                self.methods.insert(idx, method_rid);
+                self.tokens.insert(method.token.0, token(tables::METHOD_DEF, method_rid));
+                method_rid += 1;
+                for idx in method.parameter_start.indices(method.parameter_count as u32) {
+                    if let Some(param) = gm.parameters.get(idx) {
+                        self.params.insert(idx, param_rid);
+                        self.tokens.insert(param.token.0, token(tables::PARAM, param_rid));
+                        param_rid += 1;
+                    }
+                }
+            }
+            for idx in ty_def.property_start.indices(ty_def.property_count as u32) {
+                if let Some(property) = gm.properties.get(idx) {
+                    self.properties.insert(idx, property_rid);
+                    self.tokens.insert(property.token.0, token(tables::PROPERTY, property_rid));
+                    property_rid += 1;
+                }
+            }
+            for idx in ty_def.event_start.indices(ty_def.event_count as u32) {
+                if let Some(event) = gm.events.get(idx) {
+                    self.events.insert(idx, event_rid);
+                    self.tokens.insert(event.token.0, token(tables::EVENT, event_rid));
+                    event_rid += 1;
+                }
+            }
+        }
+    }
+
+    fn push_type(
+        &mut self,
+        idx: TypeDefinitionIndex,
+        ty_def: &'md Il2CppTypeDefinition,
+        generic_params: &mut Vec<GenericParamRow>,
+    ) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        let rid = self.type_defs[&idx];
+        let type_token = token(tables::TYPE_DEF, rid);
+
+        let offsets = mr
+            .field_offsets
+            .as_ref()
+            .and_then(|offsets| offsets.get(idx.index() as usize))
+            .filter(|offsets| offsets.len() == ty_def.field_count as usize);
+        let mut attributes = ty_def.attributes() - TypeAttributes::HAS_SECURITY;
+        let explicit = attributes.layout() == TypeLayout::Explicit;
+        if explicit && offsets.is_none() {
+            // Explicit layouts need an offset for every field.
+            attributes = (attributes - TypeAttributes::LAYOUT_MASK) | TypeAttributes::SEQUENTIAL_LAYOUT;
+        }
+
+        let extends = match ty_def.base_type(metadata) {
+            Some(base) => self.type_token(base),
+            None => 0,
+        };
+        let first_field = ty_def
+            .field_start
+            .indices(ty_def.field_count as u32)
+            .find_map(|idx| self.fields.get(&idx).copied())
+            .unwrap_or(self.tables.len(tables::FIELD) + 1);
+        let first_method = ty_def
+            .method_start
+            .indices(ty_def.method_count as u32)
+            .find_map(|idx| self.methods.get(&idx).copied())
+            .unwrap_or(self.tables.len(tables::METHOD_DEF) + 1);
+        let name = self.strings.add(gm.string.get(ty_def.name_index).unwrap_or_default());
+        let namespace = self.strings.add(gm.string.get(ty_def.namespace_index).unwrap_or_default());
+        self.tables.push(
+            tables::TYPE_DEF,
+            vec![
+                Column::U32(attributes.bits()),
+                Column::String(name),
+                Column::String(namespace),
+                Column::Coded(CodedIndex::TypeDefOrRef, extends),
+                Column::Index(tables::FIELD, first_field),
+                Column::Index(tables::METHOD_DEF, first_method),
+            ],
+        );
+
+        if let Some(packing) = ty_def.layout_info().packing_size {
+            self.tables.push(
+                tables::CLASS_LAYOUT,
+                vec![Column::U16(packing as u16), Column::U32(0), Column::Index(tables::TYPE_DEF, rid)],
+            );
+        }
+        if ty_def.declaring_type_index != u32::MAX {
+            let enclosing = mr
+                .types
+                .get(ty_def.declaring_type_index as usize)
+                .and_then(|ty| ty.type_definition_index(metadata))
+                .and_then(|enclosing| self.type_defs.get(&enclosing).copied());
+            if let Some(enclosing) = enclosing {
+                self.tables.push(
+                    tables::NESTED_CLASS,
+                    vec![Column::Index(tables::TYPE_DEF, rid), Column::Index(tables::TYPE_DEF, enclosing)],
+                );
+            }
+        }
+        for &interface in ty_def.interfaces(metadata).unwrap_or_default() {
+            if let Some(interface) = mr.types.get(interface as usize) {
+                let interface = self.type_token(interface);
+                self.tables.push(
+                    tables::INTERFACE_IMPL,
+                    vec![Column::Index(tables::TYPE_DEF, rid), Column::Coded(CodedIndex::TypeDefOrRef, interface)],
+                );
+            }
+        }
+        if let Some(params) = ty_def.generic_container(metadata).and_then(|container| container.generic_parameters(metadata)) {
+            self.collect_generic_params(params, type_token, generic_params);
+        }
+
+        for (i, field_idx) in ty_def.field_start.indices(ty_def.field_count as u32).enumerate() {
+            let offset = match explicit {
+                true => offsets.and_then(|offsets| offsets.get(i)).copied(),
+                false => None,
+            };
+            self.push_field(field_idx, offset);
+        }
+        for idx in ty_def.method_start.indices(ty_def.method_count as u32) {
+            self.push_method(idx, generic_params);
+        }
+        self.push_properties(ty_def, rid);
+        self.push_events(ty_def, rid);
+    }
+
+    fn push_field(&mut self, idx: FieldIndex, offset: Option<u32>) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let Some(field) = gm.fields.get(idx) else {
+            return;
+        };
+        let Some(ty) = metadata.runtime_metadata.metadata_registration.types.get(field.type_index as usize) else {
+            return;
+        };
+        let rid = self.fields[&idx];
+        let mut attributes = ty.field_attributes()
+            - FieldAttributes::HAS_FIELD_RVA
+            - FieldAttributes::HAS_FIELD_MARSHAL
+            - FieldAttributes::PINVOKE_IMPL;
+
+        let default_value = match attributes.contains(FieldAttributes::HAS_DEFAULT) {
+            true => gm.field_default_values.get_by_field(idx).and_then(|value| value.value(metadata)),
+            false => None,
+        };
+        match default_value {
+            Some(value) => self.push_constant(value, token(tables::FIELD, rid)),
+            None => attributes -= FieldAttributes::HAS_DEFAULT,
+        }
+
+        let static_ = attributes.intersects(FieldAttributes::STATIC | FieldAttributes::LITERAL);
+        if let (Some(offset), false) = (offset, static_) {
+            self.tables.push(
+                tables::FIELD_LAYOUT,
+                vec![Column::U32(offset.saturating_sub(OBJECT_HEADER_SIZE)), Column::Index(tables::FIELD, rid)],
+            );
+        }
+
+        let mut signature = vec![0x06];
+        self.write_type(&Il2CppType { attrs: 0, ..*ty }, &mut signature, 0);
+        let name = self.strings.add(gm.string.get(field.name_index).unwrap_or_default());
+        let signature = self.blobs.add(&signature);
+        self.tables.push(
+            tables::FIELD,
+            vec![Column::U16(attributes.bits()), Column::String(name), Column::Blob(signature)],
+        );
+    }
+
+    fn push_method(&mut self, idx: MethodIndex, generic_params: &mut Vec<GenericParamRow>) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let Some(method) = gm.methods.get(idx) else {
+            return;
+        };
+        let rid = self.methods[&idx];
+        let method_token = token(tables::METHOD_DEF, rid);
+
+        // There are no `ImplMap` rows or security declarations, and every
+        // method with a body gets the stub body.
+        let attributes = method.attributes()
+            - MethodAttributes::PINVOKE_IMPL
+            - MethodAttributes::HAS_SECURITY
+            - MethodAttributes::REQUIRE_SEC_OBJECT
+            - MethodAttributes::UNMANAGED_EXPORT;
+        let mut impl_attributes = method.impl_attributes() - MethodImplAttributes::INTERNAL_CALL;
+        let runtime = impl_attributes & MethodImplAttributes::CODE_TYPE_MASK == MethodImplAttributes::RUNTIME;
+        if !runtime {
+            impl_attributes -= MethodImplAttributes::CODE_TYPE_MASK | MethodImplAttributes::UNMANAGED;
+        }
+        let rva = match runtime || attributes.contains(MethodAttributes::ABSTRACT) {
+            true => 0,
+            false => pe::METHOD_BODY_RVA,
+        };
+
+        let signature = self.method_signature(method);
+        let name = self.strings.add(gm.string.get(method.name_index).unwrap_or_default());
+        let first_param = method
+            .parameter_start
+            .indices(method.parameter_count as u32)
+            .find_map(|idx| self.params.get(&idx).copied())
+            .unwrap_or(self.tables.len(tables::PARAM) + 1);
+        self.tables.push(
+            tables::METHOD_DEF,
+            vec![
+                Column::U32(rva),
+                Column::U16(impl_attributes.bits()),
+                Column::U16(attributes.bits()),
+                Column::String(name),
+                Column::Blob(signature),
+                Column::Index(tables::PARAM, first_param),
+            ],
+        );
+
+        for (i, param_idx) in method.parameter_start.indices(method.parameter_count as u32).enumerate() {
+            let Some(param) = gm.parameters.get(param_idx) else {
+                continue;
+            };
+            let param_rid = self.params[&param_idx];
+            let mut attributes = param.attributes(metadata) - ParamAttributes::HAS_FIELD_MARSHAL;
+            let default_value = match attributes.contains(ParamAttributes::HAS_DEFAULT) {
+                true => gm.parameter_default_values.get_by_parameter(param_idx).and_then(|value| value.value(metadata)),
+                false => None,
+            };
+            match default_value {
+                Some(value) => self.push_constant(value, token(tables::PARAM, param_rid)),
+                None => attributes -= ParamAttributes::HAS_DEFAULT,
+            }
+            let name = self.strings.add(gm.string.get(param.name_index).unwrap_or_default());
+            self.tables.push(
+                tables::PARAM,
+                vec![Column::U16(attributes.bits()), Column::U16(i as u16 + 1), Column::String(name)],
+            );
+        }
+
+        if let Some(params) = method.generic_container(metadata).and_then(|container| container.generic_parameters(metadata)) {
+            self.collect_generic_params(params, method_token, generic_params);
+        }
+    }
+
+    fn push_properties(&mut self, ty_def: &Il2CppTypeDefinition, type_rid: u32) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let mut first = true;
+        for idx in ty_def.property_start.indices(ty_def.property_count as u32) {
+            let Some(property) = gm.properties.get(idx) else {
+                continue;
+            };
+            let rid = self.properties[&idx];
+            if first {
+                first = false;
+                self.tables.push(
+                    tables::PROPERTY_MAP,
+                    vec![Column::Index(tables::TYPE_DEF, type_rid), Column::Index(tables::PROPERTY, rid)],
+                );
+            }
+
+            let getter = accessor(ty_def, property.get);
+            let setter = accessor(ty_def, property.set);
+
+            // The property's type and parameters are those of the getter, or
+            // the setter without its value parameter.
+            let getter_def = getter.and_then(|idx| gm.methods.get(idx));
+            let setter_def = setter.and_then(|idx| gm.methods.get(idx));
+            let (ty, params, is_static) = match (getter_def, setter_def) {
+                (Some(getter), _) => {
+                    let Some(signature) = getter.signature(metadata) else {
+                        continue;
+                    };
+                    let params: Vec<_> = signature.parameters.iter().map(|param| param.ty).collect();
+                    (signature.return_type, params, signature.is_static())
+                }
+                (None, Some(setter)) => {
+                    let Some(signature) = setter.signature(metadata) else {
+                        continue;
+                    };
+                    let mut params: Vec<_> = signature.parameters.iter().map(|param| param.ty).collect();
+                    let ty = params.pop().unwrap_or(signature.return_type);
+                    (ty, params, signature.is_static())
+                }
+                (None, None) => continue,
+            };
+            let mut signature = match is_static {
+                true => vec![0x08],
+                false => vec![0x28],
+            };
+            write_compressed_u32(&mut signature, params.len() as u32);
+            self.write_type(&ty, &mut signature, 0);
+            for param in &params {
+                self.write_type(param, &mut signature, 0);
+            }
+
+            let attributes = property.attributes() - PropertyAttributes::HAS_DEFAULT;
+            let name = self.strings.add(gm.string.get(property.name_index).unwrap_or_default());
+            let signature = self.blobs.add(&signature);
+            self.tables.push(
+                tables::PROPERTY,
+                vec![Column::U16(attributes.bits() as u16), Column::String(name), Column::Blob(signature)],
+            );
+
+            let association = token(tables::PROPERTY, rid);
+            self.push_semantics(0x0002, getter, association);
+            self.push_semantics(0x0001, setter, association);
+        }
+    }
+
+    fn push_events(&mut self, ty_def: &Il2CppTypeDefinition, type_rid: u32) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let mut first = true;
+        for idx in ty_def.event_start.indices(ty_def.event_count as u32) {
+            let Some(event) = gm.events.get(idx) else {
+                continue;
+            };
+            let rid = self.events[&idx];
+            if first {
+                first = false;
+                self.tables.push(
+                    tables::EVENT_MAP,
+                    vec![Column::Index(tables::TYPE_DEF, type_rid), Column::Index(tables::EVENT, rid)],
+                );
+            }
+
+            let ty = match metadata.runtime_metadata.metadata_registration.types.get(event.type_index as usize) {
+                Some(ty) => self.type_token(ty),
+                None => 0,
+            };
+            let name = self.strings.add(gm.string.get(event.name_index).unwrap_or_default());
+            self.tables.push(
+                tables::EVENT,
+                vec![Column::U16(0), Column::String(name), Column::Coded(CodedIndex::TypeDefOrRef, ty)],
+            );
+
+            let association = token(tables::EVENT, rid);
+            self.push_semantics(0x0008, accessor(ty_def, event.add.index()), association);
+            self.push_semantics(0x0010, accessor(ty_def, event.remove.index()), association);
+            self.push_semantics(0x0020, accessor(ty_def, event.raise.index()), association);
+        }
+    }
+
+    fn push_semantics(&mut self, semantics: u16, method: Option<MethodIndex>, association: u32) {
+        if let Some(&rid) = method.and_then(|method| self.methods.get(&method)) {
+            self.tables.push(
+                tables::METHOD_SEMANTICS,
+                vec![
+                    Column::U16(semantics),
+                    Column::Index(tables::METHOD_DEF, rid),
+                    Column::Coded(CodedIndex::HasSemantics, association),
+                ],
+            );
+        }
+    }
+
+    fn push_constant(&mut self, value: ConstantValue, parent: u32) {
+        let (ty, data) = match value {
+            ConstantValue::Boolean(value) => (0x02, vec![value as u8]),
+            ConstantValue::Char(value) => (0x03, value.to_le_bytes().to_vec()),
+            ConstantValue::I1(value) => (0x04, value.to_le_bytes().to_vec()),
+            ConstantValue::U1(value) => (0x05, value.to_le_bytes().to_vec()),
+            ConstantValue::I2(value) => (0x06, value.to_le_bytes().to_vec()),
+            ConstantValue::U2(value) => (0x07, value.to_le_bytes().to_vec()),
+            ConstantValue::I4(value) => (0x08, value.to_le_bytes().to_vec()),
+            ConstantValue::U4(value) => (0x09, value.to_le_bytes().to_vec()),
+            ConstantValue::I8(value) => (0x0A, value.to_le_bytes().to_vec()),
+            ConstantValue::U8(value) => (0x0B, value.to_le_bytes().to_vec()),
+            ConstantValue::R4(value) => (0x0C, value.to_le_bytes().to_vec()),
+            ConstantValue::R8(value) => (0x0D, value.to_le_bytes().to_vec()),
+            ConstantValue::String(value) => (0x0E, value.encode_utf16().flat_map(u16::to_le_bytes).collect()),
+            ConstantValue::Null => (0x12, 0u32.to_le_bytes().to_vec()),
+        };
+        let value = self.blobs.add(&data);
+        self.tables.push(
+            tables::CONSTANT,
+            vec![Column::U16(ty), Column::Coded(CodedIndex::HasConstant, parent), Column::Blob(value)],
+        );
+    }
+
+    fn collect_generic_params(
+        &mut self,
+        params: &[Il2CppGenericParameter],
+        owner: u32,
+        rows: &mut Vec<GenericParamRow>,
+    ) {
+        let metadata = self.metadata;
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+        for param in params {
+            let constraints = param
+                .constraints(metadata).unwrap_or_default()
+                .iter()
+                .filter_map(|&ty| types.get(ty as usize))
+                .map(|ty| self.type_token(ty))
+                .collect();
+            let name = metadata.global_metadata.string.get(param.name_index).unwrap_or_default();
+            rows.push(GenericParamRow {
+                owner,
+                number: param.num,
+                flags: param.flags,
+                name: self.strings.add(name),
+                constraints,
+            });
+        }
+    }
+
+    fn push_generic_params(&mut self, mut rows: Vec<GenericParamRow>) {
+        rows.sort_by_key(|row| (CodedIndex::TypeOrMethodDef.encode(row.owner), row.number));
+        for row in rows {
+            let rid = self.tables.push(
+                tables::GENERIC_PARAM,
+                vec![
+                    Column::U16(row.number),
+                    Column::U16(row.flags),
+                    Column::Coded(CodedIndex::TypeOrMethodDef, row.owner),
+                    Column::String(row.name),
+                ],
+            );
+            for constraint in row.constraints {
+                self.tables.push(
+                    tables::GENERIC_PARAM_CONSTRAINT,
+                    vec![
+                        Column::Index(tables::GENERIC_PARAM, rid),
+                        Column::Coded(CodedIndex::TypeDefOrRef, constraint),
+                    ],
+                );
+            }
+        }
+        self.tables.mark_sorted(tables::GENERIC_PARAM);
+        self.tables.mark_sorted(tables::GENERIC_PARAM_CONSTRAINT);
+    }
+
+    fn push_assembly(&mut self) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let Some(assembly) = gm.assemblies.get(self.image.assembly_index) else {
+            return;
+        };
+        let aname = &assembly.aname;
+        let name = self.strings.add(gm.string.get(aname.name_index).unwrap_or_default());
+        let culture = self.strings.add(gm.string.get(aname.culture_index).unwrap_or_default());
+        self.tables.push(
+            tables::ASSEMBLY,
+            vec![
+                Column::U32(aname.hash_alg),
+                Column::U16(aname.major as u16),
+                Column::U16(aname.minor as u16),
+                Column::U16(aname.build as u16),
+                Column::U16(aname.revision as u16),
+                // The public key is not kept, so the assembly is not signed.
+                Column::U32(aname.flags & !0x0001),
+                Column::Blob(0),
+                Column::String(name),
+                Column::String(culture),
+            ],
+        );
+        self.tokens.insert(assembly.token.0, token(tables::ASSEMBLY, 1));
+    }
+
+    fn push_custom_attributes(&mut self) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let image = self.image;
+        for index in image.custom_attribute_start.indices(image.custom_attribute_count) {
+            let Some(range) = gm.attribute_data_range.get(index) else {
+                continue;
+            };
+            // Attributes of items that were not written, like return values,
+            // are dropped.
+            let Some(&parent) = self.tokens.get(&range.token.0) else {
+                continue;
+            };
+            let Some(attributes) = Il2CppCustomAttributeDataRange::decode(metadata, index) else {
+                continue;
+            };
+            for attribute in &attributes {
+                let Some(value) = self.attribute_blob(attribute) else {
+                    continue;
+                };
+                let constructor = self.method_token(attribute.constructor);
+                let value = self.blobs.add(&value);
+                self.tables.push(
+                    tables::CUSTOM_ATTRIBUTE,
+                    vec![
+                        Column::Coded(CodedIndex::HasCustomAttribute, parent),
+                        Column::Coded(CodedIndex::CustomAttributeType, constructor),
+                        Column::Blob(value),
+                    ],
+                );
+            }
+        }
+    }
+
+    /// A `MethodDef` or `MemberRef` token for a method.
+    fn method_token(&mut self, idx: MethodIndex) -> u32 {
+        if let Some(&rid) = self.methods.get(&idx) {
+            return token(tables::METHOD_DEF, rid);
+        }
+        if let Some(&token) = self.member_refs.get(&idx) {
+            return token;
+        }
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let Some(method) = gm.methods.get(idx) else {
+            return 0;
+        };
+        let parent = self.type_def_or_ref(method.declaring_type, 0);
+        let name = self.strings.add(gm.string.get(method.name_index).unwrap_or_default());
+        let signature = self.method_signature(method);
+        let rid = self.tables.push(
+            tables::MEMBER_REF,
+            vec![
+                Column::Coded(CodedIndex::MemberRefParent, parent),
+                Column::String(name),
+                Column::Blob(signature),
+            ],
+        );
+        let token = token(tables::MEMBER_REF, rid);
+        self.member_refs.insert(idx, token);
+        token
+    }
+
+    fn method_signature(&mut self, method: &Il2CppMethodDefinition) -> u32 {
+        let Some(signature) = method.signature(self.metadata) else {
+            // Keep the number of parameters, with `object` for every type.
+            let object = Il2CppTypeEnum::Object.to_ty();
+            let mut blob = vec![match method.is_static() {
+                true => 0x00,
+                false => 0x20,
+            }];
+            write_compressed_u32(&mut blob, method.parameter_count as u32);
+            blob.resize(blob.len() + method.parameter_count as usize + 1, object);
+            return self.blobs.add(&blob);
+        };
+        let mut blob = vec![signature.calling_convention.bits()];
+        if signature.is_generic() {
+            write_compressed_u32(&mut blob, signature.generic_parameters.len() as u32);
+        }
+        write_compressed_u32(&mut blob, signature.parameters.len() as u32);
+        self.write_type(&signature.return_type, &mut blob, 0);
+        for param in &signature.parameters {
+            self.write_type(&param.ty, &mut blob, 0);
+        }
+        self.blobs.add(&blob)
+    }
+
+    /// A `TypeDef`, `TypeRef` or `TypeSpec` token for a type.
+    fn type_token(&mut self, ty: &Il2CppType) -> u32 {
+        if let (TypeData::TypeDefinitionIndex(idx), false) = (ty.data, ty.byref) {
+            return self.type_def_or_ref(idx, 0);
+        }
+        let mut signature = Vec::new();
+        self.write_type(ty, &mut signature, 0);
+        if let Some(&token) = self.type_specs.get(&signature) {
+            return token;
+        }
+        let blob = self.blobs.add(&signature);
+        let rid = self.tables.push(tables::TYPE_SPEC, vec![Column::Blob(blob)]);
+        let token = token(tables::TYPE_SPEC, rid);
+        self.type_specs.insert(signature, token);
+        token
+    }
+
+    /// A `TypeDef` token for types in this image, and a `TypeRef` token for
+    /// types in other images.
+    fn type_def_or_ref(&mut self, idx: TypeDefinitionIndex, depth: usize) -> u32 {
+        if let Some(&rid) = self.type_defs.get(&idx) {
+            return token(tables::TYPE_DEF, rid);
+        }
+        if let Some(&token) = self.type_refs.get(&idx) {
+            return token;
+        }
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let Some(ty_def) = gm.type_definitions.get(idx) else {
+            return 0;
+        };
+        if depth > MAX_TYPE_DEPTH {
+            return 0;
+        }
+
+        let scope = match ty_def.declaring_type_index {
+            u32::MAX => match gm.image_of(idx).and_then(|image| gm.assemblies.get(image.assembly_index)) {
+                Some(assembly) => self.assembly_ref(assembly),
+                None => 0,
+            },
+            declaring_type => match metadata
+                .runtime_metadata
+                .metadata_registration
+                .types
+                .get(declaring_type as usize)
+                .and_then(|ty| ty.type_definition_index(metadata))
+            {
+                Some(declaring_type) => self.type_def_or_ref(declaring_type, depth + 1),
+                None => 0,
+            },
+        };
+        let name = self.strings.add(gm.string.get(ty_def.name_index).unwrap_or_default());
+        let namespace = self.strings.add(gm.string.get(ty_def.namespace_index).unwrap_or_default());
+        let rid = self.tables.push(
+            tables::TYPE_REF,
+            vec![
+                Column::Coded(CodedIndex::ResolutionScope, scope),
+                Column::String(name),
+                Column::String(namespace),
+            ],
+        );
+        let token = token(tables::TYPE_REF, rid);
+        self.type_refs.insert(idx, token);
+        token
+    }
+
+    fn assembly_ref(&mut self, assembly: &'md Il2CppAssemblyDefinition) -> u32 {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let aname = &assembly.aname;
+        let name = gm.string.get(aname.name_index).unwrap_or_default();
+        if let Some(&token) = self.assembly_refs.get(name) {
+            return token;
+        }
+        let public_key_token = if aname.public_key_token == [0; 8] {
+            0
+        } else {
+            self.blobs.add(&aname.public_key_token)
+        };
+        let name_offset = self.strings.add(name);
+        let culture = self.strings.add(gm.string.get(aname.culture_index).unwrap_or_default());
+        let rid = self.tables.push(
+            tables::ASSEMBLY_REF,
+            vec![
+                Column::U16(aname.major as u16),
+                Column::U16(aname.minor as u16),
+                Column::U16(aname.build as u16),
+                Column::U16(aname.revision as u16),
+                Column::U32(aname.flags & !0x0001),
+                Column::Blob(public_key_token),
+                Column::String(name_offset),
+                Column::String(culture),
+                Column::Blob(0),
+            ],
+        );
+        let token = token(tables::ASSEMBLY_REF, rid);
+        self.assembly_refs.insert(name, token);
+        token
+    }
+
+    /// Appends the signature of a type. See ECMA-335 II.23.2.12.
+    fn write_type(&mut self, ty: &Il2CppType, out: &mut Vec<u8>, depth: usize) {
+        let metadata = self.metadata;
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        let object = Il2CppTypeEnum::Object.to_ty();
+        if ty.byref {
+            out.push(Il2CppTypeEnum::Byref.to_ty());
+        }
+        if depth > MAX_TYPE_DEPTH {
+            out.push(object);
+            return;
+        }
+
+        match (ty.ty, ty.data) {
+            (Il2CppTypeEnum::Class | Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(idx)) => {
+                out.push(ty.ty.to_ty());
+                let token = self.type_def_or_ref(idx, 0);
+                write_compressed_u32(out, CodedIndex::TypeDefOrRef.encode(token));
+            }
+            (Il2CppTypeEnum::Var | Il2CppTypeEnum::Mvar, TypeData::GenericParameterIndex(idx)) => {
+                out.push(ty.ty.to_ty());
+                let num = metadata.global_metadata.generic_parameters.get(idx).map_or(0, |param| param.num);
+                write_compressed_u32(out, num as u32);
+            }
+            (Il2CppTypeEnum::Ptr | Il2CppTypeEnum::Szarray, TypeData::TypeIndex(element)) => match mr.types.get(element) {
+                Some(element) => {
+                    out.push(ty.ty.to_ty());
+                    self.write_type(element, out, depth + 1);
+                }
+                None => out.push(object),
+            },
+            (Il2CppTypeEnum::Array, TypeData::ArrayType(idx)) => {
+                let array = mr.array_types.get(idx);
+                match array.and_then(|array| Some((array.rank, mr.types.get(array.elem_ty)?))) {
+                    Some((rank, element)) => {
+                        out.push(ty.ty.to_ty());
+                        self.write_type(element, out, depth + 1);
+                        write_compressed_u32(out, rank as u32);
+                        // No sizes or lower bounds
+                        out.extend_from_slice(&[0, 0]);
+                    }
+                    None => out.push(object),
+                }
+            }
+            (Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(idx)) => {
+                let Some(generic_class) = mr.generic_classes.get(idx) else {
+                    out.push(object);
+                    return;
+                };
+                let definition = mr
+                    .types
+                    .get(generic_class.type_index)
+                    .and_then(|ty| ty.type_definition_index(metadata));
+                let Some(definition) = definition else {
+                    out.push(object);
+                    return;
+                };
+                let args = generic_class
+                    .context
+                    .class_inst_idx
+                    .and_then(|inst| mr.generic_insts.get(inst))
+                    .map(|inst| inst.types.as_slice())
+                    .unwrap_or_default();
+                let is_value_type = metadata
+                    .global_metadata
+                    .type_definitions
+                    .get(definition)
+                    .is_some_and(|ty_def| ty_def.layout_info().is_value_type);
+                out.push(ty.ty.to_ty());
+                out.push(match is_value_type {
+                    true => Il2CppTypeEnum::Valuetype.to_ty(),
+                    false => Il2CppTypeEnum::Class.to_ty(),
+                });
+                let token = self.type_def_or_ref(definition, 0);
+                write_compressed_u32(out, CodedIndex::TypeDefOrRef.encode(token));
+                write_compressed_u32(out, args.len() as u32);
+                for &arg in args {
+                    match mr.types.get(arg) {
+                        Some(arg) => self.write_type(arg, out, depth + 1),
+                        None => out.push(object),
+                    }
+                }
+            }
+            // IL2CPP does not keep function pointer signatures.
+            (Il2CppTypeEnum::Fnptr, _) => out.push(Il2CppTypeEnum::I.to_ty()),
+            (
+                Il2CppTypeEnum::Void
+                | Il2CppTypeEnum::Boolean
+                | Il2CppTypeEnum::Char
+                | Il2CppTypeEnum::I1
+                | Il2CppTypeEnum::U1
+                | Il2CppTypeEnum::I2
+                | Il2CppTypeEnum::U2
+                | Il2CppTypeEnum::I4
+                | Il2CppTypeEnum::U4
+                | Il2CppTypeEnum::I8
+                | Il2CppTypeEnum::U8
+                | Il2CppTypeEnum::R4
+                | Il2CppTypeEnum::R8
+                | Il2CppTypeEnum::String
+                | Il2CppTypeEnum::Typedbyref
+                | Il2CppTypeEnum::I
+                | Il2CppTypeEnum::U
+                | Il2CppTypeEnum::Object,
+                _,
+            ) => out.push(ty.ty.to_ty()),
+            _ => out.push(object),
+        }
+    }
+
+    /// Encodes the value of a custom attribute. See ECMA-335 II.23.3.
+    fn attribute_blob(&mut self, attribute: &CustomAttribute) -> Option<Vec<u8>> {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+        let constructor = gm.methods.get(attribute.constructor)?;
+        let signature = constructor.signature(metadata)?;
+        if signature.parameters.len() != attribute.arguments.len() {
+            return None;
+        }
+
+        let mut out = vec![0x01, 0x00];
+        for (param, argument) in signature.parameters.iter().zip(&attribute.arguments) {
+            self.write_fixed_arg(&param.ty, argument, &mut out)?;
+        }
+
+        let named_count = attribute.fields.len() + attribute.properties.len();
+        out.extend_from_slice(&u16::try_from(named_count).ok()?.to_le_bytes());
+        for (field, argument) in &attribute.fields {
+            let field = gm.fields.get(*field)?;
+            let ty = types.get(field.type_index as usize)?;
+            out.push(0x53);
+            self.write_declared_type(ty, &mut out, 0)?;
+            write_ser_string(Some(gm.string.get(field.name_index).ok()?), &mut out);
+            self.write_fixed_arg(ty, argument, &mut out)?;
+        }
+        for (property, argument) in &attribute.properties {
+            let ty = self.property_type(constructor.declaring_type, *property)?;
+            let property = gm.properties.get(*property)?;
+            out.push(0x54);
+            self.write_declared_type(&ty, &mut out, 0)?;
+            write_ser_string(Some(gm.string.get(property.name_index).ok()?), &mut out);
+            self.write_fixed_arg(&ty, argument, &mut out)?;
+        }
+        Some(out)
+    }
+
+    /// The type of a property of `ty` or one of its base types.
+    fn property_type(&self, ty: TypeDefinitionIndex, property: PropertyIndex) -> Option<Il2CppType> {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let mut current = gm.type_definitions.get(ty);
+        for _ in 0..=gm.type_definitions.len() {
+            let ty_def = current?;
+            if ty_def.property_start.indices(ty_def.property_count as u32).any(|idx| idx == property) {
+                let property = gm.properties.get(property)?;
+                let method = |index| accessor(ty_def, index).and_then(|idx| gm.methods.get(idx));
+                if let Some(getter) = method(property.get) {
+                    return Some(getter.signature(metadata)?.return_type);
+                }
+                let setter = method(property.set)?;
+                return setter.signature(metadata)?.parameters.last().map(|param| param.ty);
+            }
+            current = ty_def.base_type_definition(metadata).and_then(|base| gm.type_definitions.get(base));
+        }
+        None
+    }
+
+    /// Writes an argument whose type is `declared`.
+    fn write_fixed_arg(&mut self, declared: &Il2CppType, argument: &AttributeArgument, out: &mut Vec<u8>) -> Option<()> {
+        let metadata = self.metadata;
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+        match (declared.ty, declared.data) {
+            // Boxed values are prefixed with their type.
+            (Il2CppTypeEnum::Object, _) => {
+                self.write_argument_type(&argument.ty, out)?;
+                self.write_value(argument, out)
+            }
+            (Il2CppTypeEnum::Szarray, TypeData::TypeIndex(element)) => {
+                let element = types.get(element)?;
+                match &argument.value {
+                    AttributeValue::Array(None) => out.extend_from_slice(&u32::MAX.to_le_bytes()),
+                    AttributeValue::Array(Some(elements)) => {
+                        out.extend_from_slice(&(elements.len() as u32).to_le_bytes());
+                        for argument in elements {
+                            self.write_fixed_arg(element, argument, out)?;
+                        }
+                    }
+                    _ => return None,
+                }
+                Some(())
+            }
+            _ => self.write_value(argument, out),
+        }
+    }
+
+    /// Writes a value in the encoding of its own type.
+    fn write_value(&mut self, argument: &AttributeArgument, out: &mut Vec<u8>) -> Option<()> {
+        match &argument.value {
+            AttributeValue::Constant(value) => match *value {
+                ConstantValue::Boolean(value) => out.push(value as u8),
+                ConstantValue::Char(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::I1(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::U1(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::I2(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::U2(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::I4(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::U4(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::I8(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::U8(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::R4(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::R8(value) => out.extend_from_slice(&value.to_le_bytes()),
+                ConstantValue::String(value) => write_ser_string(Some(value), out),
+                ConstantValue::Null => write_ser_string(None, out),
+            },
+            AttributeValue::Type(None) => write_ser_string(None, out),
+            AttributeValue::Type(Some(ty)) => {
+                let name = self.attribute_type_name(*ty)?;
+                write_ser_string(Some(&name), out);
+            }
+            AttributeValue::Array(None) => out.extend_from_slice(&u32::MAX.to_le_bytes()),
+            AttributeValue::Array(Some(elements)) => {
+                let boxed = argument.ty == AttributeArgumentType::Array(Box::new(AttributeArgumentType::Primitive(Il2CppTypeEnum::Object)));
+                out.extend_from_slice(&(elements.len() as u32).to_le_bytes());
+                for element in elements {
+                    if boxed {
+                        self.write_argument_type(&element.ty, out)?;
+                    }
+                    self.write_value(element, out)?;
+                }
+            }
+        }
+        Some(())
+    }
+
+    /// Writes the `FieldOrPropType` of a value.
+    fn write_argument_type(&mut self, ty: &AttributeArgumentType, out: &mut Vec<u8>) -> Option<()> {
+        match ty {
+            AttributeArgumentType::Primitive(Il2CppTypeEnum::Object) => out.push(0x51),
+            AttributeArgumentType::Primitive(primitive) => out.push(primitive.to_ty()),
+            AttributeArgumentType::Type => out.push(0x50),
+            AttributeArgumentType::Enum(ty) => {
+                out.push(0x55);
+                let name = self.attribute_type_name(*ty)?;
+                write_ser_string(Some(&name), out);
+            }
+            AttributeArgumentType::Array(element) => {
+                out.push(Il2CppTypeEnum::Szarray.to_ty());
+                self.write_argument_type(element, out)?;
+            }
+        }
+        Some(())
+    }
+
+    /// Writes the `FieldOrPropType` of a field or property.
+    fn write_declared_type(&mut self, ty: &Il2CppType, out: &mut Vec<u8>, depth: usize) -> Option<()> {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+        match (ty.ty, ty.data) {
+            (Il2CppTypeEnum::Object, _) => out.push(0x51),
+            (Il2CppTypeEnum::Szarray, TypeData::TypeIndex(element)) if depth == 0 => {
+                out.push(Il2CppTypeEnum::Szarray.to_ty());
+                self.write_declared_type(types.get(element)?, out, depth + 1)?;
+            }
+            (Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(idx)) => {
+                let ty_def = gm.type_definitions.get(idx)?;
+                let is_type = gm.string.get(ty_def.namespace_index) == Ok("System")
+                    && gm.string.get(ty_def.name_index) == Ok("Type");
+                if !is_type {
+                    return None;
+                }
+                out.push(0x50);
+            }
+            (Il2CppTypeEnum::Valuetype, TypeData::TypeDefinitionIndex(idx)) => {
+                let ty_def = gm.type_definitions.get(idx)?;
+                if !ty_def.layout_info().is_enum {
+                    return None;
+                }
+                out.push(0x55);
+                let name = self.attribute_type_name(ty_def.byval_type_index as usize)?;
+                write_ser_string(Some(&name), out);
+            }
+            (
+                Il2CppTypeEnum::Boolean
+                | Il2CppTypeEnum::Char
+                | Il2CppTypeEnum::I1
+                | Il2CppTypeEnum::U1
+                | Il2CppTypeEnum::I2
+                | Il2CppTypeEnum::U2
+                | Il2CppTypeEnum::I4
+                | Il2CppTypeEnum::U4
+                | Il2CppTypeEnum::I8
+                | Il2CppTypeEnum::U8
+                | Il2CppTypeEnum::R4
+                | Il2CppTypeEnum::R8
+                | Il2CppTypeEnum::String,
+                _,
+            ) => out.push(ty.ty.to_ty()),
+            _ => return None,
+        }
+        Some(())
+    }
+
+    /// The name of a type as it is written in custom attributes, qualified
+    /// with its assembly if it is not defined in this image or `mscorlib`.
+    fn attribute_type_name(&self, ty: usize) -> Option<String> {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let ty = metadata.runtime_metadata.metadata_registration.types.get(ty)?;
+        let mut name = self.names.format(ty);
+        let definition = InflatedType::new(metadata, ty).type_definition_index();
+        if let Some(image) = definition.and_then(|definition| gm.image_of(definition)) {
+            let image_name = gm.string.get(image.name_index).ok()?;
+            if !std::ptr::eq(image, self.image) && image_name != "mscorlib.dll" {
+                name.push_str(", ");
+                name.push_str(image_name.strip_suffix(".dll").unwrap_or(image_name));
+            }
+        }
+        Some(name)
+    }
+}
+
+/// The accessor of a property or event, stored relative to the first method of
+/// the declaring type.
+fn accessor(ty_def: &Il2CppTypeDefinition, index: u32) -> Option<MethodIndex> {
+    match index {
+        u32::MAX => None,
+        index => Some(MethodIndex::new(ty_def.method_start.index().checked_add(index)?)),
+    }
+}
+
+/// Appends a `SerString`, which is `0xFF` for `null`.
+fn write_ser_string(string: Option<&str>, out: &mut Vec<u8>) {
+    match string {
+        Some(string) => {
+            write_compressed_u32(out, string.len() as u32);
+            out.extend_from_slice(string.as_bytes());
+        }
+        None => out.push(0xFF),
+    }
+}
+
+/// Pads a heap to a multiple of 4 bytes.
+fn pad(data: &mut Vec<u8>) {
+    while !data.len().is_multiple_of(4) {
+        data.push(0);
+    }
+}
+
+/// Derives a module version ID from the image name, so that writing the same
+/// image twice gives the same output.
+fn mvid(name: &str) -> [u8; 16] {
+    // 128-bit FNV-1a
+    let mut hash: u128 = 0x6c62272e07bb014262b821756295c58d;
+    for byte in name.bytes() {
+        hash ^= byte as u128;
+        hash = hash.wrapping_mul(0x0000000001000000000000000000013B);
+    }
+    let mut guid = hash.to_le_bytes();
+    // Mark it as a version 4 GUID.
+    guid[7] = (guid[7] & 0x0F) | 0x40;
+    guid[8] = (guid[8] & 0x3F) | 0x80;
+    guid
+}
@@ -0,0 +1,173 @@
+//! The `#Strings`, `#Blob`, `#GUID` and `#US` metadata heaps. See ECMA-335
+//! II.24.2.
+
+use std::collections::HashMap;
+
+/// Appends `value` in the compressed unsigned integer encoding of ECMA-335
+/// II.23.2.
+pub(super) fn write_compressed_u32(out: &mut Vec<u8>, value: u32) {
+    match value {
+        0..=0x7F => out.push(value as u8),
+        0x80..=0x3FFF => out.extend_from_slice(&(value as u16 | 0x8000).to_be_bytes()),
+        _ => out.extend_from_slice(&(value | 0xC000_0000).to_be_bytes()),
+    }
+}
+
+/// Null-terminated UTF-8 strings. Offset `0` is the empty string.
+pub(super) struct StringHeap {
+    data: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl StringHeap {
+    pub fn new() -> Self {
+        Self {
+            data: vec![0],
+            offsets: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, string: &str) -> u32 {
+        if string.is_empty() {
+            return 0;
+        }
+        if let Some(&offset) = self.offsets.get(string) {
+            return offset;
+        }
+        let offset = self.data.len() as u32;
+        // Strings may not contain null characters.
+        self.data.extend(string.bytes().filter(|&b| b != 0));
+        self.data.push(0);
+        self.offsets.insert(string.to_string(), offset);
+        offset
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Length prefixed binary data. Offset `0` is the empty blob.
+pub(super) struct BlobHeap {
+    data: Vec<u8>,
+    offsets: HashMap<Vec<u8>, u32>,
+}
+
+impl BlobHeap {
+    pub fn new() -> Self {
+        Self {
+            data: vec![0],
+            offsets: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, blob: &[u8]) -> u32 {
+        if blob.is_empty() {
+            return 0;
+        }
+        if let Some(&offset) = self.offsets.get(blob) {
+            return offset;
+        }
+        let offset = self.data.len() as u32;
+        write_compressed_u32(&mut self.data, blob.len() as u32);
+        self.data.extend_from_slice(blob);
+        self.offsets.insert(blob.to_vec(), offset);
+        offset
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// 16 byte GUIDs. Indices start at `1`.
+pub(super) struct GuidHeap {
+    data: Vec<u8>,
+}
+
+impl GuidHeap {
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    pub fn add(&mut self, guid: [u8; 16]) -> u32 {
+        self.data.extend_from_slice(&guid);
+        (self.data.len() / 16) as u32
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compressed(value: u32) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_compressed_u32(&mut out, value);
+        out
+    }
+
+    /// Reads a compressed unsigned integer back.
+    fn read_compressed(data: &[u8]) -> (u32, usize) {
+        match data[0] {
+            b if b & 0x80 == 0 => (b as u32, 1),
+            b if b & 0xC0 == 0x80 => (u16::from_be_bytes([b & 0x3F, data[1]]) as u32, 2),
+            b => (u32::from_be_bytes([b & 0x1F, data[1], data[2], data[3]]), 4),
+        }
+    }
+
+    #[test]
+    fn compressed_u32_examples() {
+        // The examples of ECMA-335 II.23.2
+        assert_eq!(compressed(0x03), [0x03]);
+        assert_eq!(compressed(0x7F), [0x7F]);
+        assert_eq!(compressed(0x80), [0x80, 0x80]);
+        assert_eq!(compressed(0x2E57), [0xAE, 0x57]);
+        assert_eq!(compressed(0x3FFF), [0xBF, 0xFF]);
+        assert_eq!(compressed(0x4000), [0xC0, 0x00, 0x40, 0x00]);
+        assert_eq!(compressed(0x1FFF_FFFF), [0xDF, 0xFF, 0xFF, 0xFF]);
+    }
+
+    #[test]
+    fn compressed_u32_round_trip() {
+        for value in [0, 1, 0x7F, 0x80, 0x81, 0x3FFE, 0x3FFF, 0x4000, 0x12_3456, 0x1FFF_FFFF] {
+            let data = compressed(value);
+            assert_eq!(read_compressed(&data), (value, data.len()), "{value:#x}");
+        }
+    }
+
+    #[test]
+    fn strings_are_deduplicated() {
+        let mut strings = StringHeap::new();
+        assert_eq!(strings.add(""), 0);
+        let a = strings.add("System");
+        let b = strings.add("Object");
+        assert_eq!(strings.add("System"), a);
+        assert_eq!(strings.add("a\0b"), b + 7);
+        assert_eq!(strings.data(), b"\0System\0Object\0ab\0");
+    }
+
+    #[test]
+    fn blobs_are_length_prefixed() {
+        let mut blobs = BlobHeap::new();
+        assert_eq!(blobs.add(&[]), 0);
+        assert_eq!(blobs.add(&[1, 2, 3]), 1);
+        assert_eq!(blobs.add(&[1, 2, 3]), 1);
+        let long = vec![7; 0x80];
+        assert_eq!(blobs.add(&long), 5);
+        assert_eq!(&blobs.data()[..5], [0, 3, 1, 2, 3]);
+        assert_eq!(&blobs.data()[5..7], [0x80, 0x80]);
+        assert_eq!(blobs.data().len(), 7 + 0x80);
+    }
+
+    #[test]
+    fn guid_indices_start_at_one() {
+        let mut guids = GuidHeap::new();
+        assert_eq!(guids.add([1; 16]), 1);
+        assert_eq!(guids.add([2; 16]), 2);
+        assert_eq!(guids.data().len(), 32);
+    }
+}
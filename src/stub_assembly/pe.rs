@@ -0,0 +1,231 @@
+//! The PE file and CLI headers that wrap the metadata. See ECMA-335 II.25.
+
+const FILE_ALIGNMENT: u32 = 0x200;
+const SECTION_ALIGNMENT: u32 = 0x2000;
+const IMAGE_BASE: u32 = 0x1000_0000;
+const TEXT_RVA: u32 = SECTION_ALIGNMENT;
+const HEADERS_SIZE: u32 = FILE_ALIGNMENT;
+const CLI_HEADER_SIZE: u32 = 0x48;
+
+/// The RVA of the method body shared by every method.
+pub(super) const METHOD_BODY_RVA: u32 = TEXT_RVA + CLI_HEADER_SIZE;
+
+/// A method body that executes `throw null`, using the tiny header format
+/// from ECMA-335 II.25.4.2.
+pub(super) const THROW_NULL_BODY: [u8; 3] = [
+    // Tiny format, 2 bytes of code
+    0x02 | (2 << 2),
+    // ldnull
+    0x14,
+    // throw
+    0x7A,
+];
+
+const METADATA_VERSION: &[u8] = b"v4.0.30319";
+
+/// Builds the metadata root and stream headers followed by the streams.
+pub(super) fn metadata_root(streams: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(&0x424A_5342u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    let version_len = align(METADATA_VERSION.len() as u32 + 1, 4);
+    out.extend_from_slice(&version_len.to_le_bytes());
+    out.extend_from_slice(METADATA_VERSION);
+    out.resize(out.len() + version_len as usize - METADATA_VERSION.len(), 0);
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.extend_from_slice(&(streams.len() as u16).to_le_bytes());
+
+    let headers_len: u32 = streams
+        .iter()
+        .map(|(name, _)| 8 + align(name.len() as u32 + 1, 4))
+        .sum();
+    let mut offset = out.len() as u32 + headers_len;
+    for (name, data) in streams {
+        out.extend_from_slice(&offset.to_le_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        out.extend_from_slice(name.as_bytes());
+        out.resize(out.len() + (align(name.len() as u32 + 1, 4) as usize - name.len()), 0);
+        offset += data.len() as u32;
+    }
+    for (_, data) in streams {
+        out.extend_from_slice(data);
+    }
+    out
+}
+
+/// Builds a PE32 image containing a single `.text` section with the CLI
+/// header, the shared method body and the metadata.
+pub(super) fn write_image(metadata: &[u8]) -> Vec<u8> {
+    let metadata_rva = align(METHOD_BODY_RVA + THROW_NULL_BODY.len() as u32, 4);
+    let text_size = metadata_rva - TEXT_RVA + metadata.len() as u32;
+    let raw_text_size = align(text_size, FILE_ALIGNMENT);
+    let image_size = align(TEXT_RVA + text_size, SECTION_ALIGNMENT);
+
+    let mut out = Vec::with_capacity((HEADERS_SIZE + raw_text_size) as usize);
+
+    // DOS header, with the offset of the PE header at 0x3C
+    let mut dos_header = [0u8; 0x80];
+    dos_header[..2].copy_from_slice(b"MZ");
+    dos_header[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+    out.extend_from_slice(&dos_header);
+
+    // PE signature and COFF header
+    out.extend_from_slice(b"PE\0\0");
+    push_u16(&mut out, 0x014C); // Machine: i386
+    push_u16(&mut out, 1); // NumberOfSections
+    push_u32(&mut out, 0); // TimeDateStamp
+    push_u32(&mut out, 0); // PointerToSymbolTable
+    push_u32(&mut out, 0); // NumberOfSymbols
+    push_u16(&mut out, 0xE0); // SizeOfOptionalHeader
+    push_u16(&mut out, 0x2102); // Characteristics: executable, 32 bit, DLL
+
+    // Optional header
+    push_u16(&mut out, 0x010B); // Magic: PE32
+    out.extend_from_slice(&[8, 0]); // LinkerVersion
+    push_u32(&mut out, raw_text_size); // SizeOfCode
+    push_u32(&mut out, 0); // SizeOfInitializedData
+    push_u32(&mut out, 0); // SizeOfUninitializedData
+    push_u32(&mut out, 0); // AddressOfEntryPoint
+    push_u32(&mut out, TEXT_RVA); // BaseOfCode
+    push_u32(&mut out, 0); // BaseOfData
+    push_u32(&mut out, IMAGE_BASE);
+    push_u32(&mut out, SECTION_ALIGNMENT);
+    push_u32(&mut out, FILE_ALIGNMENT);
+    push_u16(&mut out, 4); // MajorOperatingSystemVersion
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 0); // MajorImageVersion
+    push_u16(&mut out, 0);
+    push_u16(&mut out, 4); // MajorSubsystemVersion
+    push_u16(&mut out, 0);
+    push_u32(&mut out, 0); // Win32VersionValue
+    push_u32(&mut out, image_size);
+    push_u32(&mut out, HEADERS_SIZE);
+    push_u32(&mut out, 0); // CheckSum
+    push_u16(&mut out, 3); // Subsystem: console
+    push_u16(&mut out, 0x8540); // DllCharacteristics: dynamic base, NX compatible, no SEH
+    push_u32(&mut out, 0x0010_0000); // SizeOfStackReserve
+    push_u32(&mut out, 0x1000); // SizeOfStackCommit
+    push_u32(&mut out, 0x0010_0000); // SizeOfHeapReserve
+    push_u32(&mut out, 0x1000); // SizeOfHeapCommit
+    push_u32(&mut out, 0); // LoaderFlags
+    push_u32(&mut out, 16); // NumberOfRvaAndSizes
+    for i in 0..16 {
+        // Only the CLI header directory is used.
+        let (rva, size) = match i {
+            14 => (TEXT_RVA, CLI_HEADER_SIZE),
+            _ => (0, 0),
+        };
+        push_u32(&mut out, rva);
+        push_u32(&mut out, size);
+    }
+
+    // Section header
+    out.extend_from_slice(b".text\0\0\0");
+    push_u32(&mut out, text_size); // VirtualSize
+    push_u32(&mut out, TEXT_RVA);
+    push_u32(&mut out, raw_text_size); // SizeOfRawData
+    push_u32(&mut out, HEADERS_SIZE); // PointerToRawData
+    push_u32(&mut out, 0); // PointerToRelocations
+    push_u32(&mut out, 0); // PointerToLinenumbers
+    push_u16(&mut out, 0); // NumberOfRelocations
+    push_u16(&mut out, 0); // NumberOfLinenumbers
+    push_u32(&mut out, 0x6000_0020); // Characteristics: code, execute, read
+    out.resize(HEADERS_SIZE as usize, 0);
+
+    // CLI header
+    push_u32(&mut out, CLI_HEADER_SIZE);
+    push_u16(&mut out, 2); // MajorRuntimeVersion
+    push_u16(&mut out, 5); // MinorRuntimeVersion
+    push_u32(&mut out, metadata_rva);
+    push_u32(&mut out, metadata.len() as u32);
+    push_u32(&mut out, 1); // Flags: IL only
+    push_u32(&mut out, 0); // EntryPointToken
+    // Resources, StrongNameSignature, CodeManagerTable, VTableFixups,
+    // ExportAddressTableJumps and ManagedNativeHeader
+    out.resize(out.len() + 6 * 8, 0);
+
+    out.extend_from_slice(&THROW_NULL_BODY);
+    out.resize((HEADERS_SIZE + metadata_rva - TEXT_RVA) as usize, 0);
+    out.extend_from_slice(metadata);
+    out.resize((HEADERS_SIZE + raw_text_size) as usize, 0);
+    out
+}
+
+fn align(value: u32, alignment: u32) -> u32 {
+    value.div_ceil(alignment) * alignment
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(data: &[u8], offset: usize) -> u16 {
+        u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+    }
+
+    fn u32_at(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn throw_null_body() {
+        // A tiny header with a code size of 2, `ldnull` and `throw`
+        assert_eq!(THROW_NULL_BODY, [0x0A, 0x14, 0x7A]);
+    }
+
+    #[test]
+    fn metadata_root_streams() {
+        let root = metadata_root(&[("#~", &[1, 2, 3, 4]), ("#Strings", &[0, 0, 0, 0])]);
+        assert_eq!(u32_at(&root, 0), 0x424A_5342);
+        assert_eq!(u32_at(&root, 12), 12);
+        assert_eq!(&root[16..28], b"v4.0.30319\0\0");
+        assert_eq!(u16_at(&root, 30), 2);
+        // Stream headers: offset, size and the padded name
+        assert_eq!(u32_at(&root, 32), 64);
+        assert_eq!(u32_at(&root, 36), 4);
+        assert_eq!(&root[40..44], b"#~\0\0");
+        assert_eq!(u32_at(&root, 44), 68);
+        assert_eq!(u32_at(&root, 48), 4);
+        assert_eq!(&root[52..64], b"#Strings\0\0\0\0");
+        assert_eq!(&root[64..], [1, 2, 3, 4, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn image_headers() {
+        let metadata = [0xAB; 0x30];
+        let image = write_image(&metadata);
+        assert_eq!(&image[..2], b"MZ");
+        let pe = u32_at(&image, 0x3C) as usize;
+        assert_eq!(&image[pe..pe + 4], b"PE\0\0");
+        assert_eq!(image.len() % FILE_ALIGNMENT as usize, 0);
+
+        // The CLI header is the 15th data directory.
+        let optional_header = pe + 24;
+        assert_eq!(u16_at(&image, optional_header), 0x010B);
+        let cli_directory = optional_header + 96 + 14 * 8;
+        assert_eq!(u32_at(&image, cli_directory), TEXT_RVA);
+        assert_eq!(u32_at(&image, cli_directory + 4), CLI_HEADER_SIZE);
+
+        // RVAs in the text section map to file offsets after the headers.
+        let file_offset = |rva: u32| (rva - TEXT_RVA + HEADERS_SIZE) as usize;
+        let cli_header = file_offset(TEXT_RVA);
+        assert_eq!(u32_at(&image, cli_header), CLI_HEADER_SIZE);
+        let body = file_offset(METHOD_BODY_RVA);
+        assert_eq!(image[body..body + THROW_NULL_BODY.len()], THROW_NULL_BODY);
+        let metadata_rva = u32_at(&image, cli_header + 8);
+        assert_eq!(metadata_rva % 4, 0);
+        assert_eq!(u32_at(&image, cli_header + 12), metadata.len() as u32);
+        let start = file_offset(metadata_rva);
+        assert_eq!(image[start..start + metadata.len()], metadata);
+    }
+}
@@ -0,0 +1,253 @@
+//! The `#~` metadata table stream. See ECMA-335 II.22 and II.24.2.6.
+
+pub(super) const MODULE: u8 = 0x00;
+pub(super) const TYPE_REF: u8 = 0x01;
+pub(super) const TYPE_DEF: u8 = 0x02;
+pub(super) const FIELD: u8 = 0x04;
+pub(super) const METHOD_DEF: u8 = 0x06;
+pub(super) const PARAM: u8 = 0x08;
+pub(super) const INTERFACE_IMPL: u8 = 0x09;
+pub(super) const MEMBER_REF: u8 = 0x0A;
+pub(super) const CONSTANT: u8 = 0x0B;
+pub(super) const CUSTOM_ATTRIBUTE: u8 = 0x0C;
+pub(super) const CLASS_LAYOUT: u8 = 0x0F;
+pub(super) const FIELD_LAYOUT: u8 = 0x10;
+pub(super) const EVENT_MAP: u8 = 0x12;
+pub(super) const EVENT: u8 = 0x14;
+pub(super) const PROPERTY_MAP: u8 = 0x15;
+pub(super) const PROPERTY: u8 = 0x17;
+pub(super) const METHOD_SEMANTICS: u8 = 0x18;
+pub(super) const MODULE_REF: u8 = 0x1A;
+pub(super) const TYPE_SPEC: u8 = 0x1B;
+pub(super) const ASSEMBLY: u8 = 0x20;
+pub(super) const ASSEMBLY_REF: u8 = 0x23;
+pub(super) const NESTED_CLASS: u8 = 0x29;
+pub(super) const GENERIC_PARAM: u8 = 0x2A;
+pub(super) const GENERIC_PARAM_CONSTRAINT: u8 = 0x2C;
+
+/// Marks an unused tag of a coded index.
+const UNUSED: u8 = 0xFF;
+
+const TABLE_COUNT: usize = 64;
+
+/// Builds a token from a table and a 1-based row index.
+pub(super) fn token(table: u8, rid: u32) -> u32 {
+    (table as u32) << 24 | rid
+}
+
+/// An index that can point into one of several tables. See ECMA-335
+/// II.24.2.6.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum CodedIndex {
+    TypeDefOrRef,
+    HasConstant,
+    HasCustomAttribute,
+    MemberRefParent,
+    HasSemantics,
+    ResolutionScope,
+    TypeOrMethodDef,
+    CustomAttributeType,
+}
+
+impl CodedIndex {
+    fn tables(self) -> &'static [u8] {
+        match self {
+            CodedIndex::TypeDefOrRef => &[TYPE_DEF, TYPE_REF, TYPE_SPEC],
+            CodedIndex::HasConstant => &[FIELD, PARAM, PROPERTY],
+            CodedIndex::HasCustomAttribute => &[
+                METHOD_DEF, FIELD, TYPE_REF, TYPE_DEF, PARAM, INTERFACE_IMPL, MEMBER_REF, MODULE, 0x0E, PROPERTY,
+                EVENT, 0x11, MODULE_REF, TYPE_SPEC, ASSEMBLY, ASSEMBLY_REF, 0x26, 0x27, 0x28, GENERIC_PARAM,
+                GENERIC_PARAM_CONSTRAINT, 0x2B,
+            ],
+            CodedIndex::MemberRefParent => &[TYPE_DEF, TYPE_REF, MODULE_REF, METHOD_DEF, TYPE_SPEC],
+            CodedIndex::HasSemantics => &[EVENT, PROPERTY],
+            CodedIndex::ResolutionScope => &[MODULE, MODULE_REF, ASSEMBLY_REF, TYPE_REF],
+            CodedIndex::TypeOrMethodDef => &[TYPE_DEF, METHOD_DEF],
+            CodedIndex::CustomAttributeType => &[UNUSED, UNUSED, METHOD_DEF, MEMBER_REF, UNUSED],
+        }
+    }
+
+    fn tag_bits(self) -> u32 {
+        let tags = self.tables().len() as u32;
+        u32::BITS - (tags - 1).leading_zeros()
+    }
+
+    /// Encodes `token` as this kind of coded index. A null token encodes to
+    /// `0`.
+    pub fn encode(self, token: u32) -> u32 {
+        let rid = token & 0x00FF_FFFF;
+        if rid == 0 {
+            return 0;
+        }
+        let table = (token >> 24) as u8;
+        let tag = self.tables().iter().position(|&t| t == table).unwrap_or(0) as u32;
+        rid << self.tag_bits() | tag
+    }
+}
+
+/// A column value in a table row.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum Column {
+    U16(u16),
+    U32(u32),
+    String(u32),
+    Guid(u32),
+    Blob(u32),
+    /// A 1-based row index into a table.
+    Index(u8, u32),
+    /// A token, stored as a coded index.
+    Coded(CodedIndex, u32),
+}
+
+/// The rows of every metadata table.
+pub(super) struct Tables {
+    rows: Vec<Vec<Vec<Column>>>,
+    sorted: u64,
+}
+
+/// The sizes of the heaps, which decide the width of heap indices.
+pub(super) struct HeapSizes {
+    pub strings: usize,
+    pub guids: usize,
+    pub blobs: usize,
+}
+
+impl Tables {
+    pub fn new() -> Self {
+        Self {
+            rows: vec![Vec::new(); TABLE_COUNT],
+            sorted: 0,
+        }
+    }
+
+    /// Adds a row and returns its 1-based index.
+    pub fn push(&mut self, table: u8, row: Vec<Column>) -> u32 {
+        let rows = &mut self.rows[table as usize];
+        rows.push(row);
+        rows.len() as u32
+    }
+
+    pub fn len(&self, table: u8) -> u32 {
+        self.rows[table as usize].len() as u32
+    }
+
+    /// Sorts a table by the value of one of its columns, keeping the order
+    /// of rows with equal values, and marks it as sorted.
+    ///
+    /// Rows of sorted tables must not be referenced by index.
+    pub fn sort_by_column(&mut self, table: u8, column: usize) {
+        self.rows[table as usize].sort_by_key(|row| match row[column] {
+            Column::Coded(coded, token) => coded.encode(token),
+            Column::Index(_, rid) => rid,
+            Column::U16(value) => value as u32,
+            Column::U32(value) | Column::String(value) | Column::Guid(value) | Column::Blob(value) => value,
+        });
+        self.sorted |= 1 << table;
+    }
+
+    /// Marks a table as sorted without sorting it, for tables whose rows were
+    /// added in order.
+    pub fn mark_sorted(&mut self, table: u8) {
+        self.sorted |= 1 << table;
+    }
+
+    /// Serializes the `#~` stream.
+    pub fn write(&self, heaps: &HeapSizes) -> Vec<u8> {
+        let wide_strings = heaps.strings > 0xFFFF;
+        let wide_guids = heaps.guids > 0xFFFF;
+        let wide_blobs = heaps.blobs > 0xFFFF;
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&0u32.to_le_bytes());
+        out.extend_from_slice(&[2, 0]);
+        out.push(wide_strings as u8 | (wide_guids as u8) << 1 | (wide_blobs as u8) << 2);
+        out.push(1);
+
+        let valid = (0..TABLE_COUNT).filter(|&i| !self.rows[i].is_empty()).fold(0u64, |valid, i| valid | 1 << i);
+        out.extend_from_slice(&valid.to_le_bytes());
+        out.extend_from_slice(&(self.sorted & valid).to_le_bytes());
+        for rows in self.rows.iter().filter(|rows| !rows.is_empty()) {
+            out.extend_from_slice(&(rows.len() as u32).to_le_bytes());
+        }
+
+        for rows in &self.rows {
+            for row in rows {
+                for &column in row {
+                    let (value, wide) = match column {
+                        Column::U16(value) => (value as u32, false),
+                        Column::U32(value) => (value, true),
+                        Column::String(offset) => (offset, wide_strings),
+                        Column::Guid(index) => (index, wide_guids),
+                        Column::Blob(offset) => (offset, wide_blobs),
+                        Column::Index(table, rid) => (rid, self.len(table) > 0xFFFF),
+                        Column::Coded(coded, token) => (coded.encode(token), self.is_wide(coded)),
+                    };
+                    match wide {
+                        true => out.extend_from_slice(&value.to_le_bytes()),
+                        false => out.extend_from_slice(&(value as u16).to_le_bytes()),
+                    }
+                }
+            }
+        }
+
+        while out.len() % 4 != 0 {
+            out.push(0);
+        }
+        out
+    }
+
+    fn is_wide(&self, coded: CodedIndex) -> bool {
+        let max_rows = coded
+            .tables()
+            .iter()
+            .filter(|&&table| table != UNUSED)
+            .map(|&table| self.len(table))
+            .max()
+            .unwrap_or(0);
+        max_rows >= 1 << (16 - coded.tag_bits())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coded_indices() {
+        // ECMA-335 II.24.2.6: the table tag is stored in the low bits.
+        assert_eq!(CodedIndex::TypeDefOrRef.encode(token(TYPE_REF, 3)), 3 << 2 | 1);
+        assert_eq!(CodedIndex::TypeDefOrRef.encode(token(TYPE_SPEC, 1)), 1 << 2 | 2);
+        assert_eq!(CodedIndex::HasCustomAttribute.encode(token(ASSEMBLY, 1)), 1 << 5 | 14);
+        assert_eq!(CodedIndex::CustomAttributeType.encode(token(MEMBER_REF, 2)), 2 << 3 | 3);
+        assert_eq!(CodedIndex::HasSemantics.encode(token(PROPERTY, 4)), 4 << 1 | 1);
+        assert_eq!(CodedIndex::ResolutionScope.encode(token(ASSEMBLY_REF, 0)), 0);
+    }
+
+    #[test]
+    fn table_stream() {
+        let mut tables = Tables::new();
+        tables.push(MODULE, vec![Column::U16(0), Column::String(1), Column::Guid(1), Column::Guid(0), Column::Guid(0)]);
+        tables.push(TYPE_REF, vec![Column::Coded(CodedIndex::ResolutionScope, token(MODULE, 1)), Column::String(8), Column::String(0)]);
+        tables.mark_sorted(TYPE_REF);
+        let heaps = HeapSizes { strings: 16, guids: 16, blobs: 1 };
+
+        let mut expected = vec![0, 0, 0, 0, 2, 0, 0, 1];
+        expected.extend_from_slice(&0b11u64.to_le_bytes());
+        expected.extend_from_slice(&0b10u64.to_le_bytes());
+        expected.extend_from_slice(&[1, 0, 0, 0, 1, 0, 0, 0]);
+        expected.extend_from_slice(&[0, 0, 1, 0, 1, 0, 0, 0, 0, 0]);
+        expected.extend_from_slice(&[4, 0, 8, 0, 0, 0]);
+        assert_eq!(tables.write(&heaps), expected);
+    }
+
+    #[test]
+    fn wide_indices() {
+        let mut tables = Tables::new();
+        for _ in 0..0x4000 {
+            tables.push(TYPE_REF, vec![Column::U16(0)]);
+        }
+        // TypeDefOrRef has 2 tag bits, so 0x4000 rows need 4 byte indices.
+        assert!(tables.is_wide(CodedIndex::TypeDefOrRef));
+        assert!(!tables.is_wide(CodedIndex::HasSemantics));
+    }
+}
@@ -0,0 +1,107 @@
+//! Metadata built by hand for unit tests.
+
+use crate::global_metadata::transform::{self, HEADER_SIZE};
+use crate::global_metadata::{self, GenericContainerIndex, Il2CppTypeDefinition};
+use crate::runtime_metadata::{
+    Il2CppCodeRegistration, Il2CppMetadataRegistration, Il2CppType, Il2CppTypeEnum, RegistrationAddresses,
+    RuntimeMetadata, TypeData,
+};
+use crate::Metadata;
+use binde::{BinaryDeserialize, LittleEndian};
+
+const SANITY: u32 = 0xFAB11BAF;
+const VERSION: u32 = 31;
+
+/// Empty metadata whose string table holds `strings`, which are separated by
+/// null characters.
+pub(crate) fn metadata(strings: &str) -> Metadata<'static, 'static> {
+    let mut data = vec![0; HEADER_SIZE];
+    data[0..4].copy_from_slice(&SANITY.to_le_bytes());
+    data[4..8].copy_from_slice(&VERSION.to_le_bytes());
+    if !strings.is_empty() {
+        let pos = transform::table_names().iter().position(|&name| name == "string").unwrap();
+        let entry = 8 + pos * 8;
+        data[entry..entry + 4].copy_from_slice(&(HEADER_SIZE as u32).to_le_bytes());
+        data[entry + 4..entry + 8].copy_from_slice(&(strings.len() as u32).to_le_bytes());
+        data.extend_from_slice(strings.as_bytes());
+    }
+    let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+
+    Metadata {
+        global_metadata: global_metadata::deserialize(data).unwrap(),
+        runtime_metadata: RuntimeMetadata {
+            code_registration: Il2CppCodeRegistration {
+                reverse_pinvoke_wrappers: Vec::new(),
+                generic_method_pointers: Vec::new(),
+                generic_adjustor_thunks: Vec::new(),
+                invoker_pointers: Vec::new(),
+                unresolved_indirect_call_pointers: Vec::new(),
+                code_gen_modules: Vec::new(),
+            },
+            metadata_registration: Il2CppMetadataRegistration {
+                generic_classes: Vec::new(),
+                generic_insts: Vec::new(),
+                generic_method_table: Vec::new(),
+                types: Vec::new(),
+                array_types: Vec::new(),
+                method_specs: Vec::new(),
+                field_offsets: None,
+                type_definition_sizes: None,
+            },
+            registration_addresses: RegistrationAddresses::default(),
+            segments: Vec::new(),
+        },
+    }
+}
+
+/// A metadata item whose fields are all zero.
+pub(crate) fn zeroed<T: BinaryDeserialize>() -> T {
+    T::deserialize::<LittleEndian, _>(&vec![0; T::SIZE][..]).unwrap()
+}
+
+/// A class without a base type, fields or methods, with the default packing
+/// and class size.
+pub(crate) fn type_definition() -> Il2CppTypeDefinition {
+    let mut ty_def: Il2CppTypeDefinition = zeroed();
+    ty_def.declaring_type_index = u32::MAX;
+    ty_def.parent_index = u32::MAX;
+    ty_def.element_type_index = u32::MAX;
+    ty_def.generic_container_index = GenericContainerIndex::new(u32::MAX);
+    ty_def.bitfield = 1 << 10 | 1 << 11;
+    ty_def
+}
+
+pub(crate) fn ty(ty: Il2CppTypeEnum, data: TypeData) -> Il2CppType {
+    Il2CppType {
+        data,
+        attrs: 0,
+        ty,
+        byref: false,
+        pinned: false,
+        valuetype: false,
+    }
+}
+
+/// Appends `value` in the compressed encoding of `vm/BlobReader.cpp`.
+pub(crate) fn write_compressed_u32(out: &mut Vec<u8>, value: u32) {
+    match value {
+        0..=0x7F => out.push(value as u8),
+        0x80..=0x3FFF => out.extend_from_slice(&(value as u16 | 0x8000).to_be_bytes()),
+        0x4000..=0x1FFF_FFFF => out.extend_from_slice(&(value | 0xC000_0000).to_be_bytes()),
+        _ => {
+            out.push(0xF0);
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// Appends `value` in the signed compressed encoding of `vm/BlobReader.cpp`,
+/// which stores the sign in the lowest bit.
+pub(crate) fn write_compressed_i32(out: &mut Vec<u8>, value: i32) {
+    let encoded = match value {
+        i32::MIN => u32::MAX,
+        0.. => (value as u32) << 1,
+        _ => ((-(value + 1)) as u32) << 1 | 1,
+    };
+    write_compressed_u32(out, encoded);
+}
@@ -0,0 +1,387 @@
+//! Reverse lookup from structural types to runtime type table indices.
+//!
+//! The runtime type tables are only indexed by position, and the same type may
+//! be referenced from several places. [`TypeMap`] hash-conses every type in
+//! [`Il2CppMetadataRegistration::types`] into a shared node, so that finding
+//! the index of a type described by an [`InflatedType`] only takes one hash
+//! lookup per nested type.
+//!
+//! [`Il2CppMetadataRegistration::types`]: crate::runtime_metadata::Il2CppMetadataRegistration::types
+
+use crate::generics::{InflatedType, InflatedTypeData};
+use crate::global_metadata::{GenericParameterIndex, TypeDefinitionIndex};
+use crate::runtime_metadata::{Il2CppTypeEnum, TypeData};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+use std::collections::HashMap;
+
+type NodeId = u32;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Node {
+    ty: Il2CppTypeEnum,
+    byref: bool,
+    data: NodeData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum NodeData {
+    Definition(TypeDefinitionIndex),
+    GenericParameter(GenericParameterIndex),
+    Element(NodeId),
+    Array(NodeId, u8),
+    GenericInstance(TypeDefinitionIndex, Box<[NodeId]>),
+}
+
+/// A reverse index from structural types to the runtime type tables.
+pub struct TypeMap {
+    nodes: HashMap<Node, NodeId>,
+    /// The first index into `types` for every node, if the node appears there.
+    node_types: Vec<Option<usize>>,
+    generic_classes: HashMap<NodeId, usize>,
+    generic_insts: HashMap<Box<[NodeId]>, usize>,
+    array_types: HashMap<(NodeId, u8), usize>,
+}
+
+struct Builder<'a> {
+    metadata: &'a Metadata<'a, 'a>,
+    map: TypeMap,
+    /// The node of every index into `types`, once it has been interned.
+    type_nodes: Vec<Option<NodeId>>,
+}
+
+impl TypeMap {
+    pub fn new(metadata: &Metadata) -> Self {
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        let mut builder = Builder {
+            metadata,
+            map: TypeMap {
+                nodes: HashMap::with_capacity(mr.types.len()),
+                node_types: Vec::with_capacity(mr.types.len()),
+                generic_classes: HashMap::with_capacity(mr.generic_classes.len()),
+                generic_insts: HashMap::with_capacity(mr.generic_insts.len()),
+                array_types: HashMap::with_capacity(mr.array_types.len()),
+            },
+            type_nodes: vec![None; mr.types.len()],
+        };
+
+        for i in 0..mr.types.len() {
+            if let Some(node) = builder.intern_type(i, 0) {
+                let first = &mut builder.map.node_types[node as usize];
+                first.get_or_insert(i);
+            }
+        }
+        for (i, inst) in mr.generic_insts.iter().enumerate() {
+            let args: Option<Box<[NodeId]>> = inst.types.iter().map(|&ty| builder.intern_type(ty, 0)).collect();
+            if let Some(args) = args {
+                builder.map.generic_insts.entry(args).or_insert(i);
+            }
+        }
+        for (i, generic_class) in mr.generic_classes.iter().enumerate() {
+            let Some(definition) = generic_class.type_definition_index(metadata) else {
+                continue;
+            };
+            let args = generic_class
+                .context
+                .class_inst_idx
+                .and_then(|inst| mr.generic_insts.get(inst))
+                .map(|inst| inst.types.iter().map(|&ty| builder.intern_type(ty, 0)).collect())
+                .unwrap_or_else(|| Some(Box::default()));
+            if let Some(args) = args {
+                let node = builder.intern(Node {
+                    ty: Il2CppTypeEnum::Genericinst,
+                    byref: false,
+                    data: NodeData::GenericInstance(definition, args),
+                });
+                builder.map.generic_classes.entry(node).or_insert(i);
+            }
+        }
+        for (i, array) in mr.array_types.iter().enumerate() {
+            if let Some(element) = builder.intern_type(array.elem_ty, 0) {
+                builder.map.array_types.entry((element, array.rank)).or_insert(i);
+            }
+        }
+
+        builder.map
+    }
+
+    /// Finds the index into
+    /// [`Il2CppMetadataRegistration::types`](crate::runtime_metadata::Il2CppMetadataRegistration::types)
+    /// of `ty`.
+    pub fn type_index(&self, ty: &InflatedType) -> Option<usize> {
+        self.node_types[self.find(ty, 0)? as usize]
+    }
+
+    /// Whether `ty` exists in the runtime type table.
+    pub fn contains(&self, ty: &InflatedType) -> bool {
+        self.type_index(ty).is_some()
+    }
+
+    /// Finds the index into
+    /// [`Il2CppMetadataRegistration::generic_classes`](crate::runtime_metadata::Il2CppMetadataRegistration::generic_classes)
+    /// of the instantiation of `definition` with `args`.
+    pub fn generic_class_index(&self, definition: TypeDefinitionIndex, args: &[InflatedType]) -> Option<usize> {
+        let node = Node {
+            ty: Il2CppTypeEnum::Genericinst,
+            byref: false,
+            data: NodeData::GenericInstance(definition, self.find_all(args)?),
+        };
+        self.generic_classes.get(self.nodes.get(&node)?).copied()
+    }
+
+    /// Finds the index into
+    /// [`Il2CppMetadataRegistration::generic_insts`](crate::runtime_metadata::Il2CppMetadataRegistration::generic_insts)
+    /// of the type argument list `args`.
+    pub fn generic_inst_index(&self, args: &[InflatedType]) -> Option<usize> {
+        self.generic_insts.get(&self.find_all(args)?).copied()
+    }
+
+    /// Finds the index into
+    /// [`Il2CppMetadataRegistration::array_types`](crate::runtime_metadata::Il2CppMetadataRegistration::array_types)
+    /// of the array of `element` with `rank` dimensions.
+    pub fn array_type_index(&self, element: &InflatedType, rank: u8) -> Option<usize> {
+        self.array_types.get(&(self.find(element, 0)?, rank)).copied()
+    }
+
+    fn find_all(&self, types: &[InflatedType]) -> Option<Box<[NodeId]>> {
+        types.iter().map(|ty| self.find(ty, 0)).collect()
+    }
+
+    fn find(&self, ty: &InflatedType, depth: usize) -> Option<NodeId> {
+        if depth > MAX_TYPE_DEPTH {
+            return None;
+        }
+        let data = match &ty.data {
+            InflatedTypeData::Definition(idx) => NodeData::Definition(*idx),
+            InflatedTypeData::GenericParameter(idx) => NodeData::GenericParameter(*idx),
+            InflatedTypeData::Element(element) => NodeData::Element(self.find(element, depth + 1)?),
+            InflatedTypeData::Array { element, rank } => NodeData::Array(self.find(element, depth + 1)?, *rank),
+            InflatedTypeData::GenericInstance { definition, args } => {
+                let args = args.iter().map(|arg| self.find(arg, depth + 1)).collect::<Option<_>>()?;
+                NodeData::GenericInstance(*definition, args)
+            }
+            InflatedTypeData::Invalid => return None,
+        };
+        let node = Node {
+            ty: ty.ty,
+            byref: ty.byref,
+            data,
+        };
+        self.nodes.get(&node).copied()
+    }
+}
+
+impl Builder<'_> {
+    fn intern(&mut self, node: Node) -> NodeId {
+        if let Some(&id) = self.map.nodes.get(&node) {
+            return id;
+        }
+        let id = self.map.node_types.len() as NodeId;
+        self.map.nodes.insert(node, id);
+        self.map.node_types.push(None);
+        id
+    }
+
+    fn intern_type(&mut self, index: usize, depth: usize) -> Option<NodeId> {
+        if let Some(id) = *self.type_nodes.get(index)? {
+            return Some(id);
+        }
+        if depth > MAX_TYPE_DEPTH {
+            return None;
+        }
+
+        let metadata = self.metadata;
+        let mr = &metadata.runtime_metadata.metadata_registration;
+        let ty = &mr.types[index];
+        let data = match ty.data {
+            TypeData::TypeDefinitionIndex(idx) => NodeData::Definition(idx),
+            TypeData::GenericParameterIndex(idx) => NodeData::GenericParameter(idx),
+            TypeData::TypeIndex(elem) => NodeData::Element(self.intern_type(elem, depth + 1)?),
+            TypeData::ArrayType(idx) => {
+                let array = mr.array_types.get(idx)?;
+                NodeData::Array(self.intern_type(array.elem_ty, depth + 1)?, array.rank)
+            }
+            TypeData::GenericClassIndex(idx) => {
+                let generic_class = mr.generic_classes.get(idx)?;
+                let definition = generic_class.type_definition_index(metadata)?;
+                let args = match generic_class.context.class_inst_idx {
+                    Some(inst) => mr
+                        .generic_insts
+                        .get(inst)?
+                        .types
+                        .iter()
+                        .map(|&arg| self.intern_type(arg, depth + 1))
+                        .collect::<Option<_>>()?,
+                    None => Box::default(),
+                };
+                NodeData::GenericInstance(definition, args)
+            }
+        };
+
+        let id = self.intern(Node {
+            ty: ty.ty,
+            byref: ty.byref,
+            data,
+        });
+        self.type_nodes[index] = Some(id);
+        Some(id)
+    }
+}
+
+impl InflatedType {
+    /// The type of values of the type definition `idx`, such as `int` for
+    /// `System.Int32`.
+    pub fn from_definition(metadata: &Metadata, idx: TypeDefinitionIndex) -> Option<Self> {
+        let ty_def = metadata.global_metadata.type_definitions.get(idx)?;
+        let ty = metadata
+            .runtime_metadata
+            .metadata_registration
+            .types
+            .get(ty_def.byval_type_index as usize)?;
+        Some(InflatedType::new(metadata, ty))
+    }
+
+    /// An instantiation of the generic type definition `definition`.
+    pub fn generic_instance(definition: TypeDefinitionIndex, args: Vec<InflatedType>) -> Self {
+        InflatedType {
+            ty: Il2CppTypeEnum::Genericinst,
+            byref: false,
+            data: InflatedTypeData::GenericInstance { definition, args },
+        }
+    }
+
+    /// A single dimensional, zero based array of `element`.
+    pub fn sz_array(element: InflatedType) -> Self {
+        InflatedType {
+            ty: Il2CppTypeEnum::Szarray,
+            byref: false,
+            data: InflatedTypeData::Element(Box::new(element)),
+        }
+    }
+
+    /// A multidimensional array of `element`.
+    pub fn array(element: InflatedType, rank: u8) -> Self {
+        InflatedType {
+            ty: Il2CppTypeEnum::Array,
+            byref: false,
+            data: InflatedTypeData::Array {
+                element: Box::new(element),
+                rank,
+            },
+        }
+    }
+
+    /// An unmanaged pointer to `element`.
+    pub fn pointer(element: InflatedType) -> Self {
+        InflatedType {
+            ty: Il2CppTypeEnum::Ptr,
+            byref: false,
+            data: InflatedTypeData::Element(Box::new(element)),
+        }
+    }
+
+    /// This type passed by reference.
+    pub fn by_ref(self) -> Self {
+        InflatedType { byref: true, ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime_metadata::{Il2CppArrayType, Il2CppGenericClass, Il2CppGenericContext, Il2CppGenericInst};
+    use crate::test_util;
+
+    const INT32: u32 = 0;
+    const LIST: u32 = 1;
+    const STRING: u32 = 2;
+
+    /// `int`, `int[]`, `List<int>`, `int[,]` and `ref int`, with `int` stored
+    /// twice.
+    fn metadata() -> Metadata<'static, 'static> {
+        let mut metadata = test_util::metadata("");
+        let definition = |idx| TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(idx));
+        let mr = &mut metadata.runtime_metadata.metadata_registration;
+        let mut by_ref = test_util::ty(Il2CppTypeEnum::I4, definition(INT32));
+        by_ref.byref = true;
+        mr.types = vec![
+            test_util::ty(Il2CppTypeEnum::I4, definition(INT32)),
+            test_util::ty(Il2CppTypeEnum::Szarray, TypeData::TypeIndex(0)),
+            test_util::ty(Il2CppTypeEnum::Class, definition(LIST)),
+            test_util::ty(Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(0)),
+            test_util::ty(Il2CppTypeEnum::Array, TypeData::ArrayType(0)),
+            by_ref,
+            test_util::ty(Il2CppTypeEnum::I4, definition(INT32)),
+        ];
+        mr.generic_insts = vec![Il2CppGenericInst { types: vec![6] }];
+        mr.generic_classes = vec![Il2CppGenericClass {
+            type_index: 2,
+            context: Il2CppGenericContext { class_inst_idx: Some(0), method_inst_idx: None },
+        }];
+        mr.array_types = vec![Il2CppArrayType {
+            elem_ty: 0,
+            rank: 2,
+            sizes: Vec::new(),
+            lower_bounds: Vec::new(),
+        }];
+
+        let mut int32 = test_util::type_definition();
+        int32.byval_type_index = 0;
+        metadata.global_metadata.type_definitions =
+            vec![int32, test_util::type_definition(), test_util::type_definition()].into();
+        metadata
+    }
+
+    fn int(metadata: &Metadata) -> InflatedType {
+        InflatedType::from_definition(metadata, TypeDefinitionIndex::new(INT32)).unwrap()
+    }
+
+    #[test]
+    fn type_indices() {
+        let metadata = metadata();
+        let map = TypeMap::new(&metadata);
+        let list = TypeDefinitionIndex::new(LIST);
+        // Duplicate types map to their first index
+        assert_eq!(map.type_index(&int(&metadata)), Some(0));
+        assert_eq!(map.type_index(&InflatedType::sz_array(int(&metadata))), Some(1));
+        assert_eq!(map.type_index(&InflatedType::generic_instance(list, vec![int(&metadata)])), Some(3));
+        assert_eq!(map.type_index(&InflatedType::array(int(&metadata), 2)), Some(4));
+        assert_eq!(map.type_index(&int(&metadata).by_ref()), Some(5));
+        for (index, ty) in metadata.runtime_metadata.metadata_registration.types.iter().enumerate() {
+            let expected = if index == 6 { 0 } else { index };
+            assert_eq!(map.type_index(&InflatedType::new(&metadata, ty)), Some(expected));
+        }
+    }
+
+    #[test]
+    fn missing_types() {
+        let metadata = metadata();
+        let map = TypeMap::new(&metadata);
+        let list = TypeDefinitionIndex::new(LIST);
+        let string = InflatedType {
+            ty: Il2CppTypeEnum::String,
+            byref: false,
+            data: InflatedTypeData::Definition(TypeDefinitionIndex::new(STRING)),
+        };
+        assert!(!map.contains(&string));
+        assert!(!map.contains(&InflatedType::pointer(int(&metadata))));
+        assert!(!map.contains(&InflatedType::array(int(&metadata), 3)));
+        assert!(!map.contains(&InflatedType::sz_array(InflatedType::sz_array(int(&metadata)))));
+        assert!(!map.contains(&InflatedType::generic_instance(list, vec![string])));
+        assert!(!map.contains(&InflatedType::from_index(&metadata, 7)));
+    }
+
+    #[test]
+    fn table_indices() {
+        let metadata = metadata();
+        let map = TypeMap::new(&metadata);
+        let list = TypeDefinitionIndex::new(LIST);
+        assert_eq!(map.generic_class_index(list, &[int(&metadata)]), Some(0));
+        assert_eq!(map.generic_class_index(list, &[]), None);
+        assert_eq!(map.generic_class_index(TypeDefinitionIndex::new(INT32), &[int(&metadata)]), None);
+        assert_eq!(map.generic_inst_index(&[int(&metadata)]), Some(0));
+        assert_eq!(map.generic_inst_index(&[int(&metadata), int(&metadata)]), None);
+        assert_eq!(map.array_type_index(&int(&metadata), 2), Some(0));
+        assert_eq!(map.array_type_index(&int(&metadata), 1), None);
+    }
+}
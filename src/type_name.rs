@@ -0,0 +1,430 @@
+//! Configurable rendering of type names.
+//!
+//! [`TypeNameFormatter`] renders runtime types and type definitions in one of
+//! several [`TypeNameStyle`]s: C# source syntax, the syntax used by .NET
+//! reflection, or identifiers that are safe to use in generated C++ or Rust
+//! code.
+//!
+//! Custom modifiers (`modreq` and `modopt`) are not supported, since IL2CPP
+//! doesn't record them in its types. Element types that only occur in
+//! signatures, such as [`Il2CppTypeEnum::Modifier`], are rendered as
+//! placeholders like `<modifier>`.
+
+use crate::generics::{InflatedType, InflatedTypeData};
+use crate::global_metadata::{Il2CppTypeDefinition, TypeDefinitionIndex};
+use crate::runtime_metadata::{Il2CppType, Il2CppTypeEnum};
+use crate::Metadata;
+use crate::MAX_TYPE_DEPTH;
+use std::collections::HashSet;
+
+const CPP_KEYWORDS: &[&str] = &[
+    "alignas", "alignof", "and", "asm", "auto", "bool", "break", "case", "catch", "char", "class", "const",
+    "constexpr", "continue", "decltype", "default", "delete", "do", "double", "else", "enum", "explicit", "export",
+    "extern", "false", "float", "for", "friend", "goto", "if", "inline", "int", "long", "mutable", "namespace", "new",
+    "noexcept", "not", "nullptr", "operator", "or", "private", "protected", "public", "register", "return", "short",
+    "signed", "sizeof", "static", "struct", "switch", "template", "this", "throw", "true", "try", "typedef", "typeid",
+    "typename", "union", "unsigned", "using", "virtual", "void", "volatile", "while", "xor",
+];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false", "fn",
+    "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return", "self",
+    "Self", "static", "struct", "super", "trait", "true", "try", "type", "unsafe", "use", "where", "while", "yield",
+];
+
+/// The syntax used to render type names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeNameStyle {
+    /// C# source syntax, using keywords for built-in types: `int`,
+    /// `Outer.Inner`, `List<string>`, `int[,]`, `ref int`.
+    CSharp,
+    /// The syntax of `Type.FullName` in .NET reflection: `System.Int32`,
+    /// `Outer+Inner`, ``List`1[[System.String, mscorlib]]``, `System.Int32&`.
+    Reflection,
+    /// An identifier that is valid in C++, such as
+    /// `System_Collections_Generic_List_1_System_String`.
+    Cpp,
+    /// An identifier that is valid in Rust, such as
+    /// `System_Collections_Generic_List_1_System_String`.
+    Rust,
+}
+
+/// Renders type names in a [`TypeNameStyle`].
+pub struct TypeNameFormatter<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+    style: TypeNameStyle,
+    namespaces: bool,
+    generic_arity: bool,
+}
+
+impl<'md, 'gmd, 'rmd> TypeNameFormatter<'md, 'gmd, 'rmd> {
+    /// Creates a formatter that includes namespaces. The generic arity suffix
+    /// (`` `1 ``) is included in every style except
+    /// [`TypeNameStyle::CSharp`].
+    pub fn new(metadata: &'md Metadata<'gmd, 'rmd>, style: TypeNameStyle) -> Self {
+        Self {
+            metadata,
+            style,
+            namespaces: true,
+            generic_arity: style != TypeNameStyle::CSharp,
+        }
+    }
+
+    /// Sets whether type names are qualified with their namespace.
+    pub fn namespaces(mut self, namespaces: bool) -> Self {
+        self.namespaces = namespaces;
+        self
+    }
+
+    /// Sets whether generic type names keep their arity suffix, as in
+    /// ``List`1``.
+    pub fn generic_arity(mut self, generic_arity: bool) -> Self {
+        self.generic_arity = generic_arity;
+        self
+    }
+
+    pub fn style(&self) -> TypeNameStyle {
+        self.style
+    }
+
+    /// Renders a type from the runtime type tables.
+    pub fn format(&self, ty: &Il2CppType) -> String {
+        self.format_inflated(&InflatedType::new(self.metadata, ty))
+    }
+
+    /// Renders the type at `index` in
+    /// [`Il2CppMetadataRegistration::types`](crate::runtime_metadata::Il2CppMetadataRegistration::types).
+    pub fn format_index(&self, index: usize) -> String {
+        self.format_inflated(&InflatedType::from_index(self.metadata, index))
+    }
+
+    /// Renders a type that may have been inflated with a generic context.
+    pub fn format_inflated(&self, ty: &InflatedType) -> String {
+        let mut name = String::new();
+        self.push_type(ty, &mut name, 0);
+        self.finish(name)
+    }
+
+    /// Renders the name of a type definition, without generic arguments.
+    pub fn format_type_definition(&self, idx: TypeDefinitionIndex) -> String {
+        let mut name = String::new();
+        self.push_definition(idx, &mut name);
+        self.finish(name)
+    }
+
+    /// Like [`TypeNameFormatter::format_type_definition()`], for a type
+    /// definition that was already looked up.
+    pub(crate) fn format_definition(&self, ty_def: &Il2CppTypeDefinition) -> String {
+        let mut name = String::new();
+        self.push_definition_chain(ty_def, &mut name);
+        self.finish(name)
+    }
+
+    /// Renders the names of all type definitions, indexed by
+    /// [`TypeDefinitionIndex`], adding a number to names that are already
+    /// taken. Several images define types with the same name, such as
+    /// `<Module>`.
+    ///
+    /// Names in `reserved` are taken from the start. Each name with each of
+    /// `suffixes` appended is taken along with the name, for code that
+    /// derives further names from it.
+    pub fn unique_type_definition_names(&self, reserved: &[&str], suffixes: &[&str]) -> Vec<String> {
+        let mut used: HashSet<String> = reserved.iter().map(|name| name.to_string()).collect();
+        let taken = |name: &str, used: &HashSet<String>| {
+            used.contains(name) || suffixes.iter().any(|suffix| used.contains(&format!("{}{}", name, suffix)))
+        };
+        let mut names = Vec::with_capacity(self.metadata.global_metadata.type_definitions.len());
+        for i in 0..self.metadata.global_metadata.type_definitions.len() {
+            let name = self.format_type_definition(TypeDefinitionIndex::new(i as u32));
+            let mut unique = name.clone();
+            let mut n = 1;
+            while taken(&unique, &used) {
+                unique = format!("{}_{}", name, n);
+                n += 1;
+            }
+            used.extend(suffixes.iter().map(|suffix| format!("{}{}", unique, suffix)));
+            used.insert(unique.clone());
+            names.push(unique);
+        }
+        names
+    }
+
+    /// Turns the name of a member or parameter into an identifier of this
+    /// style. Names are returned unchanged by the C# and reflection styles.
+    pub fn identifier(&self, name: &str) -> String {
+        self.finish(name.to_string())
+    }
+
+    fn finish(&self, name: String) -> String {
+        match self.style {
+            TypeNameStyle::CSharp | TypeNameStyle::Reflection => name,
+            TypeNameStyle::Cpp => to_identifier(&name, CPP_KEYWORDS),
+            TypeNameStyle::Rust => to_identifier(&name, RUST_KEYWORDS),
+        }
+    }
+
+    fn push_type(&self, ty: &InflatedType, out: &mut String, depth: usize) {
+        if depth > MAX_TYPE_DEPTH {
+            out.push_str("...");
+            return;
+        }
+        let csharp = self.style == TypeNameStyle::CSharp;
+        if ty.byref && csharp {
+            out.push_str("ref ");
+        }
+
+        if let Some(name) = self.builtin_name(ty.ty) {
+            self.push_namespaced(name, out);
+        } else {
+            match &ty.data {
+                InflatedTypeData::Definition(idx) => self.push_definition(*idx, out),
+                InflatedTypeData::GenericParameter(idx) => {
+                    let gm = &self.metadata.global_metadata;
+                    let name = gm.generic_parameters.get(*idx).and_then(|param| gm.string.get(param.name_index).ok());
+                    out.push_str(name.unwrap_or("?"));
+                }
+                InflatedTypeData::Element(element) => {
+                    self.push_type(element, out, depth + 1);
+                    out.push_str(match ty.ty {
+                        Il2CppTypeEnum::Ptr => "*",
+                        _ => "[]",
+                    });
+                }
+                InflatedTypeData::Array { element, rank } => {
+                    self.push_type(element, out, depth + 1);
+                    out.push('[');
+                    match (self.style, rank) {
+                        (TypeNameStyle::Reflection, 1) => out.push('*'),
+                        _ => (1..*rank).for_each(|_| out.push(',')),
+                    }
+                    out.push(']');
+                }
+                InflatedTypeData::GenericInstance { definition, args } => {
+                    self.push_definition(*definition, out);
+                    self.push_generic_args(args, out, depth);
+                }
+                InflatedTypeData::Invalid => out.push_str("<invalid>"),
+            }
+        }
+
+        if ty.byref && !csharp {
+            out.push('&');
+        }
+    }
+
+    fn push_generic_args(&self, args: &[InflatedType], out: &mut String, depth: usize) {
+        let reflection = self.style == TypeNameStyle::Reflection;
+        out.push_str(if reflection { "[" } else { "<" });
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                out.push_str(if reflection { "," } else { ", " });
+            }
+            if reflection {
+                out.push('[');
+            }
+            self.push_type(arg, out, depth + 1);
+            if reflection {
+                if let Some(assembly) = self.assembly_name(arg) {
+                    out.push_str(", ");
+                    out.push_str(assembly);
+                }
+                out.push(']');
+            }
+        }
+        out.push_str(if reflection { "]" } else { ">" });
+    }
+
+    /// Pushes the name of a type definition, including its declaring types.
+    fn push_definition(&self, idx: TypeDefinitionIndex, out: &mut String) {
+        if let Some(ty_def) = self.metadata.global_metadata.type_definitions.get(idx) {
+            self.push_definition_chain(ty_def, out);
+        }
+    }
+
+    fn push_definition_chain(&self, ty_def: &'md Il2CppTypeDefinition, out: &mut String) {
+        let metadata = self.metadata;
+        let gm = &metadata.global_metadata;
+        let types = &metadata.runtime_metadata.metadata_registration.types;
+
+        let mut chain = vec![ty_def];
+        let mut current = ty_def;
+        while chain.len() <= gm.type_definitions.len() && current.declaring_type_index != u32::MAX {
+            let declaring_type = types
+                .get(current.declaring_type_index as usize)
+                .and_then(|ty| ty.type_definition_index(metadata))
+                .and_then(|idx| gm.type_definitions.get(idx));
+            match declaring_type {
+                Some(declaring_type) => current = declaring_type,
+                None => break,
+            }
+            chain.push(current);
+        }
+
+        let nested_separator = match self.style {
+            TypeNameStyle::Reflection => '+',
+            _ => '.',
+        };
+        for (i, ty_def) in chain.iter().rev().enumerate() {
+            if i == 0 {
+                let namespace = gm.string.get(ty_def.namespace_index).unwrap_or_default();
+                if self.namespaces && !namespace.is_empty() {
+                    out.push_str(namespace);
+                    out.push('.');
+                }
+            } else {
+                out.push(nested_separator);
+            }
+            let name = gm.string.get(ty_def.name_index).unwrap_or("?");
+            match self.generic_arity {
+                true => out.push_str(name),
+                false => out.push_str(name.split_once('`').map_or(name, |(name, _)| name)),
+            }
+        }
+    }
+
+    fn push_namespaced(&self, name: &str, out: &mut String) {
+        match name.strip_prefix("System.") {
+            Some(name) if !self.namespaces => out.push_str(name),
+            _ => out.push_str(name),
+        }
+    }
+
+    /// The name of a type that is rendered without looking at its definition.
+    pub(crate) fn builtin_name(&self, ty: Il2CppTypeEnum) -> Option<&'static str> {
+        let csharp = self.style == TypeNameStyle::CSharp;
+        Some(match ty {
+            Il2CppTypeEnum::Void if csharp => "void",
+            Il2CppTypeEnum::Boolean if csharp => "bool",
+            Il2CppTypeEnum::Char if csharp => "char",
+            Il2CppTypeEnum::I1 if csharp => "sbyte",
+            Il2CppTypeEnum::U1 if csharp => "byte",
+            Il2CppTypeEnum::I2 if csharp => "short",
+            Il2CppTypeEnum::U2 if csharp => "ushort",
+            Il2CppTypeEnum::I4 if csharp => "int",
+            Il2CppTypeEnum::U4 if csharp => "uint",
+            Il2CppTypeEnum::I8 if csharp => "long",
+            Il2CppTypeEnum::U8 if csharp => "ulong",
+            Il2CppTypeEnum::R4 if csharp => "float",
+            Il2CppTypeEnum::R8 if csharp => "double",
+            Il2CppTypeEnum::String if csharp => "string",
+            Il2CppTypeEnum::Object if csharp => "object",
+            Il2CppTypeEnum::I | Il2CppTypeEnum::Fnptr if csharp => "nint",
+            Il2CppTypeEnum::U if csharp => "nuint",
+            Il2CppTypeEnum::Void => "System.Void",
+            Il2CppTypeEnum::Boolean => "System.Boolean",
+            Il2CppTypeEnum::Char => "System.Char",
+            Il2CppTypeEnum::I1 => "System.SByte",
+            Il2CppTypeEnum::U1 => "System.Byte",
+            Il2CppTypeEnum::I2 => "System.Int16",
+            Il2CppTypeEnum::U2 => "System.UInt16",
+            Il2CppTypeEnum::I4 => "System.Int32",
+            Il2CppTypeEnum::U4 => "System.UInt32",
+            Il2CppTypeEnum::I8 => "System.Int64",
+            Il2CppTypeEnum::U8 => "System.UInt64",
+            Il2CppTypeEnum::R4 => "System.Single",
+            Il2CppTypeEnum::R8 => "System.Double",
+            Il2CppTypeEnum::String => "System.String",
+            Il2CppTypeEnum::Object => "System.Object",
+            // IL2CPP does not keep function pointer signatures, and treats
+            // function pointers like native integers.
+            Il2CppTypeEnum::I | Il2CppTypeEnum::Fnptr => "System.IntPtr",
+            Il2CppTypeEnum::U => "System.UIntPtr",
+            Il2CppTypeEnum::Typedbyref => "System.TypedReference",
+            // Markers that only occur in signatures.
+            Il2CppTypeEnum::End => "<end>",
+            Il2CppTypeEnum::Sentinel => "<sentinel>",
+            Il2CppTypeEnum::Internal => "<internal>",
+            Il2CppTypeEnum::Modifier => "<modifier>",
+            Il2CppTypeEnum::CmodReqd => "<modreq>",
+            Il2CppTypeEnum::CmodOpt => "<modopt>",
+            Il2CppTypeEnum::Pinned => "<pinned>",
+            Il2CppTypeEnum::Byref => "<byref>",
+            Il2CppTypeEnum::Enum => "<enum>",
+            _ => return None,
+        })
+    }
+
+    /// The assembly that defines `ty`, for assembly qualified names.
+    fn assembly_name(&self, ty: &InflatedType) -> Option<&'md str> {
+        let gm = &self.metadata.global_metadata;
+        let image = gm.image_of(ty.type_definition_index()?)?;
+        let name = gm.string.get(image.name_index).ok()?;
+        Some(name.strip_suffix(".dll").unwrap_or(name))
+    }
+}
+
+/// Replaces every character that may not appear in an identifier with `_`.
+fn to_identifier(name: &str, keywords: &[&str]) -> String {
+    let mut identifier = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' => identifier.push(c),
+            '*' => identifier.push_str("_Ptr"),
+            '&' => identifier.push_str("_Ref"),
+            '[' => identifier.push_str("_Array"),
+            _ if !identifier.ends_with('_') => identifier.push('_'),
+            _ => {}
+        }
+    }
+    while identifier.ends_with('_') {
+        identifier.pop();
+    }
+    if identifier.is_empty() || identifier.starts_with(|c: char| c.is_ascii_digit()) || keywords.contains(&&*identifier) {
+        identifier.insert(0, '_');
+    }
+    identifier
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::StringIndex;
+    use crate::lookup::MetadataIndex;
+    use crate::runtime_metadata::TypeData;
+    use crate::test_util;
+
+    #[test]
+    fn unique_type_definition_names() {
+        // Offsets: 1 `<Module>`, 10 `Foo`, 14 `Foo_StaticFields`
+        let mut metadata = test_util::metadata("\0<Module>\0Foo\0Foo_StaticFields\0");
+        let type_definition = |name: u32| {
+            let mut ty_def = test_util::type_definition();
+            ty_def.name_index = StringIndex::new(name);
+            ty_def
+        };
+        metadata.global_metadata.type_definitions =
+            vec![type_definition(1), type_definition(14), type_definition(10), type_definition(1)].into();
+        let names = TypeNameFormatter::new(&metadata, TypeNameStyle::Rust);
+        assert_eq!(
+            names.unique_type_definition_names(&["_Module"], &["_StaticFields"]),
+            ["_Module_1", "Foo_StaticFields", "Foo_1", "_Module_2"]
+        );
+    }
+
+    #[test]
+    fn nested_type_names() {
+        // Offsets: 1 `Game`, 6 `Outer`, 12 `Inner`
+        let mut metadata = test_util::metadata("\0Game\0Outer\0Inner\0");
+        metadata.runtime_metadata.metadata_registration.types =
+            vec![test_util::ty(Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0)))];
+        let mut outer = test_util::type_definition();
+        outer.namespace_index = StringIndex::new(1);
+        outer.name_index = StringIndex::new(6);
+        let mut inner = test_util::type_definition();
+        inner.name_index = StringIndex::new(12);
+        inner.declaring_type_index = 0;
+        metadata.global_metadata.type_definitions = vec![outer, inner].into();
+
+        let inner = TypeDefinitionIndex::new(1);
+        let name = |style| TypeNameFormatter::new(&metadata, style).format_type_definition(inner);
+        assert_eq!(name(TypeNameStyle::CSharp), "Game.Outer.Inner");
+        assert_eq!(name(TypeNameStyle::Reflection), "Game.Outer+Inner");
+        assert_eq!(name(TypeNameStyle::Rust), "Game_Outer_Inner");
+        let ty_def = metadata.global_metadata.type_definitions.get(inner).unwrap();
+        assert_eq!(ty_def.full_name(&metadata, true), "Game.Outer.Inner");
+
+        let index = MetadataIndex::new(&metadata);
+        assert_eq!(index.find_type("Game.Outer/Inner"), Some(inner));
+        assert_eq!(index.find_type("Game.Outer+Inner"), Some(inner));
+    }
+}
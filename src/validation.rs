@@ -0,0 +1,679 @@
+//! Structural validation of a global and runtime metadata pair.
+//!
+//! The metadata tables reference each other by index, and the runtime
+//! metadata is only meaningful for the global metadata file it was built
+//! with. Accessors such as [`Il2CppTypeDefinition::methods()`] return `None`
+//! for indices that are out of range, but a mismatched pair can also have
+//! indices that are in range and still point at the wrong items.
+//! [`Metadata::validate()`] checks every cross-table reference up front.
+//!
+//! [`Il2CppTypeDefinition::methods()`]: crate::global_metadata::Il2CppTypeDefinition::methods
+
+use crate::runtime_metadata::{Il2CppTypeEnum, TypeData};
+use crate::Metadata;
+use std::fmt;
+
+/// A single problem found by [`Metadata::validate()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// An index into another table is out of range.
+    IndexOutOfRange {
+        /// The table containing the bad index.
+        table: &'static str,
+        /// The position of the item in `table`.
+        index: usize,
+        /// The field of the item holding the bad index.
+        field: &'static str,
+        /// The table being indexed into.
+        target: &'static str,
+        /// The bad index.
+        value: u64,
+        /// The length of `target`.
+        len: usize,
+    },
+    /// A `start` and `count` pair describes a range that extends past the end
+    /// of the target table.
+    RangeOutOfRange {
+        table: &'static str,
+        index: usize,
+        field: &'static str,
+        target: &'static str,
+        start: u64,
+        count: u64,
+        len: usize,
+    },
+    /// A string index is out of range or does not point to valid UTF-8.
+    InvalidString {
+        table: &'static str,
+        index: usize,
+        field: &'static str,
+    },
+    /// A runtime table does not have one entry for every item in the global
+    /// metadata table it corresponds to.
+    CountMismatch {
+        table: &'static str,
+        index: Option<usize>,
+        expected: usize,
+        found: usize,
+    },
+    /// An image does not have a code gen module with the same name.
+    MissingCodeGenModule { image: String },
+    /// A code gen module has a different number of method pointers than its
+    /// image has methods.
+    MethodCountMismatch {
+        module: String,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::IndexOutOfRange { table, index, field, target, value, len } => write!(
+                f,
+                "{table}[{index}].{field} = {value} is out of range for {target} (length {len})"
+            ),
+            Finding::RangeOutOfRange { table, index, field, target, start, count, len } => write!(
+                f,
+                "{table}[{index}].{field} = {start}..{} is out of range for {target} (length {len})",
+                start + count
+            ),
+            Finding::InvalidString { table, index, field } => {
+                write!(f, "{table}[{index}].{field} is not a valid string")
+            }
+            Finding::CountMismatch { table, index: Some(index), expected, found } => {
+                write!(f, "{table}[{index}] has {found} entries, expected {expected}")
+            }
+            Finding::CountMismatch { table, index: None, expected, found } => {
+                write!(f, "{table} has {found} entries, expected {expected}")
+            }
+            Finding::MissingCodeGenModule { image } => {
+                write!(f, "image {image} has no code gen module")
+            }
+            Finding::MethodCountMismatch { module, expected, found } => write!(
+                f,
+                "code gen module {module} has {found} method pointers, expected {expected}"
+            ),
+        }
+    }
+}
+
+struct Validator<'md, 'gmd, 'rmd> {
+    metadata: &'md Metadata<'gmd, 'rmd>,
+    findings: Vec<Finding>,
+}
+
+impl Validator<'_, '_, '_> {
+    fn index(&mut self, table: &'static str, index: usize, field: &'static str, target: &'static str, value: u64, len: usize) {
+        if value >= len as u64 {
+            self.findings.push(Finding::IndexOutOfRange { table, index, field, target, value, len });
+        }
+    }
+
+    /// Like [`Validator::index()`], but `u32::MAX` is allowed as a "no value"
+    /// marker.
+    fn optional_index(&mut self, table: &'static str, index: usize, field: &'static str, target: &'static str, value: u32, len: usize) {
+        if value != u32::MAX {
+            self.index(table, index, field, target, value as u64, len);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn range(&mut self, table: &'static str, index: usize, field: &'static str, target: &'static str, start: u64, count: u64, len: usize) {
+        if count > 0 && start + count > len as u64 {
+            self.findings.push(Finding::RangeOutOfRange { table, index, field, target, start, count, len });
+        }
+    }
+
+    fn string(&mut self, table: &'static str, index: usize, field: &'static str, string: crate::global_metadata::StringIndex) {
+        if self.metadata.global_metadata.string.get(string).is_err() {
+            self.findings.push(Finding::InvalidString { table, index, field });
+        }
+    }
+
+    fn validate_type_definitions(&mut self) {
+        let gm = &self.metadata.global_metadata;
+        let types_len = self.metadata.runtime_metadata.metadata_registration.types.len();
+        for (i, ty) in gm.type_definitions.as_vec().iter().enumerate() {
+            let t = "type_definitions";
+            self.string(t, i, "name_index", ty.name_index);
+            self.string(t, i, "namespace_index", ty.namespace_index);
+            self.index(t, i, "byval_type_index", "types", ty.byval_type_index as u64, types_len);
+            self.optional_index(t, i, "declaring_type_index", "types", ty.declaring_type_index, types_len);
+            self.optional_index(t, i, "parent_index", "types", ty.parent_index, types_len);
+            self.optional_index(t, i, "element_type_index", "types", ty.element_type_index, types_len);
+            self.optional_index(t, i, "generic_container_index", "generic_containers", ty.generic_container_index.index(), gm.generic_containers.len());
+            self.range(t, i, "field_start", "fields", ty.field_start.index() as u64, ty.field_count as u64, gm.fields.len());
+            self.range(t, i, "method_start", "methods", ty.method_start.index() as u64, ty.method_count as u64, gm.methods.len());
+            self.range(t, i, "event_start", "events", ty.event_start.index() as u64, ty.event_count as u64, gm.events.len());
+            self.range(t, i, "property_start", "properties", ty.property_start.index() as u64, ty.property_count as u64, gm.properties.len());
+            self.range(t, i, "nested_types_start", "nested_types", ty.nested_types_start.index() as u64, ty.nested_type_count as u64, gm.nested_types.len());
+            self.range(t, i, "interfaces_start", "interfaces", ty.interfaces_start.index() as u64, ty.interfaces_count as u64, gm.interfaces.len());
+            self.range(t, i, "vtable_start", "vtable_methods", ty.vtable_start.index() as u64, ty.vtable_count as u64, gm.vtable_methods.len());
+            self.range(t, i, "interface_offsets_start", "interface_offsets", ty.interface_offsets_start.index() as u64, ty.interface_offsets_count as u64, gm.interface_offsets.len());
+
+            let property_start = ty.property_start.index() as usize;
+            let properties = gm.properties.as_vec().iter().enumerate().skip(property_start).take(ty.property_count as usize);
+            for (j, property) in properties {
+                self.optional_index("properties", j, "get", "declaring type methods", property.get, ty.method_count as usize);
+                self.optional_index("properties", j, "set", "declaring type methods", property.set, ty.method_count as usize);
+            }
+
+            let event_start = ty.event_start.index() as usize;
+            let events = gm.events.as_vec().iter().enumerate().skip(event_start).take(ty.event_count as usize);
+            for (j, event) in events {
+                self.optional_index("events", j, "add", "declaring type methods", event.add.index(), ty.method_count as usize);
+                self.optional_index("events", j, "remove", "declaring type methods", event.remove.index(), ty.method_count as usize);
+                self.optional_index("events", j, "raise", "declaring type methods", event.raise.index(), ty.method_count as usize);
+            }
+        }
+    }
+
+    fn validate_members(&mut self) {
+        let gm = &self.metadata.global_metadata;
+        let types_len = self.metadata.runtime_metadata.metadata_registration.types.len();
+
+        for (i, method) in gm.methods.as_vec().iter().enumerate() {
+            let t = "methods";
+            self.string(t, i, "name_index", method.name_index);
+            self.index(t, i, "declaring_type", "type_definitions", method.declaring_type.index() as u64, gm.type_definitions.len());
+            self.index(t, i, "return_type", "types", method.return_type as u64, types_len);
+            self.range(t, i, "parameter_start", "parameters", method.parameter_start.index() as u64, method.parameter_count as u64, gm.parameters.len());
+            self.optional_index(t, i, "generic_container_index", "generic_containers", method.generic_container_index.index(), gm.generic_containers.len());
+        }
+        for (i, param) in gm.parameters.as_vec().iter().enumerate() {
+            self.string("parameters", i, "name_index", param.name_index);
+            self.index("parameters", i, "type_index", "types", param.type_index as u64, types_len);
+        }
+        for (i, field) in gm.fields.as_vec().iter().enumerate() {
+            self.string("fields", i, "name_index", field.name_index);
+            self.index("fields", i, "type_index", "types", field.type_index as u64, types_len);
+        }
+        for (i, event) in gm.events.as_vec().iter().enumerate() {
+            let t = "events";
+            self.string(t, i, "name_index", event.name_index);
+            self.index(t, i, "type_index", "types", event.type_index as u64, types_len);
+        }
+        for (i, property) in gm.properties.as_vec().iter().enumerate() {
+            self.string("properties", i, "name_index", property.name_index);
+        }
+        for (i, nested) in gm.nested_types.as_vec().iter().enumerate() {
+            self.index("nested_types", i, "value", "type_definitions", nested.index() as u64, gm.type_definitions.len());
+        }
+        for (i, &interface) in gm.interfaces.as_vec().iter().enumerate() {
+            self.index("interfaces", i, "value", "types", interface as u64, types_len);
+        }
+        for (i, pair) in gm.interface_offsets.as_vec().iter().enumerate() {
+            self.index("interface_offsets", i, "interface_type_index", "types", pair.interface_type_index as u64, types_len);
+        }
+        for (i, field_ref) in gm.field_refs.as_vec().iter().enumerate() {
+            self.index("field_refs", i, "type_index", "types", field_ref.type_index as u64, types_len);
+        }
+    }
+
+    fn validate_generics(&mut self) {
+        let gm = &self.metadata.global_metadata;
+        let types_len = self.metadata.runtime_metadata.metadata_registration.types.len();
+
+        for (i, container) in gm.generic_containers.as_vec().iter().enumerate() {
+            let t = "generic_containers";
+            let owner_table = match container.is_method != 0 {
+                true => ("methods", gm.methods.len()),
+                false => ("type_definitions", gm.type_definitions.len()),
+            };
+            self.index(t, i, "owner_index", owner_table.0, container.owner_index as u64, owner_table.1);
+            self.range(t, i, "generic_parameter_start", "generic_parameters", container.generic_parameter_start.index() as u64, container.type_argc as u64, gm.generic_parameters.len());
+        }
+        for (i, param) in gm.generic_parameters.as_vec().iter().enumerate() {
+            let t = "generic_parameters";
+            self.string(t, i, "name_index", param.name_index);
+            self.index(t, i, "owner_index", "generic_containers", param.owner_index.index() as u64, gm.generic_containers.len());
+            self.range(t, i, "constraints_start", "generic_parameter_constraints", param.constraints_start.index() as u64, param.constraints_count as u64, gm.generic_parameter_constraints.len());
+        }
+        for (i, &constraint) in gm.generic_parameter_constraints.as_vec().iter().enumerate() {
+            self.index("generic_parameter_constraints", i, "value", "types", constraint as u64, types_len);
+        }
+    }
+
+    fn validate_images(&mut self) {
+        let gm = &self.metadata.global_metadata;
+        for (i, image) in gm.images.as_vec().iter().enumerate() {
+            let t = "images";
+            self.string(t, i, "name_index", image.name_index);
+            self.index(t, i, "assembly_index", "assemblies", image.assembly_index.index() as u64, gm.assemblies.len());
+            self.range(t, i, "type_start", "type_definitions", image.type_start.index() as u64, image.type_count as u64, gm.type_definitions.len());
+            self.optional_index(t, i, "entry_point_index", "methods", image.entry_point_index.index(), gm.methods.len());
+        }
+        for (i, assembly) in gm.assemblies.as_vec().iter().enumerate() {
+            self.index("assemblies", i, "image_index", "images", assembly.image_index.index() as u64, gm.images.len());
+        }
+    }
+
+    fn validate_runtime_types(&mut self) {
+        let gm = &self.metadata.global_metadata;
+        let mr = &self.metadata.runtime_metadata.metadata_registration;
+        let types_len = mr.types.len();
+
+        for (i, ty) in mr.types.iter().enumerate() {
+            let t = "types";
+            match ty.data {
+                TypeData::TypeDefinitionIndex(idx) => {
+                    // Only classes and value types are guaranteed to
+                    // reference a definition.
+                    if matches!(ty.ty, Il2CppTypeEnum::Class | Il2CppTypeEnum::Valuetype) {
+                        self.index(t, i, "data", "type_definitions", idx.index() as u64, gm.type_definitions.len());
+                    }
+                }
+                TypeData::TypeIndex(idx) => self.index(t, i, "data", "types", idx as u64, types_len),
+                TypeData::GenericParameterIndex(idx) => self.index(t, i, "data", "generic_parameters", idx.index() as u64, gm.generic_parameters.len()),
+                TypeData::GenericClassIndex(idx) => self.index(t, i, "data", "generic_classes", idx as u64, mr.generic_classes.len()),
+                TypeData::ArrayType(idx) => self.index(t, i, "data", "array_types", idx as u64, mr.array_types.len()),
+            }
+        }
+        for (i, array_type) in mr.array_types.iter().enumerate() {
+            self.index("array_types", i, "elem_ty", "types", array_type.elem_ty as u64, types_len);
+        }
+        for (i, generic_class) in mr.generic_classes.iter().enumerate() {
+            let t = "generic_classes";
+            self.index(t, i, "type_index", "types", generic_class.type_index as u64, types_len);
+            if let Some(idx) = generic_class.context.class_inst_idx {
+                self.index(t, i, "class_inst_idx", "generic_insts", idx as u64, mr.generic_insts.len());
+            }
+            if let Some(idx) = generic_class.context.method_inst_idx {
+                self.index(t, i, "method_inst_idx", "generic_insts", idx as u64, mr.generic_insts.len());
+            }
+        }
+        for (i, inst) in mr.generic_insts.iter().enumerate() {
+            for &ty in &inst.types {
+                self.index("generic_insts", i, "types", "types", ty as u64, types_len);
+            }
+        }
+        for (i, spec) in mr.method_specs.iter().enumerate() {
+            let t = "method_specs";
+            self.index(t, i, "method_definition_index", "methods", spec.method_definition_index.index() as u64, gm.methods.len());
+            self.optional_index(t, i, "class_inst_index", "generic_insts", spec.class_inst_index, mr.generic_insts.len());
+            self.optional_index(t, i, "method_inst_index", "generic_insts", spec.method_inst_index, mr.generic_insts.len());
+        }
+
+        let cr = &self.metadata.runtime_metadata.code_registration;
+        for (i, entry) in mr.generic_method_table.iter().enumerate() {
+            let t = "generic_method_table";
+            self.index(t, i, "generic_method_index", "method_specs", entry.generic_method_index as u64, mr.method_specs.len());
+            self.index(t, i, "method_index", "generic_method_pointers", entry.indices.method_index as u64, cr.generic_method_pointers.len());
+            self.optional_index(t, i, "invoker_index", "invoker_pointers", entry.indices.invoker_index, cr.invoker_pointers.len());
+            self.optional_index(t, i, "adjustor_thunk_index", "generic_adjustor_thunks", entry.indices.adjustor_thunk_index, cr.generic_adjustor_thunks.len());
+        }
+    }
+
+    fn validate_compiler_tables(&mut self) {
+        let gm = &self.metadata.global_metadata;
+        let mr = &self.metadata.runtime_metadata.metadata_registration;
+        let type_defs_len = gm.type_definitions.len();
+
+        if let Some(field_offsets) = &mr.field_offsets {
+            if field_offsets.len() != type_defs_len {
+                self.findings.push(Finding::CountMismatch {
+                    table: "field_offsets",
+                    index: None,
+                    expected: type_defs_len,
+                    found: field_offsets.len(),
+                });
+            }
+            let type_defs = gm.type_definitions.as_vec();
+            for (i, (offsets, ty)) in field_offsets.iter().zip(type_defs).enumerate() {
+                // Types without instance fields don't have an offset table.
+                if !offsets.is_empty() && offsets.len() != ty.field_count as usize {
+                    self.findings.push(Finding::CountMismatch {
+                        table: "field_offsets",
+                        index: Some(i),
+                        expected: ty.field_count as usize,
+                        found: offsets.len(),
+                    });
+                }
+            }
+        }
+        if let Some(sizes) = &mr.type_definition_sizes {
+            if sizes.len() != type_defs_len {
+                self.findings.push(Finding::CountMismatch {
+                    table: "type_definition_sizes",
+                    index: None,
+                    expected: type_defs_len,
+                    found: sizes.len(),
+                });
+            }
+        }
+    }
+
+    fn validate_code_gen_modules(&mut self) {
+        let gm = &self.metadata.global_metadata;
+        let modules = &self.metadata.runtime_metadata.code_registration.code_gen_modules;
+        for image in gm.images.as_vec() {
+            let Ok(name) = gm.string.get(image.name_index) else {
+                continue;
+            };
+            let Some(module) = modules.iter().find(|m| m.name == name) else {
+                self.findings.push(Finding::MissingCodeGenModule { image: name.to_string() });
+                continue;
+            };
+
+            let start = image.type_start.index() as usize;
+            let types = gm.type_definitions.as_vec().iter().skip(start).take(image.type_count as usize);
+            let expected = types.map(|ty| ty.method_count as usize).sum();
+            if module.method_pointers.len() != expected {
+                self.findings.push(Finding::MethodCountMismatch {
+                    module: module.name.to_string(),
+                    expected,
+                    found: module.method_pointers.len(),
+                });
+            }
+        }
+    }
+}
+
+impl Metadata<'_, '_> {
+    /// Checks that every cross-table index is in range and that the runtime
+    /// metadata matches the global metadata, returning everything that was
+    /// found to be wrong.
+    ///
+    /// An empty list means the tables reference each other consistently. The
+    /// contents of default values, custom attribute data and vtable entries
+    /// are not checked, so decoding them can still fail.
+    pub fn validate(&self) -> Vec<Finding> {
+        let mut validator = Validator {
+            metadata: self,
+            findings: Vec::new(),
+        };
+        validator.validate_type_definitions();
+        validator.validate_members();
+        validator.validate_generics();
+        validator.validate_images();
+        validator.validate_runtime_types();
+        validator.validate_compiler_tables();
+        validator.validate_code_gen_modules();
+        validator.findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{
+        AssemblyIndex, GenericContainerIndex, GenericParameterConstraintIndex, GenericParameterIndex,
+        Il2CppAssemblyDefinition, Il2CppEventDefinition, Il2CppFieldDefinition, Il2CppFieldRef, Il2CppGenericContainer,
+        Il2CppGenericParameter, Il2CppImageDefinition, Il2CppInterfaceOffsetPair, Il2CppMethodDefinition,
+        Il2CppParameterDefinition, Il2CppPropertyDefinition, ImageIndex, MethodIndex, ParameterIndex, StringIndex,
+        TypeDefinitionIndex,
+    };
+    use crate::runtime_metadata::{
+        GenericMethodIndices, Il2CppArrayType, Il2CppCodeGenModule, Il2CppGenericClass, Il2CppGenericContext,
+        Il2CppGenericInst, Il2CppGenericMethodFunctionsDefinitions, Il2CppMethodSpec, Il2CppTypeDefinitionSizes,
+    };
+    use crate::test_util::{self, zeroed};
+
+    fn findings(metadata: &Metadata) -> Vec<String> {
+        metadata.validate().iter().map(ToString::to_string).collect()
+    }
+
+    fn module(name: &'static str, method_pointers: Vec<u64>) -> Il2CppCodeGenModule<'static> {
+        Il2CppCodeGenModule {
+            name,
+            method_pointers,
+            adjustor_thunks: Vec::new(),
+            invoker_indices: Vec::new(),
+            rgctx_ranges: Vec::new(),
+            rgctxs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn empty_metadata() {
+        assert!(test_util::metadata("").validate().is_empty());
+    }
+
+    #[test]
+    fn type_definitions() {
+        let mut metadata = test_util::metadata("\0T\0");
+        metadata.runtime_metadata.metadata_registration.types =
+            vec![test_util::ty(Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0)))];
+        let gm = &mut metadata.global_metadata;
+        let mut ty_def = test_util::type_definition();
+        ty_def.name_index = StringIndex::new(100);
+        ty_def.parent_index = 1;
+        ty_def.generic_container_index = GenericContainerIndex::new(0);
+        ty_def.method_count = 1;
+        ty_def.field_count = 1;
+        ty_def.property_count = 1;
+        ty_def.event_count = 1;
+        gm.type_definitions = vec![ty_def].into();
+        let mut method: Il2CppMethodDefinition = zeroed();
+        method.generic_container_index = GenericContainerIndex::new(u32::MAX);
+        gm.methods = vec![method].into();
+        let mut property: Il2CppPropertyDefinition = zeroed();
+        property.get = 0;
+        property.set = 1;
+        gm.properties = vec![property].into();
+        // Event accessors are relative to the declaring type, so 1 is out of
+        // range even if the method table had a second method.
+        let mut event: Il2CppEventDefinition = zeroed();
+        event.add = MethodIndex::new(1);
+        event.remove = MethodIndex::new(u32::MAX);
+        event.raise = MethodIndex::new(u32::MAX);
+        gm.events = vec![event].into();
+
+        assert_eq!(
+            findings(&metadata),
+            [
+                "type_definitions[0].name_index is not a valid string",
+                "type_definitions[0].parent_index = 1 is out of range for types (length 1)",
+                "type_definitions[0].generic_container_index = 0 is out of range for generic_containers (length 0)",
+                "type_definitions[0].field_start = 0..1 is out of range for fields (length 0)",
+                "properties[0].set = 1 is out of range for declaring type methods (length 1)",
+                "events[0].add = 1 is out of range for declaring type methods (length 1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn members() {
+        let mut metadata = test_util::metadata("");
+        let gm = &mut metadata.global_metadata;
+        let mut method: Il2CppMethodDefinition = zeroed();
+        method.declaring_type = TypeDefinitionIndex::new(0);
+        method.parameter_start = ParameterIndex::new(1);
+        method.parameter_count = 1;
+        method.generic_container_index = GenericContainerIndex::new(u32::MAX);
+        gm.methods = vec![method].into();
+        gm.parameters = vec![zeroed::<Il2CppParameterDefinition>()].into();
+        gm.fields = vec![zeroed::<Il2CppFieldDefinition>()].into();
+        gm.events = vec![zeroed::<Il2CppEventDefinition>()].into();
+        gm.nested_types = vec![TypeDefinitionIndex::new(0)].into();
+        gm.interfaces = vec![0].into();
+        gm.interface_offsets = vec![zeroed::<Il2CppInterfaceOffsetPair>()].into();
+        gm.field_refs = vec![zeroed::<Il2CppFieldRef>()].into();
+
+        assert_eq!(
+            findings(&metadata),
+            [
+                "methods[0].declaring_type = 0 is out of range for type_definitions (length 0)",
+                "methods[0].return_type = 0 is out of range for types (length 0)",
+                "methods[0].parameter_start = 1..2 is out of range for parameters (length 1)",
+                "parameters[0].type_index = 0 is out of range for types (length 0)",
+                "fields[0].type_index = 0 is out of range for types (length 0)",
+                "events[0].type_index = 0 is out of range for types (length 0)",
+                "nested_types[0].value = 0 is out of range for type_definitions (length 0)",
+                "interfaces[0].value = 0 is out of range for types (length 0)",
+                "interface_offsets[0].interface_type_index = 0 is out of range for types (length 0)",
+                "field_refs[0].type_index = 0 is out of range for types (length 0)",
+            ]
+        );
+    }
+
+    #[test]
+    fn generics() {
+        let mut metadata = test_util::metadata("");
+        let gm = &mut metadata.global_metadata;
+        let mut container: Il2CppGenericContainer = zeroed();
+        container.is_method = 1;
+        container.type_argc = 2;
+        gm.generic_containers = vec![container].into();
+        let mut param: Il2CppGenericParameter = zeroed();
+        param.owner_index = GenericContainerIndex::new(1);
+        param.constraints_start = GenericParameterConstraintIndex::new(0);
+        param.constraints_count = 2;
+        gm.generic_parameters = vec![param].into();
+        gm.generic_parameter_constraints = vec![0].into();
+
+        assert_eq!(
+            findings(&metadata),
+            [
+                "generic_containers[0].owner_index = 0 is out of range for methods (length 0)",
+                "generic_containers[0].generic_parameter_start = 0..2 is out of range for generic_parameters (length 1)",
+                "generic_parameters[0].owner_index = 1 is out of range for generic_containers (length 1)",
+                "generic_parameters[0].constraints_start = 0..2 is out of range for generic_parameter_constraints (length 1)",
+                "generic_parameter_constraints[0].value = 0 is out of range for types (length 0)",
+            ]
+        );
+    }
+
+    #[test]
+    fn images() {
+        let mut metadata = test_util::metadata("\0A.dll\0");
+        let gm = &mut metadata.global_metadata;
+        let mut image: Il2CppImageDefinition = zeroed();
+        image.name_index = StringIndex::new(1);
+        image.assembly_index = AssemblyIndex::new(1);
+        image.type_count = 1;
+        image.entry_point_index = MethodIndex::new(0);
+        gm.images = vec![image].into();
+        let mut assembly: Il2CppAssemblyDefinition = zeroed();
+        assembly.image_index = ImageIndex::new(1);
+        gm.assemblies = vec![assembly].into();
+
+        assert_eq!(
+            findings(&metadata),
+            [
+                "images[0].assembly_index = 1 is out of range for assemblies (length 1)",
+                "images[0].type_start = 0..1 is out of range for type_definitions (length 0)",
+                "images[0].entry_point_index = 0 is out of range for methods (length 0)",
+                "assemblies[0].image_index = 1 is out of range for images (length 1)",
+                "image A.dll has no code gen module",
+            ]
+        );
+    }
+
+    #[test]
+    fn runtime_types() {
+        let mut metadata = test_util::metadata("");
+        let mr = &mut metadata.runtime_metadata.metadata_registration;
+        mr.types = vec![
+            test_util::ty(Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(0))),
+            test_util::ty(Il2CppTypeEnum::Szarray, TypeData::TypeIndex(5)),
+            test_util::ty(Il2CppTypeEnum::Var, TypeData::GenericParameterIndex(GenericParameterIndex::new(0))),
+            test_util::ty(Il2CppTypeEnum::Genericinst, TypeData::GenericClassIndex(1)),
+            test_util::ty(Il2CppTypeEnum::Array, TypeData::ArrayType(1)),
+        ];
+        mr.array_types = vec![Il2CppArrayType {
+            elem_ty: 5,
+            rank: 2,
+            sizes: Vec::new(),
+            lower_bounds: Vec::new(),
+        }];
+        mr.generic_classes = vec![Il2CppGenericClass {
+            type_index: 5,
+            context: Il2CppGenericContext {
+                class_inst_idx: Some(1),
+                method_inst_idx: Some(1),
+            },
+        }];
+        mr.generic_insts = vec![Il2CppGenericInst { types: vec![5] }];
+        mr.method_specs = vec![Il2CppMethodSpec {
+            method_definition_index: MethodIndex::new(0),
+            class_inst_index: 1,
+            method_inst_index: u32::MAX,
+        }];
+        mr.generic_method_table = vec![Il2CppGenericMethodFunctionsDefinitions {
+            generic_method_index: 1,
+            indices: GenericMethodIndices {
+                method_index: 0,
+                invoker_index: 0,
+                adjustor_thunk_index: u32::MAX,
+            },
+        }];
+
+        assert_eq!(
+            findings(&metadata),
+            [
+                "types[0].data = 0 is out of range for type_definitions (length 0)",
+                "types[1].data = 5 is out of range for types (length 5)",
+                "types[2].data = 0 is out of range for generic_parameters (length 0)",
+                "types[3].data = 1 is out of range for generic_classes (length 1)",
+                "types[4].data = 1 is out of range for array_types (length 1)",
+                "array_types[0].elem_ty = 5 is out of range for types (length 5)",
+                "generic_classes[0].type_index = 5 is out of range for types (length 5)",
+                "generic_classes[0].class_inst_idx = 1 is out of range for generic_insts (length 1)",
+                "generic_classes[0].method_inst_idx = 1 is out of range for generic_insts (length 1)",
+                "generic_insts[0].types = 5 is out of range for types (length 5)",
+                "method_specs[0].method_definition_index = 0 is out of range for methods (length 0)",
+                "method_specs[0].class_inst_index = 1 is out of range for generic_insts (length 1)",
+                "generic_method_table[0].generic_method_index = 1 is out of range for method_specs (length 1)",
+                "generic_method_table[0].method_index = 0 is out of range for generic_method_pointers (length 0)",
+                "generic_method_table[0].invoker_index = 0 is out of range for invoker_pointers (length 0)",
+            ]
+        );
+    }
+
+    #[test]
+    fn compiler_tables() {
+        let mut metadata = test_util::metadata("");
+        let mut ty_def = test_util::type_definition();
+        ty_def.field_count = 2;
+        metadata.global_metadata.fields = (0..2).map(|_| zeroed::<Il2CppFieldDefinition>()).collect::<Vec<_>>().into();
+        metadata.global_metadata.type_definitions = vec![ty_def, test_util::type_definition()].into();
+        let mr = &mut metadata.runtime_metadata.metadata_registration;
+        mr.field_offsets = Some(vec![vec![0x10]]);
+        let size = || Il2CppTypeDefinitionSizes {
+            instance_size: 0,
+            native_size: 0,
+            static_fields_size: 0,
+            thread_static_fields_size: 0,
+        };
+        mr.type_definition_sizes = Some(vec![size(), size(), size()]);
+
+        let findings = findings(&metadata);
+        assert_eq!(
+            findings[findings.len() - 3..],
+            [
+                "field_offsets has 1 entries, expected 2",
+                "field_offsets[0] has 1 entries, expected 2",
+                "type_definition_sizes has 3 entries, expected 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn code_gen_modules() {
+        let mut metadata = test_util::metadata("\0A.dll\0B.dll\0");
+        let image = |name: u32| {
+            let mut image: Il2CppImageDefinition = zeroed();
+            image.name_index = StringIndex::new(name);
+            image.type_count = 1;
+            image
+        };
+        let mut ty_def = test_util::type_definition();
+        ty_def.method_count = 2;
+        metadata.global_metadata.type_definitions = vec![ty_def].into();
+        metadata.global_metadata.images = vec![image(1), image(7)].into();
+        metadata.global_metadata.assemblies = vec![zeroed::<Il2CppAssemblyDefinition>()].into();
+        metadata.runtime_metadata.code_registration.code_gen_modules = vec![module("A.dll", vec![0x10])];
+
+        let findings = findings(&metadata);
+        assert_eq!(
+            findings[findings.len() - 2..],
+            [
+                "code gen module A.dll has 1 method pointers, expected 2",
+                "image B.dll has no code gen module",
+            ]
+        );
+    }
+}
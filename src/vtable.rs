@@ -0,0 +1,506 @@
+//! Virtual method table reconstruction.
+//!
+//! Each type definition stores the methods of its vtable as
+//! [`EncodedMethodIndex`] values, and the vtable offset of every interface it
+//! implements as [`Il2CppInterfaceOffsetPair`]s. The methods of an interface
+//! occupy the slots starting at its offset, in the order of the interface
+//! methods' own [`Il2CppMethodDefinition::slot`]s.
+
+use crate::global_metadata::{
+    DecodedMethodIndex, EncodedMethodIndex, Il2CppInterfaceOffsetPair, Il2CppMethodDefinition, Il2CppTypeDefinition,
+    MethodIndex, TypeDefinitionIndex, TypeIndex,
+};
+use crate::flags::MethodAttributes;
+use crate::hierarchy::TypeHierarchy;
+use crate::lookup::MetadataIndex;
+use crate::Metadata;
+
+/// The value of [`Il2CppMethodDefinition::slot`] for methods without a slot.
+pub const INVALID_SLOT: u16 = u16::MAX;
+
+/// The method occupying a vtable slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VTableMethod {
+    /// The slot is empty, usually because the method is abstract.
+    None,
+    MethodDef(MethodIndex),
+    /// A method of a generic type or a generic method instantiation.
+    ///
+    /// Index into the
+    /// [`Il2CppMetadataRegistration::method_specs`](crate::runtime_metadata::Il2CppMetadataRegistration::method_specs)
+    /// field.
+    MethodSpec(usize),
+}
+
+impl VTableMethod {
+    fn decode(encoded: EncodedMethodIndex) -> Self {
+        match encoded.decode() {
+            DecodedMethodIndex::MethodDef(idx) => VTableMethod::MethodDef(idx),
+            DecodedMethodIndex::MethodRef(idx) => VTableMethod::MethodSpec(idx as usize),
+            _ => VTableMethod::None,
+        }
+    }
+
+    /// The definition of the method, looking through method specs.
+    pub fn method_definition(self, metadata: &Metadata) -> Option<MethodIndex> {
+        match self {
+            VTableMethod::None => None,
+            VTableMethod::MethodDef(idx) => Some(idx),
+            VTableMethod::MethodSpec(idx) => metadata
+                .runtime_metadata
+                .metadata_registration
+                .method_specs
+                .get(idx)
+                .map(|spec| spec.method_definition_index),
+        }
+    }
+
+    /// The address of the method's compiled code in the binary.
+    pub fn method_pointer(self, index: &MetadataIndex) -> Option<u64> {
+        match self {
+            VTableMethod::None => None,
+            VTableMethod::MethodDef(idx) => index.metadata().global_metadata.methods.get(idx)?.method_pointer(index),
+            VTableMethod::MethodSpec(idx) => index.method_spec_pointer(idx),
+        }
+    }
+}
+
+/// A slot in a type's vtable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VTableSlot {
+    /// The index of the slot in the vtable.
+    pub slot: usize,
+    pub method: VTableMethod,
+    /// The interface whose method this slot implements, if it lies in the
+    /// slot range of one of the type's interfaces.
+    ///
+    /// Index into the
+    /// [`Il2CppMetadataRegistration::types`](crate::runtime_metadata::Il2CppMetadataRegistration::types)
+    /// field.
+    pub interface: Option<TypeIndex>,
+    /// The address of the method's compiled code, if the runtime metadata was
+    /// read from a binary and the method has code.
+    pub method_pointer: Option<u64>,
+}
+
+impl Il2CppTypeDefinition {
+    /// Reconstructs the type's vtable.
+    ///
+    /// Method pointers are found through `index`, which should be shared
+    /// between calls.
+    pub fn vtable(&self, index: &MetadataIndex) -> Vec<VTableSlot> {
+        let metadata = index.metadata();
+        let interfaces: Vec<_> = interface_offsets(metadata, self)
+            .into_iter()
+            .map(|pair| (pair, interface_slot_count(metadata, pair)))
+            .collect();
+
+        self.vtable_methods(metadata)
+            .unwrap_or_default()
+            .iter()
+            .enumerate()
+            .map(|(slot, &encoded)| {
+                let method = VTableMethod::decode(encoded);
+                let interface = interfaces
+                    .iter()
+                    .find(|(pair, count)| (pair.offset as usize..pair.offset as usize + count).contains(&slot))
+                    .map(|(pair, _)| pair.interface_type_index);
+                VTableSlot {
+                    slot,
+                    method,
+                    interface,
+                    method_pointer: method.method_pointer(index),
+                }
+            })
+            .collect()
+    }
+}
+
+/// An interface method together with the method implementing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceImplementation {
+    /// The implemented interface, which may be an instantiation of a generic
+    /// interface.
+    ///
+    /// Index into the
+    /// [`Il2CppMetadataRegistration::types`](crate::runtime_metadata::Il2CppMetadataRegistration::types)
+    /// field.
+    pub interface: TypeIndex,
+    /// The method declared by the interface.
+    pub interface_method: MethodIndex,
+    /// The method implementing it.
+    pub implementation: VTableMethod,
+}
+
+impl Metadata<'_, '_> {
+    /// Finds the method of `class` that implements `interface_method`, either
+    /// explicitly or implicitly.
+    ///
+    /// If `class` implements several instantiations of a generic interface,
+    /// the implementation for the first one is returned. Use
+    /// [`Metadata::implementations_of()`] to get all of them.
+    pub fn implementation_of(&self, class: TypeDefinitionIndex, interface_method: MethodIndex) -> Option<VTableMethod> {
+        self.implementations_of(class, interface_method)
+            .into_iter()
+            .next()
+            .map(|implementation| implementation.implementation)
+    }
+
+    /// Finds the methods of `class` that implement `interface_method`, one for
+    /// every instantiation of the interface that `class` implements.
+    pub fn implementations_of(
+        &self,
+        class: TypeDefinitionIndex,
+        interface_method: MethodIndex,
+    ) -> Vec<InterfaceImplementation> {
+        let gm = &self.global_metadata;
+        let (Some(class_def), Some(method)) = (gm.type_definitions.get(class), gm.methods.get(interface_method)) else {
+            return Vec::new();
+        };
+        if !has_slot(method) {
+            return Vec::new();
+        }
+
+        let vtable = class_def.vtable_methods(self).unwrap_or_default();
+        interface_offsets(self, class_def)
+            .into_iter()
+            .filter(|pair| interface_definition(self, pair) == Some(method.declaring_type))
+            .filter_map(|pair| {
+                let encoded = *vtable.get(pair.offset as usize + method.slot as usize)?;
+                Some(InterfaceImplementation {
+                    interface: pair.interface_type_index,
+                    interface_method,
+                    implementation: VTableMethod::decode(encoded),
+                })
+            })
+            .collect()
+    }
+
+    /// Finds the interface methods that `method` implements in its declaring
+    /// type.
+    pub fn implemented_interface_methods(&self, method: MethodIndex) -> Vec<InterfaceImplementation> {
+        let gm = &self.global_metadata;
+        let Some(class_def) = gm.methods.get(method).and_then(|m| gm.type_definitions.get(m.declaring_type)) else {
+            return Vec::new();
+        };
+
+        let interfaces: Vec<_> = interface_offsets(self, class_def)
+            .into_iter()
+            .filter_map(|pair| {
+                let interface = gm.type_definitions.get(interface_definition(self, pair)?)?;
+                Some((pair, interface))
+            })
+            .collect();
+
+        let mut implemented = Vec::new();
+        for (slot, &encoded) in class_def.vtable_methods(self).unwrap_or_default().iter().enumerate() {
+            let implementation = VTableMethod::decode(encoded);
+            if implementation.method_definition(self) != Some(method) {
+                continue;
+            }
+            for (pair, interface) in &interfaces {
+                let Some(interface_slot) = slot.checked_sub(pair.offset as usize) else {
+                    continue;
+                };
+                let interface_method = interface
+                    .method_start
+                    .indices(interface.method_count as u32)
+                    .find(|&idx| gm.methods.get(idx).is_some_and(|m| has_slot(m) && m.slot as usize == interface_slot));
+                if let Some(interface_method) = interface_method {
+                    implemented.push(InterfaceImplementation {
+                        interface: pair.interface_type_index,
+                        interface_method,
+                        implementation,
+                    });
+                }
+            }
+        }
+        implemented
+    }
+}
+
+impl Metadata<'_, '_> {
+    /// Finds the base type method that `method` overrides.
+    ///
+    /// Returns `None` if `method` is not virtual, or if it introduces a new
+    /// vtable slot instead of reusing the slot of a base type method.
+    pub fn overrides(&self, method: MethodIndex) -> Option<MethodIndex> {
+        let gm = &self.global_metadata;
+        let method_def = gm.methods.get(method)?;
+        if !method_def.is_virtual() || method_def.attributes().contains(MethodAttributes::NEW_SLOT) || !has_slot(method_def) {
+            return None;
+        }
+
+        let base = gm.type_definitions.get(method_def.declaring_type)?.base_type_definition(self)?;
+        let encoded = *gm.type_definitions.get(base)?.vtable_methods(self)?.get(method_def.slot as usize)?;
+        VTableMethod::decode(encoded)
+            .method_definition(self)
+            .filter(|&overridden| overridden != method)
+    }
+
+    /// Lists `method` followed by every method it overrides, ending with the
+    /// method that introduced the vtable slot.
+    pub fn override_chain(&self, method: MethodIndex) -> Vec<MethodIndex> {
+        let mut chain = vec![method];
+        let mut current = method;
+        while let Some(overridden) = self.overrides(current) {
+            if chain.contains(&overridden) {
+                break;
+            }
+            chain.push(overridden);
+            current = overridden;
+        }
+        chain
+    }
+}
+
+impl TypeHierarchy<'_, '_, '_> {
+    /// Finds every method in a derived type that directly or indirectly
+    /// overrides `method`.
+    pub fn overridden_by(&self, method: MethodIndex) -> Vec<MethodIndex> {
+        let metadata = self.metadata();
+        let gm = &metadata.global_metadata;
+        let Some(method_def) = gm.methods.get(method) else {
+            return Vec::new();
+        };
+        if !method_def.is_virtual() || !has_slot(method_def) {
+            return Vec::new();
+        }
+
+        let mut overrides = Vec::new();
+        for derived in self.all_derived_types(method_def.declaring_type) {
+            let Some(encoded) = gm
+                .type_definitions
+                .get(derived)
+                .and_then(|ty_def| ty_def.vtable_methods(metadata)?.get(method_def.slot as usize))
+            else {
+                continue;
+            };
+            let Some(overriding) = VTableMethod::decode(*encoded).method_definition(metadata) else {
+                continue;
+            };
+            let declared_by_derived = gm.methods.get(overriding).is_some_and(|m| m.declaring_type == derived);
+            if overriding != method && declared_by_derived {
+                overrides.push(overriding);
+            }
+        }
+        overrides
+    }
+}
+
+/// The interface offsets of `ty_def` and all of its base types.
+///
+/// Type definitions only list the offsets of the interfaces they implement
+/// themselves, but their vtable also contains the slots of interfaces
+/// implemented by their base types. If a type lists an interface that a base
+/// type also implements, only the offset closest to `ty_def` is kept.
+fn interface_offsets<'md>(metadata: &'md Metadata, ty_def: &'md Il2CppTypeDefinition) -> Vec<&'md Il2CppInterfaceOffsetPair> {
+    let type_defs = &metadata.global_metadata.type_definitions;
+    let mut offsets: Vec<&Il2CppInterfaceOffsetPair> = Vec::new();
+    let mut push = |pairs: &'md [Il2CppInterfaceOffsetPair]| {
+        for pair in pairs {
+            if !offsets.iter().any(|seen| seen.interface_type_index == pair.interface_type_index) {
+                offsets.push(pair);
+            }
+        }
+    };
+    push(ty_def.interface_offsets(metadata).unwrap_or_default());
+    for base in ty_def.base_chain(metadata) {
+        let base = metadata
+            .runtime_metadata
+            .metadata_registration
+            .types
+            .get(base as usize)
+            .and_then(|ty| ty.type_definition_index(metadata))
+            .and_then(|idx| type_defs.get(idx));
+        if let Some(base) = base {
+            push(base.interface_offsets(metadata).unwrap_or_default());
+        }
+    }
+    offsets
+}
+
+fn interface_definition(metadata: &Metadata, pair: &Il2CppInterfaceOffsetPair) -> Option<TypeDefinitionIndex> {
+    metadata
+        .runtime_metadata
+        .metadata_registration
+        .types
+        .get(pair.interface_type_index as usize)?
+        .type_definition_index(metadata)
+}
+
+/// The number of vtable slots occupied by an interface's methods.
+fn interface_slot_count(metadata: &Metadata, pair: &Il2CppInterfaceOffsetPair) -> usize {
+    let interface = interface_definition(metadata, pair).and_then(|idx| metadata.global_metadata.type_definitions.get(idx));
+    match interface {
+        Some(interface) => interface
+            .methods(metadata)
+            .unwrap_or_default()
+            .iter()
+            .filter(|method| has_slot(method))
+            .count(),
+        None => 0,
+    }
+}
+
+fn has_slot(method: &Il2CppMethodDefinition) -> bool {
+    method.slot != INVALID_SLOT
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::global_metadata::{InterfaceIndex, InterfaceOffsetIndex, VTableMethodIndex};
+    use crate::runtime_metadata::{Il2CppTypeEnum, TypeData};
+    use crate::test_util;
+
+    const OBJECT: u32 = 0;
+    const IFOO: u32 = 1;
+    const BASE: u32 = 2;
+    const DERIVED: u32 = 3;
+
+    const OBJECT_TO_STRING: u32 = 0;
+    const IFOO_FOO: u32 = 1;
+    const BASE_TO_STRING: u32 = 2;
+    const BASE_FOO: u32 = 3;
+    const DERIVED_TO_STRING: u32 = 4;
+    const DERIVED_FOO: u32 = 5;
+
+    fn method_def(idx: u32) -> EncodedMethodIndex {
+        EncodedMethodIndex(3 << 29 | idx << 1)
+    }
+
+    /// `Base : Object, IFoo` overrides `ToString` and implements `IFoo.Foo`,
+    /// and `Derived : Base, IFoo` overrides both again. Type `i` in the
+    /// runtime types refers to type definition `i`.
+    fn metadata() -> Metadata<'static, 'static> {
+        let mut metadata = test_util::metadata("");
+        metadata.runtime_metadata.metadata_registration.types = (0..4)
+            .map(|i| test_util::ty(Il2CppTypeEnum::Class, TypeData::TypeDefinitionIndex(TypeDefinitionIndex::new(i))))
+            .collect();
+
+        let gm = &mut metadata.global_metadata;
+        let virtual_flags = MethodAttributes::VIRTUAL.bits();
+        let new_slot = virtual_flags | MethodAttributes::NEW_SLOT.bits();
+        let methods = [
+            (OBJECT, 0, new_slot),
+            (IFOO, 0, new_slot | MethodAttributes::ABSTRACT.bits()),
+            (BASE, 0, virtual_flags),
+            (BASE, 1, new_slot),
+            (DERIVED, 0, virtual_flags),
+            (DERIVED, 1, virtual_flags),
+        ];
+        gm.methods = methods
+            .iter()
+            .map(|&(declaring_type, slot, flags)| {
+                let mut method: Il2CppMethodDefinition = test_util::zeroed();
+                method.declaring_type = TypeDefinitionIndex::new(declaring_type);
+                method.slot = slot;
+                method.flags = flags;
+                method
+            })
+            .collect::<Vec<_>>()
+            .into();
+        gm.vtable_methods = [OBJECT_TO_STRING, BASE_TO_STRING, BASE_FOO, DERIVED_TO_STRING, DERIVED_FOO]
+            .map(method_def)
+            .to_vec()
+            .into();
+        let pair = || Il2CppInterfaceOffsetPair {
+            interface_type_index: IFOO,
+            offset: 1,
+        };
+        gm.interface_offsets = vec![pair(), pair()].into();
+        gm.interfaces = vec![IFOO, IFOO].into();
+
+        // (parent, methods, vtable, interfaces)
+        let types = [
+            (u32::MAX, (0, 1), (0, 1), (0, 0)),
+            (u32::MAX, (1, 1), (0, 0), (0, 0)),
+            (OBJECT, (2, 2), (1, 2), (0, 1)),
+            (BASE, (4, 2), (3, 2), (1, 1)),
+        ];
+        gm.type_definitions = types
+            .iter()
+            .map(|&(parent, methods, vtable, interfaces)| {
+                let mut ty_def = test_util::type_definition();
+                ty_def.parent_index = parent;
+                ty_def.method_start = MethodIndex::new(methods.0);
+                ty_def.method_count = methods.1;
+                ty_def.vtable_start = VTableMethodIndex::new(vtable.0);
+                ty_def.vtable_count = vtable.1;
+                ty_def.interfaces_start = InterfaceIndex::new(interfaces.0);
+                ty_def.interfaces_count = interfaces.1;
+                ty_def.interface_offsets_start = InterfaceOffsetIndex::new(interfaces.0);
+                ty_def.interface_offsets_count = interfaces.1;
+                ty_def
+            })
+            .collect::<Vec<_>>()
+            .into();
+        metadata
+    }
+
+    fn methods(indices: &[u32]) -> Vec<MethodIndex> {
+        indices.iter().copied().map(MethodIndex::new).collect()
+    }
+
+    #[test]
+    fn vtable() {
+        let metadata = metadata();
+        let index = MetadataIndex::new(&metadata);
+        let derived = metadata.global_metadata.type_definitions.get(TypeDefinitionIndex::new(DERIVED)).unwrap();
+        let slots: Vec<_> = derived.vtable(&index).iter().map(|slot| (slot.slot, slot.method, slot.interface)).collect();
+        assert_eq!(
+            slots,
+            [
+                (0, VTableMethod::MethodDef(MethodIndex::new(DERIVED_TO_STRING)), None),
+                (1, VTableMethod::MethodDef(MethodIndex::new(DERIVED_FOO)), Some(IFOO)),
+            ]
+        );
+    }
+
+    #[test]
+    fn interface_implementations() {
+        let metadata = metadata();
+        let foo = MethodIndex::new(IFOO_FOO);
+        // `Derived` declares `IFoo` again, which must not be listed twice.
+        let implementations = metadata.implementations_of(TypeDefinitionIndex::new(DERIVED), foo);
+        let expected = InterfaceImplementation {
+            interface: IFOO,
+            interface_method: foo,
+            implementation: VTableMethod::MethodDef(MethodIndex::new(DERIVED_FOO)),
+        };
+        assert_eq!(implementations, [expected]);
+        assert_eq!(
+            metadata.implementation_of(TypeDefinitionIndex::new(BASE), foo),
+            Some(VTableMethod::MethodDef(MethodIndex::new(BASE_FOO)))
+        );
+        assert_eq!(metadata.implemented_interface_methods(MethodIndex::new(DERIVED_FOO)), [expected]);
+        assert!(metadata.implemented_interface_methods(MethodIndex::new(DERIVED_TO_STRING)).is_empty());
+    }
+
+    #[test]
+    fn overrides() {
+        let metadata = metadata();
+        let overrides = |idx| metadata.overrides(MethodIndex::new(idx)).map(|idx| idx.index());
+        assert_eq!(overrides(OBJECT_TO_STRING), None);
+        assert_eq!(overrides(BASE_TO_STRING), Some(OBJECT_TO_STRING));
+        assert_eq!(overrides(BASE_FOO), None);
+        assert_eq!(overrides(DERIVED_FOO), Some(BASE_FOO));
+        assert_eq!(
+            metadata.override_chain(MethodIndex::new(DERIVED_TO_STRING)),
+            methods(&[DERIVED_TO_STRING, BASE_TO_STRING, OBJECT_TO_STRING])
+        );
+    }
+
+    #[test]
+    fn overridden_by() {
+        let metadata = metadata();
+        let hierarchy = TypeHierarchy::new(&metadata);
+        assert_eq!(
+            hierarchy.overridden_by(MethodIndex::new(OBJECT_TO_STRING)),
+            methods(&[BASE_TO_STRING, DERIVED_TO_STRING])
+        );
+        assert_eq!(hierarchy.overridden_by(MethodIndex::new(BASE_FOO)), methods(&[DERIVED_FOO]));
+        assert!(hierarchy.overridden_by(MethodIndex::new(DERIVED_FOO)).is_empty());
+    }
+}
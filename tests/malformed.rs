@@ -0,0 +1,159 @@
+//! Robustness tests feeding corrupted input to the parsers.
+//!
+//! Parsing untrusted files must return errors instead of panicking, so these
+//! tests mutate and truncate otherwise valid input and only check that every
+//! call returns.
+
+use brocolib::global_metadata::transform::{self, Diagnosis, HEADER_SIZE};
+use brocolib::global_metadata::{self, EncodedMethodIndex, StringIndex};
+use brocolib::runtime_metadata::RuntimeMetadata;
+
+const SANITY: u32 = 0xFAB11BAF;
+const VERSION: u32 = 31;
+
+/// A small xorshift generator so that failures are reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Builds a global metadata file with a string table and a few tables filled
+/// with arbitrary bytes.
+fn build_metadata() -> Vec<u8> {
+    let tables: &[(&str, Vec<u8>)] = &[
+        ("string", b"Hello\0World\0System\0".to_vec()),
+        ("type_definitions", (0..=255).cycle().take(176).collect()),
+        ("methods", (0..=255).rev().cycle().take(64).collect()),
+        ("generic_containers", vec![0xFF; 32]),
+    ];
+
+    let mut data = vec![0; HEADER_SIZE];
+    data[0..4].copy_from_slice(&SANITY.to_le_bytes());
+    data[4..8].copy_from_slice(&VERSION.to_le_bytes());
+    for (name, contents) in tables {
+        let pos = transform::table_names().iter().position(|n| n == name).unwrap();
+        let entry = 8 + pos * 8;
+        let offset = data.len() as u32;
+        data[entry..entry + 4].copy_from_slice(&offset.to_le_bytes());
+        data[entry + 4..entry + 8].copy_from_slice(&(contents.len() as u32).to_le_bytes());
+        data.extend_from_slice(contents);
+    }
+    data
+}
+
+#[test]
+fn valid_metadata_parses() {
+    let data = build_metadata();
+    assert_eq!(transform::diagnose(&data), Diagnosis::Valid);
+    let metadata = global_metadata::deserialize(&data).unwrap();
+    assert_eq!(&metadata.string[StringIndex::new(6)], "World");
+    assert_eq!(metadata.type_definitions.len(), 2);
+}
+
+#[test]
+fn truncated_metadata() {
+    let data = build_metadata();
+    for len in 0..data.len() {
+        let _ = global_metadata::deserialize(&data[..len]);
+    }
+}
+
+#[test]
+fn corrupted_metadata() {
+    let original = build_metadata();
+    let mut rng = Rng(0x2545F4914F6CDD1D);
+    for _ in 0..5000 {
+        let mut data = original.clone();
+        for _ in 0..1 + rng.below(8) {
+            let pos = rng.below(data.len());
+            data[pos] = rng.next() as u8;
+        }
+
+        let Ok(metadata) = global_metadata::deserialize(&data) else {
+            continue;
+        };
+        for _ in 0..16 {
+            let index = StringIndex::new(rng.below(64) as u32);
+            let _ = &metadata.string[index];
+            let _ = metadata.string.get(index);
+        }
+    }
+}
+
+#[test]
+fn string_table_out_of_range() {
+    let data = build_metadata();
+    let metadata = global_metadata::deserialize(&data).unwrap();
+    let index = StringIndex::new(u32::MAX - 1);
+    assert_eq!(&metadata.string[index], "");
+    assert!(metadata.string.get(index).is_err());
+}
+
+#[test]
+fn encrypted_metadata() {
+    let mut data = build_metadata();
+    let mut rng = Rng(0x9E3779B97F4A7C15);
+    data.resize(0x4000, 0);
+    let key: Vec<u8> = (0..0x4000).map(|_| rng.next() as u8).collect();
+    for (byte, k) in data.iter_mut().zip(&key) {
+        *byte ^= k;
+    }
+    let xor = transform::XorTransform::new(key);
+
+    assert_eq!(transform::diagnose(&data), Diagnosis::LooksEncrypted);
+    assert!(matches!(
+        global_metadata::deserialize(&data),
+        Err(global_metadata::MetadataDeserializeError::LooksEncrypted)
+    ));
+
+    let metadata = global_metadata::deserialize_with_transform(&mut data, &xor).unwrap();
+    assert_eq!(&metadata.string[StringIndex::new(0)], "Hello");
+}
+
+#[test]
+fn wrong_version() {
+    let mut data = build_metadata();
+    data[4..8].copy_from_slice(&29u32.to_le_bytes());
+    assert_eq!(transform::diagnose(&data), Diagnosis::WrongVersion(29));
+}
+
+#[test]
+fn encoded_method_index_decode() {
+    let mut rng = Rng(0xD1B54A32D192ED03);
+    for ty in 0..8u32 {
+        for invalid in 0..2 {
+            let _ = EncodedMethodIndex((ty << 29) | invalid).decode();
+        }
+    }
+    for _ in 0..1000 {
+        let _ = EncodedMethodIndex(rng.next() as u32).decode();
+    }
+}
+
+#[test]
+fn garbage_binary() {
+    let data = build_metadata();
+    let metadata = global_metadata::deserialize(&data).unwrap();
+    let mut rng = Rng(0xA0761D6478BD642F);
+
+    for len in [0, 4, 16, 64, 0x200, 0x1000] {
+        for _ in 0..64 {
+            let mut elf: Vec<u8> = (0..len).map(|_| rng.next() as u8).collect();
+            if len >= 16 {
+                // ELF64, little endian
+                elf[..6].copy_from_slice(&[0x7F, b'E', b'L', b'F', 2, 1]);
+            }
+            assert!(RuntimeMetadata::read_elf(&elf, &metadata).is_err());
+        }
+    }
+}